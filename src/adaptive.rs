@@ -0,0 +1,33 @@
+use crate::QuestionType;
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+/// Smallest weight a type can have, so strong types still come up now and then
+const MIN_WEIGHT: f64 = 0.1;
+
+/// How likely a type is to be picked: higher the more of it the user gets wrong.
+/// Accuracy is smoothed towards 50% so a type with one or two answers, or none,
+/// isn't judged on so little.
+pub fn type_weight(correct: u32, graded: u32) -> f64 {
+    let accuracy = (correct as f64 + 1.0) / (graded as f64 + 2.0);
+    (1.0 - accuracy).max(MIN_WEIGHT)
+}
+
+/// Picks one of `candidates`, preferring types where the user's accuracy is lowest;
+/// `by_type` is (type, correct, graded) as in [`crate::UserStats`]
+pub fn pick_adaptive_type<R: Rng + ?Sized>(
+    by_type: &[(QuestionType, u32, u32)],
+    candidates: &[QuestionType],
+    rng: &mut R,
+) -> Option<QuestionType> {
+    let weights = candidates.iter().map(|candidate| {
+        by_type
+            .iter()
+            .find(|(question_type, _, _)| question_type == candidate)
+            .map_or(type_weight(0, 0), |(_, correct, graded)| {
+                type_weight(*correct, *graded)
+            })
+    });
+    let index = WeightedIndex::new(weights).ok()?;
+    candidates.get(index.sample(rng)).copied()
+}
@@ -0,0 +1,110 @@
+use crate::exclusion::{ExclusionCommand, parse_exclusion_command};
+use crate::storage::UsageStats;
+use serde::Serialize;
+
+/// Days counted as "recent" in the usage report
+pub const USAGE_WINDOW_DAYS: i64 = 7;
+
+/// A command only users listed in `--admin-ids` may run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminCommand<'a> {
+    /// Sends a text, or the question with this numeric ID, to every known user
+    Broadcast(&'a str),
+    Ban(&'a str),
+    Unban(&'a str),
+    Usage,
+    /// Changes or shows the questions random picks leave out
+    Exclude(ExclusionCommand<'a>),
+    /// Gives students a set of questions: "<question ids> to <user ids>"
+    Assign(&'a str),
+    /// Reports every student's progress on one assignment, or on all of them
+    Assignments(Option<i64>),
+}
+
+/// Parses "broadcast <text|question-id>", "ban <user>", "unban <user>", "usage",
+/// "exclude [add|remove <ids>|list]", "assign <ids> to <users>" or
+/// "assignments status [id]"
+pub fn parse_admin_command(text: &str) -> Option<AdminCommand<'_>> {
+    let (command, args) = text
+        .split_once(char::is_whitespace)
+        .map_or((text, ""), |(command, args)| (command, args.trim()));
+    match (command.to_lowercase().as_str(), args) {
+        ("usage", "") => Some(AdminCommand::Usage),
+        ("broadcast", args) if !args.is_empty() => Some(AdminCommand::Broadcast(args)),
+        ("ban", user) if !user.is_empty() => Some(AdminCommand::Ban(user)),
+        ("unban", user) if !user.is_empty() => Some(AdminCommand::Unban(user)),
+        ("exclude", args) => parse_exclusion_command(args).map(AdminCommand::Exclude),
+        ("assign", args) => Some(AdminCommand::Assign(args)),
+        ("assignments", args) => {
+            let mut words = args.split_whitespace();
+            match (words.next(), words.next(), words.next()) {
+                (None, _, _) => Some(AdminCommand::Assignments(None)),
+                (Some(status), id, None) if status.eq_ignore_ascii_case("status") => match id {
+                    None => Some(AdminCommand::Assignments(None)),
+                    Some(id) => id
+                        .trim_start_matches('#')
+                        .parse()
+                        .ok()
+                        .map(|id| AdminCommand::Assignments(Some(id))),
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Renders aggregate activity as a text message
+/// How a broadcast went
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BroadcastSummary {
+    pub recipients: usize,
+    pub delivered: usize,
+    /// Users who blocked the bot and are skipped until they message it again
+    pub blocked: usize,
+    /// Users in quiet hours, who get it once those end
+    pub held: usize,
+}
+
+/// The admin's reply after a broadcast
+pub fn format_broadcast(summary: &BroadcastSummary) -> String {
+    let mut reply = format!(
+        "📣 Broadcast delivered to {}/{} users.",
+        summary.delivered, summary.recipients
+    );
+    if summary.blocked > 0 {
+        reply.push_str(&format!(
+            " {} blocked the bot and won't get broadcasts until they message it again.",
+            summary.blocked
+        ));
+    }
+    if summary.held > 0 {
+        reply.push_str(&format!(
+            " {} will get it when their quiet hours end.",
+            summary.held
+        ));
+    }
+    reply
+}
+
+pub fn format_usage(stats: &UsageStats) -> String {
+    let accuracy = (stats.correct_answers * 100)
+        .checked_div(stats.graded_answers)
+        .map(|percent| format!(", {}% correct", percent))
+        .unwrap_or_default();
+    format!(
+        "📈 Usage (recent = last {} days)\n\
+         👤 Users: {} ({} recent, {} banned)\n\
+         📨 Questions sent: {} ({} recent)\n\
+         ✍️ Answers: {} ({} recent{})",
+        USAGE_WINDOW_DAYS,
+        stats.users,
+        stats.active_users,
+        stats.banned_users,
+        stats.questions_sent,
+        stats.recent_questions_sent,
+        stats.answers,
+        stats.recent_answers,
+        accuracy
+    )
+}
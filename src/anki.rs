@@ -0,0 +1,259 @@
+use crate::{Difficulty, QuestionType, Result};
+use rusqlite::{Connection, params};
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Note type shared by every exported deck, so re-imports update cards instead of duplicating them
+const MODEL_ID: i64 = 1_718_000_000_001;
+
+/// Anki 2.1 collection schema (version 11), which every Anki release can import
+const SCHEMA: &str = "
+    CREATE TABLE col (
+        id integer PRIMARY KEY, crt integer NOT NULL, mod integer NOT NULL,
+        scm integer NOT NULL, ver integer NOT NULL, dty integer NOT NULL,
+        usn integer NOT NULL, ls integer NOT NULL, conf text NOT NULL,
+        models text NOT NULL, decks text NOT NULL, dconf text NOT NULL, tags text NOT NULL
+    );
+    CREATE TABLE notes (
+        id integer PRIMARY KEY, guid text NOT NULL, mid integer NOT NULL,
+        mod integer NOT NULL, usn integer NOT NULL, tags text NOT NULL,
+        flds text NOT NULL, sfld integer NOT NULL, csum integer NOT NULL,
+        flags integer NOT NULL, data text NOT NULL
+    );
+    CREATE TABLE cards (
+        id integer PRIMARY KEY, nid integer NOT NULL, did integer NOT NULL,
+        ord integer NOT NULL, mod integer NOT NULL, usn integer NOT NULL,
+        type integer NOT NULL, queue integer NOT NULL, due integer NOT NULL,
+        ivl integer NOT NULL, factor integer NOT NULL, reps integer NOT NULL,
+        lapses integer NOT NULL, left integer NOT NULL, odue integer NOT NULL,
+        odid integer NOT NULL, flags integer NOT NULL, data text NOT NULL
+    );
+    CREATE TABLE revlog (
+        id integer PRIMARY KEY, cid integer NOT NULL, usn integer NOT NULL,
+        ease integer NOT NULL, ivl integer NOT NULL, lastIvl integer NOT NULL,
+        factor integer NOT NULL, time integer NOT NULL, type integer NOT NULL
+    );
+    CREATE TABLE graves (usn integer NOT NULL, oid integer NOT NULL, type integer NOT NULL);
+    CREATE INDEX ix_notes_usn ON notes (usn);
+    CREATE INDEX ix_cards_usn ON cards (usn);
+    CREATE INDEX ix_revlog_usn ON revlog (usn);
+    CREATE INDEX ix_cards_nid ON cards (nid);
+    CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+    CREATE INDEX ix_revlog_cid ON revlog (cid);
+    CREATE INDEX ix_notes_csum ON notes (csum);
+";
+
+const CARD_CSS: &str =
+    ".card { font-family: Georgia, serif; text-align: center; background: #fff; }
+img { max-width: 100%; }
+.answer { font-size: 20px; font-weight: bold; margin: 12px 0; }";
+
+/// One flashcard: a question image on the front, its explanations image on the back
+#[derive(Debug, Clone)]
+pub struct AnkiNote {
+    pub question_id: String,
+    pub question_type: QuestionType,
+    pub difficulty: Option<Difficulty>,
+    /// The correct choice, shown above the explanations when it is known
+    pub answer: Option<char>,
+    pub front_image: PathBuf,
+    pub back_image: PathBuf,
+}
+
+/// Writes `notes` as an Anki package (`.apkg`) holding one deck named `deck_name`
+pub fn write_apkg(deck_name: &str, notes: &[AnkiNote], output_path: &Path) -> Result<()> {
+    let workdir = tempfile::tempdir()?;
+    let collection_path = workdir.path().join("collection.anki2");
+    let media = write_collection(&collection_path, deck_name, notes)?;
+
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(File::create(output_path)?);
+    let options = SimpleFileOptions::default();
+    zip.start_file("collection.anki2", options)?;
+    zip.write_all(&fs::read(&collection_path)?)?;
+
+    // Media files are stored under their index, with the index-to-name map in `media`
+    let mut media_map = serde_json::Map::new();
+    for (i, (name, path)) in media.iter().enumerate() {
+        media_map.insert(i.to_string(), json!(name));
+        zip.start_file(i.to_string(), options)?;
+        zip.write_all(&fs::read(path)?)?;
+    }
+    zip.start_file("media", options)?;
+    zip.write_all(serde_json::to_string(&media_map)?.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// Creates the collection database; returns each media file's name in the deck and its path
+fn write_collection(
+    path: &Path,
+    deck_name: &str,
+    notes: &[AnkiNote],
+) -> Result<Vec<(String, PathBuf)>> {
+    let now = chrono::Utc::now();
+    let (secs, millis) = (now.timestamp(), now.timestamp_millis());
+    let deck_id = deck_id(deck_name);
+
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+        params![
+            secs,
+            millis,
+            json!({
+                "activeDecks": [deck_id],
+                "curDeck": deck_id,
+                "curModel": MODEL_ID.to_string(),
+                "nextPos": notes.len() + 1,
+                "sortType": "noteFld",
+                "sortBackwards": false,
+                "addToCur": true,
+                "newSpread": 0,
+                "collapseTime": 1200,
+                "timeLim": 0,
+                "estTimes": true,
+                "dueCounts": true
+            })
+            .to_string(),
+            model_json(deck_id, secs).to_string(),
+            json!({
+                "1": deck_json(1, "Default", secs),
+                deck_id.to_string(): deck_json(deck_id, deck_name, secs)
+            })
+            .to_string(),
+            json!({ "1": deck_options_json() }).to_string(),
+        ],
+    )?;
+
+    let mut media = Vec::new();
+    for (i, note) in notes.iter().enumerate() {
+        let front = media_name(&note.question_id, "front", &note.front_image);
+        let back = media_name(&note.question_id, "back", &note.back_image);
+        let fields = [
+            note.question_id.clone(),
+            format!("<img src=\"{}\">", front),
+            format!("<img src=\"{}\">", back),
+            note.answer.map(String::from).unwrap_or_default(),
+        ];
+        let mut tags = vec![format!("{:?}", note.question_type).to_lowercase()];
+        tags.extend(note.difficulty.map(|difficulty| difficulty.to_string()));
+
+        // IDs are creation times in milliseconds in Anki, kept unique by the note's position
+        let note_id = millis + i as i64;
+        conn.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ?5, ?6, ?7, ?8, 0, '')",
+            params![
+                note_id,
+                format!("gmat-{}", note.question_id),
+                MODEL_ID,
+                secs,
+                format!(" gmat {} ", tags.join(" ")),
+                fields.join("\x1f"),
+                note.question_id,
+                checksum(&note.question_id),
+            ],
+        )?;
+        conn.execute(
+            "INSERT INTO cards VALUES (?1, ?2, ?3, 0, ?4, -1, 0, 0, ?5, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+            params![note_id, note_id, deck_id, secs, i as i64 + 1],
+        )?;
+        media.push((front, note.front_image.clone()));
+        media.push((back, note.back_image.clone()));
+    }
+    Ok(media)
+}
+
+/// Name of an image in the deck, keeping the rendered file's extension
+fn media_name(question_id: &str, side: &str, image: &Path) -> String {
+    let extension = image
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("png");
+    format!("gmat_{}_{}.{}", question_id, side, extension)
+}
+
+/// Stable deck ID from its name, so exporting the same deck again fills it rather than adding another
+fn deck_id(deck_name: &str) -> i64 {
+    let digest = Sha1::digest(deck_name.as_bytes());
+    let bytes: [u8; 6] = digest[..6].try_into().unwrap_or_default();
+    bytes
+        .iter()
+        .fold(0i64, |id, byte| (id << 8) | i64::from(*byte))
+}
+
+/// Anki's duplicate check: the first 8 hex digits of the SHA-1 of the first field
+fn checksum(field: &str) -> i64 {
+    let digest = Sha1::digest(field.as_bytes());
+    i64::from(u32::from_be_bytes([
+        digest[0], digest[1], digest[2], digest[3],
+    ]))
+}
+
+fn model_json(deck_id: i64, secs: i64) -> serde_json::Value {
+    let field = |name: &str, ord: u32| {
+        json!({
+            "name": name, "ord": ord, "sticky": false, "rtl": false,
+            "font": "Arial", "size": 20, "media": []
+        })
+    };
+    json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID,
+            "name": "GMAT Question",
+            "type": 0,
+            "mod": secs,
+            "usn": -1,
+            "sortf": 0,
+            "did": deck_id,
+            "flds": [field("ID", 0), field("Front", 1), field("Back", 2), field("Answer", 3)],
+            "tmpls": [{
+                "name": "Card 1",
+                "ord": 0,
+                "qfmt": "{{Front}}",
+                "afmt": "{{#Answer}}<div class=\"answer\">Answer: {{Answer}}</div>{{/Answer}}{{Back}}",
+                "bqfmt": "",
+                "bafmt": "",
+                "did": null
+            }],
+            "css": CARD_CSS,
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "req": [[0, "any", [1]]],
+            "tags": [],
+            "vers": []
+        }
+    })
+}
+
+fn deck_json(id: i64, name: &str, secs: i64) -> serde_json::Value {
+    json!({
+        "id": id, "name": name, "desc": "", "mod": secs, "usn": -1, "conf": 1,
+        "dyn": 0, "collapsed": false, "extendNew": 10, "extendRev": 50,
+        "newToday": [0, 0], "revToday": [0, 0], "lrnToday": [0, 0], "timeToday": [0, 0]
+    })
+}
+
+fn deck_options_json() -> serde_json::Value {
+    json!({
+        "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60,
+        "autoplay": true, "replayq": true, "timer": 0,
+        "new": {
+            "delays": [1, 10], "ints": [1, 4, 7], "initialFactor": 2500,
+            "order": 1, "perDay": 20, "bury": true, "separate": true
+        },
+        "rev": {
+            "perDay": 100, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1,
+            "maxIvl": 36500, "minSpace": 1, "bury": true
+        },
+        "lapse": { "delays": [10], "mult": 0, "minInt": 1, "leechFails": 8, "leechAction": 0 }
+    })
+}
@@ -0,0 +1,150 @@
+use crate::{QuestionContent, strip_html_tags};
+use std::fmt;
+
+/// How far an answer read from the explanations can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnswerConfidence {
+    /// Only a hint, like a lone letter in a spoiler block, or sources that disagree
+    Low,
+    /// A phrase like "the correct answer is (B)"
+    Medium,
+    /// An official answer, "OA: C" or "Official Answer: C", that nothing contradicts
+    High,
+}
+
+impl fmt::Display for AnswerConfidence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnswerConfidence::Low => write!(f, "low"),
+            AnswerConfidence::Medium => write!(f, "medium"),
+            AnswerConfidence::High => write!(f, "high"),
+        }
+    }
+}
+
+/// The correct choice of a question, as extracted from its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnswerKey {
+    pub letter: char,
+    pub confidence: AnswerConfidence,
+}
+
+/// Phrases followed by the answer letter, in the lowercased text, and what they're worth
+const MARKERS: [(&str, AnswerConfidence); 10] = [
+    ("official answer is", AnswerConfidence::High),
+    ("official answer", AnswerConfidence::High),
+    ("oa:", AnswerConfidence::High),
+    ("oa is", AnswerConfidence::High),
+    ("oa =", AnswerConfidence::High),
+    ("best choice is", AnswerConfidence::Medium),
+    ("correct answer is", AnswerConfidence::Medium),
+    ("answer is", AnswerConfidence::Medium),
+    ("answer:", AnswerConfidence::Medium),
+    ("correct option is", AnswerConfidence::Medium),
+];
+
+/// Finds the official answer in a question's explanations and text: answer
+/// phrases like "OA: C" or "Answer: (B)", and letters hidden in spoiler blocks.
+/// Sources that disagree lower the confidence of the most trusted letter
+pub fn extract_answer_key(content: &QuestionContent) -> Option<AnswerKey> {
+    let mut candidates = Vec::new();
+    let sources = content
+        .explanations
+        .iter()
+        .map(|html| (html, false))
+        .chain(std::iter::once((&content.question, true)));
+    for (html, is_question) in sources {
+        let text = strip_html_tags(html).to_lowercase();
+        for (marker, confidence) in MARKERS {
+            // A question may say "the answer is a prime", so only an official answer counts there
+            if is_question && confidence != AnswerConfidence::High {
+                continue;
+            }
+            candidates.extend(
+                text.match_indices(marker)
+                    .filter_map(|(pos, _)| leading_choice_letter(&text[pos + marker.len()..]))
+                    .map(|letter| (letter, confidence)),
+            );
+        }
+        candidates.extend(spoiler_letters(html).map(|letter| (letter, AnswerConfidence::Low)));
+    }
+
+    let best = candidates.iter().map(|(_, confidence)| *confidence).max()?;
+    let top: Vec<char> = candidates
+        .iter()
+        .filter(|(_, confidence)| *confidence == best)
+        .map(|(letter, _)| *letter)
+        .collect();
+    // The letter the most trusted sources name most often; the first one on a tie
+    let letter = *top
+        .iter()
+        .max_by_key(|letter| {
+            (
+                top.iter().filter(|other| other == letter).count(),
+                std::cmp::Reverse(top.iter().position(|other| other == *letter)),
+            )
+        })
+        .expect("the best confidence has a candidate");
+    let confidence = if top.iter().any(|other| *other != letter) {
+        AnswerConfidence::Low
+    } else if candidates.iter().any(|(other, _)| *other != letter) {
+        match best {
+            AnswerConfidence::High => AnswerConfidence::Medium,
+            _ => AnswerConfidence::Low,
+        }
+    } else {
+        best
+    };
+    Some(AnswerKey { letter, confidence })
+}
+
+/// Finds the correct answer letter by scanning the explanations for phrases
+/// like "The best choice is (A)" or "OA: C"; see [`extract_answer_key`]
+pub fn parse_correct_answer(content: &QuestionContent) -> Option<char> {
+    extract_answer_key(content).map(|key| key.letter)
+}
+
+/// Letters that are all a spoiler block holds, e.g. `<div class="spoiler">C</div>` or
+/// `[spoiler]C[/spoiler]`
+fn spoiler_letters(html: &str) -> impl Iterator<Item = char> + '_ {
+    let lower = html.to_ascii_lowercase();
+    let starts: Vec<usize> = lower.match_indices("spoiler").map(|(pos, _)| pos).collect();
+    starts.into_iter().filter_map(move |pos| {
+        // The block's content starts after the tag holding the word
+        let body_start = pos + html[pos..].find([']', '>'])? + 1;
+        let body = &html[body_start..];
+        let body_end = body
+            .find("[/spoiler]")
+            .or_else(|| body.to_ascii_lowercase().find("</div"))
+            .unwrap_or(body.len());
+        let text = strip_html_tags(&body[..body_end]);
+        let words: Vec<&str> = text
+            .split_whitespace()
+            .filter(|word| {
+                !word.eq_ignore_ascii_case("show") && !word.eq_ignore_ascii_case("spoiler")
+            })
+            .collect();
+        match words.as_slice() {
+            [word] => {
+                let word = word.trim_matches(|c: char| c == '(' || c == ')' || c == '.');
+                let mut chars = word.chars();
+                let letter = chars.next()?.to_ascii_uppercase();
+                (chars.next().is_none() && ('A'..='E').contains(&letter)).then_some(letter)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Returns the answer letter at the start of `text`, ignoring spaces and brackets
+fn leading_choice_letter(text: &str) -> Option<char> {
+    let trimmed = text.trim_start_matches(|c: char| c.is_whitespace() || c == '(' || c == ':');
+    let mut chars = trimmed.chars();
+    let letter = chars.next()?;
+    let standalone = chars.next().is_none_or(|c| !c.is_alphanumeric());
+    if ('a'..='e').contains(&letter) && standalone {
+        Some(letter.to_ascii_uppercase())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+/// Most questions one assignment may hold
+pub const MAX_ASSIGNMENT_QUESTIONS: usize = 50;
+
+/// A set of questions a tutor gave to a list of students
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub id: i64,
+    pub name: Option<String>,
+    /// Admin who created it with "assign"; `None` from the command line
+    pub created_by: Option<String>,
+    /// Unix seconds; only answers from then on count
+    pub created_at: i64,
+    /// In the order they are sent
+    pub question_ids: Vec<String>,
+}
+
+/// How far one student got with an assignment
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudentProgress {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    /// Unix seconds; `None` until the bot knows the student's private chat
+    pub delivered_at: Option<i64>,
+    /// The student's first answer to each assignment question: whether it was
+    /// correct, `None` when the answer key is unknown
+    pub answers: BTreeMap<String, Option<bool>>,
+    /// The question the student's latest answer was to, when it was their first
+    /// answer to it; a repeat answer moves the assignment on no further
+    pub latest_first_answer: Option<String>,
+}
+
+impl StudentProgress {
+    pub fn answered(&self) -> usize {
+        self.answers.len()
+    }
+
+    pub fn correct(&self) -> usize {
+        self.answers
+            .values()
+            .filter(|correct| **correct == Some(true))
+            .count()
+    }
+
+    /// Whether the student's latest answer was their first to `question_id`
+    pub fn just_answered(&self, question_id: &str) -> bool {
+        self.latest_first_answer.as_deref() == Some(question_id)
+    }
+
+    /// Percent correct of the graded answers, `None` before any
+    pub fn accuracy(&self) -> Option<usize> {
+        let graded = self.answers.values().filter(|c| c.is_some()).count();
+        (self.correct() * 100).checked_div(graded)
+    }
+}
+
+impl Assignment {
+    /// The first question the student hasn't answered, with its 1-based position
+    pub fn next_question(&self, progress: &StudentProgress) -> Option<(usize, &str)> {
+        self.question_ids
+            .iter()
+            .enumerate()
+            .find(|(_, id)| !progress.answers.contains_key(*id))
+            .map(|(i, id)| (i + 1, id.as_str()))
+    }
+
+    pub fn is_complete(&self, progress: &StudentProgress) -> bool {
+        self.next_question(progress).is_none()
+    }
+
+    /// "#3 Week 1", or "#3" without a name
+    pub fn title(&self) -> String {
+        match &self.name {
+            Some(name) => format!("#{} {}", self.id, name),
+            None => format!("#{}", self.id),
+        }
+    }
+}
+
+/// An assignment and every student's progress on it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignmentStatus {
+    pub assignment: Assignment,
+    pub students: Vec<StudentProgress>,
+}
+
+/// Parses the arguments of "assign <question ids> to <user ids>", both lists
+/// separated by commas or spaces
+pub fn parse_assign_command(args: &str) -> Option<(Vec<String>, Vec<String>)> {
+    // ASCII lowercasing keeps byte offsets, so the split lands on the same place in `args`
+    let split = args.to_ascii_lowercase().find(" to ")?;
+    let list = |text: &str| -> Vec<String> {
+        text.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let (question_ids, user_ids) = (list(&args[..split]), list(&args[split + 4..]));
+    (!question_ids.is_empty() && !user_ids.is_empty()).then_some((question_ids, user_ids))
+}
+
+/// Per-student completion and accuracy, one line each
+pub fn format_assignment_status(status: &AssignmentStatus) -> String {
+    let assignment = &status.assignment;
+    let total = assignment.question_ids.len();
+    let mut lines = vec![format!(
+        "📋 Assignment {}: {} question(s), {} student(s)",
+        assignment.title(),
+        total,
+        status.students.len()
+    )];
+    for student in &status.students {
+        let name = match &student.display_name {
+            Some(name) => format!("{} ({})", name, student.user_id),
+            None => student.user_id.clone(),
+        };
+        let progress = if student.delivered_at.is_none() {
+            "not delivered yet".to_string()
+        } else {
+            let done = if assignment.is_complete(student) {
+                " ✅"
+            } else {
+                ""
+            };
+            let accuracy = student
+                .accuracy()
+                .map(|percent| format!(", {}% correct", percent))
+                .unwrap_or_default();
+            format!("{}/{} done{}{}", student.answered(), total, done, accuracy)
+        };
+        lines.push(format!("👤 {}: {}", name, progress));
+    }
+    lines.join("\n")
+}
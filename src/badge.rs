@@ -0,0 +1,263 @@
+use crate::QuestionType;
+
+/// An achievement a user earns once and keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Badge {
+    /// Key stored in the database, so it must never change
+    pub id: &'static str,
+    pub icon: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    rule: BadgeRule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BadgeRule {
+    /// Questions answered in total
+    Answered(u32),
+    /// Correct answers in a row, of any type
+    CorrectRun(u32),
+    /// Correct answers in a row among questions of one type
+    TypeRun(QuestionType, u32),
+    /// Consecutive days with at least one answer
+    PracticeDays(u32),
+}
+
+const fn badge(
+    id: &'static str,
+    icon: &'static str,
+    name: &'static str,
+    description: &'static str,
+    rule: BadgeRule,
+) -> Badge {
+    Badge {
+        id,
+        icon,
+        name,
+        description,
+        rule,
+    }
+}
+
+/// Every badge, in the order the "badges" card shows them
+pub const BADGES: &[Badge] = &[
+    badge(
+        "answered_1",
+        "🌱",
+        "First Step",
+        "Answer your first question",
+        BadgeRule::Answered(1),
+    ),
+    badge(
+        "answered_10",
+        "📘",
+        "Warming Up",
+        "10 questions answered",
+        BadgeRule::Answered(10),
+    ),
+    badge(
+        "answered_100",
+        "📚",
+        "Century",
+        "100 questions answered",
+        BadgeRule::Answered(100),
+    ),
+    badge(
+        "answered_500",
+        "🏛️",
+        "Marathoner",
+        "500 questions answered",
+        BadgeRule::Answered(500),
+    ),
+    badge(
+        "run_5",
+        "🎯",
+        "Sharpshooter",
+        "5 correct in a row",
+        BadgeRule::CorrectRun(5),
+    ),
+    badge(
+        "run_20",
+        "💎",
+        "Flawless",
+        "20 correct in a row",
+        BadgeRule::CorrectRun(20),
+    ),
+    badge(
+        "ps_run_10",
+        "🔢",
+        "Problem Solver",
+        "10 PS in a row",
+        BadgeRule::TypeRun(QuestionType::PS, 10),
+    ),
+    badge(
+        "ds_run_10",
+        "📊",
+        "Sufficient",
+        "10 DS in a row",
+        BadgeRule::TypeRun(QuestionType::DS, 10),
+    ),
+    badge(
+        "cr_run_10",
+        "🧠",
+        "Critical Thinker",
+        "10 CR in a row",
+        BadgeRule::TypeRun(QuestionType::CR, 10),
+    ),
+    badge(
+        "sc_run_10",
+        "✏️",
+        "Grammarian",
+        "10 SC in a row",
+        BadgeRule::TypeRun(QuestionType::SC, 10),
+    ),
+    badge(
+        "rc_run_10",
+        "📖",
+        "Close Reader",
+        "10 RC in a row",
+        BadgeRule::TypeRun(QuestionType::RC, 10),
+    ),
+    badge(
+        "streak_3",
+        "🔥",
+        "On Fire",
+        "3-day practice streak",
+        BadgeRule::PracticeDays(3),
+    ),
+    badge(
+        "streak_7",
+        "🗓️",
+        "Week Warrior",
+        "7-day practice streak",
+        BadgeRule::PracticeDays(7),
+    ),
+    badge(
+        "streak_30",
+        "🏆",
+        "Unstoppable",
+        "30-day practice streak",
+        BadgeRule::PracticeDays(30),
+    ),
+];
+
+/// What badges are judged on, as of a user's latest answer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BadgeProgress {
+    pub answered: u32,
+    pub correct: u32,
+    /// Correct answers since the last wrong one
+    pub correct_run: u32,
+    /// The same, counting only questions of each type
+    pub type_runs: Vec<(QuestionType, u32)>,
+    /// Consecutive practice days, see [`crate::practice_streak`]
+    pub practice_streak: u32,
+}
+
+impl Badge {
+    pub fn is_earned(&self, progress: &BadgeProgress) -> bool {
+        match self.rule {
+            BadgeRule::Answered(count) => progress.answered >= count,
+            BadgeRule::CorrectRun(count) => progress.correct_run >= count,
+            BadgeRule::TypeRun(question_type, count) => progress
+                .type_runs
+                .iter()
+                .any(|(t, run)| *t == question_type && *run >= count),
+            BadgeRule::PracticeDays(days) => progress.practice_streak >= days,
+        }
+    }
+}
+
+/// Badges `progress` qualifies for, whether or not they were awarded already
+pub fn earned_badges(progress: &BadgeProgress) -> Vec<&'static Badge> {
+    BADGES
+        .iter()
+        .filter(|badge| badge.is_earned(progress))
+        .collect()
+}
+
+pub fn find_badge(id: &str) -> Option<&'static Badge> {
+    BADGES.iter().find(|badge| badge.id == id)
+}
+
+/// A rank reached by answering questions correctly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    /// 1 for a newcomer
+    pub number: u32,
+    pub name: &'static str,
+    /// Correct answers needed to reach it
+    pub min_correct: u32,
+}
+
+/// Every level, lowest first
+pub const LEVELS: &[Level] = &[
+    Level {
+        number: 1,
+        name: "Beginner",
+        min_correct: 0,
+    },
+    Level {
+        number: 2,
+        name: "Apprentice",
+        min_correct: 10,
+    },
+    Level {
+        number: 3,
+        name: "Scholar",
+        min_correct: 50,
+    },
+    Level {
+        number: 4,
+        name: "Strategist",
+        min_correct: 150,
+    },
+    Level {
+        number: 5,
+        name: "Expert",
+        min_correct: 400,
+    },
+    Level {
+        number: 6,
+        name: "Master",
+        min_correct: 1000,
+    },
+];
+
+/// The level of a user with `correct` correct answers
+pub fn level_for(correct: u32) -> Level {
+    LEVELS
+        .iter()
+        .rev()
+        .find(|level| correct >= level.min_correct)
+        .copied()
+        .unwrap_or(LEVELS[0])
+}
+
+/// The level after `level`, if there is one
+pub fn next_level(level: Level) -> Option<Level> {
+    LEVELS
+        .iter()
+        .find(|next| next.number == level.number + 1)
+        .copied()
+}
+
+/// Celebration sent when a badge is awarded, naming the user so a group can see who earned it
+pub fn format_badge_award(name: Option<&str>, badge: &Badge) -> String {
+    let who = name
+        .map(|name| format!(" for {}", name))
+        .unwrap_or_default();
+    format!(
+        "🏅 New badge{}: {} {} ({})! Send 'badges' to see the whole collection.",
+        who, badge.icon, badge.name, badge.description
+    )
+}
+
+/// Celebration sent when a correct answer reaches a new level
+pub fn format_level_up(name: Option<&str>, level: Level) -> String {
+    let who = name.unwrap_or("You");
+    format!(
+        "⭐ Level up! {} reached level {}: {}.",
+        who, level.number, level.name
+    )
+}
@@ -0,0 +1,465 @@
+use crate::zalo::DEFAULT_POLL_TIMEOUT;
+use crate::{
+    BotError, DEFAULT_CONCURRENCY, DEFAULT_DRAIN_TIMEOUT, DEFAULT_REFRESH_INTERVAL,
+    DEFAULT_RENDER_TIMEOUT, DEFAULT_STATS_TTL, DatabaseSource, DirectUpload, DiscordBot,
+    ExclusionList, HttpContext, ImageHost, MathJaxSource, MessageHandler, OffsetStore, Platform,
+    ProxyRoute, QuestionCache, QuestionEnricher, QuestionTemplates, RecentSends, RenderOptions,
+    Renderer, RendererKind, Result, RetryPolicy, Storage, TelegramBot, Theme, ZaloBot,
+    default_cache_dir, fetch_gmat_database, mathjax_script_url, zalo,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// How question images are rendered, and where rendered images and fetched questions are kept
+#[derive(Debug, Clone)]
+pub struct RenderingConfig {
+    pub renderer: RendererKind,
+    pub options: RenderOptions,
+    /// Colour scheme for users who haven't picked their own
+    pub theme: Theme,
+    /// Directory with a question.html template replacing the bundled one
+    pub template_dir: Option<PathBuf>,
+    pub mathjax_source: MathJaxSource,
+    /// Unpacked mathjax@3 package used with [`MathJaxSource::Bundled`]
+    pub mathjax_dir: Option<PathBuf>,
+    pub output_dir: String,
+    /// Where question content is cached; `~/.gmat-zalo-bot/questions` when absent
+    pub cache_dir: Option<PathBuf>,
+    /// How long a cached question is kept before it is fetched again
+    pub cache_ttl: Duration,
+    /// Fetch answer stats from each question's source page for the image footer
+    pub enrich: bool,
+    /// How long fetched stats are kept before the source page is read again
+    pub stats_ttl: Duration,
+    /// How long an external renderer may run on one page before it is killed
+    pub render_timeout: Duration,
+    /// Save each generated page next to its image and list them in [`crate::HTML_INDEX_FILE`]
+    pub keep_html: bool,
+}
+
+impl Default for RenderingConfig {
+    fn default() -> Self {
+        Self {
+            renderer: RendererKind::Wkhtmltoimage,
+            options: RenderOptions::CHAT,
+            theme: Theme::default(),
+            template_dir: None,
+            mathjax_source: MathJaxSource::default(),
+            mathjax_dir: None,
+            output_dir: "output".to_string(),
+            cache_dir: None,
+            cache_ttl: Duration::from_secs(168 * 3600),
+            enrich: false,
+            stats_ttl: DEFAULT_STATS_TTL,
+            render_timeout: DEFAULT_RENDER_TIMEOUT,
+            keep_html: false,
+        }
+    }
+}
+
+impl RenderingConfig {
+    /// The chosen renderer set up with `options`, once they are checked to make sense together
+    pub fn build_renderer(&self) -> Result<Box<dyn Renderer>> {
+        if !self.renderer.supports(self.options.format) {
+            return Err(BotError::Config(format!(
+                "The {:?} renderer can't write {} images; use the chrome renderer or another format",
+                self.renderer,
+                self.options.format.extension()
+            )));
+        }
+        if self.options.width == 0 || self.options.scale.is_nan() || self.options.scale <= 0.0 {
+            return Err(BotError::Config(
+                "Render width and scale must be greater than 0".to_string(),
+            ));
+        }
+        Ok(self.renderer.build(self.options, self.render_timeout))
+    }
+
+    /// The question templates, bundled unless `template_dir` is set, pointed at MathJax
+    pub fn templates(&self) -> Result<QuestionTemplates> {
+        let templates = match &self.template_dir {
+            Some(dir) => QuestionTemplates::load(dir)?,
+            None => QuestionTemplates::bundled(),
+        };
+        let mathjax_url = mathjax_script_url(self.mathjax_source, self.mathjax_dir.as_deref())?;
+        let templates = templates.with_mathjax_url(mathjax_url);
+        Ok(if self.keep_html {
+            templates.with_kept_html(&self.output_dir)
+        } else {
+            templates
+        })
+    }
+
+    pub fn question_cache(&self) -> QuestionCache {
+        let dir = self.cache_dir.clone().unwrap_or_else(default_cache_dir);
+        QuestionCache::new(dir, self.cache_ttl)
+    }
+
+    /// The stats fetcher when `enrich` is set, caching in a `stats` directory of the question
+    /// cache and fetching through `http`'s database client
+    pub fn enricher(&self, http: &HttpContext) -> Option<QuestionEnricher> {
+        let dir = self.cache_dir.clone().unwrap_or_else(default_cache_dir);
+        self.enrich.then(|| {
+            QuestionEnricher::new(dir.join("stats"), self.stats_ttl)
+                .with_http_client(http.client(ProxyRoute::Database).clone())
+        })
+    }
+}
+
+/// How the polling service spreads its work and protects itself
+#[derive(Debug, Clone)]
+pub struct PollingConfig {
+    /// Messages handled at once, across different chats
+    pub concurrency: usize,
+    /// Messages a sender may send per interval; no limit when absent
+    pub rate_limit: Option<(u32, Duration)>,
+    /// Users allowed to run admin commands
+    pub admin_ids: Vec<String>,
+    /// Display name members use to @mention the bot in group chats
+    pub bot_name: Option<String>,
+    /// How long an unanswered question waits for a hint; no hints when absent
+    pub hint_delay: Option<Duration>,
+    /// How long in-flight messages may take to finish after a shutdown request
+    pub drain_timeout: Duration,
+    /// How often the question index is fetched again; never when absent
+    pub database_refresh: Option<Duration>,
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            rate_limit: Some((10, Duration::from_secs(60))),
+            admin_ids: Vec::new(),
+            bot_name: None,
+            hint_delay: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            database_refresh: Some(DEFAULT_REFRESH_INTERVAL),
+        }
+    }
+}
+
+impl PollingConfig {
+    pub fn apply(&self, bot: ZaloBot) -> ZaloBot {
+        let mut bot = bot
+            .with_concurrency(self.concurrency)
+            .with_drain_timeout(self.drain_timeout);
+        if let Some((limit, interval)) = self.rate_limit {
+            bot = bot.with_rate_limit(limit, interval);
+        }
+        if !self.admin_ids.is_empty() {
+            bot = bot.with_admin_ids(self.admin_ids.clone());
+        }
+        if let Some(bot_name) = &self.bot_name {
+            bot = bot.with_bot_name(bot_name.clone());
+        }
+        if let Some(delay) = self.hint_delay {
+            bot = bot.with_hint_delay(delay);
+        }
+        if let Some(interval) = self.database_refresh {
+            bot = bot.with_database_refresh(interval);
+        }
+        bot
+    }
+}
+
+/// Where the chat platform's Bot API is and how long its requests may take
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiConfig {
+    /// Bot API host, e.g. a staging or mock server; the platform's own when absent
+    pub base_url: Option<String>,
+    /// Seconds `getUpdates` holds the connection open waiting for messages
+    pub poll_timeout: u64,
+    /// Limit on each Bot API request, on top of which long polls wait; the shared
+    /// HTTP timeout when absent
+    pub request_timeout: Option<Duration>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: None,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            request_timeout: None,
+        }
+    }
+}
+
+/// Points `bot` at `platform`'s Bot API through `http`'s chat client, saving the update
+/// position in `offset_store` when given
+pub fn connect_platform(
+    bot: ZaloBot,
+    platform: Platform,
+    api: &ApiConfig,
+    http: &HttpContext,
+    offset_store: Option<OffsetStore>,
+    retry_policy: RetryPolicy,
+) -> ZaloBot {
+    let client = http.client(ProxyRoute::Chat).clone();
+    match platform {
+        Platform::Zalo => {
+            let mut zalo = zalo::Client::new(bot.bot_token.clone())
+                .with_http_client(client)
+                .with_poll_timeout(api.poll_timeout)
+                .with_retry_policy(retry_policy);
+            if let Some(base_url) = &api.base_url {
+                zalo = zalo.with_base_url(base_url.as_str());
+            }
+            if let Some(timeout) = api.request_timeout {
+                zalo = zalo.with_timeout(timeout);
+            }
+            let bot = bot.with_api_client(zalo);
+            match offset_store {
+                Some(offset_store) => bot.with_offset_store(offset_store),
+                None => bot,
+            }
+        }
+        Platform::Telegram => {
+            let mut telegram = TelegramBot::new(bot.bot_token.clone())
+                .with_http_client(client)
+                .with_poll_timeout(api.poll_timeout)
+                .with_retry_policy(retry_policy);
+            if let Some(base_url) = &api.base_url {
+                telegram = telegram.with_api_url(base_url.as_str());
+            }
+            if let Some(timeout) = api.request_timeout {
+                telegram = telegram.with_timeout(timeout);
+            }
+            if let Some(offset_store) = offset_store {
+                telegram = telegram.with_offset_store(offset_store);
+            }
+            bot.with_platform(Box::new(telegram))
+        }
+        Platform::Discord => {
+            let mut discord = DiscordBot::new(bot.bot_token.clone())
+                .with_http_client(client)
+                .with_poll_timeout(api.poll_timeout)
+                .with_retry_policy(retry_policy);
+            if let Some(base_url) = &api.base_url {
+                discord = discord.with_api_url(base_url.as_str());
+            }
+            if let Some(timeout) = api.request_timeout {
+                discord = discord.with_timeout(timeout);
+            }
+            if let Some(offset_store) = offset_store {
+                discord = discord.with_offset_store(offset_store);
+            }
+            bot.with_platform(Box::new(discord))
+        }
+    }
+}
+
+/// Assembles a polling bot without the command line, for programs embedding it.
+///
+/// ```no_run
+/// # async fn run() -> gmat_zalo_bot::Result<()> {
+/// use gmat_zalo_bot::{BotBuilder, Platform, Storage};
+///
+/// BotBuilder::new(std::env::var("TELEGRAM_BOT_TOKEN").unwrap())
+///     .with_platform(Platform::Telegram)
+///     .with_storage(Storage::open("gmat_bot.db")?)
+///     .run()
+///     .await
+/// # }
+/// ```
+pub struct BotBuilder {
+    token: String,
+    platform: Platform,
+    api: ApiConfig,
+    http: HttpContext,
+    database_source: DatabaseSource,
+    rendering: RenderingConfig,
+    polling: PollingConfig,
+    renderer: Option<Arc<dyn Renderer>>,
+    image_host: Option<Arc<dyn ImageHost>>,
+    storage: Option<Storage>,
+    offset_store: Option<OffsetStore>,
+    recent_sends: Option<RecentSends>,
+    exclusions: Arc<ExclusionList>,
+    retry_policy: RetryPolicy,
+    handlers: Vec<Arc<dyn MessageHandler>>,
+    customize: Vec<Box<dyn FnOnce(ZaloBot) -> ZaloBot + Send>>,
+}
+
+impl BotBuilder {
+    /// A Zalo bot with the default rendering and polling settings, sending images
+    /// as uploads and keeping its state in memory
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            platform: Platform::Zalo,
+            api: ApiConfig::default(),
+            http: HttpContext::default(),
+            database_source: DatabaseSource::default(),
+            rendering: RenderingConfig::default(),
+            polling: PollingConfig::default(),
+            renderer: None,
+            image_host: None,
+            storage: None,
+            offset_store: None,
+            recent_sends: None,
+            exclusions: Arc::default(),
+            retry_policy: RetryPolicy::default(),
+            handlers: Vec::new(),
+            customize: Vec::new(),
+        }
+    }
+
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Talks to another Bot API host or changes its timeouts, e.g. for a mock server in tests
+    pub fn with_api(mut self, api: ApiConfig) -> Self {
+        self.api = api;
+        self
+    }
+
+    /// Sends every request the bot makes through these clients, e.g. to use a proxy
+    pub fn with_http(mut self, http: HttpContext) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn with_database_source(mut self, database_source: DatabaseSource) -> Self {
+        self.database_source = database_source;
+        self
+    }
+
+    pub fn with_rendering(mut self, rendering: RenderingConfig) -> Self {
+        self.rendering = rendering;
+        self
+    }
+
+    pub fn with_polling(mut self, polling: PollingConfig) -> Self {
+        self.polling = polling;
+        self
+    }
+
+    /// Uses `renderer` instead of building the one named in the rendering config
+    pub fn with_renderer(mut self, renderer: impl Into<Arc<dyn Renderer>>) -> Self {
+        self.renderer = Some(renderer.into());
+        self
+    }
+
+    /// Where images are hosted before they are sent; uploaded with each message when unset
+    pub fn with_image_host(mut self, image_host: impl ImageHost + 'static) -> Self {
+        self.image_host = Some(Arc::new(image_host));
+        self
+    }
+
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn with_offset_store(mut self, offset_store: OffsetStore) -> Self {
+        self.offset_store = Some(offset_store);
+        self
+    }
+
+    /// Keeps the questions each chat got lately, e.g. in a file with [`RecentSends::load`];
+    /// in memory for a week by default
+    pub fn with_recent_sends(mut self, recent_sends: RecentSends) -> Self {
+        self.recent_sends = Some(recent_sends);
+        self
+    }
+
+    /// Questions random picks leave out, e.g. loaded with [`ExclusionList::load`]; admins
+    /// can change it with "exclude"
+    pub fn with_exclusions(mut self, exclusions: ExclusionList) -> Self {
+        self.exclusions = Arc::new(exclusions);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_handler(mut self, handler: impl MessageHandler + 'static) -> Self {
+        self.handlers.push(Arc::new(handler));
+        self
+    }
+
+    /// Applies any other [`ZaloBot`] setting, e.g. `|bot| bot.with_ocr()`
+    pub fn configure(
+        mut self,
+        customize: impl FnOnce(ZaloBot) -> ZaloBot + Send + 'static,
+    ) -> Self {
+        self.customize.push(Box::new(customize));
+        self
+    }
+
+    /// Assembles the bot without starting it, so the caller can keep hold of it,
+    /// e.g. to trigger its shutdown
+    pub fn build(self) -> Result<BotService> {
+        let renderer = match self.renderer {
+            Some(renderer) => renderer,
+            None => self.rendering.build_renderer()?.into(),
+        };
+        let mut bot = ZaloBot::new(self.token)
+            .with_renderer(renderer)
+            .with_theme(self.rendering.theme)
+            .with_templates(self.rendering.templates()?)
+            .with_database_source(self.database_source)
+            .with_http(self.http.clone())
+            .with_question_cache(self.rendering.question_cache())
+            .with_retry_policy(self.retry_policy);
+        if let Some(storage) = self.storage {
+            bot = bot.with_storage(storage);
+        }
+        if let Some(recent_sends) = self.recent_sends {
+            bot = bot.with_recent_sends(recent_sends);
+        }
+        bot = bot.with_exclusions(self.exclusions);
+        if let Some(enricher) = self.rendering.enricher(&self.http) {
+            bot = bot.with_enricher(enricher);
+        }
+        bot = connect_platform(
+            bot,
+            self.platform,
+            &self.api,
+            &self.http,
+            self.offset_store,
+            self.retry_policy,
+        );
+        bot = self.polling.apply(bot);
+        bot.handlers.extend(self.handlers);
+        for customize in self.customize {
+            bot = customize(bot);
+        }
+        Ok(BotService {
+            bot: Arc::new(bot),
+            image_host: self.image_host.unwrap_or_else(|| Arc::new(DirectUpload)),
+            output_dir: self.rendering.output_dir,
+        })
+    }
+
+    /// Builds the bot and polls until it is shut down
+    pub async fn run(self) -> Result<()> {
+        self.build()?.run().await
+    }
+}
+
+/// A bot assembled by [`BotBuilder`], ready to poll
+pub struct BotService {
+    pub bot: Arc<ZaloBot>,
+    image_host: Arc<dyn ImageHost>,
+    output_dir: String,
+}
+
+impl BotService {
+    /// Fetches the question index, then polls until `bot.shutdown` is triggered or a shutdown signal arrives
+    pub async fn run(&self) -> Result<()> {
+        self.bot.check_identity().await?;
+        info!("Fetching GMAT database...");
+        let database = fetch_gmat_database(&self.bot.database_source).await?;
+        self.bot
+            .start_polling_service(&database, &self.output_dir, &self.image_host)
+            .await
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{ANSWER_CHOICES, AnswerConfidence, QuestionContent, QuestionType};
+
+/// Formats answer choices as labelled options
+fn answers_html(content: &QuestionContent) -> String {
+    content
+        .answers
+        .iter()
+        .enumerate()
+        .map(|(i, answer)| {
+            let label = ANSWER_CHOICES
+                .get(i)
+                .map_or_else(|| (i + 1).to_string(), |label| label.to_string());
+            format!(
+                "<div class=\"answer-option\"><strong>{})</strong> {}</div>",
+                label, answer
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Generates one printable page set: every question first, then an answer key
+/// with the correct choice and explanations for each. `mathjax_url` is the MathJax script to load
+pub fn generate_bundle_html(
+    questions: &[(QuestionContent, QuestionType)],
+    mathjax_url: &str,
+) -> String {
+    let accent_color = "#0068ff";
+
+    let questions_html = questions
+        .iter()
+        .enumerate()
+        .map(|(i, (content, question_type))| {
+            format!(
+                r#"
+    <div class="question">
+        <div class="question-header">Question {} · {} · ID {}</div>
+        <div class="question-text">{}</div>
+        <div class="answers">{}</div>
+    </div>"#,
+                i + 1,
+                question_type,
+                content.id,
+                content.question,
+                answers_html(content)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let key_html = questions
+        .iter()
+        .enumerate()
+        .map(|(i, (content, _))| {
+            let answer = match content.answer_key() {
+                Some(key) if key.confidence == AnswerConfidence::Low => {
+                    format!("{} (uncertain)", key.letter)
+                }
+                Some(key) => key.letter.to_string(),
+                None => "not found".to_string(),
+            };
+            let explanations = content
+                .explanations
+                .iter()
+                .map(|explanation| format!("<div class=\"explanation\">{}</div>", explanation))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                r#"
+    <div class="key-entry">
+        <div class="question-header">Question {}: answer {}</div>
+        {}
+        <div class="source"><a href="{}">{}</a></div>
+    </div>"#,
+                i + 1,
+                answer,
+                explanations,
+                content.src,
+                content.src
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>GMAT Practice Set</title>
+    <script>
+        window.status = 'loading';
+
+        function setReady() {{
+            window.status = 'ready_to_print';
+        }}
+
+        window.MathJax = {{
+            startup: {{
+                ready: function() {{
+                    MathJax.startup.defaultReady().then(setReady);
+                }}
+            }}
+        }};
+
+        // Fallback in case MathJax fails to load
+        setTimeout(setReady, 3000);
+    </script>
+    <script id="MathJax-script" async src="{}"></script>
+    <style>
+        body {{
+            font-family: Georgia, 'Times New Roman', Times, serif;
+            margin: 0 auto;
+            padding: 20px;
+            line-height: 1.6;
+            color: #333;
+        }}
+
+        h1 {{
+            color: {};
+            border-bottom: 3px solid {};
+            padding-bottom: 8px;
+        }}
+
+        .question, .key-entry {{
+            margin-bottom: 30px;
+            page-break-inside: avoid;
+        }}
+
+        .question-header {{
+            font-weight: 700;
+            color: {};
+            margin-bottom: 10px;
+        }}
+
+        .answer-option {{
+            padding: 4px 10px;
+        }}
+
+        .answer-key {{
+            page-break-before: always;
+        }}
+
+        .explanation {{
+            background: #f9f9f9;
+            padding: 12px;
+            margin-bottom: 10px;
+        }}
+
+        .source {{
+            font-size: 0.8em;
+        }}
+
+        .source a {{
+            color: {};
+            text-decoration: none;
+        }}
+
+        table {{
+            border-collapse: collapse;
+            width: 100%;
+        }}
+
+        th, td {{
+            padding: 8px;
+            text-align: left;
+            border-bottom: 1px solid #eee;
+        }}
+    </style>
+</head>
+<body>
+    <h1>Questions</h1>
+    {}
+
+    <div class="answer-key">
+        <h1>Answer Key</h1>
+        {}
+    </div>
+</body>
+</html>
+    "#,
+        mathjax_url,
+        accent_color, // heading text
+        accent_color, // heading underline
+        accent_color, // question headers
+        accent_color, // source links
+        questions_html,
+        key_html
+    )
+}
@@ -0,0 +1,83 @@
+use crate::{QuestionContent, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Default location of the question cache, `~/.gmat-zalo-bot/questions`
+pub fn default_cache_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".gmat-zalo-bot")
+        .join("questions")
+}
+
+/// Disk-backed cache of question content, one JSON file per question ID
+pub struct QuestionCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl QuestionCache {
+    /// Creates a cache in `dir`; entries older than `ttl` are refetched
+    pub fn new(dir: impl AsRef<Path>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            ttl,
+        }
+    }
+
+    /// Returns the cached content for a question if present and fresh
+    pub fn get(&self, question_id: &str) -> Option<QuestionContent> {
+        let path = self.entry_path(question_id);
+        let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+        if age > self.ttl {
+            debug!("Cached question {} expired", question_id);
+            return None;
+        }
+
+        let json = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&json) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                warn!("Ignoring unreadable cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Stores question content, replacing any previous entry
+    pub fn put(&self, question_id: &str, content: &QuestionContent) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(question_id);
+        // Write then rename so a concurrent reader never sees a partial file
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(content)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Every fresh cached question, for searching by text
+    pub fn all(&self) -> Vec<QuestionContent> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                self.get(path.file_stem()?.to_str()?)
+            })
+            .collect()
+    }
+
+    fn entry_path(&self, question_id: &str) -> PathBuf {
+        let file_stem: String = question_id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        self.dir.join(format!("{}.json", file_stem))
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{Difficulty, QuestionContent, QuestionType};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Caption under a question image when no template is configured
+pub const DEFAULT_QUESTION_CAPTION: &str = "You can do it! 💪\n{streak}";
+
+/// Caption of the explanations image when no template is configured
+pub const DEFAULT_EXPLANATIONS_CAPTION: &str = "Explanations 📖";
+
+/// Where a caption appears; each can have its own template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CaptionContext {
+    /// A question sent on request
+    Question,
+    Quiz,
+    Mock,
+    Challenge,
+    /// The question of the day
+    Daily,
+    /// Questions sent by the `send` command
+    Send,
+    /// A tutor's assignment, sent one question at a time
+    Assignment,
+    /// The explanations image sent after an answer
+    Explanations,
+}
+
+impl CaptionContext {
+    pub const ALL: [CaptionContext; 8] = [
+        CaptionContext::Question,
+        CaptionContext::Quiz,
+        CaptionContext::Mock,
+        CaptionContext::Challenge,
+        CaptionContext::Daily,
+        CaptionContext::Send,
+        CaptionContext::Assignment,
+        CaptionContext::Explanations,
+    ];
+
+    /// Key of the context in the `[captions]` table
+    pub fn name(self) -> &'static str {
+        match self {
+            CaptionContext::Question => "question",
+            CaptionContext::Quiz => "quiz",
+            CaptionContext::Mock => "mock",
+            CaptionContext::Challenge => "challenge",
+            CaptionContext::Daily => "daily",
+            CaptionContext::Send => "send",
+            CaptionContext::Assignment => "assignment",
+            CaptionContext::Explanations => "explanations",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|context| context.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Questions use the `question` template unless their own context has one
+    fn fallback(self) -> Option<Self> {
+        match self {
+            CaptionContext::Question | CaptionContext::Explanations => None,
+            _ => Some(CaptionContext::Question),
+        }
+    }
+
+    fn builtin(self) -> &'static str {
+        match self {
+            CaptionContext::Explanations => DEFAULT_EXPLANATIONS_CAPTION,
+            _ => DEFAULT_QUESTION_CAPTION,
+        }
+    }
+}
+
+/// What a caption's placeholders are filled with
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionValues {
+    /// `{type}`
+    pub question_type: QuestionType,
+    /// `{id}`
+    pub id: String,
+    /// `{difficulty}`; empty when the question has none
+    pub difficulty: Option<Difficulty>,
+    /// `{streak}`, e.g. "🔥 Day 7 streak!"; empty without one
+    pub streak: Option<String>,
+    /// `{index}` and `{total}`: the question's place in a quiz, mock test, challenge,
+    /// assignment or `send` batch, 1/1 for a single question
+    pub index: usize,
+    pub total: usize,
+}
+
+impl CaptionValues {
+    pub fn new(content: &QuestionContent, question_type: QuestionType) -> Self {
+        Self {
+            question_type,
+            id: content.id.clone(),
+            difficulty: Difficulty::of(content),
+            streak: None,
+            index: 1,
+            total: 1,
+        }
+    }
+
+    pub fn with_streak(mut self, streak: Option<String>) -> Self {
+        self.streak = streak;
+        self
+    }
+
+    pub fn with_position(mut self, index: usize, total: usize) -> Self {
+        self.index = index;
+        self.total = total;
+        self
+    }
+}
+
+/// Fills a template's placeholders; lines left blank, like `{streak}` without a
+/// streak, are dropped
+pub fn fill_caption(template: &str, values: &CaptionValues) -> String {
+    let filled = template
+        .replace("{type}", &values.question_type.to_string())
+        .replace("{id}", &values.id)
+        .replace(
+            "{difficulty}",
+            &values.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+        )
+        .replace("{streak}", values.streak.as_deref().unwrap_or_default())
+        .replace("{index}", &values.index.to_string())
+        .replace("{total}", &values.total.to_string());
+    filled
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Caption templates from the `[captions]` config table: one per context, and
+/// per-language variants in subtables such as `[captions.vi]`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, CaptionEntry>")]
+pub struct CaptionTemplates {
+    templates: BTreeMap<CaptionContext, String>,
+    /// Keyed by lowercase language code
+    languages: BTreeMap<String, BTreeMap<CaptionContext, String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CaptionEntry {
+    Template(String),
+    Language(BTreeMap<String, String>),
+}
+
+impl TryFrom<BTreeMap<String, CaptionEntry>> for CaptionTemplates {
+    type Error = String;
+
+    fn try_from(entries: BTreeMap<String, CaptionEntry>) -> Result<Self, Self::Error> {
+        let context = |name: &str| {
+            CaptionContext::parse(name).ok_or_else(|| {
+                let names: Vec<_> = CaptionContext::ALL.iter().map(|c| c.name()).collect();
+                format!(
+                    "unknown caption '{}', expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+        };
+        let mut captions = CaptionTemplates::default();
+        for (key, entry) in entries {
+            match entry {
+                CaptionEntry::Template(template) => {
+                    captions.templates.insert(context(&key)?, template);
+                }
+                CaptionEntry::Language(templates) => {
+                    let language = captions.languages.entry(key.to_lowercase()).or_default();
+                    for (name, template) in templates {
+                        language.insert(context(&name)?, template);
+                    }
+                }
+            }
+        }
+        Ok(captions)
+    }
+}
+
+impl CaptionTemplates {
+    /// Uses `template` for `context` in every language without its own
+    pub fn with_template(mut self, context: CaptionContext, template: impl Into<String>) -> Self {
+        self.templates.insert(context, template.into());
+        self
+    }
+
+    /// Uses `template` for `context` for users who picked `language`
+    pub fn with_language_template(
+        mut self,
+        language: &str,
+        context: CaptionContext,
+        template: impl Into<String>,
+    ) -> Self {
+        self.languages
+            .entry(language.to_lowercase())
+            .or_default()
+            .insert(context, template.into());
+        self
+    }
+
+    /// Languages with templates of their own
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.languages.keys().map(String::as_str)
+    }
+
+    /// The template for `context`, looked up in the user's language first: its own
+    /// context, then its `question` one, before the same in the default language
+    pub fn template(&self, context: CaptionContext, language: Option<&str>) -> &str {
+        let language = language.and_then(|language| self.languages.get(&language.to_lowercase()));
+        let contexts = std::iter::once(context).chain(context.fallback());
+        language
+            .into_iter()
+            .chain(std::iter::once(&self.templates))
+            .find_map(|templates| contexts.clone().find_map(|c| templates.get(&c)))
+            .map_or(context.builtin(), String::as_str)
+    }
+
+    pub fn render(
+        &self,
+        context: CaptionContext,
+        language: Option<&str>,
+        values: &CaptionValues,
+    ) -> String {
+        fill_caption(self.template(context, language), values)
+    }
+}
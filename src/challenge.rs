@@ -0,0 +1,272 @@
+use crate::quiz::{QuizAnswer, format_duration};
+use crate::{QuestionContent, QuestionType, parse_question_type};
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+/// Upper bound for "challenge @name N" so both players get a short match
+pub const MAX_CHALLENGE_QUESTIONS: usize = 10;
+
+/// How long a challenge may run before it is scored with the answers so far
+pub const CHALLENGE_TIME_LIMIT: Duration = Duration::from_secs(30 * 60);
+
+/// A parsed "challenge @name 5 ps"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengeRequest {
+    /// Display name (or user ID) of the friend being challenged, without the '@'
+    pub opponent: String,
+    pub count: usize,
+    /// Restricts the questions to one type; `None` mixes all supported types
+    pub question_type: Option<QuestionType>,
+}
+
+/// Parses "challenge @Minh 5 ps"; the count and type come last so names may contain spaces
+pub fn parse_challenge_command(text: &str) -> Option<ChallengeRequest> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    if !words.first()?.eq_ignore_ascii_case("challenge") {
+        return None;
+    }
+    words.remove(0);
+
+    let mut count = 5;
+    let mut question_type = None;
+    while let Some(word) = words.last() {
+        if let Ok(n) = word.parse::<usize>() {
+            count = n.clamp(1, MAX_CHALLENGE_QUESTIONS);
+        } else if let Some(t) = parse_question_type(word) {
+            question_type = Some(t);
+        } else {
+            break;
+        }
+        words.pop();
+    }
+
+    let opponent = words.join(" ");
+    let opponent = opponent.strip_prefix('@')?.trim();
+    if opponent.is_empty() {
+        return None;
+    }
+    Some(ChallengeRequest {
+        opponent: opponent.to_string(),
+        count,
+        question_type,
+    })
+}
+
+/// One side of a challenge, answering in their own private chat
+#[derive(Debug)]
+pub struct ChallengePlayer {
+    pub user_id: String,
+    pub name: String,
+    /// Private chat the questions go to
+    pub chat_id: String,
+    /// One entry per question dealt with; `None` when it couldn't be sent
+    pub answers: Vec<Option<QuizAnswer>>,
+    /// When the current question was delivered
+    pub question_started: Instant,
+    /// ID of the question on screen, so only a reply to it counts
+    pub current: Option<String>,
+}
+
+impl ChallengePlayer {
+    pub fn new(user_id: &str, name: &str, chat_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            chat_id: chat_id.to_string(),
+            answers: Vec::new(),
+            question_started: Instant::now(),
+            current: None,
+        }
+    }
+
+    pub fn correct(&self) -> usize {
+        self.answers
+            .iter()
+            .flatten()
+            .filter(|a| a.correct == Some(true))
+            .count()
+    }
+
+    pub fn answered(&self) -> usize {
+        self.answers.iter().flatten().count()
+    }
+
+    /// Time spent on the questions answered
+    pub fn total_time(&self) -> Duration {
+        self.answers.iter().flatten().map(|a| a.elapsed).sum()
+    }
+}
+
+/// A head-to-head where two users get the same questions
+#[derive(Debug)]
+pub struct ChallengeSession {
+    /// The challenger first, then their opponent
+    pub players: [ChallengePlayer; 2],
+    pub questions: Vec<(QuestionType, QuestionContent)>,
+    /// Chat the challenge was issued in, which also gets the result
+    pub origin_chat_id: String,
+    pub started: Instant,
+}
+
+impl ChallengeSession {
+    pub fn new(
+        challenger: ChallengePlayer,
+        opponent: ChallengePlayer,
+        questions: Vec<(QuestionType, QuestionContent)>,
+        origin_chat_id: &str,
+    ) -> Self {
+        Self {
+            players: [challenger, opponent],
+            questions,
+            origin_chat_id: origin_chat_id.to_string(),
+            started: Instant::now(),
+        }
+    }
+
+    /// The player answering in `chat_id`
+    pub fn player(&self, chat_id: &str) -> Option<&ChallengePlayer> {
+        self.players.iter().find(|p| p.chat_id == chat_id)
+    }
+
+    fn player_mut(&mut self, chat_id: &str) -> Option<&mut ChallengePlayer> {
+        self.players.iter_mut().find(|p| p.chat_id == chat_id)
+    }
+
+    pub fn has_user(&self, user_id: &str) -> bool {
+        self.players.iter().any(|p| p.user_id == user_id)
+    }
+
+    /// 1-based number and the question the player in `chat_id` gets next
+    pub fn next_question(
+        &self,
+        chat_id: &str,
+    ) -> Option<(usize, &(QuestionType, QuestionContent))> {
+        let index = self.player(chat_id)?.answers.len();
+        self.questions.get(index).map(|q| (index + 1, q))
+    }
+
+    /// Starts the clock on a question that reached the player
+    pub fn question_sent(&mut self, chat_id: &str, question_id: &str) {
+        if let Some(player) = self.player_mut(chat_id) {
+            player.current = Some(question_id.to_string());
+            player.question_started = Instant::now();
+        }
+    }
+
+    /// Whether `question_id` is the challenge question waiting for a reply in `chat_id`
+    pub fn is_current(&self, chat_id: &str, question_id: &str) -> bool {
+        self.player(chat_id)
+            .is_some_and(|p| p.current.as_deref() == Some(question_id))
+    }
+
+    /// Records the player's answer to their current question, timed from its delivery
+    pub fn record(&mut self, chat_id: &str, correct: Option<bool>) {
+        let Some(question_id) = self.player(chat_id).and_then(|p| p.current.clone()) else {
+            return;
+        };
+        let Some(question_type) = self
+            .questions
+            .iter()
+            .find(|(_, content)| content.id == question_id)
+            .map(|(t, _)| *t)
+        else {
+            return;
+        };
+        if let Some(player) = self.player_mut(chat_id) {
+            player.current = None;
+            player.answers.push(Some(QuizAnswer {
+                question_id,
+                question_type,
+                correct,
+                elapsed: player.question_started.elapsed(),
+            }));
+        }
+    }
+
+    /// Passes over the player's next question when it couldn't be sent
+    pub fn skip(&mut self, chat_id: &str) {
+        if let Some(player) = self.player_mut(chat_id) {
+            player.current = None;
+            player.answers.push(None);
+        }
+    }
+
+    pub fn player_finished(&self, chat_id: &str) -> bool {
+        self.player(chat_id)
+            .is_some_and(|p| p.answers.len() >= self.questions.len())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.players
+            .iter()
+            .all(|p| p.answers.len() >= self.questions.len())
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.started) >= CHALLENGE_TIME_LIMIT
+    }
+
+    /// The player with more correct answers, then more answered, then the faster
+    /// one; `None` for a draw
+    pub fn winner(&self) -> Option<&ChallengePlayer> {
+        let [a, b] = &self.players;
+        let ordering = a
+            .correct()
+            .cmp(&b.correct())
+            .then(a.answered().cmp(&b.answered()))
+            .then(b.total_time().cmp(&a.total_time()));
+        match ordering {
+            Ordering::Greater => Some(a),
+            Ordering::Less => Some(b),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// Side-by-side result: scores, total times and each question's outcome
+    pub fn summary(&self) -> String {
+        let [a, b] = &self.players;
+        let mut summary = format!("⚔️ Challenge result: {} vs {}\n", a.name, b.name);
+        match self.winner() {
+            Some(winner) => summary.push_str(&format!("🏆 {} wins!", winner.name)),
+            None => summary.push_str("🤝 It's a draw!"),
+        }
+        for player in &self.players {
+            summary.push_str(&format!(
+                "\n{}: {}/{} in {}",
+                player.name,
+                player.correct(),
+                self.questions.len(),
+                format_duration(player.total_time())
+            ));
+        }
+
+        summary.push('\n');
+        for (i, (_, content)) in self.questions.iter().enumerate() {
+            summary.push_str(&format!(
+                "\n{}. #{} {}: {} · {}: {}",
+                i + 1,
+                content.id,
+                a.name,
+                outcome(a, i),
+                b.name,
+                outcome(b, i)
+            ));
+        }
+        summary
+    }
+}
+
+/// "✅ 42s" for an answered question, "—" for one the player never got to
+fn outcome(player: &ChallengePlayer, index: usize) -> String {
+    match player.answers.get(index).and_then(Option::as_ref) {
+        Some(answer) => {
+            let mark = match answer.correct {
+                Some(true) => "✅",
+                Some(false) => "❌",
+                None => "❔",
+            };
+            format!("{} {}", mark, format_duration(answer.elapsed))
+        }
+        None => "—".to_string(),
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{QuestionType, parse_question_type, parse_timezone};
+use chrono::{NaiveTime, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// Prefix group members put before commands when not mentioning the bot
+pub const DEFAULT_COMMAND_PREFIX: &str = "/";
+
+/// Per-chat settings, changed with "config ..." commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatConfig {
+    /// Marks a group message as addressed to the bot, e.g. "/ps"
+    pub command_prefix: String,
+    /// Question types members may request; `None` allows every supported type
+    pub allowed_types: Option<Vec<QuestionType>>,
+    /// Local times during which the bot stays silent
+    pub mute_hours: Option<MuteHours>,
+    /// Timezone the mute hours are evaluated in
+    pub timezone: Tz,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            command_prefix: DEFAULT_COMMAND_PREFIX.to_string(),
+            allowed_types: None,
+            mute_hours: None,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+impl ChatConfig {
+    pub fn allows(&self, question_type: QuestionType) -> bool {
+        self.allowed_types
+            .as_ref()
+            .is_none_or(|types| types.contains(&question_type))
+    }
+
+    /// Whether the current local time falls within the mute hours
+    pub fn is_muted(&self) -> bool {
+        self.mute_hours
+            .is_some_and(|mute_hours| mute_hours.is_active(self.timezone))
+    }
+
+    /// Extracts the command from a message, or `None` if a group message isn't addressed to the bot
+    ///
+    /// Private chats accept every message; groups need the command prefix or an `@bot_name` mention.
+    pub fn command_text(
+        &self,
+        text: &str,
+        is_group: bool,
+        bot_name: Option<&str>,
+    ) -> Option<String> {
+        if let Some(rest) = text.strip_prefix(self.command_prefix.as_str()) {
+            return Some(rest.trim().to_string());
+        }
+        if let Some(bot_name) = bot_name {
+            let mention = format!("@{}", bot_name);
+            let found = text.char_indices().map(|(i, _)| i).find(|&i| {
+                text.get(i..i + mention.len())
+                    .is_some_and(|s| s.eq_ignore_ascii_case(&mention))
+            });
+            if let Some(pos) = found {
+                let mut rest = text.to_string();
+                rest.replace_range(pos..pos + mention.len(), "");
+                return Some(rest.trim().to_string());
+            }
+        }
+        (!is_group).then(|| text.to_string())
+    }
+
+    /// Applies a "config ..." command, returning the reply for the chat
+    pub fn apply_command(&mut self, args: &str) -> Result<String, String> {
+        let (setting, value) = args
+            .trim()
+            .split_once(char::is_whitespace)
+            .map(|(s, v)| (s, v.trim()))
+            .unwrap_or((args.trim(), ""));
+
+        match setting.to_lowercase().as_str() {
+            "" => {}
+            "prefix" if !value.is_empty() && !value.contains(char::is_whitespace) => {
+                self.command_prefix = value.to_string();
+            }
+            "types" if value.eq_ignore_ascii_case("all") => self.allowed_types = None,
+            "types" => {
+                let types = value
+                    .split(',')
+                    .map(|t| {
+                        parse_question_type(t)
+                            .ok_or_else(|| format!("Unknown question type '{}'", t.trim()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if types.is_empty() {
+                    return Err(CONFIG_USAGE.to_string());
+                }
+                self.allowed_types = Some(types);
+            }
+            "mute" if value.eq_ignore_ascii_case("off") => self.mute_hours = None,
+            "mute" => self.mute_hours = Some(value.parse()?),
+            "timezone" => self.timezone = parse_timezone(value).map_err(|e| e.to_string())?,
+            _ => return Err(CONFIG_USAGE.to_string()),
+        }
+        Ok(self.describe())
+    }
+
+    /// Human-readable summary of the settings
+    pub fn describe(&self) -> String {
+        let types = match &self.allowed_types {
+            Some(types) => types
+                .iter()
+                .map(|t| format!("{:?}", t))
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => "all".to_string(),
+        };
+        let mute = match self.mute_hours {
+            Some(mute_hours) => mute_hours.to_string(),
+            None => "off".to_string(),
+        };
+        format!(
+            "⚙️ Chat settings\nPrefix: {}\nQuestion types: {}\nMute hours: {}\nTimezone: {}",
+            self.command_prefix, types, mute, self.timezone
+        )
+    }
+}
+
+const CONFIG_USAGE: &str = "Usage: config prefix <p> | config types ps,ds|all | config mute 22:00-07:00|off | config timezone <Area/City>";
+
+/// Local times `start`–`end` during which a chat is muted or a user's quiet hours hold
+/// back proactive messages; may wrap midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MuteHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl MuteHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Whether the current time in `timezone` falls within the window
+    pub fn is_active(&self, timezone: Tz) -> bool {
+        self.contains(Utc::now().with_timezone(&timezone).time())
+    }
+}
+
+impl FromStr for MuteHours {
+    type Err = String;
+
+    /// Parses "22:00-07:00"; whole hours may be written "22-7"
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_time = |t: &str| {
+            let t = t.trim();
+            NaiveTime::parse_from_str(t, "%H:%M").ok().or_else(|| {
+                t.parse::<u32>()
+                    .ok()
+                    .and_then(|h| NaiveTime::from_hms_opt(h, 0, 0))
+            })
+        };
+        value
+            .split_once('-')
+            .and_then(|(start, end)| {
+                Some(MuteHours {
+                    start: parse_time(start)?,
+                    end: parse_time(end)?,
+                })
+            })
+            .filter(|hours| hours.start != hours.end)
+            .ok_or_else(|| "Hours look like '22:00-07:00' (24-hour clock)".to_string())
+    }
+}
+
+impl fmt::Display for MuteHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
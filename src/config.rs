@@ -0,0 +1,205 @@
+use crate::{
+    BotError, CaptionTemplates, ImageFormat, ImageHostKind, LogFormat, MathJaxSource, Platform,
+    QuestionType, RendererKind, Result, Theme, WebhookConfig,
+};
+use serde::Deserialize;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Settings loaded from a TOML file such as `gmat-bot.toml`.
+///
+/// Every field is optional; command line flags and environment variables
+/// take precedence over anything set here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log_level: Option<String>,
+    pub log_format: Option<LogFormat>,
+    pub database_path: Option<PathBuf>,
+    pub database_url: Option<String>,
+    pub topic_tags: Option<PathBuf>,
+    pub exclusions_path: Option<PathBuf>,
+    pub http_timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    pub platform: Option<Platform>,
+    pub bot_token: Option<BotTokens>,
+    pub bot_name: Option<String>,
+    pub hint_after_secs: Option<u64>,
+    pub concurrency: Option<usize>,
+    pub rate_limit: Option<u32>,
+    pub rate_limit_secs: Option<u64>,
+    pub admin_ids: Option<Vec<String>>,
+    pub drain_timeout_secs: Option<u64>,
+    pub streak_reminder_hour: Option<u32>,
+    pub weekly_report_hour: Option<u32>,
+    pub quiet_hours: Option<String>,
+    pub image_manifest: Option<PathBuf>,
+    pub manifest: Option<String>,
+    pub health_addr: Option<SocketAddr>,
+    pub health_unhealthy_after_secs: Option<u64>,
+    pub control_addr: Option<SocketAddr>,
+    pub control_token: Option<String>,
+    pub ocr: Option<bool>,
+    pub warm_cache: Option<usize>,
+    pub warm_questions: Option<Vec<String>>,
+    pub database_refresh_mins: Option<u64>,
+    pub storage_path: Option<String>,
+    pub recent_sends: Option<PathBuf>,
+    pub max_attempts: Option<u32>,
+    pub api_url: Option<String>,
+    pub poll_timeout_secs: Option<u64>,
+    pub api_timeout_secs: Option<u64>,
+    pub renderer: Option<RendererKind>,
+    pub render_width: Option<u32>,
+    pub render_quality: Option<u32>,
+    pub render_format: Option<ImageFormat>,
+    pub render_scale: Option<f64>,
+    pub max_image_height: Option<u32>,
+    pub theme: Option<Theme>,
+    pub template_dir: Option<PathBuf>,
+    pub mathjax_source: Option<MathJaxSource>,
+    pub mathjax_dir: Option<PathBuf>,
+    pub output_dir: Option<String>,
+    pub cache_dir: Option<String>,
+    pub cache_ttl_hours: Option<u64>,
+    pub enrich: Option<bool>,
+    pub stats_ttl_hours: Option<u64>,
+    pub render_timeout_secs: Option<u64>,
+    pub keep_html: Option<bool>,
+    pub question_type: Option<QuestionType>,
+    pub count: Option<usize>,
+    pub topic: Option<String>,
+    pub show_explanations: Option<bool>,
+    pub user_ids: Option<Vec<String>>,
+    pub fetch_concurrency: Option<usize>,
+    pub render_concurrency: Option<usize>,
+    pub upload_concurrency: Option<usize>,
+    pub send_rate: Option<f64>,
+    pub shuffle_answers: Option<bool>,
+    pub captions: Option<CaptionTemplates>,
+    /// `[[webhooks]]` entries bot events are posted to
+    pub webhooks: Vec<WebhookConfig>,
+    pub schedule: ScheduleSettings,
+    pub daily: DailySettings,
+    pub progression: ProgressionSettings,
+    pub hosting: HostingSettings,
+    pub proxy: ProxyUrls,
+}
+
+/// `bot_token`: one token, or a list of them to run several bots from one `serve`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum BotTokens {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl BotTokens {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            BotTokens::One(token) => vec![token.clone()],
+            BotTokens::Many(tokens) => tokens.clone(),
+        }
+    }
+}
+
+/// The `[schedule]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleSettings {
+    /// Cron expression, e.g. "0 8 * * *"
+    pub cron: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// The `[daily]` table, for `serve --daily`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DailySettings {
+    pub enabled: Option<bool>,
+    pub question_hour: Option<u32>,
+    pub answer_hour: Option<u32>,
+    pub timezone: Option<String>,
+}
+
+/// The `[progression]` table, for `serve --progression`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProgressionSettings {
+    pub enabled: Option<bool>,
+    pub accuracy: Option<u32>,
+    pub answers: Option<u32>,
+}
+
+/// The `[hosting]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HostingSettings {
+    pub image_host: Option<ImageHostKind>,
+    pub github: GitHubSettings,
+    pub s3: S3Settings,
+    pub imgur: ImgurSettings,
+    pub serve_images: Option<SocketAddr>,
+    pub serve_images_url: Option<String>,
+}
+
+/// The `[hosting.github]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitHubSettings {
+    pub repo: Option<String>,
+    pub token: Option<String>,
+    pub app_id: Option<String>,
+    pub app_installation_id: Option<u64>,
+    pub app_private_key: Option<PathBuf>,
+    pub release_id: Option<u64>,
+    pub create_release: Option<bool>,
+    pub use_latest_release: Option<bool>,
+    pub release_tag: Option<String>,
+    pub max_assets: Option<usize>,
+}
+
+/// The `[hosting.s3]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3Settings {
+    pub endpoint: Option<String>,
+    pub bucket: Option<String>,
+    pub region: Option<String>,
+    pub public_url: Option<String>,
+    pub prefix: Option<String>,
+    pub presign_ttl_hours: Option<u64>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+}
+
+/// The `[hosting.imgur]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImgurSettings {
+    pub client_id: Option<String>,
+}
+
+/// The `[proxy]` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyUrls {
+    pub url: Option<String>,
+    pub chat: Option<String>,
+    pub hosting: Option<String>,
+    pub database: Option<String>,
+}
+
+impl Config {
+    /// Reads and parses a TOML config file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| {
+            BotError::Config(format!("Cannot read config file {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text)
+            .map_err(|e| BotError::Config(format!("Invalid config file {}: {}", path.display(), e)))
+    }
+}
@@ -0,0 +1,259 @@
+use crate::admin::USAGE_WINDOW_DAYS;
+use crate::{BotError, Result};
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{DefaultBodyLimit, Query, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{Method, Uri};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower_http::timeout::RequestBodyTimeoutLayer;
+use tracing::{debug, info};
+
+/// Largest request body read; bigger requests get 413
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// How long a client gets to send its body before the request is dropped; handling
+/// it has no limit, since a broadcast reaches every user
+const BODY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the control API listens and the token callers must present
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlApi {
+    pub addr: SocketAddr,
+    /// Expected as `Authorization: Bearer <token>`; only optional on a loopback address
+    pub token: Option<String>,
+}
+
+impl ControlApi {
+    /// Rejects an API reachable from other machines without a token, since it can
+    /// message every user
+    pub fn validate(&self) -> Result<()> {
+        if self.token.is_none() && !self.addr.ip().is_loopback() {
+            return Err(BotError::Config(format!(
+                "The control API on {} needs --control-token (or CONTROL_API_TOKEN) unless it listens on localhost",
+                self.addr
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether an `Authorization` header value lets the caller in
+    pub fn authorizes(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let presented = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // Digests of equal length, so the comparison time says nothing about the token
+        Sha256::digest(presented.trim().as_bytes()) == Sha256::digest(token.as_bytes())
+    }
+}
+
+/// What a control API call asks the bot to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlRequest {
+    /// `POST /send {"user_id", "question_id"}`
+    Send {
+        user_id: String,
+        question_id: String,
+    },
+    /// `GET /stats?days=N`; recent means the last [`USAGE_WINDOW_DAYS`] by default
+    Stats { days: i64 },
+    /// `POST /broadcast {"text"}`; a question ID sends that question
+    Broadcast { text: String },
+    /// `GET /sessions`
+    Sessions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SendBody {
+    user_id: String,
+    question_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BroadcastBody {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    days: Option<i64>,
+}
+
+/// A control API reply: its status and JSON body
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlResponse {
+    pub status: StatusCode,
+    pub body: Value,
+}
+
+impl ControlResponse {
+    pub fn ok(body: Value) -> Self {
+        Self {
+            status: StatusCode::OK,
+            body,
+        }
+    }
+
+    /// `{"error": message}` with `status`
+    pub fn error(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+impl IntoResponse for ControlResponse {
+    fn into_response(self) -> Response {
+        let status = axum::http::StatusCode::from_u16(self.status.as_u16())
+            .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        (status, axum::Json(self.body)).into_response()
+    }
+}
+
+/// Turns a `/send` body into its request, or the 4xx reply for a bad one
+fn send_request(
+    body: Result<Json<SendBody>, JsonRejection>,
+) -> Result<ControlRequest, ControlResponse> {
+    let Json(body) = body.map_err(rejected)?;
+    if body.user_id.trim().is_empty() || body.question_id.trim().is_empty() {
+        return Err(ControlResponse::error(
+            StatusCode::BAD_REQUEST,
+            "user_id and question_id can't be empty",
+        ));
+    }
+    Ok(ControlRequest::Send {
+        user_id: body.user_id.trim().to_string(),
+        question_id: body.question_id.trim().to_string(),
+    })
+}
+
+fn stats_request(
+    query: Result<Query<StatsQuery>, QueryRejection>,
+) -> Result<ControlRequest, ControlResponse> {
+    query
+        .ok()
+        .and_then(|Query(query)| match query.days {
+            Some(days) if days <= 0 => None,
+            days => Some(ControlRequest::Stats {
+                days: days.unwrap_or(USAGE_WINDOW_DAYS),
+            }),
+        })
+        .ok_or_else(|| {
+            ControlResponse::error(StatusCode::BAD_REQUEST, "days must be a positive number")
+        })
+}
+
+fn broadcast_request(
+    body: Result<Json<BroadcastBody>, JsonRejection>,
+) -> Result<ControlRequest, ControlResponse> {
+    let Json(body) = body.map_err(rejected)?;
+    if body.text.trim().is_empty() {
+        return Err(ControlResponse::error(
+            StatusCode::BAD_REQUEST,
+            "text can't be empty",
+        ));
+    }
+    Ok(ControlRequest::Broadcast {
+        text: body.text.trim().to_string(),
+    })
+}
+
+/// The reply for a body axum couldn't read: too large, timed out or not the JSON expected
+fn rejected(rejection: JsonRejection) -> ControlResponse {
+    let status =
+        StatusCode::from_u16(rejection.status().as_u16()).unwrap_or(StatusCode::BAD_REQUEST);
+    ControlResponse::error(status, rejection.body_text())
+}
+
+/// Serves the control API on `api.addr` until dropped, passing each authorized
+/// request to `handle`. Each connection gets its own task, so a long broadcast
+/// doesn't hold up the calls made while it runs
+pub async fn serve_control<F, Fut>(api: &ControlApi, handle: F) -> Result<()>
+where
+    F: Fn(ControlRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = ControlResponse> + Send,
+{
+    let listener = TcpListener::bind(api.addr).await?;
+    info!("Control API listening on http://{}", api.addr);
+    let app = Router::new()
+        .route("/send", {
+            let handle = handle.clone();
+            post(move |body| dispatch(handle, send_request(body)))
+        })
+        .route("/stats", {
+            let handle = handle.clone();
+            get(move |query| dispatch(handle, stats_request(query)))
+        })
+        .route("/broadcast", {
+            let handle = handle.clone();
+            post(move |body| dispatch(handle, broadcast_request(body)))
+        })
+        .route(
+            "/sessions",
+            get(move || dispatch(handle, Ok(ControlRequest::Sessions))),
+        )
+        .method_not_allowed_fallback(async |method: Method, uri: Uri| {
+            ControlResponse::error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                format!("{} is not allowed on {}", method, uri.path()),
+            )
+        })
+        .fallback(async || ControlResponse::error(StatusCode::NOT_FOUND, "not found"))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(api.clone()),
+            require_token,
+        ))
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .layer(RequestBodyTimeoutLayer::new(BODY_TIMEOUT));
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Turns away callers without the bearer token before any route runs
+async fn require_token(
+    State(api): State<Arc<ControlApi>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !api.authorizes(authorization) {
+        return ControlResponse::error(StatusCode::UNAUTHORIZED, "missing or wrong bearer token")
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn dispatch<F, Fut>(
+    handle: F,
+    request: Result<ControlRequest, ControlResponse>,
+) -> ControlResponse
+where
+    F: Fn(ControlRequest) -> Fut,
+    Fut: Future<Output = ControlResponse>,
+{
+    match request {
+        Ok(request) => {
+            debug!(?request, "Control API request");
+            handle(request).await
+        }
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{ExclusionList, GmatDatabase, QuestionType};
+use chrono::{Datelike, NaiveDate};
+use chrono_tz::Tz;
+
+/// When the question of the day is posted and answered, in local hours
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailySchedule {
+    /// Hour (0–23) the question goes out to subscribed chats
+    pub question_hour: u32,
+    /// Hour (0–23) the answer and explanations follow
+    pub answer_hour: u32,
+    /// Timezone both hours, and the date that picks the question, are evaluated in
+    pub timezone: Tz,
+}
+
+impl Default for DailySchedule {
+    fn default() -> Self {
+        Self {
+            question_hour: 8,
+            answer_hour: 20,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+/// The two posts made each day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyPost {
+    Question,
+    Answer,
+}
+
+impl DailyPost {
+    /// Name the post is recorded under so a restart doesn't repeat it
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DailyPost::Question => "question",
+            DailyPost::Answer => "answer",
+        }
+    }
+}
+
+/// The question of the day: the same for every chat on a given date, and a
+/// different one each day until the whole bank has been used
+pub fn daily_question(
+    database: &GmatDatabase,
+    exclusions: &ExclusionList,
+    date: NaiveDate,
+) -> Option<(QuestionType, String)> {
+    let mut questions: Vec<(QuestionType, String)> = database
+        .get_all_questions()
+        .into_iter()
+        .flat_map(|(question_type, ids)| ids.iter().map(move |id| (question_type, id.clone())))
+        .filter(|(_, id)| !exclusions.contains(id))
+        .collect();
+    if questions.is_empty() {
+        return None;
+    }
+    // HashMap order varies between runs, so sort before indexing
+    questions.sort_by(|a, b| {
+        a.1.cmp(&b.1)
+            .then_with(|| a.0.to_string().cmp(&b.0.to_string()))
+    });
+
+    // Stepping by a large prime visits every question before repeating unless
+    // the bank size is a multiple of it
+    const STEP: u64 = 7919;
+    let day = date.num_days_from_ce().unsigned_abs() as u64;
+    let index = (day * STEP) % questions.len() as u64;
+    Some(questions.swap_remove(index as usize))
+}
@@ -0,0 +1,84 @@
+use crate::{QuestionContent, QuestionType, parse_question_type};
+use clap::ValueEnum;
+use std::fmt;
+
+/// Coarse difficulty band derived from GMAT Club's score-level tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Difficulty {
+    /// Below the 555 level
+    Easy,
+    /// 555 to 655 level
+    Medium,
+    /// 655 level and above
+    Hard,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+impl Difficulty {
+    /// Maps a score level such as 605 (from "605-655 Level") to a band
+    pub fn from_level(level: u32) -> Self {
+        match level {
+            0..555 => Difficulty::Easy,
+            555..655 => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    /// Difficulty of a question, from its `difficulty` field or a "NNN Level" tag in its text
+    pub fn of(content: &QuestionContent) -> Option<Self> {
+        content
+            .difficulty
+            .iter()
+            .chain(std::iter::once(&content.question))
+            .chain(&content.explanations)
+            .find_map(|text| parse_level(text))
+            .map(Self::from_level)
+    }
+}
+
+/// Parses a difficulty word like "hard" or "Medium"
+pub fn parse_difficulty(text: &str) -> Option<Difficulty> {
+    match text.trim().to_lowercase().as_str() {
+        "easy" => Some(Difficulty::Easy),
+        "medium" => Some(Difficulty::Medium),
+        "hard" => Some(Difficulty::Hard),
+        _ => None,
+    }
+}
+
+/// Parses a request like "ps hard" into a question type and difficulty
+pub fn parse_difficulty_request(text: &str) -> Option<(QuestionType, Difficulty)> {
+    let mut words = text.split_whitespace();
+    let question_type = parse_question_type(words.next()?)?;
+    let difficulty = parse_difficulty(words.next()?)?;
+    if words.next().is_some() {
+        return None;
+    }
+    Some((question_type, difficulty))
+}
+
+/// Finds the lower bound of the first "505-555 Level" / "805+ Level" style tag
+fn parse_level(text: &str) -> Option<u32> {
+    let lower = text.to_lowercase();
+    lower.match_indices("level").find_map(|(pos, _)| {
+        // The score range sits just before the word, e.g. "sub 505 level" or "655-705 level"
+        let window = &lower[lower[..pos]
+            .char_indices()
+            .rev()
+            .nth(15)
+            .map_or(0, |(i, _)| i)..pos];
+        window
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|digits| digits.len() == 3)
+            .and_then(|digits| digits.parse().ok())
+    })
+}
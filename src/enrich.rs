@@ -0,0 +1,216 @@
+use crate::http::default_client;
+use crate::{QuestionContent, Result, strip_html_tags};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How long fetched stats are kept unless configured otherwise; they drift as more people answer
+pub const DEFAULT_STATS_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Forum pages are fetched as a browser would, since some reject unknown clients
+const PAGE_USER_AGENT: &str = "Mozilla/5.0 (compatible; gmat-zalo-bot)";
+
+/// Stats are optional, so a slow forum page is given up on early
+const PAGE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Community stats from a question's GMAT Club thread
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuestionStats {
+    /// Share of timed sessions answered correctly, e.g. 65
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent_correct: Option<u8>,
+    /// GMAT Club's difficulty rating, 0–100
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty_percent: Option<u8>,
+    /// Sessions the stats are based on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<u32>,
+    /// Thread tags, e.g. "Geometry" or "655-705 Level"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl QuestionStats {
+    pub fn is_empty(&self) -> bool {
+        *self == QuestionStats::default()
+    }
+
+    /// The score-level tag, e.g. "655-705 Level"
+    pub fn level_tag(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .map(String::as_str)
+            .find(|tag| tag.ends_with(" Level"))
+    }
+}
+
+/// Reads the stats block and tags of a GMAT Club thread page: "Difficulty: 95% (hard)",
+/// "Question Stats: 65% (01:42) correct 35% (02:03) wrong based on 1234 sessions" and
+/// links to tag searches
+pub fn parse_question_stats(html: &str) -> QuestionStats {
+    let text = strip_html_tags(html)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let after = |marker: &str| text.find(marker).map(|pos| &text[pos + marker.len()..]);
+    let leading_number = |text: &str| {
+        let digits: String = text
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == ',')
+            .filter(char::is_ascii_digit)
+            .collect();
+        digits.parse::<u32>().ok()
+    };
+    let leading_percent = |text: &str| {
+        let trimmed = text.trim_start();
+        let digits = trimmed.find(|c: char| !c.is_ascii_digit())?;
+        trimmed[digits..]
+            .starts_with('%')
+            .then(|| trimmed[..digits].parse::<u8>().ok())
+            .flatten()
+            .filter(|percent| *percent <= 100)
+    };
+
+    let mut tags = Vec::new();
+    for (pos, _) in html.match_indices("tag_id=") {
+        let Some(text_start) = html[pos..].find('>').map(|i| pos + i + 1) else {
+            continue;
+        };
+        let Some(text_end) = html[text_start..].find("</a>").map(|i| text_start + i) else {
+            continue;
+        };
+        let tag = strip_html_tags(&html[text_start..text_end])
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    QuestionStats {
+        percent_correct: after("Question Stats:").and_then(leading_percent),
+        difficulty_percent: after("Difficulty:").and_then(leading_percent),
+        sessions: after("based on").and_then(leading_number),
+        tags,
+    }
+}
+
+/// Adds community stats to questions, from their source pages and a disk cache of
+/// what was found there, one JSON file per question ID
+pub struct QuestionEnricher {
+    client: reqwest::Client,
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl QuestionEnricher {
+    /// Caches stats in `dir`; entries older than `ttl` are fetched again
+    pub fn new(dir: impl AsRef<Path>, ttl: Duration) -> Self {
+        Self {
+            client: default_client(),
+            dir: dir.as_ref().to_path_buf(),
+            ttl,
+        }
+    }
+
+    /// Fetches source pages through `client`, e.g. the database route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Stats for a question, cached or fetched from `content.src`; `None` when its page
+    /// has none or can't be fetched, which is logged and retried on the next call
+    pub async fn stats(&self, content: &QuestionContent) -> Option<QuestionStats> {
+        if !content.src.starts_with("http://") && !content.src.starts_with("https://") {
+            return None;
+        }
+        let stats = match self.cached(&content.id) {
+            Some(stats) => stats,
+            None => {
+                let stats = match self.fetch(&content.src).await {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        warn!("Failed to fetch stats for question {}: {}", content.id, e);
+                        return None;
+                    }
+                };
+                // Pages without stats are remembered too, so they aren't fetched on every send
+                if let Err(e) = self.put(&content.id, &stats) {
+                    warn!("Failed to cache stats for question {}: {}", content.id, e);
+                }
+                stats
+            }
+        };
+        (!stats.is_empty()).then_some(stats)
+    }
+
+    /// Attaches stats to `content`, filling in its difficulty from the level tag when it has none
+    pub async fn enrich(&self, content: &mut QuestionContent) {
+        if content.stats.is_some() {
+            return;
+        }
+        let Some(stats) = self.stats(content).await else {
+            return;
+        };
+        if content.difficulty.is_none() {
+            content.difficulty = stats.level_tag().map(str::to_string);
+        }
+        content.stats = Some(stats);
+    }
+
+    /// A copy of `content` with its stats attached
+    pub async fn enriched(&self, content: &QuestionContent) -> QuestionContent {
+        let mut content = content.clone();
+        self.enrich(&mut content).await;
+        content
+    }
+
+    async fn fetch(&self, url: &str) -> Result<QuestionStats> {
+        debug!("Fetching question stats from {}", url);
+        let html = self
+            .client
+            .get(url)
+            .header(reqwest::header::USER_AGENT, PAGE_USER_AGENT)
+            .timeout(PAGE_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(parse_question_stats(&html))
+    }
+
+    fn cached(&self, question_id: &str) -> Option<QuestionStats> {
+        let path = self.entry_path(question_id);
+        let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+        if age > self.ttl {
+            return None;
+        }
+        let json = fs::read_to_string(&path).ok()?;
+        serde_json::from_str(&json)
+            .inspect_err(|e| warn!("Ignoring unreadable stats {}: {}", path.display(), e))
+            .ok()
+    }
+
+    fn put(&self, question_id: &str, stats: &QuestionStats) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(question_id);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(stats)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn entry_path(&self, question_id: &str) -> PathBuf {
+        let file_stem: String = question_id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        self.dir.join(format!("{}.json", file_stem))
+    }
+}
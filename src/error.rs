@@ -0,0 +1,262 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+pub type Result<T, E = BotError> = std::result::Result<T, E>;
+
+/// Everything that can go wrong while fetching, rendering, hosting or sending a question
+#[derive(Debug, Error)]
+pub enum BotError {
+    /// The Zalo Bot API rejected a request or returned an unexpected payload
+    #[error("{context}: {}", describe_response(.status, .body))]
+    ZaloApi {
+        context: String,
+        status: Option<StatusCode>,
+        body: String,
+    },
+
+    /// Zalo rejected the bot token
+    #[error("{context}: invalid bot token - {description}")]
+    ZaloInvalidToken {
+        context: String,
+        description: String,
+    },
+
+    /// The chat doesn't exist or the bot can't reach it
+    #[error("{context}: chat not found - {description}")]
+    ZaloChatNotFound {
+        context: String,
+        description: String,
+    },
+
+    /// The user blocked the bot, so it can't message them
+    #[error("{context}: user blocked the bot - {description}")]
+    ZaloUserBlocked {
+        context: String,
+        description: String,
+    },
+
+    /// The Telegram Bot API rejected a request or returned an unexpected payload
+    #[error("{context}: {}", describe_response(.status, .body))]
+    TelegramApi {
+        context: String,
+        status: Option<StatusCode>,
+        body: String,
+    },
+
+    /// Telegram rejected the bot token
+    #[error("{context}: invalid bot token - {description}")]
+    TelegramInvalidToken {
+        context: String,
+        description: String,
+    },
+
+    /// The Discord API rejected a request or returned an unexpected payload
+    #[error("{context}: {}", describe_response(.status, .body))]
+    DiscordApi {
+        context: String,
+        status: Option<StatusCode>,
+        body: String,
+    },
+
+    /// Discord rejected the bot token
+    #[error("{context}: invalid bot token - {description}")]
+    DiscordInvalidToken {
+        context: String,
+        description: String,
+    },
+
+    /// A GitHub release or asset request failed
+    #[error("{context}: {}", describe_response(.status, .body))]
+    GitHubUpload {
+        context: String,
+        status: Option<StatusCode>,
+        body: String,
+    },
+
+    /// An S3-compatible bucket rejected an upload
+    #[error("S3 upload failed: {status} - {body}")]
+    S3Upload { status: StatusCode, body: String },
+
+    /// Imgur rejected an upload
+    #[error("Imgur upload failed: {status} - {body}")]
+    ImgurUpload { status: StatusCode, body: String },
+
+    /// A webhook endpoint rejected an event
+    #[error("Webhook {url} failed: {status} - {body}")]
+    Webhook {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+
+    /// HTML-to-image rendering failed
+    #[error("{0}")]
+    Render(String),
+
+    /// A renderer process hung and was killed
+    #[error("{program} didn't finish within {timeout:?} and was stopped")]
+    RenderTimeout {
+        program: &'static str,
+        timeout: std::time::Duration,
+    },
+
+    /// Reading text out of an image failed
+    #[error("{0}")]
+    Ocr(String),
+
+    /// The question database returned an error for a question
+    #[error("Failed to fetch question {question_id}: {status}")]
+    QuestionFetch {
+        question_id: String,
+        status: StatusCode,
+    },
+
+    /// Missing or invalid configuration
+    #[error("{0}")]
+    Config(String),
+
+    /// A retried operation kept failing; `source` is the last error
+    #[error("{operation} failed after {attempts} attempt(s): {source}")]
+    Retry {
+        operation: String,
+        attempts: u32,
+        #[source]
+        source: Box<BotError>,
+    },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] rusqlite::Error),
+
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+impl BotError {
+    pub(crate) fn zalo(
+        context: impl Into<String>,
+        status: Option<StatusCode>,
+        body: impl Into<String>,
+    ) -> Self {
+        BotError::ZaloApi {
+            context: context.into(),
+            status,
+            body: body.into(),
+        }
+    }
+
+    pub(crate) fn telegram(
+        context: impl Into<String>,
+        status: Option<StatusCode>,
+        body: impl Into<String>,
+    ) -> Self {
+        BotError::TelegramApi {
+            context: context.into(),
+            status,
+            body: body.into(),
+        }
+    }
+
+    pub(crate) fn discord(
+        context: impl Into<String>,
+        status: Option<StatusCode>,
+        body: impl Into<String>,
+    ) -> Self {
+        BotError::DiscordApi {
+            context: context.into(),
+            status,
+            body: body.into(),
+        }
+    }
+
+    pub(crate) fn github(
+        context: impl Into<String>,
+        status: Option<StatusCode>,
+        body: impl Into<String>,
+    ) -> Self {
+        BotError::GitHubUpload {
+            context: context.into(),
+            status,
+            body: body.into(),
+        }
+    }
+
+    /// HTTP status attached to the error, if it came from an HTTP response
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            BotError::ZaloApi { status, .. }
+            | BotError::TelegramApi { status, .. }
+            | BotError::DiscordApi { status, .. }
+            | BotError::GitHubUpload { status, .. } => *status,
+            BotError::S3Upload { status, .. }
+            | BotError::ImgurUpload { status, .. }
+            | BotError::Webhook { status, .. }
+            | BotError::QuestionFetch { status, .. } => Some(*status),
+            BotError::Http(e) => e.status(),
+            BotError::Retry { source, .. } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// The error behind any retries
+    pub fn root(&self) -> &BotError {
+        match self {
+            BotError::Retry { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Whether the chat platform rejected the bot token, which no retry will fix
+    pub fn is_invalid_token(&self) -> bool {
+        matches!(
+            self.root(),
+            BotError::ZaloInvalidToken { .. }
+                | BotError::TelegramInvalidToken { .. }
+                | BotError::DiscordInvalidToken { .. }
+        )
+    }
+
+    /// Whether the error is a network timeout, which is expected during long polling
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            BotError::Http(e) => e.is_timeout(),
+            BotError::Retry { source, .. } => source.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Whether trying again might succeed: connection problems, timeouts,
+    /// rate limiting and server errors
+    pub fn is_retryable(&self) -> bool {
+        if let BotError::Http(e) = self
+            && (e.is_connect() || e.is_timeout())
+        {
+            return true;
+        }
+        match self {
+            BotError::Retry { .. } => false,
+            // A hang is usually a one-off, like a MathJax load that never finished
+            BotError::RenderTimeout { .. } => true,
+            _ => self.status().is_some_and(|status| {
+                status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::REQUEST_TIMEOUT
+                    || status.is_server_error()
+            }),
+        }
+    }
+}
+
+fn describe_response(status: &Option<StatusCode>, body: &str) -> String {
+    match status {
+        Some(status) => format!("{} - {}", status, body),
+        None => body.to_string(),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{BotError, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Default location of the exclusion list, `~/.gmat-zalo-bot/excluded.txt`
+pub fn default_exclusions_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".gmat-zalo-bot")
+        .join("excluded.txt")
+}
+
+/// Reads question IDs separated by newlines, commas or spaces, each optionally written
+/// "#1234", so `verify --format ids` and `reports --format ids` output can be used as is
+pub fn parse_id_list(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|id| id.trim_start_matches('#'))
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Questions known to be broken, which random picks leave out; saved to a text file
+/// with one ID per line whenever it changes, when loaded from one
+#[derive(Debug, Default)]
+pub struct ExclusionList {
+    path: Option<PathBuf>,
+    ids: RwLock<BTreeSet<String>>,
+}
+
+impl ExclusionList {
+    pub fn new(ids: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            path: None,
+            ids: RwLock::new(ids.into_iter().collect()),
+        }
+    }
+
+    /// Loads the list at `path` and saves there from now on; a missing file is an empty list
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let ids = match fs::read_to_string(path) {
+            Ok(text) => parse_id_list(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(BotError::Config(format!(
+                    "Failed to read exclusion list {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..Self::new(ids)
+        })
+    }
+
+    pub fn contains(&self, question_id: &str) -> bool {
+        self.ids.read().unwrap().contains(question_id)
+    }
+
+    /// Excluded IDs, sorted
+    pub fn ids(&self) -> Vec<String> {
+        self.ids.read().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Excludes `question_ids`; returns the ones that weren't excluded already
+    pub fn add(&self, question_ids: impl IntoIterator<Item = String>) -> Result<Vec<String>> {
+        let mut ids = self.ids.write().unwrap();
+        let added: Vec<String> = question_ids
+            .into_iter()
+            .filter(|id| ids.insert(id.clone()))
+            .collect();
+        if !added.is_empty() {
+            self.save(&ids)?;
+        }
+        Ok(added)
+    }
+
+    /// Lets `question_ids` be picked again; returns the ones that were excluded
+    pub fn remove(&self, question_ids: impl IntoIterator<Item = String>) -> Result<Vec<String>> {
+        let mut ids = self.ids.write().unwrap();
+        let removed: Vec<String> = question_ids
+            .into_iter()
+            .filter(|id| ids.remove(id))
+            .collect();
+        if !removed.is_empty() {
+            self.save(&ids)?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self, ids: &BTreeSet<String>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text: String = ids.iter().map(|id| format!("{}\n", id)).collect();
+        // Write then rename so a crash mid-write keeps the previous copy
+        let tmp_path = path.with_extension("txt.tmp");
+        fs::write(&tmp_path, text)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// An admin's change to the exclusion list, with the question IDs as typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusionCommand<'a> {
+    Add(&'a str),
+    Remove(&'a str),
+    List,
+}
+
+/// Parses what follows "exclude": "add <ids>", "remove <ids>", or "list" or nothing
+pub fn parse_exclusion_command(args: &str) -> Option<ExclusionCommand<'_>> {
+    let args = args.trim();
+    let (action, ids) = args
+        .split_once(char::is_whitespace)
+        .map_or((args, ""), |(action, ids)| (action, ids.trim()));
+    match (action.to_lowercase().as_str(), ids) {
+        ("" | "list", "") => Some(ExclusionCommand::List),
+        ("add", ids) if !ids.is_empty() => Some(ExclusionCommand::Add(ids)),
+        ("remove", ids) if !ids.is_empty() => Some(ExclusionCommand::Remove(ids)),
+        _ => None,
+    }
+}
+
+/// Runs an exclusion command and describes the outcome as a chat reply
+pub fn run_exclusion_command(exclusions: &ExclusionList, command: ExclusionCommand<'_>) -> String {
+    let numbered = |ids: &[String]| {
+        ids.iter()
+            .map(|id| format!("#{}", id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let changed = match command {
+        ExclusionCommand::List if exclusions.is_empty() => {
+            return "✅ No questions are excluded.".to_string();
+        }
+        ExclusionCommand::List => {
+            return format!(
+                "🚫 Excluded questions ({}): {}",
+                exclusions.len(),
+                numbered(&exclusions.ids())
+            );
+        }
+        ExclusionCommand::Add(ids) => exclusions.add(parse_id_list(ids)),
+        ExclusionCommand::Remove(ids) => exclusions.remove(parse_id_list(ids)),
+    };
+    match (command, changed) {
+        (_, Err(e)) => format!("❌ Failed to save the exclusion list: {}", e),
+        (ExclusionCommand::Add(_), Ok(added)) if added.is_empty() => {
+            "🤷 Those questions are already excluded.".to_string()
+        }
+        (ExclusionCommand::Add(_), Ok(added)) => format!(
+            "🚫 {} won't be picked anymore ({} excluded).",
+            numbered(&added),
+            exclusions.len()
+        ),
+        (_, Ok(removed)) if removed.is_empty() => "🤷 Those questions aren't excluded.".to_string(),
+        (_, Ok(removed)) => format!(
+            "✅ {} can be picked again ({} excluded).",
+            numbered(&removed),
+            exclusions.len()
+        ),
+    }
+}
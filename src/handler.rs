@@ -0,0 +1,83 @@
+use crate::{ChatMessage, Result, ZaloBot};
+use std::sync::Arc;
+use tracing::error;
+
+/// Priority of a handler that doesn't pick one: after higher ones, before the built-in commands
+pub const DEFAULT_HANDLER_PRIORITY: i32 = 0;
+
+/// Custom command added by a program embedding the bot.
+///
+/// Handlers see text messages addressed to the bot once the ban list, group
+/// mentions and the rate limit have been checked, highest priority first.
+/// Those with a priority of 0 or more run before any built-in command; those
+/// below 0 only see messages no built-in command understood, in place of the
+/// help reply.
+#[async_trait::async_trait]
+pub trait MessageHandler: Send + Sync {
+    /// Handles `command`, the message text with any @mention or prefix removed.
+    /// Returns `false` to leave the message to the next handler and the bot itself
+    async fn handle(&self, bot: &ZaloBot, message: &ChatMessage, command: &str) -> Result<bool>;
+
+    fn priority(&self) -> i32 {
+        DEFAULT_HANDLER_PRIORITY
+    }
+}
+
+/// Registered handlers, kept in the order they run
+#[derive(Default, Clone)]
+pub struct HandlerRegistry {
+    handlers: Vec<Arc<dyn MessageHandler>>,
+}
+
+impl HandlerRegistry {
+    /// Adds `handler` after every handler of the same or higher priority
+    pub fn register(&mut self, handler: Arc<dyn MessageHandler>) {
+        let priority = handler.priority();
+        let position = self
+            .handlers
+            .partition_point(|existing| existing.priority() >= priority);
+        self.handlers.insert(position, handler);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    /// Offers the message to the handlers that run before the built-in commands,
+    /// or with `fallback` to those that run after them, until one takes it. A
+    /// handler that fails has its error logged and counts as having taken it
+    pub async fn dispatch(
+        &self,
+        bot: &ZaloBot,
+        message: &ChatMessage,
+        command: &str,
+        fallback: bool,
+    ) -> bool {
+        for handler in &self.handlers {
+            if (handler.priority() < 0) != fallback {
+                continue;
+            }
+            match handler.handle(bot, message, command).await {
+                Ok(true) => return true,
+                Ok(false) => {}
+                Err(e) => {
+                    error!("Message handler failed: {}", e);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Extend<Arc<dyn MessageHandler>> for HandlerRegistry {
+    fn extend<I: IntoIterator<Item = Arc<dyn MessageHandler>>>(&mut self, handlers: I) {
+        for handler in handlers {
+            self.register(handler);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::Result;
+use axum::Router;
+use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::info;
+
+/// How long `getUpdates` may keep failing before `/healthz` reports 503 unless configured otherwise
+pub const DEFAULT_UNHEALTHY_AFTER: Duration = Duration::from_secs(300);
+
+/// Where `/healthz` is served and when it starts failing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub addr: SocketAddr,
+    /// How long polling may fail before the bot counts as unhealthy
+    pub unhealthy_after: Duration,
+}
+
+/// Liveness facts gathered while the bot runs
+#[derive(Debug, Default)]
+pub struct HealthMonitor {
+    state: Mutex<HealthState>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct HealthState {
+    last_poll: Option<DateTime<Utc>>,
+    /// First failed poll since the last successful one
+    polling_failing_since: Option<DateTime<Utc>>,
+    last_send: Option<DateTime<Utc>>,
+    database_questions: Option<usize>,
+}
+
+/// Body of a `/healthz` response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub last_poll: Option<DateTime<Utc>>,
+    pub polling_failing_since: Option<DateTime<Utc>>,
+    pub last_send: Option<DateTime<Utc>>,
+    pub database_loaded: bool,
+    pub database_questions: usize,
+}
+
+/// One bot's part of a [`BotsHealthReport`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BotHealthReport {
+    /// The bot's ID from its token, never the secret part
+    pub bot: String,
+    #[serde(flatten)]
+    pub report: HealthReport,
+}
+
+/// Body of a `/healthz` response when one process runs several bots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BotsHealthReport {
+    /// Whether every bot is healthy
+    pub healthy: bool,
+    pub bots: Vec<BotHealthReport>,
+}
+
+impl HealthMonitor {
+    /// A `getUpdates` call came back, with or without messages
+    pub fn poll_succeeded(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.last_poll = Some(Utc::now());
+        state.polling_failing_since = None;
+    }
+
+    /// A `getUpdates` call failed with something other than a long-poll timeout
+    pub fn poll_failed(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.polling_failing_since.get_or_insert_with(Utc::now);
+    }
+
+    /// A message or photo was delivered
+    pub fn send_succeeded(&self) {
+        self.state.lock().unwrap().last_send = Some(Utc::now());
+    }
+
+    /// The question index was fetched with this many questions
+    pub fn database_loaded(&self, questions: usize) {
+        self.state.lock().unwrap().database_questions = Some(questions);
+    }
+
+    /// Current status; unhealthy once polling has failed for longer than
+    /// `unhealthy_after` or while no question index is loaded
+    pub fn report(&self, unhealthy_after: Duration) -> HealthReport {
+        let state = self.state.lock().unwrap().clone();
+        let polling_down = state.polling_failing_since.is_some_and(|since| {
+            (Utc::now() - since).to_std().unwrap_or_default() > unhealthy_after
+        });
+        HealthReport {
+            healthy: !polling_down && state.database_questions.is_some(),
+            last_poll: state.last_poll,
+            polling_failing_since: state.polling_failing_since,
+            last_send: state.last_send,
+            database_loaded: state.database_questions.is_some(),
+            database_questions: state.database_questions.unwrap_or(0),
+        }
+    }
+}
+
+/// Serves `GET /healthz` on `check.addr` until dropped; any other path gets 404
+pub async fn serve_health(check: HealthCheck, monitor: Arc<HealthMonitor>) -> Result<()> {
+    listen(check, move || {
+        let report = monitor.report(check.unhealthy_after);
+        Ok((report.healthy, serde_json::to_string(&report)?))
+    })
+    .await
+}
+
+/// Like [`serve_health`] for several bots in one process, each labelled with its
+/// ID; unhealthy as soon as one of them is
+pub async fn serve_bots_health(
+    check: HealthCheck,
+    monitors: Vec<(String, Arc<HealthMonitor>)>,
+) -> Result<()> {
+    listen(check, move || {
+        let bots: Vec<BotHealthReport> = monitors
+            .iter()
+            .map(|(bot, monitor)| BotHealthReport {
+                bot: bot.clone(),
+                report: monitor.report(check.unhealthy_after),
+            })
+            .collect();
+        let report = BotsHealthReport {
+            healthy: bots.iter().all(|bot| bot.report.healthy),
+            bots,
+        };
+        Ok((report.healthy, serde_json::to_string(&report)?))
+    })
+    .await
+}
+
+/// Answers probes on `check.addr`; `report` gives the health status and JSON body
+async fn listen(
+    check: HealthCheck,
+    report: impl Fn() -> Result<(bool, String)> + Clone + Send + Sync + 'static,
+) -> Result<()> {
+    let listener = TcpListener::bind(check.addr).await?;
+    info!("Health check listening on http://{}/healthz", check.addr);
+    let app = Router::new()
+        .route("/healthz", get(move || async move { respond(report()) }))
+        .fallback(|| async {
+            json(
+                StatusCode::NOT_FOUND,
+                r#"{"error":"not found"}"#.to_string(),
+            )
+        });
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+fn respond(report: Result<(bool, String)>) -> Response {
+    match report {
+        Ok((true, body)) => json(StatusCode::OK, body),
+        Ok((false, body)) => json(StatusCode::SERVICE_UNAVAILABLE, body),
+        Err(e) => json(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::json!({ "error": e.to_string() }).to_string(),
+        ),
+    }
+}
+
+fn json(status: StatusCode, body: String) -> Response {
+    (status, [(CONTENT_TYPE, "application/json")], body).into_response()
+}
@@ -0,0 +1,49 @@
+use crate::QuestionContent;
+use std::time::{Duration, Instant};
+
+/// Longest hint, in characters, taken from the start of the first explanation
+pub const HINT_LENGTH: usize = 200;
+
+/// Hint-then-reveal schedule for a question nobody has answered yet
+#[derive(Debug, Clone)]
+pub struct QuestionTimer {
+    pub question_id: String,
+    /// When the next step (hint, then reveal) is due
+    pub due: Instant,
+    pub hint_sent: bool,
+}
+
+impl QuestionTimer {
+    pub fn new(question_id: &str, delay: Duration) -> Self {
+        Self {
+            question_id: question_id.to_string(),
+            due: Instant::now() + delay,
+            hint_sent: false,
+        }
+    }
+}
+
+/// The opening of the first explanation, cut at a word boundary
+pub fn format_hint(content: &QuestionContent) -> Option<String> {
+    let explanation = content.explanations.first()?;
+    let text = crate::strip_html_tags(explanation)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.is_empty() {
+        return None;
+    }
+
+    if text.chars().count() <= HINT_LENGTH {
+        return Some(text);
+    }
+    let cut = text
+        .char_indices()
+        .nth(HINT_LENGTH)
+        .map_or(text.len(), |(i, _)| i);
+    let truncated = match text[..cut].rfind(' ') {
+        Some(space) => &text[..space],
+        None => &text[..cut],
+    };
+    Some(format!("{}…", truncated))
+}
@@ -0,0 +1,67 @@
+use crate::storage::HistoryEntry;
+
+/// How many questions each page of "history" lists
+pub const HISTORY_PAGE_SIZE: usize = 10;
+
+/// A "history" command: the latest page, or the next older one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryCommand {
+    Latest,
+    More,
+}
+
+/// Parses "history" and "history more"
+pub fn parse_history_command(text: &str) -> Option<HistoryCommand> {
+    let mut words = text.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("history") {
+        return None;
+    }
+    match (words.next(), words.next()) {
+        (None, _) => Some(HistoryCommand::Latest),
+        (Some(word), None) if word.eq_ignore_ascii_case("more") => Some(HistoryCommand::More),
+        _ => None,
+    }
+}
+
+/// Lists one page of a chat's questions, newest first; `offset` is how many
+/// newer questions earlier pages already showed
+pub fn format_history(entries: &[HistoryEntry], offset: usize, has_more: bool) -> String {
+    if entries.is_empty() {
+        return if offset == 0 {
+            "📜 No questions have been sent to this chat yet. Send a type like 'PS' to get one!"
+                .to_string()
+        } else {
+            "📜 That's everything, there are no older questions. Send 'history' to start over."
+                .to_string()
+        };
+    }
+
+    let mut text = format!(
+        "📜 Questions sent to this chat ({}–{})",
+        offset + 1,
+        offset + entries.len()
+    );
+    for (i, entry) in entries.iter().enumerate() {
+        let outcome = match (entry.answered, entry.correct) {
+            (false, _) => "⏳ unanswered",
+            (true, Some(true)) => "✅ correct",
+            (true, Some(false)) => "❌ incorrect",
+            (true, None) => "📝 answered",
+        };
+        text.push_str(&format!(
+            "\n{}. #{} {:?} - {}",
+            offset + i + 1,
+            entry.question_id,
+            entry.question_type,
+            outcome
+        ));
+    }
+    text.push_str("\n\nSend a question ID like '");
+    text.push_str(&entries[0].question_id);
+    text.push_str("' to see it again");
+    if has_more {
+        text.push_str(", or 'history more' for older ones");
+    }
+    text.push('.');
+    text
+}
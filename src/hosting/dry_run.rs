@@ -0,0 +1,37 @@
+use super::{ImageHost, ImageHostKind};
+use crate::Result;
+use std::path::Path;
+use tracing::info;
+
+/// Stands in for a real host under `--dry-run`: logs each image it would upload
+/// and hands back a placeholder URL
+pub struct DryRunHost {
+    host: ImageHostKind,
+}
+
+impl DryRunHost {
+    /// Pretends to be `host`, so direct uploads still go to the chat as files
+    pub fn new(host: ImageHostKind) -> Self {
+        Self { host }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageHost for DryRunHost {
+    async fn upload(&self, image_path: &str) -> Result<String> {
+        let size = std::fs::metadata(image_path)?.len();
+        info!(
+            "[dry run] Would upload {} ({} bytes) to {:?}",
+            image_path, size, self.host
+        );
+        let file_name = Path::new(image_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("question.png");
+        Ok(format!("dry-run://{}", file_name))
+    }
+
+    fn uploads_directly(&self) -> bool {
+        self.host == ImageHostKind::Direct
+    }
+}
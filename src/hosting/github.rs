@@ -0,0 +1,586 @@
+use super::{GitHubAuth, ImageHost, content_file_name};
+use crate::http::default_client;
+use crate::retry::{RetryPolicy, retry};
+use crate::{BotError, ImageFormat, Result};
+use serde::Deserialize;
+use std::fs;
+use tracing::{debug, info, instrument, warn};
+
+/// GitHub REST API host
+pub const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Assets a release holds before uploads move to a new one; GitHub caps releases at 1000
+pub const DEFAULT_MAX_RELEASE_ASSETS: usize = 900;
+
+#[derive(Debug, Clone)]
+pub struct GitHubConfig {
+    /// REST API host, e.g. a GitHub Enterprise server or a mock server
+    pub api_url: String,
+    pub repo: String,
+    pub release_id: u64,
+    pub auth: GitHubAuth,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubReleaseResponse {
+    upload_url: String,
+    /// The release's assets as listed with it, which may not be all of them
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubReleaseIdResponse {
+    id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAssetResponse {
+    browser_download_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+pub async fn create_github_release(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    auth: &GitHubAuth,
+    tag_name: &str,
+) -> Result<u64> {
+    info!("Creating GitHub release with tag: {}", tag_name);
+    let token = auth.token().await?;
+
+    let url = format!("{}/repos/{}/releases", api_url, repo);
+
+    let release_data = serde_json::json!({
+        "tag_name": tag_name,
+        "name": format!("GMAT Bot Images - {}", tag_name),
+        "body": "Automated release for GMAT question images",
+        "draft": false,
+        "prerelease": false
+    });
+
+    let response = client
+        .post(&url)
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .json(&release_data)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(BotError::github(
+            "Failed to create release",
+            Some(status),
+            error_text,
+        ));
+    }
+
+    let release_response: serde_json::Value = response.json().await?;
+    let release_id = release_response["id"].as_u64().ok_or_else(|| {
+        BotError::github(
+            "Failed to get release ID from response",
+            Some(status),
+            release_response.to_string(),
+        )
+    })?;
+
+    info!("Created release with ID: {}", release_id);
+    Ok(release_id)
+}
+
+pub async fn get_latest_release_id(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    auth: &GitHubAuth,
+) -> Result<u64> {
+    info!("Getting latest release ID...");
+    let token = auth.token().await?;
+
+    let url = format!("{}/repos/{}/releases/latest", api_url, repo);
+
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(BotError::github(
+            "Failed to get latest release",
+            Some(status),
+            error_text,
+        ));
+    }
+
+    let release_response: serde_json::Value = response.json().await?;
+    let release_id = release_response["id"].as_u64().ok_or_else(|| {
+        BotError::github(
+            "Failed to get release ID from response",
+            Some(status),
+            release_response.to_string(),
+        )
+    })?;
+
+    info!("Found latest release ID: {}", release_id);
+    Ok(release_id)
+}
+
+/// ID of the release tagged `tag_name`, if there is one
+pub async fn find_release_by_tag(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    auth: &GitHubAuth,
+    tag_name: &str,
+) -> Result<Option<u64>> {
+    let token = auth.token().await?;
+    let url = format!("{}/repos/{}/releases/tags/{}", api_url, repo, tag_name);
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if status == 404 {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(BotError::github(
+            "Failed to look up release by tag",
+            Some(status),
+            error_text,
+        ));
+    }
+    let release: GitHubReleaseIdResponse = response.json().await?;
+    Ok(Some(release.id))
+}
+
+/// Number of assets attached to a release
+pub async fn count_release_assets(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    release_id: u64,
+    auth: &GitHubAuth,
+) -> Result<usize> {
+    let token = auth.token().await?;
+    const PER_PAGE: usize = 100;
+    let mut count = 0;
+    for page in 1.. {
+        let url = format!(
+            "{}/repos/{}/releases/{}/assets?per_page={}&page={}",
+            api_url, repo, release_id, PER_PAGE, page
+        );
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("token {}", token))
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BotError::github(
+                "Failed to list release assets",
+                Some(status),
+                error_text,
+            ));
+        }
+        let assets: Vec<serde_json::Value> = response.json().await?;
+        count += assets.len();
+        if assets.len() < PER_PAGE {
+            break;
+        }
+    }
+    debug!("Release {} has {} assets", release_id, count);
+    Ok(count)
+}
+
+/// Download URL of the asset named `name` in a release, if it has one
+pub async fn find_release_asset(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    release_id: u64,
+    auth: &GitHubAuth,
+    name: &str,
+) -> Result<Option<String>> {
+    let token = auth.token().await?;
+    const PER_PAGE: usize = 100;
+    for page in 1.. {
+        let url = format!(
+            "{}/repos/{}/releases/{}/assets?per_page={}&page={}",
+            api_url, repo, release_id, PER_PAGE, page
+        );
+        let response = client
+            .get(&url)
+            .header("Authorization", format!("token {}", token))
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BotError::github(
+                "Failed to list release assets",
+                Some(status),
+                error_text,
+            ));
+        }
+        let assets: Vec<GitHubAsset> = response.json().await?;
+        if let Some(asset) = assets.iter().find(|asset| asset.name == name) {
+            return Ok(Some(asset.browser_download_url.clone()));
+        }
+        if assets.len() < PER_PAGE {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+/// Tag of the `attempt`th release created in a month: `images-2024-06`, then `images-2024-06-2`, ...
+pub fn rotation_tag(date: chrono::NaiveDate, attempt: u32) -> String {
+    let tag = format!("images-{}", date.format("%Y-%m"));
+    if attempt > 1 {
+        format!("{}-{}", tag, attempt)
+    } else {
+        tag
+    }
+}
+
+/// Uploads an image as a release asset named after its content and returns its download
+/// URL; an asset of the same name already in the release is reused instead
+pub async fn upload_to_github_release(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    release_id: u64,
+    auth: &GitHubAuth,
+    image_path: &str,
+) -> Result<String> {
+    upload_release_asset(client, api_url, repo, release_id, auth, image_path)
+        .await
+        .map(|(url, _)| url)
+}
+
+/// Like [`upload_to_github_release`], also telling whether a new asset was created
+#[instrument(skip(client, auth))]
+async fn upload_release_asset(
+    client: &reqwest::Client,
+    api_url: &str,
+    repo: &str,
+    release_id: u64,
+    auth: &GitHubAuth,
+    image_path: &str,
+) -> Result<(String, bool)> {
+    info!("Uploading image to GitHub release...");
+    let token = auth.token().await?;
+
+    // First, get the release info to obtain the upload_url
+    info!("Getting release upload URL...");
+    let release_url = format!("{}/repos/{}/releases/{}", api_url, repo, release_id);
+
+    let release_response = client
+        .get(&release_url)
+        .header("Authorization", format!("token {}", token))
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?;
+
+    if !release_response.status().is_success() {
+        let status = release_response.status();
+        let error_text = release_response.text().await.unwrap_or_default();
+
+        if status == 404 {
+            return Err(BotError::github(
+                format!(
+                    "Release not found. Please create a release first or use --github-release-id with a valid release ID.\n\
+                    You can create a release manually on GitHub or the bot can auto-create one.\n\
+                    Repository: {}, Release ID: {}",
+                    repo, release_id
+                ),
+                Some(status),
+                error_text,
+            ));
+        }
+
+        return Err(BotError::github(
+            "Failed to get release info",
+            Some(status),
+            error_text,
+        ));
+    }
+
+    let release_info: GitHubReleaseResponse = release_response.json().await?;
+
+    // Extract the base upload URL (remove the {?name,label} template part)
+    let upload_url = release_info
+        .upload_url
+        .split('{')
+        .next()
+        .unwrap_or(&release_info.upload_url);
+
+    // Read the image file
+    let file_bytes = fs::read(image_path)?;
+    debug!("Image size: {} bytes", file_bytes.len());
+
+    let file_name = content_file_name(image_path, &file_bytes);
+    if let Some(asset) = release_info
+        .assets
+        .iter()
+        .find(|asset| asset.name == file_name)
+    {
+        info!("Image already in the release, reusing {}", file_name);
+        return Ok((asset.browser_download_url.clone(), false));
+    }
+
+    // Upload the asset using the upload_url
+    let upload_url_with_name = format!("{}?name={}", upload_url, file_name);
+    info!("Uploading {} to GitHub...", file_name);
+
+    let response = client
+        .post(&upload_url_with_name)
+        .header("Authorization", format!("token {}", token))
+        .header("Content-Type", ImageFormat::mime_type_of(&file_bytes))
+        .header("Accept", "application/vnd.github+json")
+        .body(file_bytes)
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+
+        if status == 422 {
+            // Most likely the same image uploaded meanwhile or missing from the release's
+            // first page of assets, so look it up before giving up
+            match find_release_asset(client, api_url, repo, release_id, auth, &file_name).await {
+                Ok(Some(url)) => {
+                    info!("Image already in the release, reusing {}", file_name);
+                    return Ok((url, false));
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to look up existing asset {}: {}", file_name, e),
+            }
+            return Err(BotError::github(
+                format!(
+                    "Asset upload failed - likely due to duplicate filename: {}\n\
+                    GitHub returns 422 when an asset with the same name already exists",
+                    file_name
+                ),
+                Some(status),
+                error_text,
+            ));
+        }
+
+        return Err(BotError::github(
+            "GitHub upload failed. Make sure your GitHub token has the 'repo' scope (or, for fine-grained tokens and GitHub Apps, read and write access to Contents) on the repository",
+            Some(status),
+            error_text,
+        ));
+    }
+
+    let github_response: GitHubAssetResponse = response.json().await?;
+
+    info!(
+        "Image uploaded to GitHub: {}",
+        github_response.browser_download_url
+    );
+    Ok((github_response.browser_download_url, true))
+}
+
+/// The release uploads currently go to
+struct ReleaseState {
+    config: GitHubConfig,
+    /// Assets in the release, counted on first use; `None` until then or after a failed upload
+    asset_count: Option<usize>,
+}
+
+/// Hosts images as assets of a GitHub release, moving to a new dated release
+/// once the current one holds `max_assets`
+pub struct GitHubReleaseHost {
+    // Held while a slot is reserved or the release rotated, never during uploads
+    state: tokio::sync::Mutex<ReleaseState>,
+    pub retry_policy: RetryPolicy,
+    /// Assets per release before rotating; `None` keeps uploading to the same release
+    pub max_assets: Option<usize>,
+    client: reqwest::Client,
+}
+
+impl GitHubReleaseHost {
+    pub fn new(config: GitHubConfig) -> Self {
+        Self {
+            state: tokio::sync::Mutex::new(ReleaseState {
+                config,
+                asset_count: None,
+            }),
+            retry_policy: RetryPolicy::default(),
+            max_assets: Some(DEFAULT_MAX_RELEASE_ASSETS),
+            client: default_client(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_max_assets(mut self, max_assets: Option<usize>) -> Self {
+        self.max_assets = max_assets;
+        self
+    }
+
+    /// Talks to GitHub through `client`, e.g. the hosting route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// The release uploads currently go to
+    pub async fn config(&self) -> GitHubConfig {
+        self.state.lock().await.config.clone()
+    }
+
+    /// Claims room for one more asset, rotating to a new release when the
+    /// current one is full, and returns the release to upload to
+    async fn reserve_slot(&self) -> GitHubConfig {
+        let mut state = self.state.lock().await;
+        let Some(max_assets) = self.max_assets else {
+            return state.config.clone();
+        };
+        if state.asset_count.is_none() {
+            let config = &state.config;
+            match count_release_assets(
+                &self.client,
+                &config.api_url,
+                &config.repo,
+                config.release_id,
+                &config.auth,
+            )
+            .await
+            {
+                Ok(count) => state.asset_count = Some(count),
+                Err(e) => warn!("Failed to count release assets: {}", e),
+            }
+        }
+        if let Some(count) = state.asset_count
+            && count >= max_assets
+        {
+            match self.next_release(&state.config, max_assets).await {
+                Ok((release_id, count)) => {
+                    info!(
+                        "Release {} holds {} assets; uploading to release {} from now on",
+                        state.config.release_id, max_assets, release_id
+                    );
+                    state.config.release_id = release_id;
+                    state.asset_count = Some(count);
+                }
+                Err(e) => warn!("Failed to rotate to a new release: {}", e),
+            }
+        }
+        if let Some(count) = &mut state.asset_count {
+            *count += 1;
+        }
+        state.config.clone()
+    }
+
+    /// Finds this month's release with room left, creating it if needed;
+    /// returns its ID and asset count
+    async fn next_release(&self, config: &GitHubConfig, max_assets: usize) -> Result<(u64, usize)> {
+        let today = chrono::Utc::now().date_naive();
+        let mut attempt = 1;
+        loop {
+            let tag = rotation_tag(today, attempt);
+            match find_release_by_tag(
+                &self.client,
+                &config.api_url,
+                &config.repo,
+                &config.auth,
+                &tag,
+            )
+            .await?
+            {
+                // Left behind by an earlier run; reuse it unless it is full too
+                Some(release_id) => {
+                    let count = count_release_assets(
+                        &self.client,
+                        &config.api_url,
+                        &config.repo,
+                        release_id,
+                        &config.auth,
+                    )
+                    .await?;
+                    if count < max_assets {
+                        return Ok((release_id, count));
+                    }
+                    attempt += 1;
+                }
+                None => {
+                    let release_id = create_github_release(
+                        &self.client,
+                        &config.api_url,
+                        &config.repo,
+                        &config.auth,
+                        &tag,
+                    )
+                    .await?;
+                    return Ok((release_id, 0));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageHost for GitHubReleaseHost {
+    async fn upload(&self, image_path: &str) -> Result<String> {
+        let config = self.reserve_slot().await;
+        let result = retry(&self.retry_policy, "GitHub upload", || {
+            upload_release_asset(
+                &self.client,
+                &config.api_url,
+                &config.repo,
+                config.release_id,
+                &config.auth,
+                image_path,
+            )
+        })
+        .await;
+        match result {
+            Ok((url, created)) => {
+                if !created {
+                    // Reused an existing asset, so the slot is free again
+                    let mut state = self.state.lock().await;
+                    if state.config.release_id == config.release_id
+                        && let Some(count) = &mut state.asset_count
+                    {
+                        *count = count.saturating_sub(1);
+                    }
+                }
+                Ok(url)
+            }
+            Err(e) => {
+                // The slot may or may not have been used; count again next time
+                self.state.lock().await.asset_count = None;
+                Err(e)
+            }
+        }
+    }
+}
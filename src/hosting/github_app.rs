@@ -0,0 +1,180 @@
+use crate::http::default_client;
+use crate::{BotError, Result};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{RSA_PKCS1_SHA256, RsaKeyPair};
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// How long before it expires an installation token is replaced, so none runs out mid-upload
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// How requests to GitHub authenticate
+#[derive(Clone)]
+pub enum GitHubAuth {
+    /// A personal access token; fine-grained tokens need read and write access to Contents
+    Token(String),
+    /// A GitHub App installation, exchanging its key for short-lived tokens
+    App(Arc<GitHubApp>),
+}
+
+impl GitHubAuth {
+    /// The token to send, exchanging a new installation token when the cached one is about to expire
+    pub async fn token(&self) -> Result<String> {
+        match self {
+            GitHubAuth::Token(token) => Ok(token.clone()),
+            GitHubAuth::App(app) => app.installation_token().await,
+        }
+    }
+}
+
+impl From<&str> for GitHubAuth {
+    fn from(token: &str) -> Self {
+        GitHubAuth::Token(token.to_string())
+    }
+}
+
+impl From<String> for GitHubAuth {
+    fn from(token: String) -> Self {
+        GitHubAuth::Token(token)
+    }
+}
+
+// Keeps tokens out of logs
+impl fmt::Debug for GitHubAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitHubAuth::Token(_) => write!(f, "Token(..)"),
+            GitHubAuth::App(app) => write!(
+                f,
+                "App {{ app_id: {}, installation_id: {} }}",
+                app.app_id, app.installation_id
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationTokenResponse {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// A GitHub App installed on the account owning the image repository
+pub struct GitHubApp {
+    api_url: String,
+    /// The app's ID or client ID, the issuer of its JWTs
+    app_id: String,
+    installation_id: u64,
+    key: RsaKeyPair,
+    cached: tokio::sync::Mutex<Option<InstallationTokenResponse>>,
+    client: reqwest::Client,
+}
+
+impl GitHubApp {
+    /// Reads the app's private key, as downloaded from its settings page (PKCS#1 or PKCS#8 PEM)
+    pub fn new(
+        api_url: impl Into<String>,
+        app_id: impl Into<String>,
+        installation_id: u64,
+        private_key_pem: &str,
+    ) -> Result<Self> {
+        let invalid_key = |reason: String| {
+            BotError::Config(format!("Invalid GitHub App private key: {}", reason))
+        };
+        let body: String = private_key_pem
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+            .collect();
+        let der = STANDARD
+            .decode(body)
+            .map_err(|e| invalid_key(e.to_string()))?;
+        let key = if private_key_pem.contains("BEGIN RSA PRIVATE KEY") {
+            RsaKeyPair::from_der(&der)
+        } else {
+            RsaKeyPair::from_pkcs8(&der)
+        }
+        .map_err(|e| invalid_key(e.to_string()))?;
+        Ok(Self {
+            api_url: api_url.into(),
+            app_id: app_id.into(),
+            installation_id,
+            key,
+            cached: tokio::sync::Mutex::new(None),
+            client: default_client(),
+        })
+    }
+
+    /// Exchanges tokens through `client`, e.g. the hosting route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// A JWT identifying the app for ten minutes, backdated a minute for clock drift
+    pub fn jwt(&self) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            serde_json::json!({ "iat": now - 60, "exp": now + 9 * 60, "iss": self.app_id })
+                .to_string(),
+        );
+        let message = format!("{}.{}", header, claims);
+        let mut signature = vec![0; self.key.public().modulus_len()];
+        self.key
+            .sign(
+                &RSA_PKCS1_SHA256,
+                &SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|_| BotError::Config("Failed to sign the GitHub App JWT".to_string()))?;
+        Ok(format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature)))
+    }
+
+    /// An installation token, reused until shortly before it expires
+    pub async fn installation_token(&self) -> Result<String> {
+        let mut cached = self.cached.lock().await;
+        let refresh_after = Utc::now() + TOKEN_REFRESH_MARGIN;
+        if let Some(token) = cached.as_ref()
+            && token.expires_at > refresh_after
+        {
+            return Ok(token.token.clone());
+        }
+
+        let url = format!(
+            "{}/app/installations/{}/access_tokens",
+            self.api_url, self.installation_id
+        );
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.jwt()?))
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BotError::github(
+                "Failed to get a GitHub App installation token",
+                Some(status),
+                error_text,
+            ));
+        }
+        let token: InstallationTokenResponse = response.json().await?;
+        info!(
+            "Got a GitHub App installation token valid until {}",
+            token.expires_at
+        );
+        let value = token.token.clone();
+        *cached = Some(token);
+        Ok(value)
+    }
+}
@@ -0,0 +1,96 @@
+use super::ImageHost;
+use crate::http::default_client;
+use crate::retry::{RetryPolicy, retry};
+use crate::{BotError, Result};
+use serde::Deserialize;
+use std::fs;
+use tracing::{debug, info};
+
+/// Imgur's anonymous image upload endpoint
+pub const IMGUR_UPLOAD_URL: &str = "https://api.imgur.com/3/image";
+
+#[derive(Debug, Deserialize)]
+struct ImgurResponse {
+    data: ImgurImage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImgurImage {
+    link: String,
+}
+
+/// Hosts images anonymously on Imgur, identified only by an application client ID
+pub struct ImgurHost {
+    /// Client ID of an Imgur application registered for anonymous uploads
+    pub client_id: String,
+    pub upload_url: String,
+    pub retry_policy: RetryPolicy,
+    client: reqwest::Client,
+}
+
+impl ImgurHost {
+    pub fn new(client_id: String) -> Self {
+        Self {
+            client_id,
+            upload_url: IMGUR_UPLOAD_URL.to_string(),
+            retry_policy: RetryPolicy::default(),
+            client: default_client(),
+        }
+    }
+
+    /// Uploads somewhere else, e.g. a mock server
+    pub fn with_upload_url(mut self, upload_url: impl Into<String>) -> Self {
+        self.upload_url = upload_url.into();
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Uploads through `client`, e.g. the hosting route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn upload_once(&self, file_bytes: Vec<u8>) -> Result<String> {
+        let form = reqwest::multipart::Form::new()
+            .part("image", reqwest::multipart::Part::bytes(file_bytes))
+            .text("type", "file");
+        let response = self
+            .client
+            .post(&self.upload_url)
+            .header("Authorization", format!("Client-ID {}", self.client_id))
+            .multipart(form)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(BotError::ImgurUpload { status, body: text });
+        }
+        let parsed: ImgurResponse = serde_json::from_str(&text)?;
+        Ok(parsed.data.link)
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageHost for ImgurHost {
+    async fn upload(&self, image_path: &str) -> Result<String> {
+        info!("Uploading image to Imgur...");
+
+        let file_bytes = fs::read(image_path)?;
+        debug!("Image size: {} bytes", file_bytes.len());
+
+        let url = retry(&self.retry_policy, "Imgur upload", || {
+            self.upload_once(file_bytes.clone())
+        })
+        .await?;
+
+        info!("Image uploaded to Imgur: {}", url);
+        Ok(url)
+    }
+}
@@ -0,0 +1,119 @@
+use super::{ImageHost, content_file_name};
+use crate::Result;
+use axum::Router;
+use axum::extract::Request;
+use axum::http::header::CACHE_CONTROL;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::net::TcpListener;
+use tower_http::services::ServeDir;
+use tracing::{info, warn};
+
+/// Hosts images on the bot itself: uploads are copied into `dir`, which
+/// [`LocalHost::serve`] makes reachable under `/images/` at `public_url`
+pub struct LocalHost {
+    pub dir: PathBuf,
+    /// Base URL the server is publicly reachable at, e.g. `https://bot.example.com`
+    pub public_url: String,
+}
+
+impl LocalHost {
+    pub fn new(dir: impl Into<PathBuf>, public_url: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            public_url: public_url.into(),
+        }
+    }
+
+    /// Binds `addr` and serves the images from a background task for as long as the
+    /// process runs; returns the address actually bound, which differs for port 0
+    pub async fn serve(&self, addr: SocketAddr) -> Result<SocketAddr> {
+        std::fs::create_dir_all(&self.dir)?;
+        let listener = TcpListener::bind(addr).await?;
+        let bound = listener.local_addr()?;
+        info!(
+            "Serving images from {} on http://{}/images/",
+            self.dir.display(),
+            bound
+        );
+
+        let app = Router::new()
+            .nest_service("/images", ServeDir::new(&self.dir))
+            .layer(middleware::from_fn(plain_files_only));
+        // axum gives each connection its own task, so slow downloads don't hold up others
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                warn!("Image server stopped: {}", e);
+            }
+        });
+        Ok(bound)
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageHost for LocalHost {
+    async fn upload(&self, image_path: &str) -> Result<String> {
+        // Rendered images are deleted once sent, so the served copy must be our own
+        let file_bytes = std::fs::read(image_path)?;
+        let file_name = content_file_name(image_path, &file_bytes);
+        let served_path = self.dir.join(&file_name);
+        if served_path.exists() {
+            info!("Image already served, reusing it: {}", file_name);
+        } else {
+            std::fs::create_dir_all(&self.dir)?;
+            // Write then rename so the server never hands out half an image
+            let tmp_path = served_path.with_extension("tmp");
+            std::fs::write(&tmp_path, file_bytes)?;
+            std::fs::rename(&tmp_path, &served_path)?;
+            info!("Image stored for serving: {}", file_name);
+        }
+        Ok(format!(
+            "{}/images/{}",
+            self.public_url.trim_end_matches('/'),
+            file_name
+        ))
+    }
+}
+
+/// Answers 404 for anything but a plain file name under `/images/`, and lets
+/// found images be cached for good
+async fn plain_files_only(request: Request, next: Next) -> Response {
+    let plain = request
+        .uri()
+        .path()
+        .strip_prefix("/images/")
+        .is_some_and(is_plain_file_name);
+    if !plain {
+        return (
+            StatusCode::NOT_FOUND,
+            [(CACHE_CONTROL, "no-store")],
+            "not found",
+        )
+            .into_response();
+    }
+    let mut response = next.run(request).await;
+    // Names follow the content, so a served image never changes
+    let cache_control = if response.status().is_success() {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-store"
+    };
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    response
+}
+
+/// Only names of files directly in the served directory, never paths out of it nor
+/// the `.tmp` copies an upload writes before renaming them into place
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.ends_with(".tmp")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
@@ -0,0 +1,98 @@
+use crate::{BotError, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+pub mod dry_run;
+pub mod github;
+pub mod github_app;
+pub mod imgur;
+pub mod local;
+pub mod s3;
+
+pub use dry_run::DryRunHost;
+pub use github::{
+    DEFAULT_GITHUB_API_URL, DEFAULT_MAX_RELEASE_ASSETS, GitHubConfig, GitHubReleaseHost,
+    count_release_assets, create_github_release, find_release_asset, find_release_by_tag,
+    get_latest_release_id, rotation_tag, upload_to_github_release,
+};
+pub use github_app::{GitHubApp, GitHubAuth};
+pub use imgur::{IMGUR_UPLOAD_URL, ImgurHost};
+pub use local::LocalHost;
+pub use s3::{MAX_PRESIGN_TTL, S3Config, S3Host};
+
+/// A place to upload rendered question images so Zalo can fetch them by URL
+#[async_trait::async_trait]
+pub trait ImageHost: Send + Sync {
+    /// Uploads the image at `image_path` and returns its public URL
+    async fn upload(&self, image_path: &str) -> Result<String>;
+
+    /// Whether images skip hosting and go to the chat as file uploads instead of URLs
+    fn uploads_directly(&self) -> bool {
+        false
+    }
+
+    /// How long the URLs `upload` returns keep working; `None` when they don't expire
+    fn url_lifetime(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// No host at all: each image is uploaded to the chat along with its message
+pub struct DirectUpload;
+
+#[async_trait::async_trait]
+impl ImageHost for DirectUpload {
+    async fn upload(&self, _image_path: &str) -> Result<String> {
+        Err(BotError::Config(
+            "Direct uploads have no public URL; send the file instead".to_string(),
+        ))
+    }
+
+    fn uploads_directly(&self) -> bool {
+        true
+    }
+}
+
+/// No host for `serve --manifest`: question images come from the manifest, and
+/// nothing else can be uploaded
+pub struct NoImageHost;
+
+#[async_trait::async_trait]
+impl ImageHost for NoImageHost {
+    async fn upload(&self, _image_path: &str) -> Result<String> {
+        Err(BotError::Config(
+            "Nothing is uploaded with --manifest; only the manifest's images can be sent"
+                .to_string(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageHostKind {
+    /// GitHub release assets
+    Github,
+    /// S3-compatible object storage (AWS S3, Cloudflare R2, MinIO)
+    S3,
+    /// Anonymous Imgur uploads, needing only an application client ID
+    Imgur,
+    /// No hosting; images are uploaded to the chat with each message
+    Direct,
+    /// Served by the bot itself over HTTP, see `--serve-images`
+    Local,
+}
+
+/// Names an image after its content, e.g. `question_123__9f86d081884c7d65.jpg`, so the same
+/// render always gets the same name and hosts can reuse what they already have
+pub fn content_file_name(image_path: &str, bytes: &[u8]) -> String {
+    let hash = hex::encode(&Sha256::digest(bytes)[..8]);
+    let path = std::path::Path::new(image_path);
+    let base_name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("question");
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("png");
+    format!("{}__{}.{}", base_name, hash, extension)
+}
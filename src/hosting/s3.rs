@@ -0,0 +1,326 @@
+use super::{ImageHost, content_file_name};
+use crate::http::default_client;
+use crate::retry::{RetryPolicy, retry};
+use crate::{BotError, ImageFormat, Result};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::time::Duration;
+use tracing::{debug, info};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Longest a presigned URL may stay valid under Signature Version 4
+pub const MAX_PRESIGN_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Connection settings for an S3-compatible bucket
+#[derive(Debug, Clone)]
+pub struct S3Config {
+    /// Endpoint URL, e.g. `https://<account>.r2.cloudflarestorage.com` or `http://localhost:9000` for MinIO
+    pub endpoint: String,
+    pub bucket: String,
+    /// Signing region; Cloudflare R2 uses `auto`
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Session token of temporary credentials, e.g. from AWS STS
+    pub session_token: Option<String>,
+    /// Folder objects are uploaded into, e.g. `gmat/questions`; the bucket root when empty
+    pub prefix: String,
+    /// Base URL the uploaded objects are publicly reachable from; the bucket's path-style
+    /// URL on `endpoint` when unset
+    pub public_url: Option<String>,
+    /// Hand out presigned `GET` URLs valid this long instead of public ones, so the bucket
+    /// can stay private; at most [`MAX_PRESIGN_TTL`]
+    pub presign_ttl: Option<Duration>,
+}
+
+/// Hosts images in an S3-compatible bucket using path-style `PUT` requests
+pub struct S3Host {
+    pub config: S3Config,
+    pub retry_policy: RetryPolicy,
+    client: reqwest::Client,
+}
+
+impl S3Host {
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            config,
+            retry_policy: RetryPolicy::default(),
+            client: default_client(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Uploads through `client`, e.g. the hosting route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// The key an object named `file_name` is stored under, inside the prefix
+    pub fn object_key(&self, file_name: &str) -> String {
+        match self.config.prefix.trim_matches('/') {
+            "" => file_name.to_string(),
+            prefix => format!("{}/{}", prefix, file_name),
+        }
+    }
+
+    /// Uploads `body` under `key` and returns the URL it is fetched from, see [`S3Host::object_url`]
+    pub async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<String> {
+        let response = self
+            .signed_request(reqwest::Method::PUT, key, &body)?
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(BotError::S3Upload {
+                status,
+                body: error_text,
+            });
+        }
+
+        self.object_url(key)
+    }
+
+    /// Whether an object is already stored under `key`; anything but a success, including
+    /// the 403 buckets without list permission answer for missing keys, counts as no
+    pub async fn object_exists(&self, key: &str) -> Result<bool> {
+        let response = self
+            .signed_request(reqwest::Method::HEAD, key, &[])?
+            .send()
+            .await?;
+        Ok(response.status().is_success())
+    }
+
+    /// A request for the object under `key` signed with the `Authorization` header
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: &[u8],
+    ) -> Result<reqwest::RequestBuilder> {
+        let host = self.endpoint_host()?;
+        let canonical_uri = self.canonical_uri(key);
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(body));
+
+        // AWS Signature Version 4, see
+        // https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.config.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method, canonical_uri, canonical_headers, signed_headers, payload_hash
+        );
+        let signature = self.sign(&amz_date, &date_stamp, &canonical_request);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key_id,
+            self.scope(&date_stamp),
+            signed_headers,
+            signature
+        );
+
+        let url = format!(
+            "{}{}",
+            self.config.endpoint.trim_end_matches('/'),
+            canonical_uri
+        );
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header("Authorization", authorization)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date);
+        if let Some(token) = &self.config.session_token {
+            request = request.header("x-amz-security-token", token);
+        }
+        Ok(request)
+    }
+
+    /// Where the object under `key` is fetched from: a presigned URL when `presign_ttl`
+    /// is set, otherwise its public URL
+    pub fn object_url(&self, key: &str) -> Result<String> {
+        if let Some(ttl) = self.config.presign_ttl {
+            return self.presigned_url(key, ttl);
+        }
+        let base = match &self.config.public_url {
+            Some(public_url) => public_url.trim_end_matches('/').to_string(),
+            None => format!(
+                "{}/{}",
+                self.config.endpoint.trim_end_matches('/'),
+                self.config.bucket
+            ),
+        };
+        Ok(format!("{}/{}", base, uri_encode(key, true)))
+    }
+
+    /// A `GET` URL for the object under `key` that works without credentials for `ttl`
+    pub fn presigned_url(&self, key: &str, ttl: Duration) -> Result<String> {
+        if ttl.is_zero() || ttl > MAX_PRESIGN_TTL {
+            return Err(BotError::Config(format!(
+                "S3 presigned URLs must be valid for 1 second to 7 days, not {:?}",
+                ttl
+            )));
+        }
+        let host = self.endpoint_host()?;
+        let canonical_uri = self.canonical_uri(key);
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+        let credential = format!("{}/{}", self.config.access_key_id, self.scope(&date_stamp));
+
+        // Query-string signing, see
+        // https://docs.aws.amazon.com/AmazonS3/latest/API/sigv4-query-string-auth.html
+        let mut query = vec![
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+            ("X-Amz-Credential", credential),
+            ("X-Amz-Date", amz_date.clone()),
+            ("X-Amz-Expires", ttl.as_secs().to_string()),
+        ];
+        if let Some(token) = &self.config.session_token {
+            query.push(("X-Amz-Security-Token", token.clone()));
+        }
+        query.push(("X-Amz-SignedHeaders", "host".to_string()));
+        let canonical_query = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, uri_encode(value, false)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let canonical_request = format!(
+            "GET\n{}\n{}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+            canonical_uri, canonical_query, host
+        );
+        let signature = self.sign(&amz_date, &date_stamp, &canonical_request);
+
+        Ok(format!(
+            "{}{}?{}&X-Amz-Signature={}",
+            self.config.endpoint.trim_end_matches('/'),
+            canonical_uri,
+            canonical_query,
+            signature
+        ))
+    }
+
+    /// The endpoint's `host[:port]`, as signed in the `host` header
+    fn endpoint_host(&self) -> Result<String> {
+        let endpoint = reqwest::Url::parse(&self.config.endpoint).map_err(|e| {
+            BotError::Config(format!(
+                "Invalid S3 endpoint {}: {}",
+                self.config.endpoint, e
+            ))
+        })?;
+        match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => Ok(format!("{}:{}", host, port)),
+            (Some(host), None) => Ok(host.to_string()),
+            (None, _) => Err(BotError::Config(format!(
+                "Invalid S3 endpoint: {}",
+                self.config.endpoint
+            ))),
+        }
+    }
+
+    fn canonical_uri(&self, key: &str) -> String {
+        format!("/{}/{}", self.config.bucket, uri_encode(key, true))
+    }
+
+    fn scope(&self, date_stamp: &str) -> String {
+        format!("{}/{}/s3/aws4_request", date_stamp, self.config.region)
+    }
+
+    /// Signs a canonical request with a key derived from the secret, date and region
+    fn sign(&self, amz_date: &str, date_stamp: &str, canonical_request: &str) -> String {
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            self.scope(date_stamp),
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut signing_key = format!("AWS4{}", self.config.secret_access_key).into_bytes();
+        for part in [
+            date_stamp,
+            self.config.region.as_str(),
+            "s3",
+            "aws4_request",
+        ] {
+            signing_key = hmac_sha256(&signing_key, part.as_bytes());
+        }
+        hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()))
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageHost for S3Host {
+    async fn upload(&self, image_path: &str) -> Result<String> {
+        info!("Uploading image to S3 bucket {}...", self.config.bucket);
+
+        let file_bytes = fs::read(image_path)?;
+        debug!("Image size: {} bytes", file_bytes.len());
+
+        let key = self.object_key(&content_file_name(image_path, &file_bytes));
+        if self.object_exists(&key).await? {
+            info!("Image already in S3, reusing it: {}", key);
+            return self.object_url(&key);
+        }
+        let content_type = ImageFormat::mime_type_of(&file_bytes);
+        let url = retry(&self.retry_policy, "S3 upload", || {
+            self.put_object(&key, file_bytes.clone(), content_type)
+        })
+        .await?;
+
+        info!("Image uploaded to S3: {}", key);
+        Ok(url)
+    }
+
+    fn url_lifetime(&self) -> Option<Duration> {
+        self.config.presign_ttl
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes all but unreserved characters, as Signature Version 4 expects;
+/// slashes are kept when `keep_slash` is set, for object keys
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
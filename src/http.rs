@@ -0,0 +1,107 @@
+use crate::Result;
+use crate::proxy::{ProxyRoute, ProxySettings, redact};
+use std::time::Duration;
+use tracing::info;
+
+/// How long a request may take end to end unless configured otherwise
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long connecting to a host may take unless configured otherwise
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeouts, pooling, identity and proxies shared by every outbound request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpSettings {
+    pub user_agent: String,
+    pub connect_timeout: Duration,
+    /// Limit on a whole request; long polls add their wait on top
+    pub timeout: Duration,
+    /// How long an unused pooled connection is kept open
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+    pub proxies: ProxySettings,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            user_agent: concat!("gmat-zalo-bot/", env!("CARGO_PKG_VERSION")).to_string(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 8,
+            proxies: ProxySettings::default(),
+        }
+    }
+}
+
+impl HttpSettings {
+    /// A client for requests on `route`, failing on a proxy URL that can't be used
+    pub fn client(&self, route: ProxyRoute) -> Result<reqwest::Client> {
+        Ok(self
+            .proxies
+            .client_builder(route)?
+            .user_agent(&self.user_agent)
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .build()?)
+    }
+}
+
+/// The HTTP clients network calls go through, one per proxy route, so connections to
+/// the same host are pooled and reused across calls. Built once at startup and handed
+/// to the bot, hosts and database source, which otherwise use default settings
+#[derive(Debug, Clone)]
+pub struct HttpContext {
+    chat: reqwest::Client,
+    hosting: reqwest::Client,
+    database: reqwest::Client,
+    webhook: reqwest::Client,
+}
+
+impl HttpContext {
+    /// Builds the clients, failing on a proxy URL that can't be used
+    pub fn new(settings: &HttpSettings) -> Result<Self> {
+        for (name, route) in [
+            ("Chat", ProxyRoute::Chat),
+            ("Hosting", ProxyRoute::Hosting),
+            ("Database", ProxyRoute::Database),
+            ("Webhook", ProxyRoute::Webhook),
+        ] {
+            if let Some(url) = settings.proxies.for_route(route) {
+                info!("{} requests go through proxy {}", name, redact(url));
+            }
+        }
+        Ok(Self {
+            chat: settings.client(ProxyRoute::Chat)?,
+            hosting: settings.client(ProxyRoute::Hosting)?,
+            database: settings.client(ProxyRoute::Database)?,
+            webhook: settings.client(ProxyRoute::Webhook)?,
+        })
+    }
+
+    /// The client for requests on `route`; clones share its connection pool
+    pub fn client(&self, route: ProxyRoute) -> &reqwest::Client {
+        match route {
+            ProxyRoute::Chat => &self.chat,
+            ProxyRoute::Hosting => &self.hosting,
+            ProxyRoute::Database => &self.database,
+            ProxyRoute::Webhook => &self.webhook,
+        }
+    }
+}
+
+impl Default for HttpContext {
+    fn default() -> Self {
+        HttpContext::new(&HttpSettings::default()).expect("default HTTP settings are valid")
+    }
+}
+
+/// A client with the default settings and no proxy, for types built without an [`HttpContext`]
+pub(crate) fn default_client() -> reqwest::Client {
+    HttpSettings::default()
+        .client(ProxyRoute::Chat)
+        .expect("default HTTP settings are valid")
+}
@@ -0,0 +1,129 @@
+use crate::{BotError, Result, Storage, Theme, hosted_image_key};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// One hosted question image, as `serve` looks it up before rendering
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostedImage {
+    /// Question or part ID, with the theme and choice order when they aren't the defaults
+    pub key: String,
+    pub show_explanations: bool,
+    /// One URL per page
+    pub urls: Vec<String>,
+    /// Unix seconds, so expiring URLs are only reused while they're fresh
+    pub uploaded_at: i64,
+}
+
+/// The URLs written by `preupload`, which `serve --image-manifest` loads so a
+/// fresh store reuses them instead of rendering
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageManifest {
+    /// RFC 3339
+    pub generated_at: String,
+    pub images: Vec<HostedImage>,
+}
+
+impl ImageManifest {
+    /// Every image the store has hosted
+    pub fn from_storage(storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            images: storage.hosted_images()?,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| {
+            BotError::Config(format!(
+                "Failed to read image manifest {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        serde_json::from_str(&text).map_err(|e| {
+            BotError::Config(format!("Invalid image manifest {}: {}", path.display(), e))
+        })
+    }
+
+    /// Loads the manifest from a file, or downloads it with `client` when `location` is an http(s) URL
+    pub async fn fetch(location: &str, client: &reqwest::Client) -> Result<Self> {
+        if !location.starts_with("http://") && !location.starts_with("https://") {
+            return Self::load(location);
+        }
+        let response = client.get(location).send().await?.error_for_status()?;
+        response
+            .json()
+            .await
+            .map_err(|e| BotError::Config(format!("Invalid image manifest at {}: {}", location, e)))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds the manifest's images to `storage`, keeping any copy there that was
+    /// uploaded later; returns how many were added or replaced
+    pub fn import_into(&self, storage: &Storage) -> Result<usize> {
+        let mut imported = 0;
+        for image in &self.images {
+            if storage.import_hosted_image(image)? {
+                imported += 1;
+            }
+        }
+        Ok(imported)
+    }
+}
+
+/// A manifest's images by key, which `serve --manifest` sends without rendering
+#[derive(Debug, Clone, Default)]
+pub struct ManifestImages {
+    urls: HashMap<(String, bool), Vec<String>>,
+}
+
+impl From<ImageManifest> for ManifestImages {
+    fn from(manifest: ImageManifest) -> Self {
+        Self {
+            urls: manifest
+                .images
+                .into_iter()
+                .map(|image| ((image.key, image.show_explanations), image.urls))
+                .collect(),
+        }
+    }
+}
+
+impl ManifestImages {
+    /// The pages of an image, if the manifest has it
+    pub fn urls(&self, key: &str, show_explanations: bool) -> Option<&[String]> {
+        self.urls
+            .get(&(key.to_string(), show_explanations))
+            .map(Vec::as_slice)
+    }
+
+    /// Whether the question can be sent in `theme` without its explanations, whole
+    /// or, for a multi-part question, starting with its passage
+    pub fn has_question(&self, question_id: &str, theme: Theme) -> bool {
+        [question_id.to_string(), format!("{}-passage", question_id)]
+            .iter()
+            .any(|id| {
+                self.urls(&hosted_image_key(id, theme, None), false)
+                    .is_some()
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+}
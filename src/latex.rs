@@ -0,0 +1,256 @@
+//! Plain-text rendering of the TeX in GMAT questions, for the text fallback and
+//! `render --format text`
+
+/// TeX commands with a direct Unicode equivalent
+const SYMBOLS: &[(&str, &str)] = &[
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "·"),
+    ("pm", "±"),
+    ("leq", "≤"),
+    ("leqslant", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("geqslant", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("cdots", "⋯"),
+    ("infty", "∞"),
+    ("circ", "°"),
+    ("degree", "°"),
+    ("angle", "∠"),
+    ("triangle", "△"),
+    ("pi", "π"),
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("theta", "θ"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("%", "%"),
+    ("$", "$"),
+    ("{", "{"),
+    ("}", "}"),
+    ("\\", " "),
+    (",", " "),
+    (";", " "),
+    ("quad", " "),
+    ("left", ""),
+    ("right", ""),
+    ("(", ""),
+    (")", ""),
+    ("[", ""),
+    ("]", ""),
+];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('n', 'ⁿ'),
+    ('x', 'ˣ'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('n', 'ₙ'),
+];
+
+/// Rewrites inline TeX as readable text: `\frac{1}{x^2}` becomes `1/x²`, `\sqrt{2}`
+/// becomes `√2`, `\leq` becomes `≤`, and math delimiters are dropped. Unknown
+/// commands lose their backslash
+pub fn latex_to_unicode(text: &str) -> String {
+    let chars: Vec<char> = strip_dollar_delimiters(text).chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                let (name, next) = read_command(&chars, i + 1);
+                i = next;
+                match name.as_str() {
+                    "frac" | "dfrac" | "tfrac" => {
+                        let (numerator, next) = read_group(&chars, i);
+                        let (denominator, next) = read_group(&chars, next);
+                        i = next;
+                        out.push_str(&format!(
+                            "{}/{}",
+                            parenthesize(&latex_to_unicode(&numerator)),
+                            parenthesize(&latex_to_unicode(&denominator))
+                        ));
+                    }
+                    "sqrt" => {
+                        let (index, next) = read_root_index(&chars, i);
+                        let (radicand, next) = read_group(&chars, next);
+                        i = next;
+                        match index.as_deref().map(str::trim) {
+                            None | Some("2") => out.push('√'),
+                            Some("3") => out.push('∛'),
+                            Some("4") => out.push('∜'),
+                            Some(index) => out.push_str(&format!("{}√", superscript(index))),
+                        }
+                        out.push_str(&parenthesize(&latex_to_unicode(&radicand)));
+                    }
+                    "text" | "mathrm" | "textbf" | "mathbf" => {
+                        let (group, next) = read_group(&chars, i);
+                        i = next;
+                        out.push_str(&latex_to_unicode(&group));
+                    }
+                    name => match SYMBOLS.iter().find(|(command, _)| *command == name) {
+                        Some((_, symbol)) => out.push_str(symbol),
+                        None => out.push_str(name),
+                    },
+                }
+            }
+            marker @ ('^' | '_') => {
+                let (group, next) = read_group(&chars, i + 1);
+                i = next;
+                let group = latex_to_unicode(&group);
+                let table = if marker == '^' {
+                    SUPERSCRIPTS
+                } else {
+                    SUBSCRIPTS
+                };
+                match map_chars(&group, table) {
+                    Some(mapped) if !mapped.is_empty() => out.push_str(&mapped),
+                    _ if group.chars().count() == 1 => {
+                        out.push_str(&format!("{}{}", marker, group))
+                    }
+                    _ => out.push_str(&format!("{}({})", marker, group.trim())),
+                }
+            }
+            '{' | '}' => i += 1,
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Removes `$$` and the `$` pairs around inline math, leaving lone dollar signs
+/// such as prices alone
+fn strip_dollar_delimiters(text: &str) -> String {
+    let text = text.replace("$$", "");
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('$') {
+            Some(end) if after[..end].contains(['\\', '^', '_', '{']) => {
+                out.push_str(&after[..end]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('$');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Maps every character through `table`, or gives up if one has no equivalent
+fn map_chars(text: &str, table: &[(char, char)]) -> Option<String> {
+    text.chars()
+        .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+        .collect()
+}
+
+/// A root's index as superscript where possible, e.g. the `⁵` of `⁵√x`
+fn superscript(text: &str) -> String {
+    map_chars(text, SUPERSCRIPTS).unwrap_or_else(|| format!("({})", text))
+}
+
+/// Reads the optional `[n]` of `\sqrt[n]{x}`
+fn read_root_index(chars: &[char], start: usize) -> (Option<String>, usize) {
+    if chars.get(start) != Some(&'[') {
+        return (None, start);
+    }
+    match chars[start..].iter().position(|&c| c == ']') {
+        Some(end) => (
+            Some(chars[start + 1..start + end].iter().collect()),
+            start + end + 1,
+        ),
+        None => (None, start),
+    }
+}
+
+/// Reads a command name after a backslash: a run of letters, or one other character
+fn read_command(chars: &[char], start: usize) -> (String, usize) {
+    let letters = chars[start..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .count();
+    match letters {
+        0 => match chars.get(start) {
+            Some(c) => (c.to_string(), start + 1),
+            None => (String::new(), start),
+        },
+        n => (chars[start..start + n].iter().collect(), start + n),
+    }
+}
+
+/// Reads a `{...}` argument, or a single character when there are no braces
+fn read_group(chars: &[char], start: usize) -> (String, usize) {
+    let start = start
+        + chars[start.min(chars.len())..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+    match chars.get(start) {
+        Some('{') => {
+            let mut depth = 0;
+            for (offset, c) in chars[start..].iter().enumerate() {
+                match c {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            let end = start + offset;
+                            return (chars[start + 1..end].iter().collect(), end + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (chars[start + 1..].iter().collect(), chars.len())
+        }
+        Some(c) => (c.to_string(), start + 1),
+        None => (String::new(), start),
+    }
+}
+
+/// Wraps anything longer than a single number or symbol in parentheses
+fn parenthesize(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') || text.chars().count() == 1 {
+        text.to_string()
+    } else {
+        format!("({})", text)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::storage::LeaderboardEntry;
+use chrono::{Datelike, Duration, Utc};
+
+/// How many members a leaderboard lists
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// Unix timestamp of this week's Monday 00:00 UTC
+pub fn week_start() -> i64 {
+    let today = Utc::now().date_naive();
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    monday
+        .and_hms_opt(0, 0, 0)
+        .map(|start| start.and_utc().timestamp())
+        .unwrap_or_default()
+}
+
+/// Renders a ranked table of the week's top scorers as a text message
+pub fn format_leaderboard(entries: &[LeaderboardEntry]) -> String {
+    if entries.is_empty() {
+        return "🏆 No graded answers in this chat yet this week. Send a type like 'PS' and be the first on the board!".to_string();
+    }
+
+    let mut text = "🏆 This week's leaderboard".to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        let medal = match i {
+            0 => "🥇".to_string(),
+            1 => "🥈".to_string(),
+            2 => "🥉".to_string(),
+            _ => format!("{}.", i + 1),
+        };
+        let answered = entry.correct + entry.incorrect;
+        text.push_str(&format!(
+            "\n{} {} - {}/{} correct ({}%)",
+            medal,
+            entry.display_name.as_deref().unwrap_or(&entry.user_id),
+            entry.correct,
+            answered,
+            entry.correct * 100 / answered.max(1)
+        ));
+    }
+    text
+}
@@ -1,16 +1,231 @@
+use chrono::Timelike;
 use clap::ValueEnum;
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use rand::seq::SliceRandom;
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tempfile::TempDir;
-
-const BOT_API_URL: &str = "https://bot-api.zapps.me";
-const LONG_POLLING_TIMEOUT: u64 = 30;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+use tokio::sync::Semaphore;
+use tracing::{Instrument, debug, error, info, instrument, warn};
+
+pub mod adaptive;
+pub mod admin;
+pub mod anki;
+pub mod answer_key;
+pub mod assignment;
+pub mod badge;
+pub mod builder;
+pub mod bundle;
+pub mod cache;
+pub mod campaign;
+pub mod caption;
+pub mod challenge;
+pub mod chat_config;
+pub mod config;
+pub mod control;
+pub mod daily;
+pub mod difficulty;
+pub mod enrich;
+pub mod error;
+pub mod exclusion;
+pub mod handler;
+pub mod health;
+pub mod hint;
+pub mod history;
+pub mod hosting;
+pub mod http;
+pub mod image_manifest;
+pub mod latex;
+pub mod leaderboard;
+pub mod logging;
+pub mod mathjax;
+pub mod mock;
+pub mod ocr;
+pub mod offset;
+pub mod pipeline;
+pub mod platform;
+pub mod progress;
+pub mod progression;
+pub mod proxy;
+pub mod quiet_hours;
+pub mod quiz;
+pub mod rate_limit;
+pub mod recent;
+pub mod refresh;
+pub mod render;
+pub mod report;
+pub mod retry;
+pub mod schedule;
+pub mod settings;
+pub mod shuffle;
+pub mod shutdown;
+pub mod source;
+pub mod srs;
+pub mod storage;
+pub mod streak;
+pub mod template;
+pub mod theme;
+pub mod topic;
+pub mod user_data;
+pub mod verify;
+pub mod webhook;
+pub mod weekly;
+pub mod zalo;
+pub mod zip_export;
+
+pub use adaptive::{pick_adaptive_type, type_weight};
+pub use admin::{
+    AdminCommand, BroadcastSummary, format_broadcast, format_usage, parse_admin_command,
+};
+pub use anki::{AnkiNote, write_apkg};
+pub use answer_key::{AnswerConfidence, AnswerKey, extract_answer_key, parse_correct_answer};
+pub use assignment::{
+    Assignment, AssignmentStatus, MAX_ASSIGNMENT_QUESTIONS, StudentProgress,
+    format_assignment_status, parse_assign_command,
+};
+pub use badge::{
+    BADGES, Badge, BadgeProgress, LEVELS, Level, earned_badges, find_badge, format_badge_award,
+    format_level_up, level_for, next_level,
+};
+pub use builder::{
+    ApiConfig, BotBuilder, BotService, PollingConfig, RenderingConfig, connect_platform,
+};
+pub use bundle::generate_bundle_html;
+pub use cache::{QuestionCache, default_cache_dir};
+pub use campaign::{Campaign, CampaignPlan, Throttle, default_campaign_path};
+pub use caption::{
+    CaptionContext, CaptionTemplates, CaptionValues, DEFAULT_EXPLANATIONS_CAPTION,
+    DEFAULT_QUESTION_CAPTION, fill_caption,
+};
+pub use challenge::{
+    CHALLENGE_TIME_LIMIT, ChallengePlayer, ChallengeRequest, ChallengeSession,
+    MAX_CHALLENGE_QUESTIONS, parse_challenge_command,
+};
+pub use chat_config::{ChatConfig, DEFAULT_COMMAND_PREFIX, MuteHours};
+pub use config::{BotTokens, Config};
+pub use control::{ControlApi, ControlRequest, ControlResponse, serve_control};
+pub use daily::{DailyPost, DailySchedule, daily_question};
+pub use difficulty::{Difficulty, parse_difficulty, parse_difficulty_request};
+pub use enrich::{DEFAULT_STATS_TTL, QuestionEnricher, QuestionStats, parse_question_stats};
+pub use error::{BotError, Result};
+pub use exclusion::{
+    ExclusionCommand, ExclusionList, default_exclusions_path, parse_exclusion_command,
+    parse_id_list, run_exclusion_command,
+};
+pub use handler::{DEFAULT_HANDLER_PRIORITY, HandlerRegistry, MessageHandler};
+pub use health::{
+    BotHealthReport, BotsHealthReport, DEFAULT_UNHEALTHY_AFTER, HealthCheck, HealthMonitor,
+    HealthReport, serve_bots_health, serve_health,
+};
+pub use hint::{QuestionTimer, format_hint};
+pub use history::{HISTORY_PAGE_SIZE, HistoryCommand, format_history, parse_history_command};
+
+pub use hosting::{
+    DEFAULT_GITHUB_API_URL, DEFAULT_MAX_RELEASE_ASSETS, DirectUpload, DryRunHost, GitHubApp,
+    GitHubAuth, GitHubConfig, GitHubReleaseHost, IMGUR_UPLOAD_URL, ImageHost, ImageHostKind,
+    ImgurHost, LocalHost, MAX_PRESIGN_TTL, NoImageHost, S3Config, S3Host, create_github_release,
+    get_latest_release_id, upload_to_github_release,
+};
+pub use http::{DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT, HttpContext, HttpSettings};
+pub use image_manifest::{HostedImage, ImageManifest, ManifestImages};
+pub use latex::latex_to_unicode;
+pub use leaderboard::{LEADERBOARD_SIZE, format_leaderboard, week_start};
+pub use logging::{LogFormat, init_logging};
+pub use mathjax::{
+    MATHJAX_CDN_URL, MATHJAX_SCRIPT, MathJaxSource, default_mathjax_dir, mathjax_script_url,
+};
+pub use mock::{
+    MOCK_QUESTION_CAP, MOCK_SECTION_QUESTIONS, MOCK_TIME_LIMIT, MockAnswer, MockSection,
+    MockSession,
+};
+pub use ocr::{
+    MAX_SUGGESTIONS, MIN_KEYWORD_MATCHES, QuestionMatch, check_tesseract, keywords,
+    question_id_candidates, rank_matches, recognize_text,
+};
+pub use offset::{OffsetStore, default_offset_path};
+pub use pipeline::{
+    DEFAULT_FETCH_CONCURRENCY, DEFAULT_RENDER_CONCURRENCY, DEFAULT_UPLOAD_CONCURRENCY,
+    PipelineLimits, WarmCache, send_questions_to_users, warm_question_images,
+};
+pub use platform::{
+    BotIdentity, ChatMessage, ChatPlatform, DISCORD_API_URL, DISCORD_MESSAGE_LIMIT, DiscordBot,
+    DryRunPlatform, Platform, TELEGRAM_API_URL, TelegramBot,
+};
+pub use progress::{
+    generate_badges_card_html, generate_mock_report_html, generate_stats_card_html,
+    generate_weekly_report_html,
+};
+pub use progression::{Progression, STARTING_BAND, format_band_up, next_band};
+pub use proxy::{ProxyRoute, ProxySettings};
+pub use quiet_hours::{DndCommand, QUIET_HOURS_PREFERENCE, QueuedPost, parse_dnd_command};
+pub use quiz::{QuizSession, parse_quiz_command};
+pub use rate_limit::{RateDecision, RateLimiter};
+pub use recent::{
+    DEFAULT_RECENT_CAPACITY, DEFAULT_RECENT_WINDOW, RECENT_FLUSH_INTERVAL, RecentSends,
+    default_recent_path,
+};
+pub use refresh::{DEFAULT_REFRESH_INTERVAL, DatabaseDiff, LiveDatabase};
+pub use render::{
+    ChromeRenderer, DEFAULT_RENDER_TIMEOUT, HTML_INDEX_FILE, ImageFormat, KeptHtml, RenderOptions,
+    Renderer, RendererKind, SvgRenderer, WkhtmltoimageRenderer, check_wkhtmltoimage, keep_html,
+    page_path, question_text,
+};
+pub use report::{FlaggedQuestion, ReportFormat, format_reports, parse_flag_command};
+pub use retry::{RetryPolicy, retry};
+pub use schedule::{
+    ScheduledRecipient, parse_recipients, parse_schedule, parse_timezone, run_schedule,
+};
+pub use settings::UserSettings;
+pub use shuffle::{order_code, original_choice, reorder_choices, shown_choice, shuffle_order};
+pub use shutdown::{DEFAULT_DRAIN_TIMEOUT, ShutdownController, shutdown_signal};
+pub use source::{DEFAULT_DATABASE_URL, DatabaseSource};
+pub use srs::ReviewState;
+pub use storage::{
+    ActivityStats, HistoryEntry, LeaderboardEntry, PeriodStats, Storage, UsageStats, UserStats,
+};
+pub use streak::{
+    STREAK_REMINDED_PREFERENCE, format_streak, format_streak_reminder, practice_streak,
+};
+use template::bundled_templates;
+pub use template::{QUESTION_TEMPLATE, QuestionTemplates};
+pub use theme::{Palette, THEME_PREFERENCE, Theme, TypeStyle, parse_theme, type_style};
+pub use topic::{
+    TopicLookup, TopicTags, has_topic, parse_topic_command, pick_random_questions_by_topic,
+    same_topic,
+};
+pub use user_data::{
+    MAX_EXPORT_MESSAGE_CHARS, UserData, UserDataFormat, format_user_data, parse_mydata_command,
+    split_into_messages,
+};
+pub use verify::{VerifyIssue, VerifyProblem, VerifyReport, format_verify_report, verify_database};
+pub use webhook::{
+    SIGNATURE_HEADER, WEBHOOK_EVENTS, WebhookConfig, WebhookEvent, WebhookFormat, Webhooks,
+    webhook_payload, webhook_signature,
+};
+pub use weekly::{WEEKLY_REPORTED_PREFERENCE, WeeklyReport, report_week, week_bounds};
+pub use zalo::{
+    EVENT_EDITED, EVENT_IMAGE, EVENT_MEMBER_JOINED, EVENT_STICKER, EVENT_TEXT, MessageKind,
+    ZaloChat, ZaloEvent, ZaloMessage, ZaloSendResult, ZaloSender, ZaloUpdate, ZaloUpdatesResult,
+};
+pub use zip_export::{MANIFEST_NAME, ZipQuestion, write_question_zip};
+
+/// Chat actions expire after about five seconds, so the typing indicator is resent this often
+const TYPING_REFRESH_INTERVAL: Duration = Duration::from_secs(4);
+
+/// `daily_posts` kind marking a day whose webhook summary went out
+const WEBHOOK_SUMMARY_POST: &str = "webhook_summary";
+/// Messages handled at once by the polling service unless configured otherwise
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum QuestionType {
     /// Reading Comprehension
     RC,
@@ -36,7 +251,7 @@ impl std::fmt::Display for QuestionType {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GmatDatabase {
     #[serde(rename = "RC")]
     pub reading_comprehension: Vec<String>,
@@ -50,7 +265,7 @@ pub struct GmatDatabase {
     pub data_sufficiency: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct QuestionContent {
     pub id: String,
     pub src: String,
@@ -59,77 +274,238 @@ pub struct QuestionContent {
     pub question_type: String,
     pub question: String,
     pub answers: Vec<String>,
+    /// Score-level tag from the source page, e.g. "655-705 Level", when the database provides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<String>,
+    /// Reading passage shared by `sub_questions`, for multi-part (RC) questions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passage: Option<String>,
+    /// Questions about `passage`; each one gets its own image
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_questions: Vec<SubQuestion>,
+    /// Topics such as "geometry" or "assumption", from the database or a [`TopicTags`] file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+    /// Community stats from the source page, attached by a [`QuestionEnricher`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<QuestionStats>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloMessage {
-    #[serde(rename = "from")]
-    pub sender: ZaloSender,
-    pub chat: ZaloChat,
-    pub text: Option<String>,
-    pub photo: Option<String>,
-    pub caption: Option<String>,
-    pub message_id: String,
-    pub date: u64,
+/// One of several questions about a shared passage
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SubQuestion {
+    pub question: String,
+    pub answers: Vec<String>,
+    #[serde(default)]
+    pub explanations: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloSender {
-    pub id: String,
-    pub is_bot: bool,
-    pub display_name: Option<String>,
-}
+impl QuestionContent {
+    /// The official answer found in the explanations, with how sure the extraction is
+    pub fn answer_key(&self) -> Option<AnswerKey> {
+        extract_answer_key(self)
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloChat {
-    pub id: String,
-    pub chat_type: String,
-}
+    /// The official answer's letter, see [`QuestionContent::answer_key`]
+    pub fn correct_answer(&self) -> Option<char> {
+        self.answer_key().map(|key| key.letter)
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloUpdate {
-    pub message: Option<ZaloMessage>,
-    pub event_name: String,
-}
+    /// Splits a multi-part question into the passage followed by each sub-question,
+    /// so none of them has to squeeze into one image; other questions are a single part
+    pub fn parts(&self) -> Vec<QuestionContent> {
+        if self.sub_questions.is_empty() {
+            return vec![self.clone()];
+        }
+        let part = |id: String, question: &str, answers: &[String], explanations: &[String]| {
+            QuestionContent {
+                id,
+                question: question.to_string(),
+                answers: answers.to_vec(),
+                explanations: explanations.to_vec(),
+                passage: None,
+                sub_questions: Vec::new(),
+                ..self.clone()
+            }
+        };
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(untagged)]
-pub enum ZaloUpdatesResult {
-    Single(ZaloUpdate),
-    Multiple(Vec<ZaloUpdate>),
-    Empty(serde_json::Value),
+        let mut parts = vec![part(
+            format!("{}-passage", self.id),
+            self.passage.as_deref().unwrap_or(&self.question),
+            &[],
+            &[],
+        )];
+        parts.extend(self.sub_questions.iter().enumerate().map(|(i, sub)| {
+            part(
+                format!("{}-{}", self.id, i + 1),
+                &sub.question,
+                &sub.answers,
+                &sub.explanations,
+            )
+        }));
+        parts
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloUpdatesResponse {
-    pub ok: bool,
-    pub result: ZaloUpdatesResult,
+/// A question that was delivered to a chat and is waiting for an A–E reply
+#[derive(Debug, Clone)]
+pub struct PendingQuestion {
+    pub content: QuestionContent,
+    pub question_type: QuestionType,
+    pub correct_answer: Option<char>,
+    /// When the question was delivered (unix seconds)
+    pub sent_at: i64,
+    /// Order the choices were shown in when they were shuffled; see [`shuffle_order`]
+    pub choice_order: Option<Vec<usize>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloSendPhotoResponse {
-    pub ok: bool,
-    pub result: ZaloSendResult,
-}
+impl PendingQuestion {
+    /// Original letter of the choice the chat saw as `shown`
+    pub fn original_answer(&self, shown: char) -> char {
+        self.choice_order
+            .as_deref()
+            .and_then(|order| original_choice(order, shown))
+            .unwrap_or(shown)
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloSendResult {
-    pub message_id: String,
-    pub date: u64,
-}
+    /// Letter the chat saw an original choice as
+    pub fn shown_answer(&self, original: char) -> char {
+        self.choice_order
+            .as_deref()
+            .and_then(|order| shown_choice(order, original))
+            .unwrap_or(original)
+    }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct ZaloSendMessageResponse {
-    pub ok: bool,
-    pub result: ZaloSendResult,
+    /// The correct choice as the chat saw it, like "(B)", with its letter in the
+    /// explanations when the choices were shuffled: "(B), which is (D) in the explanations"
+    pub fn correct_answer_label(&self) -> Option<String> {
+        let correct = self.correct_answer?;
+        let shown = self.shown_answer(correct);
+        Some(if shown == correct {
+            format!("({})", correct)
+        } else {
+            format!("({}), which is ({}) in the explanations", shown, correct)
+        })
+    }
 }
 
 pub struct ZaloBot {
     pub bot_token: String,
-    pub client: reqwest::Client,
+    /// Zalo Bot API client used when no other platform is set
+    pub api: zalo::Client,
+    /// Clients for the bot's other requests, e.g. downloading screenshots
+    pub http: HttpContext,
+    /// Last question sent to each chat, keyed by chat ID
+    pub pending_questions: Mutex<HashMap<String, PendingQuestion>>,
+    /// Persistent user state; when absent the bot only keeps in-memory state
+    pub storage: Option<Storage>,
+    /// Backend used to turn question pages into images
+    pub renderer: Arc<dyn Renderer>,
+    /// Quizzes in progress, keyed by chat ID
+    pub quiz_sessions: Mutex<HashMap<String, QuizSession>>,
+    /// Mock tests in progress, keyed by chat ID
+    pub mock_sessions: Mutex<HashMap<String, MockSession>>,
+    /// Head-to-head challenges in progress, each found by either player's chat ID
+    pub challenges: Mutex<Vec<ChallengeSession>>,
+    /// How far "history more" has paged back, keyed by chat ID
+    pub history_offsets: Mutex<HashMap<String, usize>>,
+    /// Transport override, e.g. Telegram; the bot talks to Zalo itself when absent
+    pub platform: Option<Box<dyn ChatPlatform>>,
+    /// Backoff used for Zalo API calls
+    pub retry_policy: RetryPolicy,
+    /// Display name members use to @mention the bot in group chats
+    pub bot_name: Option<String>,
+    /// Where question content is fetched from
+    pub database_source: DatabaseSource,
+    /// On-disk copy of question content; when absent every question is fetched
+    pub question_cache: Option<QuestionCache>,
+    /// Adds source-page stats to rendered questions; none are fetched when absent
+    pub enricher: Option<QuestionEnricher>,
+    /// Topics for questions the database doesn't tag, used by "topic" requests
+    pub topic_tags: Option<TopicTags>,
+    /// Questions each chat got lately, never picked for it again within the window
+    pub recent_sends: RecentSends,
+    /// Broken questions random picks leave out, managed with the admin "exclude" commands
+    pub exclusions: Arc<ExclusionList>,
+    /// Acknowledged `getUpdates` position; when absent every poll starts from the server's default
+    pub offset_store: Option<OffsetStore>,
+    /// How long an unanswered question waits for a hint, and again for the answer
+    pub hint_delay: Option<Duration>,
+    /// Hint/reveal schedules for unanswered questions, keyed by chat ID
+    pub question_timers: Mutex<HashMap<String, QuestionTimer>>,
+    /// Messages the polling service handles at once, across different chats
+    pub concurrency: usize,
+    /// Per-sender flood protection; when absent every message is handled
+    pub rate_limiter: Option<RateLimiter>,
+    /// Users allowed to run admin commands (broadcast, ban, unban, usage, exclude)
+    pub admin_ids: HashSet<String>,
+    /// Colour scheme for users who haven't picked their own
+    pub theme: Theme,
+    /// Templates question images are rendered from
+    pub templates: QuestionTemplates,
+    /// Captions under question and explanations images
+    pub captions: CaptionTemplates,
+    /// Where bot events are posted for monitoring
+    pub webhooks: Webhooks,
+    /// Stops polling on Ctrl+C or SIGTERM and bounds how long in-flight messages may take to finish
+    pub shutdown: ShutdownController,
+    /// Local hour after which users with an active streak who haven't practiced today get a reminder
+    pub streak_reminder_hour: Option<u32>,
+    /// Question-of-the-day posting times for subscribed chats; when absent nothing is posted
+    pub daily_schedule: Option<DailySchedule>,
+    /// Local hour on Sundays the weekly progress report goes out; when absent none is sent
+    pub weekly_report_hour: Option<u32>,
+    /// Quiet hours of users who haven't set their own with "dnd"
+    pub default_quiet_hours: Option<MuteHours>,
+    /// Last successful poll and send, reported by the health check
+    pub health: Arc<HealthMonitor>,
+    /// Where the polling service serves `/healthz`; when absent there is no endpoint
+    pub health_check: Option<HealthCheck>,
+    /// Where the polling service serves the control API; when absent there is none
+    pub control_api: Option<ControlApi>,
+    /// Question images sent straight from a prebuilt manifest; when set nothing is rendered
+    /// or uploaded, and questions it lacks go out as text
+    pub manifest: Option<ManifestImages>,
+    /// When users move up to harder questions of a type; when absent they get any difficulty
+    pub progression: Option<Progression>,
+    /// Screenshots sent in private chats are read with tesseract to find the question they show
+    pub ocr: bool,
+    /// Shuffle every question's answer choices, not only for users who turned it on
+    pub shuffle_answers: bool,
+    /// Question images rendered and uploaded in the background when polling starts
+    pub warm_cache: Option<WarmCache>,
+    /// How often the question index is fetched again while polling; never when absent
+    pub database_refresh: Option<Duration>,
+    /// Custom commands, tried before or after the built-in ones by priority
+    pub handlers: HandlerRegistry,
+    /// Daily posts and broadcasts held back by a user's quiet hours, keyed by chat ID
+    pub quiet_queue: Mutex<HashMap<String, Vec<QueuedPost>>>,
 }
 
 impl GmatDatabase {
+    /// Keeps only the questions `keep` accepts
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        for question_type in QuestionType::value_variants() {
+            let ids = match question_type {
+                QuestionType::RC => &mut self.reading_comprehension,
+                QuestionType::SC => &mut self.sentence_correction,
+                QuestionType::CR => &mut self.critical_reasoning,
+                QuestionType::PS => &mut self.problem_solving,
+                QuestionType::DS => &mut self.data_sufficiency,
+            };
+            ids.retain(|id| keep(id));
+        }
+    }
+
+    /// The type a question ID is listed under
+    pub fn find_question_type(&self, question_id: &str) -> Option<QuestionType> {
+        QuestionType::value_variants().iter().copied().find(|t| {
+            self.get_questions_by_type(t)
+                .iter()
+                .any(|id| id == question_id)
+        })
+    }
+
     pub fn get_questions_by_type(&self, question_type: &QuestionType) -> &Vec<String> {
         match question_type {
             QuestionType::RC => &self.reading_comprehension,
@@ -167,7 +543,7 @@ impl ZaloBot {
     /// * `content` - The question content to send
     /// * `question_type` - Optional question type
     /// * `output_dir` - Directory to store temporary files
-    /// * `github_config` - GitHub configuration for uploads
+    /// * `image_host` - Where to upload the rendered image
     /// * `show_explanations` - Whether to include explanations in the question
     pub async fn send_question(
         &self,
@@ -175,503 +551,5643 @@ impl ZaloBot {
         content: &QuestionContent,
         question_type: Option<&QuestionType>,
         output_dir: &str,
-        github_config: &GitHubConfig,
+        image_host: &dyn ImageHost,
+        show_explanations: bool,
+    ) -> Result<()> {
+        self.send_question_in(
+            None,
+            chat_id,
+            content,
+            question_type,
+            output_dir,
+            image_host,
+            show_explanations,
+        )
+        .await
+    }
+
+    /// [`ZaloBot::send_question`] captioned for a context and the question's 1-based
+    /// place out of a total, or for the chat's running quiz, mock test or challenge when `None`
+    #[allow(clippy::too_many_arguments)]
+    async fn send_question_in(
+        &self,
+        position: Option<(CaptionContext, usize, usize)>,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: Option<&QuestionType>,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
         show_explanations: bool,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<()> {
         // Determine the question type (use provided or default to ProblemSolving)
         let q_type = question_type.unwrap_or(&QuestionType::PS);
 
-        // Generate the question image
-        let image_path =
-            render_question_to_image(content, q_type, show_explanations, output_dir).await?;
-
-        self.upload_and_send(chat_id, &image_path, "You can do it! 💪", github_config)
-            .await?;
+        // Explanations name the original letters, so choices only move while the answer is hidden
+        let choice_order = if !show_explanations && self.shuffles_answers(chat_id) {
+            shuffle_order(content, *q_type, &mut rand::thread_rng())
+        } else {
+            None
+        };
 
+        let caption = self.question_caption(chat_id, content, *q_type, position);
+        self.send_question_image(
+            chat_id,
+            content,
+            q_type,
+            show_explanations,
+            choice_order.as_deref(),
+            &caption,
+            output_dir,
+            image_host,
+        )
+        .await?;
+        self.question_delivered(chat_id, content, q_type, choice_order)
+            .await;
         Ok(())
     }
 
-    pub fn new(bot_token: String) -> Self {
-        Self {
-            bot_token,
-            client: reqwest::Client::new(),
-        }
+    /// Whether questions for this chat go out with their choices shuffled
+    fn shuffles_answers(&self, chat_id: &str) -> bool {
+        self.shuffle_answers || self.user_settings(chat_id).shuffle_answers
     }
 
-    pub async fn get_updates(&self) -> Result<Vec<ZaloMessage>, Box<dyn std::error::Error>> {
-        let url = format!("{}/bot{}/getUpdates", BOT_API_URL, self.bot_token);
-
-        println!("🌐 Making API request to: {}", url);
-        println!("📤 Request payload: {{\"timeout\": {LONG_POLLING_TIMEOUT}}}");
+    /// Caption under a question image, from the template for its context (see
+    /// [`ZaloBot::send_question_in`]) in the user's language
+    fn question_caption(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: QuestionType,
+        position: Option<(CaptionContext, usize, usize)>,
+    ) -> String {
+        let (context, index, total) = position.unwrap_or_else(|| self.session_position(chat_id));
+        let values = CaptionValues::new(content, question_type)
+            .with_streak(self.streak_line(chat_id))
+            .with_position(index, total);
+        let language = self.user_settings(chat_id).language;
+        self.captions.render(context, language.as_deref(), &values)
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({
-                "timeout": LONG_POLLING_TIMEOUT,
-            }))
-            .send()
-            .await?;
+    /// Caption of the `index`th of `total` questions sent by the `send` command
+    fn send_caption(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: QuestionType,
+        index: usize,
+        total: usize,
+    ) -> String {
+        let values = CaptionValues::new(content, question_type)
+            .with_streak(self.streak_line(chat_id))
+            .with_position(index, total);
+        let language = self.user_settings(chat_id).language;
+        self.captions
+            .render(CaptionContext::Send, language.as_deref(), &values)
+    }
 
-        let status = response.status();
-        println!("📥 Response status: {}", status);
+    /// Caption of the explanations image that follows an answer
+    fn explanations_caption(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: QuestionType,
+    ) -> String {
+        let values = CaptionValues::new(content, question_type);
+        let language = self.user_settings(chat_id).language;
+        self.captions
+            .render(CaptionContext::Explanations, language.as_deref(), &values)
+    }
 
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            println!("❌ Error response body: {}", error_text);
-            return Err(format!("Failed to get updates: {} - {}", status, error_text).into());
+    /// Which session the chat's next question belongs to and its 1-based place in it
+    fn session_position(&self, chat_id: &str) -> (CaptionContext, usize, usize) {
+        if let Some(session) = self.quiz_sessions.lock().unwrap().get(chat_id) {
+            return (CaptionContext::Quiz, session.next_number(), session.total);
         }
+        if let Some(session) = self.mock_sessions.lock().unwrap().get(chat_id) {
+            return (
+                CaptionContext::Mock,
+                session.next_number(),
+                session.plan.len(),
+            );
+        }
+        let challenge = self
+            .with_challenge(chat_id, |session| {
+                session
+                    .next_question(chat_id)
+                    .map(|(number, _)| (number, session.questions.len()))
+            })
+            .flatten();
+        match challenge {
+            Some((number, total)) => (CaptionContext::Challenge, number, total),
+            None => (CaptionContext::Question, 1, 1),
+        }
+    }
 
-        let response_text = response.text().await?;
-
-        // Always log the response for debugging
-        println!("🔍 DEBUG - Full API Response:");
-        println!("----------------------------------------");
-        println!("{}", response_text);
-        println!("----------------------------------------");
-        println!("📏 Response length: {} bytes", response_text.len());
+    /// Offers the answer buttons and waits for the chat's answer to a question it just received
+    async fn question_delivered(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        q_type: &QuestionType,
+        choice_order: Option<Vec<usize>>,
+    ) {
+        if let Err(e) = self
+            .send_choices(chat_id, "Your answer?", &ANSWER_CHOICES)
+            .await
+        {
+            warn!("Failed to send answer buttons: {}", e);
+        }
 
-        // Try to pretty print the JSON for better readability
-        if let Ok(parsed_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
-            if let Ok(pretty_json) = serde_json::to_string_pretty(&parsed_json) {
-                println!("🎨 Pretty JSON:");
-                println!("----------------------------------------");
-                println!("{}", pretty_json);
-                println!("----------------------------------------");
+        // Remember the question so a follow-up "A".."E" reply can be graded
+        let pending = PendingQuestion {
+            content: content.clone(),
+            question_type: *q_type,
+            correct_answer: content.correct_answer(),
+            sent_at: chrono::Utc::now().timestamp(),
+            choice_order,
+        };
+        self.recent_sends.record(chat_id, &content.id);
+        if let Some(storage) = &self.storage {
+            let saved = storage
+                .record_question_sent(chat_id, &content.id, q_type)
+                .and_then(|_| storage.save_pending_question(chat_id, &pending));
+            if let Err(e) = saved {
+                warn!("Failed to persist sent question: {}", e);
             }
         }
-
-        let updates: ZaloUpdatesResponse = serde_json::from_str(&response_text).map_err(|e| {
-            format!(
-                "Failed to parse JSON response: {}\n\nRaw response: {}\n\nError details: {:?}",
-                e, response_text, e
-            )
-        })?;
-
-        println!("✅ Successfully parsed response: ok={}", updates.ok);
-
-        if !updates.ok {
-            return Err(format!("API returned error: {}", response_text).into());
+        self.pending_questions
+            .lock()
+            .unwrap()
+            .insert(chat_id.to_string(), pending);
+        self.webhooks.emit(WebhookEvent::QuestionSent {
+            chat_id: chat_id.to_string(),
+            question_id: content.id.clone(),
+            question_type: *q_type,
+        });
+
+        // Quizzes, mock tests and challenges are timed already and keep their answers to themselves
+        if let Some(delay) = self.hint_delay
+            && !self.quiz_sessions.lock().unwrap().contains_key(chat_id)
+            && !self.mock_sessions.lock().unwrap().contains_key(chat_id)
+            && !self.in_challenge(chat_id)
+        {
+            self.question_timers
+                .lock()
+                .unwrap()
+                .insert(chat_id.to_string(), QuestionTimer::new(&content.id, delay));
         }
+    }
 
-        let mut messages = Vec::new();
+    /// Assembles a polling bot step by step, see [`BotBuilder`]
+    pub fn builder(bot_token: impl Into<String>) -> BotBuilder {
+        BotBuilder::new(bot_token)
+    }
 
-        match updates.result {
-            ZaloUpdatesResult::Single(update) => {
-                println!(
-                    "📝 Received single update with event: {}",
-                    update.event_name
-                );
-                if let Some(message) = update.message {
-                    println!(
-                        "💬 Message from user: {} in chat: {}",
-                        message.sender.id, message.chat.id
-                    );
-                    messages.push(message);
-                }
-            }
-            ZaloUpdatesResult::Multiple(update_list) => {
-                println!("📝 Received {} updates", update_list.len());
-                for (i, update) in update_list.iter().enumerate() {
-                    println!("  Update {}: event={}", i + 1, update.event_name);
-                    if let Some(message) = &update.message {
-                        println!(
-                            "    Message from user: {} in chat: {}",
-                            message.sender.id, message.chat.id
-                        );
-                    }
-                }
-                for update in update_list {
-                    if let Some(message) = update.message {
-                        messages.push(message);
-                    }
-                }
-            }
-            ZaloUpdatesResult::Empty(value) => {
-                println!("📝 Received empty/unknown result: {:?}", value);
-            }
+    pub fn new(bot_token: String) -> Self {
+        Self {
+            api: zalo::Client::new(bot_token.clone()),
+            http: HttpContext::default(),
+            bot_token,
+            pending_questions: Mutex::new(HashMap::new()),
+            storage: None,
+            renderer: Arc::new(WkhtmltoimageRenderer::default()),
+            quiz_sessions: Mutex::new(HashMap::new()),
+            mock_sessions: Mutex::new(HashMap::new()),
+            challenges: Mutex::new(Vec::new()),
+            history_offsets: Mutex::new(HashMap::new()),
+            platform: None,
+            retry_policy: RetryPolicy::default(),
+            bot_name: None,
+            database_source: DatabaseSource::default(),
+            question_cache: None,
+            enricher: None,
+            topic_tags: None,
+            recent_sends: RecentSends::default(),
+            exclusions: Arc::default(),
+            offset_store: None,
+            hint_delay: None,
+            question_timers: Mutex::new(HashMap::new()),
+            concurrency: DEFAULT_CONCURRENCY,
+            rate_limiter: None,
+            admin_ids: HashSet::new(),
+            theme: Theme::default(),
+            templates: QuestionTemplates::bundled(),
+            captions: CaptionTemplates::default(),
+            webhooks: Webhooks::default(),
+            shutdown: ShutdownController::default(),
+            streak_reminder_hour: None,
+            default_quiet_hours: None,
+            daily_schedule: None,
+            weekly_report_hour: None,
+            health: Arc::default(),
+            health_check: None,
+            control_api: None,
+            manifest: None,
+            progression: None,
+            ocr: false,
+            shuffle_answers: false,
+            warm_cache: None,
+            database_refresh: None,
+            handlers: HandlerRegistry::default(),
+            quiet_queue: Mutex::new(HashMap::new()),
         }
+    }
 
-        println!("📊 Total messages extracted: {}", messages.len());
-        Ok(messages)
+    /// Replaces the default wkhtmltoimage renderer; pass an `Arc` to share one between bots
+    pub fn with_renderer(mut self, renderer: impl Into<Arc<dyn Renderer>>) -> Self {
+        self.renderer = renderer.into();
+        self
     }
 
-    pub async fn start_polling_service(
-        &self,
-        database: &GmatDatabase,
-        output_dir: &str,
-        github_config: &GitHubConfig,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        println!("🔄 Starting long polling service...");
-        println!("📱 Bot is now listening for messages. Send any message to get a GMAT question!");
-        println!("🛑 Press Ctrl+C to stop the bot");
+    /// Attaches a persistent store so history and pending questions survive restarts
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
 
-        loop {
-            tokio::select! {
-                // Handle shutdown signal
-                _ = tokio::signal::ctrl_c() => {
-                    println!("\n🛑 Received shutdown signal. Stopping bot gracefully...");
-                    break;
-                }
+    /// Sends a hint when a question stays unanswered for `delay`, and reveals the
+    /// answer after another `delay`; only the polling service runs these timers
+    pub fn with_hint_delay(mut self, delay: Duration) -> Self {
+        self.hint_delay = Some(delay);
+        self
+    }
 
-                // Handle API updates
-                result = self.get_updates() => {
-                    match result {
-                        Ok(messages) => {
-                            if !messages.is_empty() {
-                                println!("\n📨 Received {} new message(s)", messages.len());
+    /// Caps how many messages are handled at once; messages in one chat still run in order
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
 
-                                for message in messages {
-                                    self.handle_message(
-                                        &message,
-                                        database,
-                                        output_dir,
-                                        github_config,
-                                    )
-                                    .await;
-                                }
-                            } else {
-                                println!("⏳ No new messages (normal for long polling)");
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("⚠️  Error getting updates: {}", e);
+    /// Limits each sender to `limit` messages per `interval`
+    pub fn with_rate_limit(mut self, limit: u32, interval: Duration) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(limit, interval));
+        self
+    }
 
-                            // Check if it's a timeout (normal for long polling) or a real error
-                            if e.to_string().contains("timeout") {
-                                println!("🔄 Polling timeout, continuing...");
-                            } else {
-                                println!("🔄 Error occurred, retrying in 5 seconds...");
-                                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    /// Lets these user IDs run admin commands
+    pub fn with_admin_ids(mut self, admin_ids: impl IntoIterator<Item = String>) -> Self {
+        self.admin_ids = admin_ids.into_iter().collect();
+        self
+    }
 
-        println!("✅ Bot stopped successfully");
-        Ok(())
+    /// Sets the theme used for users without a saved preference
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
     }
 
-    async fn handle_message(
-        &self,
-        message: &ZaloMessage,
-        database: &GmatDatabase,
-        output_dir: &str,
-        github_config: &GitHubConfig,
-    ) {
-        let chat_id = &message.chat.id;
-        let sender_id = &message.sender.id;
+    /// Renders question images from custom templates instead of the bundled ones
+    pub fn with_templates(mut self, templates: QuestionTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
 
-        let message_text = message.text.as_deref().unwrap_or("").trim();
+    /// Captions question and explanations images from `captions` instead of the built-in text
+    pub fn with_captions(mut self, captions: CaptionTemplates) -> Self {
+        self.captions = captions;
+        self
+    }
 
-        println!(
-            "💬 Processing message '{}' from user: {} in chat: {}",
-            message_text, sender_id, chat_id
-        );
+    pub fn with_webhooks(mut self, webhooks: Webhooks) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
 
-        // Check if the message is a question ID (numeric)
-        if let Ok(question_id) = message_text.parse::<u32>() {
-            // User sent a direct question ID
-            println!("🔍 User requested question with ID: {}", question_id);
+    /// Sets how long shutdown waits for in-flight messages before giving up on them
+    pub fn with_drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.shutdown = ShutdownController::new(drain_timeout);
+        self
+    }
 
-            // Inform user that the bot is processing the request
-            if let Err(e) = self
-                .send_message(
-                    chat_id,
-                    &format!("⏳ Fetching question #{}...", question_id),
-                )
-                .await
-            {
-                eprintln!("❌ Failed to send processing message: {}", e);
-            }
+    /// Runs the bot on another chat platform instead of Zalo
+    /// Reminds users with an active streak at `hour` (0–23, in their chat's timezone)
+    /// if they haven't practiced yet that day; needs storage
+    pub fn with_streak_reminder_hour(mut self, hour: u32) -> Self {
+        self.streak_reminder_hour = Some(hour);
+        self
+    }
 
-            // Try to fetch the specific question
-            match fetch_question_content(&question_id.to_string()).await {
-                Ok(content) => {
-                    // Generate and send the question image with explanations
-                    if let Err(e) = self
-                        .send_question(chat_id, &content, None, output_dir, github_config, true) // Always show explanations when the user requested for a specific question
-                        .await
-                    {
-                        eprintln!("❌ Failed to send question: {}", e);
-                        let _ = self.send_message(chat_id, "❌ Failed to process the requested question. Please try again later.").await;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("❌ Failed to fetch question: {}", e);
-                    let _ = self.send_message(chat_id, &format!("💁 We don't have the question #{} your are looking for. Please try another one.", question_id)).await;
-                }
-            }
-            return;
-        }
+    /// Holds proactive messages during `quiet_hours` for users who haven't set their own
+    pub fn with_default_quiet_hours(mut self, quiet_hours: MuteHours) -> Self {
+        self.default_quiet_hours = Some(quiet_hours);
+        self
+    }
 
-        // Parse message to determine question type
-        let requested_type = match message_text.to_lowercase().as_str() {
-            "rc" => Some(QuestionType::RC),
-            "sc" => Some(QuestionType::SC),
-            "cr" => Some(QuestionType::CR),
-            "ps" => Some(QuestionType::PS),
-            "ds" => Some(QuestionType::DS),
-            _ => None,
-        };
+    /// Posts the question of the day, and later its answer, to subscribed chats; needs storage
+    pub fn with_daily_schedule(mut self, daily_schedule: DailySchedule) -> Self {
+        self.daily_schedule = Some(daily_schedule);
+        self
+    }
 
-        if let Some(q_type) = requested_type {
-            // User requested a specific question type
-            println!("🎯 User requested {} questions", q_type);
+    /// Sends active users a progress report image on Sundays at `hour` (0–23, in
+    /// their chat's timezone); needs storage
+    pub fn with_weekly_report_hour(mut self, hour: u32) -> Self {
+        self.weekly_report_hour = Some(hour);
+        self
+    }
 
-            // Inform user that the bot is processing the request
-            if let Err(e) = self
-                .send_message(chat_id, "⏳ Processing your request, please wait...")
-                .await
-            {
-                eprintln!("❌ Failed to send processing message: {}", e);
-            }
+    /// Serves `/healthz` while polling, failing once `getUpdates` has failed for `unhealthy_after`
+    pub fn with_health_check(mut self, health_check: HealthCheck) -> Self {
+        self.health_check = Some(health_check);
+        self
+    }
 
-            // Pick a random question of the requested type
-            let mut attempts = 0;
-            let max_attempts = 3;
-            let mut last_error = None;
+    /// Serves the control API while polling, for dashboards and cron jobs
+    pub fn with_control_api(mut self, control_api: ControlApi) -> Self {
+        self.control_api = Some(control_api);
+        self
+    }
 
-            while attempts < max_attempts {
-                let selected_questions = pick_random_questions(database, &Some(q_type), 1);
+    /// Sends question images only from `manifest`'s URLs, so no renderer or image host
+    /// is needed; questions it lacks are sent as text
+    pub fn with_manifest(mut self, manifest: ManifestImages) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
 
-                if selected_questions.is_empty() {
-                    let error_msg = format!(
-                        "⚠️ Sorry, no {} questions are available at the moment. Please try another type.",
-                        q_type
-                    );
-                    if let Err(e) = self.send_message(chat_id, &error_msg).await {
-                        eprintln!("❌ Failed to send error message: {}", e);
-                    }
-                    return;
-                }
+    /// Serves each user easy questions of a type until they keep getting them right, then
+    /// medium and hard ones, telling them when they level up; needs storage
+    pub fn with_progression(mut self, progression: Progression) -> Self {
+        self.progression = Some(progression);
+        self
+    }
 
-                let (selected_type, question_id) = &selected_questions[0];
-                println!("🎯 Selected question: {} ({})", question_id, selected_type);
-
-                // Fetch question content
-                match fetch_question_content(question_id).await {
-                    Ok(content) => {
-                        // Use send_question to handle the rest
-                        match self
-                            .send_question(
-                                chat_id,
-                                &content,
-                                Some(selected_type),
-                                output_dir,
-                                github_config,
-                                false, // Don't show explanations for random questions
-                            )
-                            .await
-                        {
-                            Ok(()) => {
-                                println!(
-                                    "✅ Successfully sent {} question {} to user {}",
-                                    selected_type, question_id, sender_id
-                                );
-                                return;
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "❌ Failed to send question to user {}: {}",
-                                    sender_id, e
-                                );
-                                last_error = Some(format!("Failed to send question: {}", e));
-                                attempts += 1;
-                                if attempts < max_attempts {
-                                    println!(
-                                        "🔄 Retrying with another question (attempt {}/{})...",
-                                        attempts + 1,
-                                        max_attempts
-                                    );
-                                }
-                                continue;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("❌ Failed to fetch question {}: {}", question_id, e);
-                        last_error = Some(format!("Failed to fetch question: {}", e));
-                        break;
-                    }
-                }
-            }
+    /// Reads screenshots with OCR to find the question they show; needs `tesseract`
+    pub fn with_ocr(mut self) -> Self {
+        self.ocr = true;
+        self
+    }
 
-            // If we reach here, all attempts failed
-            let error_msg = last_error.unwrap_or_else(|| {
-                "⚠️ Sorry, something went wrong and your request could not be processed."
-                    .to_string()
-            });
-            if let Err(e) = self.send_message(chat_id, &error_msg).await {
-                eprintln!("❌ Failed to send error message: {}", e);
-            }
-            return;
-        } else {
-            // User message doesn't match any question type, send help message
-            let help_message = format!(
-                "Hello! 👋 I'm your GMAT practice bot.\n\n\
-                To get a question, please send one of these types:\n\n\
-                ✏️ **SC** - Sentence Correction\n\
-                🧠 **CR** - Critical Reasoning\n\
-                🔢 **PS** - Problem Solving\n\
-                📊 **DS** - Data Sufficiency\n\n\
-                Just type the abbreviation (like 'PS' or 'ds') to get a random question of that type!"
-            );
+    /// Shows answer choices in a random order for everyone; grading still uses the original letters
+    pub fn with_shuffled_answers(mut self) -> Self {
+        self.shuffle_answers = true;
+        self
+    }
 
-            match self.send_message(chat_id, &help_message).await {
-                Ok(()) => {
-                    println!(
-                        "💡 Sent help message to user {} (unrecognized input: '{}')",
-                        sender_id, message_text
-                    );
-                }
-                Err(e) => {
-                    eprintln!(
-                        "❌ Failed to send help message to user {}: {}",
-                        sender_id, e
-                    );
-                }
-            }
-        }
+    /// Renders and uploads these questions at startup so their first requests are instant
+    pub fn with_warm_cache(mut self, warm_cache: WarmCache) -> Self {
+        self.warm_cache = Some(warm_cache);
+        self
     }
 
-    pub async fn send_photo(
-        &self,
-        chat_id: &str,
-        photo: &str,
-        caption: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("{}/bot{}/sendPhoto", BOT_API_URL, self.bot_token);
-
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({
-                "chat_id": chat_id,
-                "photo": photo,
-                "caption": caption
-            }))
-            .send()
-            .await?;
+    /// Fetches the question index again every `interval` while polling, so newly
+    /// published questions are picked up without a restart
+    pub fn with_database_refresh(mut self, interval: Duration) -> Self {
+        self.database_refresh = Some(interval);
+        self
+    }
 
-        let status = response.status();
-        let text = response.text().await?;
+    /// Adds a custom command; see [`MessageHandler`]
+    pub fn with_handler(mut self, handler: impl MessageHandler + 'static) -> Self {
+        self.handlers.register(Arc::new(handler));
+        self
+    }
 
-        if !status.is_success() {
-            return Err(format!("Failed to send photo: {} - {}", status, text).into());
-        }
+    pub fn with_platform(mut self, platform: Box<dyn ChatPlatform>) -> Self {
+        self.platform = Some(platform);
+        self
+    }
 
-        // Log the raw response for debugging
-        println!("🔍 sendPhoto raw response: {}", text);
-        // Try to parse only if ok: true
-        let json: serde_json::Value = serde_json::from_str(&text)?;
-        if json.get("ok") == Some(&serde_json::Value::Bool(true)) {
-            // Only try to parse result if present
-            if json.get("result").is_some() {
-                let _result: ZaloSendPhotoResponse = serde_json::from_value(json)?;
-                println!("  ✅ Photo sent successfully to chat: {}", chat_id);
-                Ok(())
-            } else {
-                Err("Photo sent but no result field in response".into())
-            }
-        } else {
-            // Remove duplicated "Failed to send photo:" in error message
-            let err_msg = if let Some(desc) = json.get("description").and_then(|d| d.as_str()) {
-                format!("Failed to send photo: {}", desc)
-            } else {
-                text
-            };
-            Err(err_msg.into())
-        }
+    /// Replaces the default retry policy for Zalo API calls
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self.api = self.api.with_retry_policy(retry_policy);
+        self
     }
 
-    pub async fn upload_and_send(
-        &self,
-        chat_id: &str,
-        image_path: &str,
-        caption: &str,
-        github_config: &GitHubConfig,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // Upload to GitHub release first, then send the URL
-        let github_url = upload_to_github_release(
-            &github_config.repo,
-            github_config.release_id,
-            &github_config.token,
-            image_path,
-        )
-        .await?;
+    /// Replaces the default Zalo Bot API client, e.g. to change its base URL or timeouts
+    pub fn with_api_client(mut self, api: zalo::Client) -> Self {
+        self.api = api;
+        self
+    }
 
-        if let Err(e) = std::fs::remove_file(&image_path) {
-            eprintln!("⚠️ Failed to remove temporary file {}: {}", image_path, e);
-        }
-        self.send_photo(chat_id, &github_url, caption).await
+    /// Sends the bot's requests through `http`: those of its current Zalo API client,
+    /// database source and webhooks, and its own; a platform set with
+    /// [`ZaloBot::with_platform`] keeps its client
+    pub fn with_http(mut self, http: HttpContext) -> Self {
+        self.api = self
+            .api
+            .with_http_client(http.client(ProxyRoute::Chat).clone());
+        self.database_source = self
+            .database_source
+            .with_http_client(http.client(ProxyRoute::Database).clone());
+        self.webhooks = self
+            .webhooks
+            .with_http_client(http.client(ProxyRoute::Webhook).clone());
+        self.http = http;
+        self
     }
 
-    pub async fn send_message(
-        &self,
-        chat_id: &str,
-        text: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("{}/bot{}/sendMessage", BOT_API_URL, self.bot_token);
-
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({
-                "chat_id": chat_id,
-                "text": text
-            }))
-            .send()
-            .await?;
+    /// Lets group members address the bot with `@bot_name`
+    pub fn with_bot_name(mut self, bot_name: String) -> Self {
+        self.bot_name = Some(bot_name);
+        self
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to send message: {} - {}", status, error_text).into());
-        }
+    /// Fetches questions from a mirror or local directory instead of the hosted bank
+    pub fn with_database_source(mut self, database_source: DatabaseSource) -> Self {
+        self.database_source = database_source;
+        self
+    }
 
-        let _result: ZaloSendMessageResponse = response.json().await?;
-        println!("  ✅ Message sent successfully to chat: {}", chat_id);
-        Ok(())
+    /// Serves question content from disk before hitting the question database
+    pub fn with_question_cache(mut self, question_cache: QuestionCache) -> Self {
+        self.question_cache = Some(question_cache);
+        self
     }
-}
 
-pub async fn fetch_gmat_database() -> Result<GmatDatabase, Box<dyn std::error::Error>> {
-    let url = "https://mister-teddy.github.io/gmat-database/index.json";
-    let response = reqwest::get(url).await?;
-    let database: GmatDatabase = response.json().await?;
-    Ok(database)
-}
+    /// Shows answer stats from each question's source page in the image footer
+    pub fn with_enricher(mut self, enricher: QuestionEnricher) -> Self {
+        self.enricher = Some(enricher);
+        self
+    }
 
-pub async fn fetch_question_content(
-    question_id: &str,
-) -> Result<QuestionContent, Box<dyn std::error::Error>> {
-    let url = format!(
-        "https://mister-teddy.github.io/gmat-database/{}.json",
-        question_id
-    );
-    println!("  📥 Fetching question content for ID: {}", question_id);
+    /// Tags questions with topics from a supplemental file, for "topic" requests
+    pub fn with_topic_tags(mut self, topic_tags: TopicTags) -> Self {
+        self.topic_tags = Some(topic_tags);
+        self
+    }
 
-    let response = reqwest::get(&url).await?;
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to fetch question {}: {}",
+    /// Remembers recent sends with another window or size, or in a file across restarts
+    pub fn with_recent_sends(mut self, recent_sends: RecentSends) -> Self {
+        self.recent_sends = recent_sends;
+        self
+    }
+
+    /// Leaves the questions on an exclusion list out of random picks; shared, so
+    /// changes made through the bot apply everywhere the list is used
+    pub fn with_exclusions(mut self, exclusions: Arc<ExclusionList>) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Questions not to pick for a chat: everything in its stored history, plus
+    /// whatever it was sent within the recent-sends window
+    pub fn seen_question_ids(&self, chat_id: &str) -> HashSet<String> {
+        let mut seen = match &self.storage {
+            Some(storage) => storage.sent_question_ids(chat_id).unwrap_or_else(|e| {
+                warn!("Failed to load question history: {}", e);
+                HashSet::new()
+            }),
+            None => HashSet::new(),
+        };
+        seen.extend(self.recent_sends.recent_ids(chat_id));
+        seen
+    }
+
+    /// Where "topic" requests look up question topics
+    pub fn topic_lookup(&self) -> TopicLookup<'_> {
+        TopicLookup {
+            source: &self.database_source,
+            cache: self.question_cache.as_ref(),
+            enricher: self.enricher.as_ref(),
+            tags: self.topic_tags.as_ref(),
+        }
+    }
+
+    /// `content` with its source-page stats attached, when an enricher is configured
+    pub async fn enriched(&self, content: &QuestionContent) -> QuestionContent {
+        match &self.enricher {
+            Some(enricher) => enricher.enriched(content).await,
+            None => content.clone(),
+        }
+    }
+
+    /// Persists the `getUpdates` offset so messages aren't handled twice across restarts
+    pub fn with_offset_store(mut self, offset_store: OffsetStore) -> Self {
+        self.offset_store = Some(offset_store);
+        self
+    }
+
+    /// Looks up and logs the account the bot is signed in as. A rejected token is an
+    /// error, so a bad token stops the bot at startup rather than on its first poll;
+    /// other failures may be passing network trouble and are only logged
+    pub async fn check_identity(&self) -> Result<Option<BotIdentity>> {
+        let identity = match &self.platform {
+            Some(platform) => platform.get_me().await,
+            None => ChatPlatform::get_me(self).await,
+        };
+        match identity {
+            Ok(Some(identity)) => {
+                info!("Signed in as {}", identity);
+                Ok(Some(identity))
+            }
+            Ok(None) => Ok(None),
+            Err(e) if e.is_invalid_token() => Err(e),
+            Err(e) => {
+                warn!("Failed to look up the bot account: {}", e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Long-polls the active platform for new messages
+    #[instrument(skip(self))]
+    pub async fn get_updates(&self) -> Result<Vec<ZaloMessage>> {
+        match &self.platform {
+            Some(platform) => platform.get_updates().await,
+            None => self.zalo_get_updates().await,
+        }
+    }
+
+    async fn zalo_get_updates(&self) -> Result<Vec<ZaloMessage>> {
+        let offset = self.offset_store.as_ref().and_then(|s| s.current());
+        let update_list = self.api.get_updates(offset).await?;
+
+        let mut messages = Vec::new();
+        for update in update_list {
+            debug!(
+                event = %update.event_name,
+                update_id = ?update.update_id,
+                "Received update"
+            );
+            if let (Some(offset_store), Some(update_id)) = (&self.offset_store, update.update_id) {
+                if offset_store.is_handled(update_id) {
+                    debug!(update_id, "Skipping already handled update");
+                    continue;
+                }
+                // Acknowledge before handling so a crash can't replay a question
+                if let Err(e) = offset_store.advance(update_id) {
+                    warn!("Failed to save update offset: {}", e);
+                }
+            }
+            match update.into_event().into_message() {
+                Some(message) => messages.push(message),
+                None => debug!("Ignoring update without a usable message"),
+            }
+        }
+
+        debug!(count = messages.len(), "Extracted messages");
+        Ok(messages)
+    }
+
+    pub async fn start_polling_service(
+        self: &Arc<Self>,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &Arc<dyn ImageHost>,
+    ) -> Result<()> {
+        info!("Starting long polling service...");
+        info!("Bot is now listening for messages. Send any message to get a GMAT question!");
+        info!("Press Ctrl+C to stop the bot");
+        self.health.database_loaded(database.total_questions());
+        let database = Arc::new(LiveDatabase::new(database.clone()));
+
+        tokio::select! {
+            result = self.poll_updates(&database, output_dir, image_host) => result?,
+            result = self.run_health_check() => result?,
+            result = self.run_control_api(&database, output_dir, image_host) => result?,
+            _ = self.run_question_timers(output_dir, image_host.as_ref()) => {}
+            _ = self.run_mock_timers(&database, output_dir, image_host.as_ref()) => {}
+            _ = self.run_challenge_timers() => {}
+            _ = self.run_assignment_deliveries(&database, output_dir, image_host.as_ref()) => {}
+            _ = self.run_cache_warmup(&database, output_dir, image_host.as_ref()) => {}
+            _ = self.run_streak_reminders() => {}
+            _ = self.run_daily_posts(&database, output_dir, image_host.as_ref()) => {}
+            _ = self.run_weekly_reports(output_dir, image_host.as_ref()) => {}
+            _ = self.run_webhook_summaries() => {}
+            _ = self.run_database_refresh(&database) => {}
+            _ = self.run_quiet_queue(&database, output_dir, image_host.as_ref()) => {}
+            _ = self.run_recent_flushes() => {}
+        }
+
+        self.flush_state();
+        info!("Bot stopped successfully");
+        Ok(())
+    }
+
+    /// Writes the update offset and user store to disk before exiting
+    pub fn flush_state(&self) {
+        let offset_stores = [
+            self.offset_store.as_ref(),
+            self.platform.as_ref().and_then(|p| p.offset_store()),
+        ];
+        for offset_store in offset_stores.into_iter().flatten() {
+            if let Err(e) = offset_store.flush() {
+                warn!("Failed to save update offset: {}", e);
+            }
+        }
+        if let Some(storage) = &self.storage
+            && let Err(e) = storage.flush()
+        {
+            warn!("Failed to flush storage: {}", e);
+        }
+        if let Err(e) = self.recent_sends.flush() {
+            warn!("Failed to save recent sends: {}", e);
+        }
+    }
+
+    /// Saves recent sends every [`RECENT_FLUSH_INTERVAL`] on a blocking thread, so
+    /// sending never waits on the file
+    async fn run_recent_flushes(self: &Arc<Self>) {
+        loop {
+            tokio::time::sleep(RECENT_FLUSH_INTERVAL).await;
+            let bot = self.clone();
+            match tokio::task::spawn_blocking(move || bot.recent_sends.flush()).await {
+                Ok(Err(e)) => warn!("Failed to save recent sends: {}", e),
+                Err(e) => warn!("Recent sends save panicked: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    }
+
+    /// Serves `/healthz` if configured; otherwise never returns
+    async fn run_health_check(&self) -> Result<()> {
+        let Some(health_check) = self.health_check else {
+            return std::future::pending().await;
+        };
+        serve_health(health_check, self.health.clone()).await
+    }
+
+    async fn run_control_api(
+        self: &Arc<Self>,
+        database: &Arc<LiveDatabase>,
+        output_dir: &str,
+        image_host: &Arc<dyn ImageHost>,
+    ) -> Result<()> {
+        let Some(control_api) = &self.control_api else {
+            return std::future::pending().await;
+        };
+        let (bot, database, output_dir, image_host) = (
+            self.clone(),
+            database.clone(),
+            output_dir.to_string(),
+            image_host.clone(),
+        );
+        serve_control(control_api, move |request| {
+            let (bot, database, output_dir, image_host) = (
+                bot.clone(),
+                database.clone(),
+                output_dir.clone(),
+                image_host.clone(),
+            );
+            async move {
+                let database = database.current();
+                bot.handle_control_request(request, &database, &output_dir, image_host.as_ref())
+                    .await
+            }
+        })
+        .await
+    }
+
+    /// Carries out one control API request
+    pub async fn handle_control_request(
+        &self,
+        request: ControlRequest,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> ControlResponse {
+        let failed = |status: StatusCode, context: &str, e: BotError| {
+            error!("{}: {}", context, e);
+            ControlResponse::error(status, format!("{}: {}", context, e))
+        };
+        let no_storage = || {
+            ControlResponse::error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "the bot runs without storage",
+            )
+        };
+        match request {
+            ControlRequest::Send {
+                user_id,
+                question_id,
+            } => {
+                let Some(question_type) = database.find_question_type(&question_id) else {
+                    return ControlResponse::error(
+                        StatusCode::NOT_FOUND,
+                        format!("Unknown question ID {}", question_id),
+                    );
+                };
+                // Users are reached in their private chat, as `send --user-ids` does
+                let mut chat_id = user_id.clone();
+                if let Some(storage) = &self.storage {
+                    match storage.is_banned(&user_id) {
+                        Ok(true) => {
+                            return ControlResponse::error(
+                                StatusCode::FORBIDDEN,
+                                format!("User {} is banned", user_id),
+                            );
+                        }
+                        Ok(false) => {}
+                        Err(e) => {
+                            return failed(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "Failed to check ban list",
+                                e,
+                            );
+                        }
+                    }
+                    match storage.private_chat_id(&user_id) {
+                        Ok(Some(private_chat_id)) => chat_id = private_chat_id,
+                        Ok(None) => {}
+                        Err(e) => {
+                            return failed(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "Failed to look up user",
+                                e,
+                            );
+                        }
+                    }
+                }
+                let content = match fetch_question_content(
+                    &self.database_source,
+                    &question_id,
+                    self.question_cache.as_ref(),
+                )
+                .await
+                {
+                    Ok(content) => content,
+                    Err(e) => {
+                        return failed(StatusCode::BAD_GATEWAY, "Failed to fetch question", e);
+                    }
+                };
+                let sent = self
+                    .send_question(
+                        &chat_id,
+                        &content,
+                        Some(&question_type),
+                        output_dir,
+                        image_host,
+                        false,
+                    )
+                    .await;
+                match sent {
+                    Ok(()) => ControlResponse::ok(json!({
+                        "sent": true,
+                        "chat_id": chat_id,
+                        "question_id": question_id,
+                        "question_type": question_type,
+                    })),
+                    Err(e) => {
+                        if let Some(storage) = &self.storage {
+                            mark_inactive_if_blocked(storage, &chat_id, &e);
+                        }
+                        failed(StatusCode::BAD_GATEWAY, "Failed to send question", e)
+                    }
+                }
+            }
+            ControlRequest::Stats { days } => {
+                let Some(storage) = &self.storage else {
+                    return no_storage();
+                };
+                let since = chrono::Utc::now().timestamp() - days * 24 * 60 * 60;
+                match storage.usage_stats(since) {
+                    Ok(stats) => {
+                        let mut body = json!(stats);
+                        body["recent_days"] = json!(days);
+                        ControlResponse::ok(body)
+                    }
+                    Err(e) => failed(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to load usage stats",
+                        e,
+                    ),
+                }
+            }
+            ControlRequest::Broadcast { text } => {
+                let Some(storage) = &self.storage else {
+                    return no_storage();
+                };
+                let chat_ids = match storage.broadcast_chat_ids() {
+                    Ok(chat_ids) => chat_ids,
+                    Err(e) => {
+                        return failed(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "Failed to load known users",
+                            e,
+                        );
+                    }
+                };
+                let question = match self.broadcast_question(&text, database).await {
+                    Ok(question) => question,
+                    Err(e) => {
+                        return failed(StatusCode::BAD_GATEWAY, "Failed to fetch question", e);
+                    }
+                };
+                let summary = self
+                    .deliver_broadcast(
+                        storage,
+                        &chat_ids,
+                        &text,
+                        question.as_ref(),
+                        output_dir,
+                        image_host,
+                    )
+                    .await;
+                ControlResponse::ok(json!(summary))
+            }
+            ControlRequest::Sessions => ControlResponse::ok(self.sessions_report()),
+        }
+    }
+
+    /// Questions waiting for an answer and the quizzes, mock tests and challenges under way
+    fn sessions_report(&self) -> serde_json::Value {
+        let pending: Vec<_> = self
+            .pending_questions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(chat_id, pending)| {
+                json!({
+                    "chat_id": chat_id,
+                    "question_id": pending.content.id,
+                    "question_type": pending.question_type,
+                    "sent_at": pending.sent_at,
+                })
+            })
+            .collect();
+        let quizzes: Vec<_> = self
+            .quiz_sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(chat_id, quiz)| {
+                json!({
+                    "chat_id": chat_id,
+                    "question_type": quiz.question_type,
+                    "answered": quiz.answers.len(),
+                    "total": quiz.total,
+                })
+            })
+            .collect();
+        let mock_tests: Vec<_> = self
+            .mock_sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(chat_id, mock)| {
+                json!({
+                    "chat_id": chat_id,
+                    "user_id": mock.user_id,
+                    "answered": mock.answers.len(),
+                    "total": mock.plan.len(),
+                    "elapsed_secs": mock.started.elapsed().as_secs(),
+                })
+            })
+            .collect();
+        let challenges: Vec<_> = self
+            .challenges
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|challenge| {
+                json!({
+                    "origin_chat_id": challenge.origin_chat_id,
+                    "players": challenge.players.iter().map(|player| json!({
+                        "user_id": player.user_id,
+                        "name": player.name,
+                        "answered": player.answers.len(),
+                    })).collect::<Vec<_>>(),
+                    "total": challenge.questions.len(),
+                    "elapsed_secs": challenge.started.elapsed().as_secs(),
+                })
+            })
+            .collect();
+        json!({
+            "pending_questions": pending,
+            "quizzes": quizzes,
+            "mock_tests": mock_tests,
+            "challenges": challenges,
+        })
+    }
+
+    /// Long-polls and handles messages until a shutdown is requested
+    async fn poll_updates(
+        self: &Arc<Self>,
+        database: &LiveDatabase,
+        output_dir: &str,
+        image_host: &Arc<dyn ImageHost>,
+    ) -> Result<()> {
+        // Chats are handled concurrently, each message on a task of its own, up to
+        // `concurrency` at a time; within a chat, messages wait in a queue so they're
+        // handled in order
+        let permits = Arc::new(Semaphore::new(self.concurrency.max(1)));
+        let output_dir: Arc<str> = output_dir.into();
+        let mut chat_queues: HashMap<String, VecDeque<ChatMessage>> = HashMap::new();
+        let mut in_flight = FuturesUnordered::new();
+
+        // Polling never gives up, but backs off while getUpdates keeps failing
+        let mut consecutive_failures = 0;
+        let poll = self.next_updates(Duration::ZERO);
+        tokio::pin!(poll);
+        let shutdown = self.shutdown.requested();
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                // Stop accepting updates; the poll in progress is dropped unacknowledged
+                _ = &mut shutdown => {
+                    info!("Received shutdown signal. Stopping bot gracefully...");
+                    break;
+                }
+
+                // Start the next queued message of a chat whose previous one finished
+                Some(chat_id) = in_flight.next(), if !in_flight.is_empty() => {
+                    match chat_queues.get_mut(&chat_id).and_then(|queue| queue.pop_front()) {
+                        Some(message) => in_flight.push(self.spawn_queued_message(
+                            message, &permits, database.current(), &output_dir, image_host,
+                        )),
+                        None => {
+                            chat_queues.remove(&chat_id);
+                        }
+                    }
+                }
+
+                // Handle API updates
+                result = &mut poll => {
+                    let mut delay = Duration::ZERO;
+                    match result {
+                        Ok(messages) => {
+                            consecutive_failures = 0;
+                            self.health.poll_succeeded();
+                            if !messages.is_empty() {
+                                info!("Received {} new message(s)", messages.len());
+
+                                for message in messages {
+                                    match chat_queues.get_mut(&message.chat.id) {
+                                        Some(queue) => queue.push_back(message),
+                                        None => {
+                                            let chat_id = message.chat.id.clone();
+                                            chat_queues.insert(chat_id, VecDeque::new());
+                                            in_flight.push(self.spawn_queued_message(
+                                                message, &permits, database.current(), &output_dir, image_host,
+                                            ));
+                                        }
+                                    }
+                                }
+                            } else {
+                                debug!("No new messages (normal for long polling)");
+                            }
+                        }
+                        Err(e) => {
+                            // Check if it's a timeout (normal for long polling) or a real error
+                            if e.is_timeout() {
+                                debug!("Polling timeout, continuing...");
+                                self.health.poll_succeeded();
+                            } else {
+                                self.health.poll_failed();
+                                consecutive_failures += 1;
+                                delay = self.retry_policy.delay(consecutive_failures);
+                                warn!(error = %e, "Error getting updates, retrying in {:?}...", delay);
+                            }
+                        }
+                    }
+                    poll.set(self.next_updates(delay));
+                }
+            }
+        }
+
+        // Let messages already received finish rather than dropping them mid-reply
+        if in_flight.is_empty() {
+            return Ok(());
+        }
+        info!(
+            "Waiting up to {:?} for {} chat(s) to finish...",
+            self.shutdown.drain_timeout(),
+            in_flight.len()
+        );
+        let drained = self
+            .shutdown
+            .drain(async {
+                while let Some(chat_id) = in_flight.next().await {
+                    if let Some(message) = chat_queues
+                        .get_mut(&chat_id)
+                        .and_then(|queue| queue.pop_front())
+                    {
+                        in_flight.push(self.spawn_queued_message(
+                            message,
+                            &permits,
+                            database.current(),
+                            &output_dir,
+                            image_host,
+                        ));
+                    }
+                }
+            })
+            .await;
+        if !drained {
+            let queued: usize = chat_queues.values().map(VecDeque::len).sum();
+            warn!(
+                "Abandoned {} in-flight and {} queued message(s)",
+                in_flight.len(),
+                queued
+            );
+        }
+        Ok(())
+    }
+
+    /// Waits `delay`, then long-polls for updates
+    async fn next_updates(&self, delay: Duration) -> Result<Vec<ChatMessage>> {
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        self.get_updates().await
+    }
+
+    /// Handles one message on a task of its own once a worker slot is free, so a slow
+    /// render doesn't hold up other chats; resolves to its chat ID when done
+    fn spawn_queued_message(
+        self: &Arc<Self>,
+        message: ChatMessage,
+        permits: &Arc<Semaphore>,
+        database: Arc<GmatDatabase>,
+        output_dir: &Arc<str>,
+        image_host: &Arc<dyn ImageHost>,
+    ) -> impl Future<Output = String> + use<> {
+        let chat_id = message.chat.id.clone();
+        let bot = self.clone();
+        let permits = permits.clone();
+        let output_dir = output_dir.clone();
+        let image_host = image_host.clone();
+        let mut task = AbortOnDrop(tokio::spawn(
+            async move {
+                // The semaphore is never closed, so acquiring only waits
+                let _permit = permits.acquire().await;
+                bot.handle_message(&message, &database, &output_dir, image_host.as_ref())
+                    .await;
+            }
+            .in_current_span(),
+        ));
+        async move {
+            if let Err(e) = (&mut task.0).await {
+                error!("Handling a message in chat {} failed: {}", chat_id, e);
+            }
+            chat_id
+        }
+    }
+
+    /// Sends hints and reveals answers for questions left unanswered; never returns
+    async fn run_question_timers(&self, output_dir: &str, image_host: &dyn ImageHost) {
+        let Some(delay) = self.hint_delay else {
+            return std::future::pending().await;
+        };
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let mut due = Vec::new();
+            self.question_timers
+                .lock()
+                .unwrap()
+                .retain(|chat_id, timer| {
+                    if timer.due > now {
+                        return true;
+                    }
+                    due.push((chat_id.clone(), timer.question_id.clone(), timer.hint_sent));
+                    // The hint is followed by the reveal, which ends the timer
+                    let reveal_next = !timer.hint_sent;
+                    timer.hint_sent = true;
+                    timer.due = now + delay;
+                    reveal_next
+                });
+
+            for (chat_id, question_id, hint_sent) in due {
+                if hint_sent {
+                    self.reveal_answer(&chat_id, &question_id, output_dir, image_host)
+                        .await;
+                } else {
+                    self.send_hint(&chat_id, &question_id).await;
+                }
+            }
+        }
+    }
+
+    /// Fetches the question index every `database_refresh` and swaps in the new copy
+    /// when questions were added or removed; never returns
+    async fn run_database_refresh(&self, database: &LiveDatabase) {
+        let Some(interval) = self.database_refresh else {
+            return std::future::pending().await;
+        };
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        loop {
+            ticker.tick().await;
+            let fresh = match fetch_gmat_database(&self.database_source).await {
+                Ok(fresh) => fresh,
+                Err(e) => {
+                    warn!("Failed to refresh the question index: {}", e);
+                    continue;
+                }
+            };
+            let current = database.current();
+            let diff = DatabaseDiff::between(&current, &fresh);
+            if diff.is_empty() {
+                debug!("Question index unchanged");
+                continue;
+            }
+            info!(
+                added = diff.added.len(),
+                removed = diff.removed.len(),
+                "Question index updated"
+            );
+            if !diff.added.is_empty() {
+                let ids: Vec<&str> = diff.added.iter().map(|(_, id)| id.as_str()).collect();
+                info!("New questions: {}", ids.join(", "));
+            }
+            self.health.database_loaded(fresh.total_questions());
+            database.replace(fresh);
+        }
+    }
+
+    /// Fills the hosted image cache with the warm list once, alongside polling; never returns
+    async fn run_cache_warmup(
+        &self,
+        database: &LiveDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        if let Some(warm_cache) = &self.warm_cache {
+            match &self.storage {
+                // Hosted URLs are cached in storage, and direct uploads have nothing to cache
+                None => info!("Skipping cache warm-up: nothing is being stored"),
+                Some(_) if image_host.uploads_directly() => {
+                    info!("Skipping cache warm-up: images are sent without an image host")
+                }
+                Some(storage) => match warm_cache.questions(&database.current(), storage) {
+                    Ok(questions) => {
+                        info!("Warming the image cache with {} questions", questions.len());
+                        let warmed = warm_question_images(
+                            self,
+                            &questions,
+                            output_dir,
+                            image_host,
+                            PipelineLimits::default(),
+                        )
+                        .await;
+                        info!("Warmed {} of {} questions", warmed, questions.len());
+                    }
+                    Err(e) => warn!("Failed to list questions to warm: {}", e),
+                },
+            }
+        }
+        std::future::pending().await
+    }
+
+    /// Scores challenges that ran past [`CHALLENGE_TIME_LIMIT`] with the answers so far; never returns
+    async fn run_challenge_timers(&self) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let expired: Vec<String> = self
+                .challenges
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|session| session.is_expired(now))
+                .map(|session| session.players[0].chat_id.clone())
+                .collect();
+            for chat_id in expired {
+                self.finish_challenge(&chat_id, Some("⏰ Time's up!")).await;
+            }
+        }
+    }
+
+    /// Moves mock tests on when a question's time cap or the test's total time runs out; never returns
+    async fn run_mock_timers(
+        &self,
+        database: &LiveDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            let due: Vec<(String, Option<String>)> = self
+                .mock_sessions
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, session)| session.question_deadline() <= now)
+                .map(|(chat_id, session)| (chat_id.clone(), session.current.clone()))
+                .collect();
+
+            for (chat_id, current) in due {
+                let pending = current.and_then(|question_id| {
+                    self.is_mock_question(&chat_id, &question_id)
+                        .then(|| self.take_pending_question(&chat_id))
+                        .flatten()
+                });
+                let out_of_time = self
+                    .mock_sessions
+                    .lock()
+                    .unwrap()
+                    .get(&chat_id)
+                    .is_some_and(|session| session.time_left().is_zero());
+                match pending {
+                    Some(pending) => {
+                        info!("Mock question {} timed out", pending.content.id);
+                        self.continue_mock(
+                            &chat_id,
+                            &pending,
+                            None,
+                            &database.current(),
+                            output_dir,
+                            image_host,
+                        )
+                        .await;
+                    }
+                    // Nothing on screen, e.g. the next question is still being sent
+                    None if out_of_time => self.finish_mock(&chat_id, output_dir, image_host).await,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    async fn send_hint(&self, chat_id: &str, question_id: &str) {
+        let hint = {
+            let pending = self.pending_questions.lock().unwrap();
+            match pending.get(chat_id) {
+                Some(pending) if pending.content.id == question_id => format_hint(&pending.content),
+                _ => return,
+            }
+        };
+        let Some(hint) = hint else {
+            return;
+        };
+        info!("Sending hint for question {}", question_id);
+        if let Err(e) = self
+            .send_message(chat_id, &format!("💡 Need a hint? {}", hint))
+            .await
+        {
+            error!("Failed to send hint: {}", e);
+        }
+    }
+
+    /// Gives up on an unanswered question: shows its answer and explanations
+    async fn reveal_answer(
+        &self,
+        chat_id: &str,
+        question_id: &str,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let still_pending = self
+            .pending_questions
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .is_some_and(|pending| pending.content.id == question_id);
+        if !still_pending {
+            return;
+        }
+        let Some(pending) = self.take_pending_question(chat_id) else {
+            return;
+        };
+
+        info!("Revealing answer for question {}", question_id);
+        let reveal = match pending.correct_answer_label() {
+            Some(correct) => format!("⏰ Time's up! The answer is {}.", correct),
+            None => "⏰ Time's up! Here are the explanations.".to_string(),
+        };
+        if let Err(e) = self.send_message(chat_id, &reveal).await {
+            error!("Failed to send reveal message: {}", e);
+        }
+        let result = self
+            .send_question_image(
+                chat_id,
+                &pending.content,
+                &pending.question_type,
+                true,
+                None,
+                &self.explanations_caption(chat_id, &pending.content, pending.question_type),
+                output_dir,
+                image_host,
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Failed to send explanations: {}", e);
+        }
+    }
+
+    /// Practice streak of the user whose private chat this is, e.g. "🔥 Day 7 streak!"
+    fn streak_line(&self, chat_id: &str) -> Option<String> {
+        let storage = self.storage.as_ref()?;
+        let streak = storage.private_chat_user(chat_id).and_then(|user_id| {
+            user_id.map_or(Ok(0), |user_id| self.practice_streak(&user_id, chat_id))
+        });
+        match streak {
+            Ok(streak) => format_streak(streak),
+            Err(e) => {
+                warn!("Failed to load practice streak: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Consecutive days the user has answered questions, by their private chat's timezone
+    fn practice_streak(&self, user_id: &str, chat_id: &str) -> Result<u32> {
+        let Some(storage) = &self.storage else {
+            return Ok(0);
+        };
+        let timezone = self.chat_config(chat_id).timezone;
+        let days = storage.practice_days(user_id, timezone)?;
+        let today = chrono::Utc::now().with_timezone(&timezone).date_naive();
+        Ok(practice_streak(&days, today))
+    }
+
+    /// Nudges users whose streak ends tonight once the reminder hour passes; never returns
+    async fn run_streak_reminders(&self) {
+        let (Some(hour), Some(storage)) = (self.streak_reminder_hour, &self.storage) else {
+            return std::future::pending().await;
+        };
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            // Anyone with a live streak answered within the last two local days
+            let since = chrono::Utc::now().timestamp() - 3 * 24 * 60 * 60;
+            let users = match storage.recently_practiced_users(since) {
+                Ok(users) => users,
+                Err(e) => {
+                    warn!("Failed to load users for streak reminders: {}", e);
+                    continue;
+                }
+            };
+            for (user_id, chat_id) in users {
+                self.send_streak_reminder(storage, &user_id, &chat_id, hour)
+                    .await;
+            }
+        }
+    }
+
+    /// Sends one user's streak reminder if it is due and wasn't sent today
+    async fn send_streak_reminder(
+        &self,
+        storage: &Storage,
+        user_id: &str,
+        chat_id: &str,
+        hour: u32,
+    ) {
+        let config = self.chat_config(chat_id);
+        let now = chrono::Utc::now().with_timezone(&config.timezone);
+        // Checked again every minute, so a reminder held back by quiet hours goes out when they end
+        if now.hour() < hour || self.in_quiet_hours(user_id, chat_id) {
+            return;
+        }
+        let today = now.date_naive().to_string();
+        match storage.get_preference(user_id, STREAK_REMINDED_PREFERENCE) {
+            Ok(Some(reminded)) if reminded == today => return,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to load streak reminder date: {}", e);
+                return;
+            }
+        }
+        let days = match storage.practice_days(user_id, config.timezone) {
+            Ok(days) => days,
+            Err(e) => {
+                warn!("Failed to load practice days: {}", e);
+                return;
+            }
+        };
+        // Only a streak that ended yesterday is at risk; practicing today already kept it
+        let streak = practice_streak(&days, now.date_naive());
+        if streak == 0 || days.contains(&now.date_naive()) {
+            return;
+        }
+
+        info!(user_id, "Sending {}-day streak reminder", streak);
+        if let Err(e) = self
+            .send_message(chat_id, &format_streak_reminder(streak))
+            .await
+        {
+            error!("Failed to send streak reminder: {}", e);
+            mark_inactive_if_blocked(storage, chat_id, &e);
+            return;
+        }
+        if let Err(e) = storage.set_preference(user_id, STREAK_REMINDED_PREFERENCE, &today) {
+            warn!("Failed to save streak reminder date: {}", e);
+        }
+    }
+
+    /// Sends each active user their weekly report once Sunday's report hour passes; never returns
+    async fn run_weekly_reports(&self, output_dir: &str, image_host: &dyn ImageHost) {
+        let (Some(hour), Some(storage)) = (self.weekly_report_hour, &self.storage) else {
+            return std::future::pending().await;
+        };
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            // Anyone who answered this week or last has something to compare
+            let since = chrono::Utc::now().timestamp() - 15 * 24 * 60 * 60;
+            let users = match storage.recently_practiced_users(since) {
+                Ok(users) => users,
+                Err(e) => {
+                    warn!("Failed to load users for weekly reports: {}", e);
+                    continue;
+                }
+            };
+            for (user_id, chat_id) in users {
+                self.send_weekly_report(storage, &user_id, &chat_id, hour, output_dir, image_host)
+                    .await;
+            }
+        }
+    }
+
+    /// Renders and sends one user's weekly report if it is due and wasn't sent this week
+    async fn send_weekly_report(
+        &self,
+        storage: &Storage,
+        user_id: &str,
+        chat_id: &str,
+        hour: u32,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let config = self.chat_config(chat_id);
+        let now = chrono::Utc::now().with_timezone(&config.timezone);
+        let Some(week_start) = report_week(now, hour) else {
+            return;
+        };
+        if self.in_quiet_hours(user_id, chat_id) {
+            return;
+        }
+        let week = week_start.to_string();
+        match storage.get_preference(user_id, WEEKLY_REPORTED_PREFERENCE) {
+            Ok(Some(reported)) if reported == week => return,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to load weekly report date: {}", e);
+                return;
+            }
+        }
+
+        let report = match self.weekly_report(storage, user_id, week_start, now) {
+            Ok(report) => report,
+            Err(e) => {
+                warn!("Failed to load weekly report stats: {}", e);
+                return;
+            }
+        };
+        // Recorded before sending, so a failed report isn't retried every minute
+        // and quiet users aren't checked again until next week
+        if let Err(e) = storage.set_preference(user_id, WEEKLY_REPORTED_PREFERENCE, &week) {
+            warn!("Failed to save weekly report date: {}", e);
+            return;
+        }
+        if !report.is_active() {
+            return;
+        }
+
+        info!(user_id, "Sending weekly report for the week of {}", week);
+        let html = generate_weekly_report_html(&report);
+        let result = self
+            .send_card(
+                chat_id,
+                &html,
+                &format!("weekly_{}", user_id),
+                "Your week in review 📅",
+                output_dir,
+                image_host,
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Failed to send weekly report: {}", e);
+            mark_inactive_if_blocked(storage, chat_id, &e);
+        }
+    }
+
+    /// A user's answers in the week starting `week_start` and the week before it
+    fn weekly_report(
+        &self,
+        storage: &Storage,
+        user_id: &str,
+        week_start: chrono::NaiveDate,
+        now: chrono::DateTime<chrono_tz::Tz>,
+    ) -> Result<WeeklyReport> {
+        let timezone = now.timezone();
+        let (since, until) = week_bounds(week_start, timezone);
+        let (last_since, _) = week_bounds(week_start - chrono::Days::new(7), timezone);
+        let days = storage.practice_days(user_id, timezone)?;
+        Ok(WeeklyReport {
+            week_start,
+            this_week: storage.period_stats(user_id, since, until)?,
+            last_week: storage.period_stats(user_id, last_since, since)?,
+            streak: practice_streak(&days, now.date_naive()),
+        })
+    }
+
+    /// Posts the question of the day and its answer at the scheduled hours; never returns
+    async fn run_daily_posts(
+        &self,
+        database: &LiveDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let (Some(schedule), Some(storage)) = (self.daily_schedule, &self.storage) else {
+            return std::future::pending().await;
+        };
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            let now = chrono::Utc::now().with_timezone(&schedule.timezone);
+            let today = now.date_naive();
+            let posted = |post: DailyPost| match storage.daily_posted(today, post.as_str()) {
+                Ok(posted) => posted,
+                Err(e) => {
+                    // Treat as posted so a broken store can't cause repeated posts
+                    warn!("Failed to check daily posts: {}", e);
+                    true
+                }
+            };
+
+            // A question missed because the bot was down is skipped once its answer is due
+            let due = if now.hour() >= schedule.answer_hour {
+                (posted(DailyPost::Question) && !posted(DailyPost::Answer))
+                    .then_some(DailyPost::Answer)
+            } else if now.hour() >= schedule.question_hour {
+                (!posted(DailyPost::Question)).then_some(DailyPost::Question)
+            } else {
+                None
+            };
+            if let Some(post) = due {
+                let database = database.current();
+                self.post_daily(storage, post, today, &database, output_dir, image_host)
+                    .await;
+            }
+        }
+    }
+
+    /// Posts yesterday's activity (UTC) to the webhooks once the day is over; never returns
+    async fn run_webhook_summaries(&self) {
+        let Some(storage) = &self.storage else {
+            return std::future::pending().await;
+        };
+        if self.webhooks.is_empty() {
+            return std::future::pending().await;
+        }
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            let today = chrono::Utc::now().date_naive();
+            let Some(yesterday) = today.pred_opt() else {
+                continue;
+            };
+            match storage.daily_posted(yesterday, WEBHOOK_SUMMARY_POST) {
+                Ok(false) => {}
+                Ok(true) => continue,
+                Err(e) => {
+                    warn!("Failed to check daily posts: {}", e);
+                    continue;
+                }
+            }
+            let day_start = |date: chrono::NaiveDate| {
+                date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp()
+            };
+            let stats = match storage.activity_stats(day_start(yesterday), day_start(today)) {
+                Ok(stats) => stats,
+                Err(e) => {
+                    warn!("Failed to summarise activity: {}", e);
+                    continue;
+                }
+            };
+            // Recorded before delivery so a failing endpoint isn't posted to every minute
+            if let Err(e) = storage.record_daily_post(yesterday, WEBHOOK_SUMMARY_POST, "") {
+                warn!("Failed to record webhook summary: {}", e);
+                continue;
+            }
+            self.webhooks.emit(WebhookEvent::DailySummary {
+                date: yesterday,
+                active_users: stats.active_users,
+                questions_sent: stats.questions_sent,
+                answers: stats.answers,
+                correct_answers: stats.correct_answers,
+                graded_answers: stats.graded_answers,
+            });
+        }
+    }
+
+    /// Sends one of the day's posts to every subscribed chat
+    async fn post_daily(
+        &self,
+        storage: &Storage,
+        post: DailyPost,
+        date: chrono::NaiveDate,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some((question_type, question_id)) = daily_question(database, &self.exclusions, date)
+        else {
+            warn!("No questions available for the question of the day");
+            return;
+        };
+        let content = match fetch_question_content(
+            &self.database_source,
+            &question_id,
+            self.question_cache.as_ref(),
+        )
+        .await
+        {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to fetch the question of the day: {}", e);
+                return;
+            }
+        };
+        let chat_ids = match storage.subscribed_chat_ids() {
+            Ok(chat_ids) => chat_ids,
+            Err(e) => {
+                error!("Failed to load subscribed chats: {}", e);
+                return;
+            }
+        };
+        // Recorded first: a crash halfway through skips some chats rather than repeating others
+        if let Err(e) = storage.record_daily_post(date, post.as_str(), &question_id) {
+            error!("Failed to record the daily post: {}", e);
+            return;
+        }
+
+        info!(
+            "Posting daily {} for question {} to {} chat(s)",
+            post.as_str(),
+            question_id,
+            chat_ids.len()
+        );
+        for chat_id in chat_ids {
+            if self.chat_config(&chat_id).is_muted() {
+                debug!(chat_id, "Chat is muted, skipping daily post");
+                continue;
+            }
+            if self.chat_in_quiet_hours(&chat_id) {
+                debug!(chat_id, "Holding the daily post until quiet hours end");
+                self.hold_for_quiet_hours(&chat_id, QueuedPost::Daily(post, date));
+                continue;
+            }
+            if let Err(e) = self
+                .send_daily_post(
+                    &chat_id,
+                    post,
+                    &content,
+                    &question_type,
+                    output_dir,
+                    image_host,
+                )
+                .await
+            {
+                error!(chat_id, "Failed to post the daily {}: {}", post.as_str(), e);
+            }
+        }
+    }
+
+    async fn send_daily_post(
+        &self,
+        chat_id: &str,
+        post: DailyPost,
+        content: &QuestionContent,
+        question_type: &QuestionType,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        match post {
+            DailyPost::Question => {
+                self.send_daily_question(chat_id, content, question_type, output_dir, image_host)
+                    .await
+            }
+            DailyPost::Answer => {
+                self.send_daily_answer(chat_id, content, question_type, output_dir, image_host)
+                    .await
+            }
+        }
+    }
+
+    /// A user's do-not-disturb window: the one they set with "dnd", or the default
+    /// unless they turned it off
+    pub fn quiet_hours(&self, user_id: &str) -> Option<MuteHours> {
+        let Some(storage) = &self.storage else {
+            return self.default_quiet_hours;
+        };
+        match storage.get_preference(user_id, QUIET_HOURS_PREFERENCE) {
+            // "dnd off" is saved as an empty window
+            Ok(Some(value)) => value.parse().ok(),
+            Ok(None) => self.default_quiet_hours,
+            Err(e) => {
+                warn!("Failed to load quiet hours: {}", e);
+                self.default_quiet_hours
+            }
+        }
+    }
+
+    /// Whether proactive messages to the user in `chat_id` should wait now: the chat is
+    /// muted or the user's quiet hours are on, both in the chat's timezone
+    fn in_quiet_hours(&self, user_id: &str, chat_id: &str) -> bool {
+        let config = self.chat_config(chat_id);
+        [config.mute_hours, self.quiet_hours(user_id)]
+            .into_iter()
+            .flatten()
+            .any(|hours| hours.is_active(config.timezone))
+    }
+
+    /// Whether the user of a private chat has quiet hours on now; groups have mute hours instead
+    fn chat_in_quiet_hours(&self, chat_id: &str) -> bool {
+        let Some(storage) = &self.storage else {
+            return false;
+        };
+        match storage.private_chat_user(chat_id) {
+            Ok(Some(user_id)) => self.in_quiet_hours(&user_id, chat_id),
+            Ok(None) => false,
+            Err(e) => {
+                warn!("Failed to look up the chat's user: {}", e);
+                false
+            }
+        }
+    }
+
+    fn hold_for_quiet_hours(&self, chat_id: &str, post: QueuedPost) {
+        self.quiet_queue
+            .lock()
+            .unwrap()
+            .entry(chat_id.to_string())
+            .or_default()
+            .push(post);
+    }
+
+    /// Sends what quiet hours held back once each chat's quiet hours end; never returns
+    async fn run_quiet_queue(
+        &self,
+        database: &LiveDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            let queued: Vec<String> = self.quiet_queue.lock().unwrap().keys().cloned().collect();
+            for chat_id in queued {
+                if self.chat_in_quiet_hours(&chat_id) {
+                    continue;
+                }
+                let Some(posts) = self.quiet_queue.lock().unwrap().remove(&chat_id) else {
+                    continue;
+                };
+                info!(
+                    chat_id,
+                    "Quiet hours over, sending {} held message(s)",
+                    posts.len()
+                );
+                let database = database.current();
+                for post in posts {
+                    let result = match &post {
+                        QueuedPost::Daily(post, date) => {
+                            self.send_held_daily_post(
+                                &chat_id, *post, *date, &database, output_dir, image_host,
+                            )
+                            .await
+                        }
+                        QueuedPost::Broadcast(payload) => {
+                            match self.broadcast_question(payload, &database).await {
+                                Ok(question) => {
+                                    self.send_broadcast(
+                                        &chat_id,
+                                        payload,
+                                        question.as_ref(),
+                                        output_dir,
+                                        image_host,
+                                    )
+                                    .await
+                                }
+                                Err(e) => Err(e),
+                            }
+                        }
+                    };
+                    if let Err(e) = result {
+                        error!(chat_id, "Failed to send held message {:?}: {}", post, e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn send_held_daily_post(
+        &self,
+        chat_id: &str,
+        post: DailyPost,
+        date: chrono::NaiveDate,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        let Some((question_type, question_id)) = daily_question(database, &self.exclusions, date)
+        else {
+            return Ok(());
+        };
+        let content = fetch_question_content(
+            &self.database_source,
+            &question_id,
+            self.question_cache.as_ref(),
+        )
+        .await?;
+        self.send_daily_post(
+            chat_id,
+            post,
+            &content,
+            &question_type,
+            output_dir,
+            image_host,
+        )
+        .await
+    }
+
+    async fn send_daily_question(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: &QuestionType,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        self.send_message(chat_id, "📅 Here's today's question of the day!")
+            .await?;
+        self.send_question_in(
+            Some((CaptionContext::Daily, 1, 1)),
+            chat_id,
+            content,
+            Some(question_type),
+            output_dir,
+            image_host,
+            false,
+        )
+        .await
+    }
+
+    async fn send_daily_answer(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: &QuestionType,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        // Replies to the daily question are no longer graded once the answer is out
+        let still_pending = self
+            .pending_questions
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .is_some_and(|pending| pending.content.id == content.id);
+        let pending = if still_pending {
+            self.take_pending_question(chat_id)
+        } else {
+            None
+        };
+
+        let correct = match &pending {
+            Some(pending) => pending.correct_answer_label(),
+            None => content
+                .correct_answer()
+                .map(|correct| format!("({})", correct)),
+        };
+        let text = match correct {
+            Some(correct) => format!("📅 The answer to today's question is {}.", correct),
+            None => "📅 Here are the explanations for today's question.".to_string(),
+        };
+        self.send_message(chat_id, &text).await?;
+        self.send_question_image(
+            chat_id,
+            content,
+            question_type,
+            true,
+            None,
+            &self.explanations_caption(chat_id, content, *question_type),
+            output_dir,
+            image_host,
+        )
+        .await
+    }
+
+    async fn handle_subscription(&self, chat_id: &str, subscribe: bool) {
+        let reply = match &self.storage {
+            None => {
+                "🤷 Subscriptions aren't available because nothing is being stored.".to_string()
+            }
+            Some(storage) => {
+                let result = if subscribe {
+                    storage.subscribe(chat_id)
+                } else {
+                    storage.unsubscribe(chat_id)
+                };
+                match (result, subscribe) {
+                    (Ok(true), true) => {
+                        "📅 Subscribed! This chat will get the question of the day every morning and its answer in the evening.".to_string()
+                    }
+                    (Ok(false), true) => "📅 This chat is already subscribed.".to_string(),
+                    (Ok(true), false) => {
+                        "👋 Unsubscribed from the question of the day.".to_string()
+                    }
+                    (Ok(false), false) => "🤔 This chat isn't subscribed.".to_string(),
+                    (Err(e), _) => {
+                        error!("Failed to save subscription: {}", e);
+                        "❌ Failed to update the subscription. Please try again later.".to_string()
+                    }
+                }
+            }
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send subscription reply: {}", e);
+        }
+    }
+
+    /// Removes and returns the question a chat is expected to answer
+    fn take_pending_question(&self, chat_id: &str) -> Option<PendingQuestion> {
+        let mut pending = self.pending_questions.lock().unwrap().remove(chat_id);
+        if let Some(storage) = &self.storage {
+            // Also clears the persisted copy; it is the only copy after a restart
+            match storage.take_pending_question(chat_id) {
+                Ok(stored) => pending = pending.or(stored),
+                Err(e) => warn!("Failed to load pending question: {}", e),
+            }
+        }
+        pending
+    }
+
+    #[instrument(skip_all, fields(chat_id = %message.chat.id, sender_id = %message.sender.id))]
+    async fn handle_message(
+        &self,
+        message: &ZaloMessage,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let chat_id = &message.chat.id;
+        let sender_id = &message.sender.id;
+
+        let raw_text = message.text.as_deref().unwrap_or("").trim();
+        debug!(text = %raw_text, "Message text");
+
+        // Remember every sender for broadcasts, and drop banned users' messages
+        if let Some(storage) = &self.storage {
+            let private_chat_id = (!message.chat.is_group()).then_some(chat_id.as_str());
+            if let Err(e) = storage.touch_user(
+                sender_id,
+                message.sender.display_name.as_deref(),
+                private_chat_id,
+            ) {
+                warn!("Failed to register user: {}", e);
+            }
+            match storage.is_banned(sender_id) {
+                Ok(true) => {
+                    debug!("Ignoring message from banned user");
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => warn!("Failed to check ban list: {}", e),
+            }
+        }
+
+        // Only text is read as a command; other events have hooks of their own
+        match message.kind {
+            MessageKind::Text => {}
+            MessageKind::MemberJoined => return self.on_member_joined(message).await,
+            MessageKind::Photo if self.ocr && !message.chat.is_group() => {
+                return self
+                    .find_question_from_photo(message, database, output_dir, image_host)
+                    .await;
+            }
+            MessageKind::Photo | MessageKind::Sticker => return self.on_media(message).await,
+            MessageKind::Edited => {
+                debug!("Ignoring edited message; the original was already handled");
+                return;
+            }
+        }
+
+        // Groups only get a reply when the message is addressed to the bot
+        let is_group = message.chat.is_group();
+        let mut config = self.chat_config(chat_id);
+        let command = if message.from_button {
+            Some(raw_text.to_string())
+        } else {
+            config.command_text(raw_text, is_group, self.bot_name.as_deref())
+        };
+        let Some(command) = command else {
+            debug!("Ignoring group message not addressed to the bot");
+            return;
+        };
+        let message_text = command.as_str();
+
+        // Flood protection: one polite warning, then silence until the bucket refills
+        match self
+            .rate_limiter
+            .as_ref()
+            .map_or(RateDecision::Allow, |limiter| limiter.check(sender_id))
+        {
+            RateDecision::Allow => {}
+            RateDecision::Warn => {
+                info!("Rate limit exceeded");
+                if let Err(e) = self
+                    .send_message(
+                        chat_id,
+                        "🐢 Whoa, slow down! You're sending messages too fast. Please wait a moment and try again.",
+                    )
+                    .await
+                {
+                    error!("Failed to send rate limit message: {}", e);
+                }
+                return;
+            }
+            RateDecision::Drop => {
+                debug!("Rate limited, ignoring message");
+                return;
+            }
+        }
+        info!("Processing message");
+
+        if self
+            .handlers
+            .dispatch(self, message, message_text, false)
+            .await
+        {
+            return;
+        }
+
+        if self.admin_ids.contains(sender_id)
+            && let Some(command) = parse_admin_command(message_text)
+        {
+            self.handle_admin_command(
+                chat_id, sender_id, command, database, output_dir, image_host,
+            )
+            .await;
+            return;
+        }
+
+        // Settings stay reachable during mute hours so a chat can unmute itself
+        let (first_word, args) = message_text
+            .split_once(char::is_whitespace)
+            .unwrap_or((message_text, ""));
+        if first_word.eq_ignore_ascii_case("config") {
+            let reply = match (&self.storage, config.apply_command(args)) {
+                (None, _) => {
+                    "🤷 Chat settings aren't available because nothing is being stored.".to_string()
+                }
+                (Some(_), Err(usage)) => format!("❌ {}", usage),
+                (Some(storage), Ok(summary)) => match storage.save_chat_config(chat_id, &config) {
+                    Ok(()) => summary,
+                    Err(e) => {
+                        error!("Failed to save chat config: {}", e);
+                        "❌ Failed to save chat settings. Please try again later.".to_string()
+                    }
+                },
+            };
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send config reply: {}", e);
+            }
+            return;
+        }
+
+        if config.is_muted() {
+            debug!("Chat is muted, ignoring message");
+            return;
+        }
+
+        // Refuse question types the chat has turned off
+        let explicit_type = parse_question_type(message_text)
+            .or_else(|| parse_difficulty_request(message_text).map(|(t, _)| t))
+            .or_else(|| parse_quiz_command(message_text).and_then(|(_, t)| t));
+        if let Some(q_type) = explicit_type
+            && !config.allows(q_type)
+        {
+            let reply = format!("🚫 {} questions are turned off in this chat.", q_type);
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send message: {}", e);
+            }
+            return;
+        }
+
+        // Check if the message is an answer to the pending question
+        if let Some(answer) = parse_answer_choice(message_text) {
+            self.handle_answer(
+                chat_id,
+                &message.sender,
+                answer,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("review") {
+            self.send_review(chat_id, sender_id, output_dir, image_host)
+                .await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("why") || message_text.eq_ignore_ascii_case("explain")
+        {
+            if self.mock_sessions.lock().unwrap().contains_key(chat_id) {
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "📝 Explanations come at the end of the mock test. Send 'mock stop' to end it now.",
+                    )
+                    .await;
+                return;
+            }
+            self.send_last_explanation(chat_id, sender_id, database, output_dir, image_host)
+                .await;
+            return;
+        }
+
+        if let Some(note) = parse_flag_command(message_text) {
+            self.flag_last_question(chat_id, sender_id, &note).await;
+            return;
+        }
+
+        if let Some(command) = parse_history_command(message_text) {
+            self.send_history(chat_id, command).await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("leaderboard") {
+            self.send_leaderboard(chat_id).await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("stats") {
+            self.send_stats(chat_id, &message.sender, output_dir, image_host)
+                .await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("badges") {
+            self.send_badges(chat_id, &message.sender, output_dir, image_host)
+                .await;
+            return;
+        }
+
+        if let Some(format) = parse_mydata_command(message_text) {
+            self.send_user_data(chat_id, sender_id, is_group, format)
+                .await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("homework") {
+            self.send_homework(
+                chat_id, sender_id, is_group, database, output_dir, image_host,
+            )
+            .await;
+            return;
+        }
+
+        if first_word.eq_ignore_ascii_case("forgetme") {
+            self.handle_forgetme(chat_id, sender_id, args).await;
+            return;
+        }
+
+        if first_word.eq_ignore_ascii_case("settings") {
+            self.handle_settings_command(chat_id, sender_id, is_group, args)
+                .await;
+            return;
+        }
+
+        if first_word.eq_ignore_ascii_case("theme") {
+            self.handle_theme_command(chat_id, sender_id, args).await;
+            return;
+        }
+
+        if let Some(command) = parse_dnd_command(message_text) {
+            self.handle_dnd_command(chat_id, sender_id, command).await;
+            return;
+        }
+
+        if message_text.eq_ignore_ascii_case("subscribe")
+            || message_text.eq_ignore_ascii_case("unsubscribe")
+        {
+            self.handle_subscription(chat_id, message_text.eq_ignore_ascii_case("subscribe"))
+                .await;
+            return;
+        }
+
+        // Check if the message starts or stops a challenge
+        if message_text.eq_ignore_ascii_case("challenge stop") {
+            let chat = self
+                .challenges
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|session| session.has_user(sender_id))
+                .map(|session| session.players[0].chat_id.clone());
+            match chat {
+                Some(chat) => {
+                    let headline = format!(
+                        "🏳️ {} ended the challenge.",
+                        message.sender.display_name.as_deref().unwrap_or("A player")
+                    );
+                    self.finish_challenge(&chat, Some(&headline)).await;
+                }
+                None => {
+                    let _ = self
+                        .send_message(chat_id, "🤔 You're not in a challenge.")
+                        .await;
+                }
+            }
+            return;
+        }
+        if let Some(request) = parse_challenge_command(message_text) {
+            self.start_challenge(
+                chat_id,
+                &message.sender,
+                message.chat.is_group(),
+                &request,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            return;
+        }
+
+        // Check if the message starts or stops a quiz
+        if message_text.eq_ignore_ascii_case("quiz stop") {
+            let session = self.quiz_sessions.lock().unwrap().remove(chat_id.as_str());
+            let reply = match session {
+                Some(session) => session.summary(),
+                None => "🤔 There is no quiz in progress.".to_string(),
+            };
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send quiz summary: {}", e);
+            }
+            return;
+        }
+        if let Some((count, question_type)) = parse_quiz_command(message_text) {
+            if self
+                .mock_sessions
+                .lock()
+                .unwrap()
+                .contains_key(chat_id.as_str())
+            {
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "🤔 Finish your mock test first, or send 'mock stop' to end it.",
+                    )
+                    .await;
+                return;
+            }
+            if self.in_challenge(chat_id) {
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "🤔 Finish your challenge first, or send 'challenge stop' to end it.",
+                    )
+                    .await;
+                return;
+            }
+            self.start_quiz(
+                chat_id,
+                sender_id,
+                count,
+                question_type,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            return;
+        }
+
+        // Check if the message starts or stops a mock test
+        if message_text.eq_ignore_ascii_case("mock stop") {
+            if self
+                .mock_sessions
+                .lock()
+                .unwrap()
+                .contains_key(chat_id.as_str())
+            {
+                self.finish_mock(chat_id, output_dir, image_host).await;
+            } else if let Err(e) = self
+                .send_message(chat_id, "🤔 There is no mock test in progress.")
+                .await
+            {
+                error!("Failed to send message: {}", e);
+            }
+            return;
+        }
+        if message_text.eq_ignore_ascii_case("mock") {
+            self.start_mock(
+                chat_id, sender_id, &config, database, output_dir, image_host,
+            )
+            .await;
+            return;
+        }
+
+        // Check if the message is a question ID (numeric)
+        if let Ok(question_id) = message_text.parse::<u32>() {
+            // User sent a direct question ID
+            info!("User requested question with ID: {}", question_id);
+
+            // Inform user that the bot is processing the request; the message is
+            // removed once the question lands, or updated in place with the error
+            let status = self
+                .send_status(
+                    chat_id,
+                    &format!("⏳ Fetching question #{}...", question_id),
+                )
+                .await;
+
+            // Try to fetch the specific question
+            match fetch_question_content(
+                &self.database_source,
+                &question_id.to_string(),
+                self.question_cache.as_ref(),
+            )
+            .await
+            {
+                Ok(content) => {
+                    // Generate and send the question image with explanations
+                    match self
+                        .send_question(chat_id, &content, None, output_dir, image_host, true) // Always show explanations when the user requested for a specific question
+                        .await
+                    {
+                        Ok(()) => self.clear_status(chat_id, status.as_deref()).await,
+                        Err(e) => {
+                            error!("Failed to send question: {}", e);
+                            self.update_status(chat_id, status.as_deref(), "❌ Failed to process the requested question. Please try again later.").await;
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to fetch question: {}", e);
+                    self.update_status(chat_id, status.as_deref(), &format!("💁 We don't have the question #{} your are looking for. Please try another one.", question_id)).await;
+                }
+            }
+            return;
+        }
+
+        // Check if the message asks for a topic, like "topic geometry"
+        let settings = self.user_settings(sender_id);
+        if let Some(topic) = parse_topic_command(message_text) {
+            info!("User requested questions on topic '{}'", topic);
+            self.send_topic_request(
+                chat_id,
+                &topic,
+                settings.always_explain,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            return;
+        }
+
+        // Check if the message asks for a type at a given difficulty, like "ps hard"
+        if let Some((q_type, difficulty)) = parse_difficulty_request(message_text) {
+            info!("User requested {} {} questions", difficulty, q_type);
+            self.send_difficulty_request(
+                chat_id,
+                Some(q_type),
+                difficulty,
+                settings.always_explain,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            return;
+        }
+
+        // Parse message to determine question type; "next" picks from the sender's defaults
+        // and "question" leans towards the types the sender gets wrong most
+        let requested_type = if message_text.eq_ignore_ascii_case("question") {
+            Some(self.adaptive_question_type(chat_id, sender_id, &config, database))
+        } else if message_text.eq_ignore_ascii_case("next") {
+            match next_question_type(&settings, &config) {
+                Ok(q_type) => Some(q_type),
+                Err(reply) => {
+                    if let Err(e) = self.send_message(chat_id, &reply).await {
+                        error!("Failed to send message: {}", e);
+                    }
+                    return;
+                }
+            }
+        } else {
+            parse_question_type(message_text).map(Some)
+        };
+
+        if let Some(q_type) = requested_type {
+            info!("User requested {} questions", type_label(q_type));
+
+            // The sender's preferred difficulty applies unless the request named one,
+            // then the band they've reached in the type
+            if let Some(difficulty) = settings
+                .difficulty
+                .or_else(|| self.progression_band(sender_id, q_type))
+            {
+                self.send_difficulty_request(
+                    chat_id,
+                    q_type,
+                    difficulty,
+                    settings.always_explain,
+                    database,
+                    output_dir,
+                    image_host,
+                )
+                .await;
+                return;
+            }
+
+            // Inform user that the bot is processing the request
+            let status = self
+                .send_status(chat_id, "⏳ Processing your request, please wait...")
+                .await;
+
+            self.send_random_question(
+                chat_id,
+                sender_id,
+                q_type,
+                settings.always_explain,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            self.clear_status(chat_id, status.as_deref()).await;
+        } else if self
+            .handlers
+            .dispatch(self, message, message_text, true)
+            .await
+        {
+            debug!("Unrecognized input taken by a fallback handler");
+        } else {
+            // User message doesn't match any question type, send help message
+            let help_message = help_message(is_group, &config.command_prefix);
+            match self.send_message(chat_id, &help_message).await {
+                Ok(()) => {
+                    info!("Sent help message for unrecognized input");
+                }
+                Err(e) => {
+                    error!("Failed to send help message to user {}: {}", sender_id, e);
+                }
+            }
+        }
+    }
+
+    /// Greets someone who just joined a group with the help message
+    async fn on_member_joined(&self, message: &ZaloMessage) {
+        let chat_id = &message.chat.id;
+        let config = self.chat_config(chat_id);
+        let greeting = format!(
+            "🎉 Welcome to the group, {}!\n\n{}",
+            message.sender.display_name.as_deref().unwrap_or("newcomer"),
+            help_message(message.chat.is_group(), &config.command_prefix)
+        );
+        match self.send_message(chat_id, &greeting).await {
+            Ok(()) => info!("Greeted new group member"),
+            Err(e) => error!("Failed to greet new group member: {}", e),
+        }
+    }
+
+    /// Reads a screenshot with OCR and sends the question it shows, or lists the
+    /// closest cached questions when none is a clear match
+    async fn find_question_from_photo(
+        &self,
+        message: &ZaloMessage,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let chat_id = &message.chat.id;
+        let Some(photo_url) = message.photo.as_deref() else {
+            return self.on_media(message).await;
+        };
+        let status = self
+            .send_status(chat_id, "🔎 Reading your screenshot...")
+            .await;
+        let text = match read_photo(
+            self.http.client(ProxyRoute::Chat),
+            photo_url,
+            &message.message_id,
+            output_dir,
+        )
+        .await
+        {
+            Ok(text) => text,
+            Err(e) => {
+                error!("Failed to read screenshot: {}", e);
+                self.update_status(
+                    chat_id,
+                    status.as_deref(),
+                    "❌ I couldn't read that image. Send the question number instead.",
+                )
+                .await;
+                return;
+            }
+        };
+
+        // An ID in the screenshot is the surest match, then the question text itself
+        let by_id = question_id_candidates(&text)
+            .into_iter()
+            .find(|id| database.find_question_type(id).is_some());
+        let matches = match (&by_id, &self.question_cache) {
+            (None, Some(cache)) => rank_matches(&keywords(&text), cache.all()),
+            _ => Vec::new(),
+        };
+        let found = by_id.or_else(|| {
+            matches
+                .first()
+                .filter(|best| best.score >= MIN_KEYWORD_MATCHES)
+                .map(|best| best.question_id.clone())
+        });
+
+        let Some(question_id) = found else {
+            info!("No question matches the screenshot");
+            let reply = format_suggestions(&matches);
+            self.update_status(chat_id, status.as_deref(), &reply).await;
+            return;
+        };
+        info!("Screenshot shows question {}", question_id);
+        let result = match fetch_question_content(
+            &self.database_source,
+            &question_id,
+            self.question_cache.as_ref(),
+        )
+        .await
+        {
+            Ok(content) => {
+                let question_type = database.find_question_type(&question_id);
+                self.send_question(
+                    chat_id,
+                    &content,
+                    question_type.as_ref(),
+                    output_dir,
+                    image_host,
+                    false,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => self.clear_status(chat_id, status.as_deref()).await,
+            Err(e) => {
+                error!("Failed to send question from screenshot: {}", e);
+                self.update_status(
+                    chat_id,
+                    status.as_deref(),
+                    &format!(
+                        "❌ I found question #{} but couldn't send it. Please try again later.",
+                        question_id
+                    ),
+                )
+                .await;
+            }
+        }
+    }
+
+    /// Photos and stickers can't be commands; in private chats the sender is told how to reply
+    async fn on_media(&self, message: &ZaloMessage) {
+        if message.chat.is_group() {
+            debug!("Ignoring photo or sticker in group");
+            return;
+        }
+        let reply = "🙈 I can only read text. Reply with A, B, C, D or E to answer a question, \
+            or send 'help' to see what I can do.";
+        if let Err(e) = self.send_message(&message.chat.id, reply).await {
+            error!("Failed to reply to photo or sticker: {}", e);
+        }
+    }
+
+    /// Picks a question the chat hasn't seen yet and sends it, telling the user
+    /// when nothing could be delivered. Returns whether a question was sent.
+    #[allow(clippy::too_many_arguments)]
+    async fn send_random_question(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        question_type: Option<QuestionType>,
+        show_explanations: bool,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> bool {
+        let type_label = type_label(question_type);
+
+        // Pick a random question of the requested type, retrying with another one on failure
+        let mut attempts = 0;
+        let max_attempts = 3;
+        let mut last_error = None;
+
+        while attempts < max_attempts {
+            // Skip questions this chat has already received
+            let seen = self.seen_question_ids(chat_id);
+            let selected_questions = pick_random_questions_excluding(
+                database,
+                &self.exclusions,
+                &question_type,
+                1,
+                &seen,
+            );
+
+            if selected_questions.is_empty() && !seen.is_empty() {
+                let done_msg = format!(
+                    "🎉 You've already practiced every {} question! Please try another type.",
+                    type_label
+                );
+                if let Err(e) = self.send_message(chat_id, &done_msg).await {
+                    error!("Failed to send message: {}", e);
+                }
+                return false;
+            }
+
+            if selected_questions.is_empty() {
+                let error_msg = format!(
+                    "⚠️ Sorry, no {} questions are available at the moment. Please try another type.",
+                    type_label
+                );
+                if let Err(e) = self.send_message(chat_id, &error_msg).await {
+                    error!("Failed to send error message: {}", e);
+                }
+                return false;
+            }
+
+            let (selected_type, question_id) = &selected_questions[0];
+            info!("Selected question: {} ({})", question_id, selected_type);
+
+            // Fetch question content
+            match fetch_question_content(
+                &self.database_source,
+                question_id,
+                self.question_cache.as_ref(),
+            )
+            .await
+            {
+                Ok(content) => {
+                    // Use send_question to handle the rest
+                    match self
+                        .send_question(
+                            chat_id,
+                            &content,
+                            Some(selected_type),
+                            output_dir,
+                            image_host,
+                            show_explanations,
+                        )
+                        .await
+                    {
+                        Ok(()) => {
+                            info!(
+                                "Successfully sent {} question {} to user {}",
+                                selected_type, question_id, sender_id
+                            );
+                            return true;
+                        }
+                        Err(e) => {
+                            error!("Failed to send question to user {}: {}", sender_id, e);
+                            last_error = Some(format!("Failed to send question: {}", e));
+                            attempts += 1;
+                            if attempts < max_attempts {
+                                info!(
+                                    "Retrying with another question (attempt {}/{})...",
+                                    attempts + 1,
+                                    max_attempts
+                                );
+                            }
+                            continue;
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to fetch question {}: {}", question_id, e);
+                    last_error = Some(format!("Failed to fetch question: {}", e));
+                    break;
+                }
+            }
+        }
+
+        // If we reach here, all attempts failed
+        let error_msg = last_error.unwrap_or_else(|| {
+            "⚠️ Sorry, something went wrong and your request could not be processed.".to_string()
+        });
+        if let Err(e) = self.send_message(chat_id, &error_msg).await {
+            error!("Failed to send error message: {}", e);
+        }
+        false
+    }
+
+    /// Type for a bare "question": weighted towards the sender's weakest types among
+    /// those the chat allows and still has unseen questions of; `None` means any type
+    fn adaptive_question_type(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        config: &ChatConfig,
+        database: &GmatDatabase,
+    ) -> Option<QuestionType> {
+        let seen = self.seen_question_ids(chat_id);
+        let by_type = match &self.storage {
+            Some(storage) => storage
+                .user_stats(sender_id)
+                .map(|stats| stats.by_type)
+                .unwrap_or_else(|e| {
+                    warn!("Failed to load user stats: {}", e);
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+        let candidates: Vec<QuestionType> = database
+            .get_all_questions()
+            .into_iter()
+            .filter(|(question_type, ids)| {
+                config.allows(*question_type) && ids.iter().any(|id| !seen.contains(id))
+            })
+            .map(|(question_type, _)| question_type)
+            .collect();
+        let mut rng = rand::thread_rng();
+        let picked = pick_adaptive_type(&by_type, &candidates, &mut rng).or_else(|| {
+            // Everything allowed has been seen; pick one so the reply names the type
+            config
+                .allowed_types
+                .as_ref()
+                .and_then(|types| types.choose(&mut rng).copied())
+        });
+        debug!(?picked, ?by_type, "Picked adaptive question type");
+        picked
+    }
+
+    /// The band the sender has reached in a type with progression on; `None` when it's
+    /// off or the request is for any type
+    fn progression_band(
+        &self,
+        sender_id: &str,
+        question_type: Option<QuestionType>,
+    ) -> Option<Difficulty> {
+        self.progression?;
+        let (Some(storage), Some(question_type)) = (&self.storage, question_type) else {
+            return None;
+        };
+        match storage.progression_band(sender_id, &question_type) {
+            Ok(band) => Some(band.unwrap_or(STARTING_BAND)),
+            Err(e) => {
+                warn!("Failed to load progression band: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Sends a question at a difficulty behind a "⏳ Looking for..." message
+    #[allow(clippy::too_many_arguments)]
+    async fn send_difficulty_request(
+        &self,
+        chat_id: &str,
+        question_type: Option<QuestionType>,
+        difficulty: Difficulty,
+        show_explanations: bool,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let status = self
+            .send_status(
+                chat_id,
+                &format!(
+                    "⏳ Looking for a {} {} question...",
+                    difficulty,
+                    type_label(question_type)
+                ),
+            )
+            .await;
+        self.send_question_by_difficulty(
+            chat_id,
+            question_type,
+            difficulty,
+            show_explanations,
+            database,
+            output_dir,
+            image_host,
+        )
+        .await;
+        // Failures are reported in their own message, so the status can go either way
+        self.clear_status(chat_id, status.as_deref()).await;
+    }
+
+    /// Sends an unseen question on `topic` of a type the chat allows, behind a
+    /// "⏳ Looking for..." message; an empty topic gets the usage and known topics
+    async fn send_topic_request(
+        &self,
+        chat_id: &str,
+        topic: &str,
+        show_explanations: bool,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        if topic.is_empty() {
+            let mut reply = "🏷️ Send 'topic' with a topic to get a question on it, like 'topic geometry' or 'topic assumption'.".to_string();
+            if let Some(tags) = &self.topic_tags {
+                let topics = tags.all_topics();
+                if !topics.is_empty() {
+                    reply.push_str(&format!("\n\nTopics: {}", topics.join(", ")));
+                }
+            }
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send topic usage: {}", e);
+            }
+            return;
+        }
+
+        let status = self
+            .send_status(chat_id, &format!("⏳ Looking for a {} question...", topic))
+            .await;
+        let mut exclude = self.seen_question_ids(chat_id);
+        let config = self.chat_config(chat_id);
+        for (question_type, ids) in database.get_all_questions() {
+            if !config.allows(question_type) {
+                exclude.extend(ids.iter().cloned());
+            }
+        }
+        let picked = pick_random_questions_by_topic(
+            database,
+            &self.exclusions,
+            &None,
+            topic,
+            1,
+            &exclude,
+            self.topic_lookup(),
+        )
+        .await;
+
+        match picked.into_iter().next() {
+            Some((q_type, content)) => {
+                if let Err(e) = self
+                    .send_question(
+                        chat_id,
+                        &content,
+                        Some(&q_type),
+                        output_dir,
+                        image_host,
+                        show_explanations,
+                    )
+                    .await
+                {
+                    error!("Failed to send question: {}", e);
+                    let _ = self
+                        .send_message(
+                            chat_id,
+                            "❌ Failed to process the requested question. Please try again later.",
+                        )
+                        .await;
+                }
+            }
+            None => {
+                let msg = format!(
+                    "😕 I couldn't find a {} question right now. Try again, or send 'topic' to see the topics I know.",
+                    topic
+                );
+                if let Err(e) = self.send_message(chat_id, &msg).await {
+                    error!("Failed to send message: {}", e);
+                }
+            }
+        }
+        // Failures are reported in their own message, so the status can go either way
+        self.clear_status(chat_id, status.as_deref()).await;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn start_quiz(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        count: usize,
+        question_type: Option<QuestionType>,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        info!(
+            "Starting {}-question quiz in chat: {} ({})",
+            count,
+            chat_id,
+            question_type.map_or("mixed".to_string(), |t| t.to_string())
+        );
+        let intro = format!(
+            "🏁 Quiz started: {} {} question(s). Reply with A–E to each one, the clock is ticking! ⏱️",
+            count,
+            question_type.map_or("mixed".to_string(), |t| t.to_string())
+        );
+        if let Err(e) = self.send_message(chat_id, &intro).await {
+            error!("Failed to send quiz intro: {}", e);
+        }
+
+        self.quiz_sessions
+            .lock()
+            .unwrap()
+            .insert(chat_id.to_string(), QuizSession::new(count, question_type));
+        self.send_next_quiz_question(chat_id, sender_id, database, output_dir, image_host)
+            .await;
+    }
+
+    /// Starts "challenge @name": the same questions go to both players' private chats
+    #[allow(clippy::too_many_arguments)]
+    async fn start_challenge(
+        &self,
+        chat_id: &str,
+        sender: &ZaloSender,
+        is_group: bool,
+        request: &ChallengeRequest,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let (challenger, opponent) =
+            match self.challenge_players(chat_id, sender, is_group, request) {
+                Ok(players) => players,
+                Err(reply) => {
+                    if let Err(e) = self.send_message(chat_id, &reply).await {
+                        error!("Failed to send message: {}", e);
+                    }
+                    return;
+                }
+            };
+
+        // Questions neither player has seen, if there are enough of them
+        let mut seen = self.seen_question_ids(&challenger.chat_id);
+        seen.extend(self.seen_question_ids(&opponent.chat_id));
+        let mut picked = pick_random_questions_excluding(
+            database,
+            &self.exclusions,
+            &request.question_type,
+            request.count,
+            &seen,
+        );
+        if picked.is_empty() {
+            picked = pick_random_questions_excluding(
+                database,
+                &self.exclusions,
+                &request.question_type,
+                request.count,
+                &HashSet::new(),
+            );
+        }
+        let mut questions = Vec::new();
+        for (question_type, question_id) in picked {
+            match fetch_question_content(
+                &self.database_source,
+                &question_id,
+                self.question_cache.as_ref(),
+            )
+            .await
+            {
+                Ok(content) => questions.push((question_type, content)),
+                Err(e) => warn!("Skipping challenge question {}: {}", question_id, e),
+            }
+        }
+        let type_label = type_label(request.question_type);
+        if questions.is_empty() {
+            let reply = format!(
+                "⚠️ Sorry, no {} questions are available at the moment. Please try another type.",
+                type_label
+            );
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send error message: {}", e);
+            }
+            return;
+        }
+
+        info!(
+            "Starting {}-question challenge between {} and {}",
+            questions.len(),
+            challenger.user_id,
+            opponent.user_id
+        );
+        let intro = format!(
+            "⚔️ {} vs {}: {} {} question(s), the same for both of you. Reply with A–E to each one, the clock is ticking! ⏱️ Send 'challenge stop' to end it.",
+            challenger.name,
+            opponent.name,
+            questions.len(),
+            type_label
+        );
+        let announcement = format!(
+            "⚔️ {} challenged {} to {} {} question(s)! The questions go out privately and the result comes back here.",
+            challenger.name,
+            opponent.name,
+            questions.len(),
+            type_label
+        );
+        let chats = [challenger.chat_id.clone(), opponent.chat_id.clone()];
+        if !self.begin_challenge(ChallengeSession::new(
+            challenger, opponent, questions, chat_id,
+        )) {
+            let reply = "🤔 One of you just started a quiz, mock test or challenge. Try again once it's over.";
+            if let Err(e) = self.send_message(chat_id, reply).await {
+                error!("Failed to send message: {}", e);
+            }
+            return;
+        }
+        if is_group && let Err(e) = self.send_message(chat_id, &announcement).await {
+            error!("Failed to send challenge announcement: {}", e);
+        }
+        for chat in &chats {
+            if let Err(e) = self.send_message(chat, &intro).await {
+                error!("Failed to send challenge intro: {}", e);
+            }
+            self.advance_challenge(chat, output_dir, image_host).await;
+        }
+    }
+
+    /// Adds `session` unless a player has started a quiz, mock test or challenge
+    /// since [`Self::challenge_players`] looked. The check and the insert share the
+    /// challenges lock, so two challenges started at once can't both claim a player
+    pub fn begin_challenge(&self, session: ChallengeSession) -> bool {
+        let mut challenges = self.challenges.lock().unwrap();
+        let busy = session.players.iter().any(|player| {
+            let chat = player.chat_id.as_str();
+            challenges.iter().any(|other| other.player(chat).is_some())
+                || self.quiz_sessions.lock().unwrap().contains_key(chat)
+                || self.mock_sessions.lock().unwrap().contains_key(chat)
+        });
+        if !busy {
+            challenges.push(session);
+        }
+        !busy
+    }
+
+    /// Both sides of a challenge, or the reason it can't start
+    fn challenge_players(
+        &self,
+        chat_id: &str,
+        sender: &ZaloSender,
+        is_group: bool,
+        request: &ChallengeRequest,
+    ) -> std::result::Result<(ChallengePlayer, ChallengePlayer), String> {
+        let Some(storage) = &self.storage else {
+            return Err(
+                "🤷 Challenges can't find your friend because nothing is being stored.".to_string(),
+            );
+        };
+        let lookup_failed = |e: BotError| {
+            error!("Failed to look up challenge players: {}", e);
+            "❌ Failed to start the challenge. Please try again later.".to_string()
+        };
+
+        let challenger_chat = if is_group {
+            storage.private_chat_id(&sender.id).map_err(lookup_failed)?
+        } else {
+            Some(chat_id.to_string())
+        };
+        let Some(challenger_chat) = challenger_chat else {
+            return Err(
+                "📭 Send me a private message first, so I can send you the questions.".to_string(),
+            );
+        };
+
+        let name = &request.opponent;
+        let users = storage.find_users(name).map_err(lookup_failed)?;
+        let (opponent_id, opponent_chat) = match users.as_slice() {
+            [] => {
+                return Err(format!(
+                    "🤔 I don't know anyone called {} yet. They need to send me a message first.",
+                    name
+                ));
+            }
+            [user] => user.clone(),
+            _ => {
+                return Err(format!(
+                    "🤔 More than one person is called {}. Try their user ID instead.",
+                    name
+                ));
+            }
+        };
+        if opponent_id == sender.id {
+            return Err("🙃 You can't challenge yourself! Pick a friend.".to_string());
+        }
+        let Some(opponent_chat) = opponent_chat else {
+            return Err(format!(
+                "📭 {} needs to send me a private message first, so I can send them the questions.",
+                name
+            ));
+        };
+
+        let busy = |chat: &str| {
+            self.in_challenge(chat)
+                || self.quiz_sessions.lock().unwrap().contains_key(chat)
+                || self.mock_sessions.lock().unwrap().contains_key(chat)
+        };
+        if busy(&challenger_chat) {
+            return Err("🤔 Finish your quiz, mock test or challenge first.".to_string());
+        }
+        if busy(&opponent_chat) {
+            return Err(format!(
+                "🤔 {} is busy with a quiz, mock test or challenge. Try again later!",
+                name
+            ));
+        }
+
+        let challenger_name = sender.display_name.as_deref().unwrap_or(&sender.id);
+        Ok((
+            ChallengePlayer::new(&sender.id, challenger_name, &challenger_chat),
+            ChallengePlayer::new(&opponent_id, name, &opponent_chat),
+        ))
+    }
+
+    /// Whether `chat_id` belongs to a player of a running challenge
+    fn in_challenge(&self, chat_id: &str) -> bool {
+        self.challenges
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|session| session.player(chat_id).is_some())
+    }
+
+    /// Whether `question_id` is the challenge question waiting for the chat's reply
+    fn is_challenge_question(&self, chat_id: &str, question_id: &str) -> bool {
+        self.challenges
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|session| session.is_current(chat_id, question_id))
+    }
+
+    /// Runs `f` on the challenge `chat_id` is playing in, if any
+    fn with_challenge<T>(
+        &self,
+        chat_id: &str,
+        f: impl FnOnce(&mut ChallengeSession) -> T,
+    ) -> Option<T> {
+        self.challenges
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|session| session.player(chat_id).is_some())
+            .map(f)
+    }
+
+    /// Sends the player in `chat_id` their next challenge question, skipping any that
+    /// fail to send; once they're done, ends the challenge or waits for the other player
+    async fn advance_challenge(&self, chat_id: &str, output_dir: &str, image_host: &dyn ImageHost) {
+        loop {
+            let next = self
+                .with_challenge(chat_id, |session| {
+                    session
+                        .next_question(chat_id)
+                        .map(|(number, (t, content))| {
+                            (number, session.questions.len(), *t, content.clone())
+                        })
+                })
+                .flatten();
+            let Some((number, total, question_type, content)) = next else {
+                break;
+            };
+            info!(
+                "Challenge question {}/{} for chat: {}",
+                number, total, chat_id
+            );
+            let result = self
+                .send_question(
+                    chat_id,
+                    &content,
+                    Some(&question_type),
+                    output_dir,
+                    image_host,
+                    false,
+                )
+                .await;
+            match result {
+                Ok(()) => {
+                    self.with_challenge(chat_id, |session| {
+                        session.question_sent(chat_id, &content.id)
+                    });
+                    return;
+                }
+                Err(e) => {
+                    error!("Failed to send challenge question {}: {}", content.id, e);
+                    self.with_challenge(chat_id, |session| session.skip(chat_id));
+                }
+            }
+        }
+
+        let waiting_for = self
+            .with_challenge(chat_id, |session| {
+                if session.is_finished() {
+                    return None;
+                }
+                session
+                    .players
+                    .iter()
+                    .find(|player| player.chat_id != chat_id)
+                    .map(|player| player.name.clone())
+            })
+            .flatten();
+        match waiting_for {
+            Some(name) => {
+                let note = format!("🏁 You're done! Waiting for {} to finish…", name);
+                if let Err(e) = self.send_message(chat_id, &note).await {
+                    error!("Failed to send message: {}", e);
+                }
+            }
+            None => self.finish_challenge(chat_id, None).await,
+        }
+    }
+
+    /// Ends the challenge `chat_id` plays in and posts the comparison to both
+    /// players, and to the chat it was issued in when that's a group
+    async fn finish_challenge(&self, chat_id: &str, headline: Option<&str>) {
+        let session = {
+            let mut challenges = self.challenges.lock().unwrap();
+            let Some(index) = challenges
+                .iter()
+                .position(|session| session.player(chat_id).is_some())
+            else {
+                return;
+            };
+            challenges.remove(index)
+        };
+        // A question still on screen is no longer graded
+        for player in &session.players {
+            let on_screen = player.current.as_deref().is_some_and(|question_id| {
+                self.pending_questions
+                    .lock()
+                    .unwrap()
+                    .get(&player.chat_id)
+                    .is_some_and(|pending| pending.content.id == question_id)
+            });
+            if on_screen {
+                self.take_pending_question(&player.chat_id);
+            }
+        }
+        info!(
+            "Challenge finished between {} and {}",
+            session.players[0].user_id, session.players[1].user_id
+        );
+
+        let summary = match headline {
+            Some(headline) => format!("{}\n\n{}", headline, session.summary()),
+            None => session.summary(),
+        };
+        let mut chats: Vec<&str> = session.players.iter().map(|p| p.chat_id.as_str()).collect();
+        if !chats.contains(&session.origin_chat_id.as_str()) {
+            chats.push(&session.origin_chat_id);
+        }
+        for chat in chats {
+            if let Err(e) = self.send_message(chat, &summary).await {
+                error!("Failed to send challenge result: {}", e);
+            }
+        }
+    }
+
+    /// Starts a mock test, or says how far along the chat's running one is
+    #[allow(clippy::too_many_arguments)]
+    async fn start_mock(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        config: &ChatConfig,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let running = self
+            .mock_sessions
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .map(|session| session.progress());
+        let session = if let Some(progress) = running {
+            Err(format!(
+                "📝 A mock test is already running: {}. Send 'mock stop' to end it now.",
+                progress
+            ))
+        } else if self.quiz_sessions.lock().unwrap().contains_key(chat_id) {
+            Err("🤔 Finish your quiz first, or send 'quiz stop' to end it.".to_string())
+        } else if self.in_challenge(chat_id) {
+            Err("🤔 Finish your challenge first, or send 'challenge stop' to end it.".to_string())
+        } else {
+            MockSession::new(sender_id, |t| config.allows(t), &mut rand::thread_rng()).ok_or_else(
+                || "🚫 Mock tests need quant or verbal question types, and this chat has them turned off.".to_string(),
+            )
+        };
+        let session = match session {
+            Ok(session) => session,
+            Err(reply) => {
+                if let Err(e) = self.send_message(chat_id, &reply).await {
+                    error!("Failed to send message: {}", e);
+                }
+                return;
+            }
+        };
+
+        info!(
+            "Starting {}-question mock test in chat: {}",
+            session.plan.len(),
+            chat_id
+        );
+        let intro = format!(
+            "📝 Mock test started: {} questions in {} minutes, at most {} minutes each. Reply with A–E; your score and the explanations come at the end.",
+            session.plan.len(),
+            MOCK_TIME_LIMIT.as_secs() / 60,
+            MOCK_QUESTION_CAP.as_secs() / 60
+        );
+        if let Err(e) = self.send_message(chat_id, &intro).await {
+            error!("Failed to send mock test intro: {}", e);
+        }
+        self.mock_sessions
+            .lock()
+            .unwrap()
+            .insert(chat_id.to_string(), session);
+        self.send_next_mock_question(chat_id, database, output_dir, image_host)
+            .await;
+    }
+
+    /// Sends the next question of the chat's mock test, ending the test if none can be delivered
+    async fn send_next_mock_question(
+        &self,
+        chat_id: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let next = self
+            .mock_sessions
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .map(|session| {
+                (
+                    session.next_type(),
+                    session.starts_section(),
+                    session.next_number(),
+                    session.plan.len(),
+                    session.user_id.clone(),
+                )
+            });
+        let Some((Some(question_type), section, number, total, user_id)) = next else {
+            return;
+        };
+
+        if let Some(section) = section {
+            let (_, questions) = self
+                .mock_sessions
+                .lock()
+                .unwrap()
+                .get(chat_id)
+                .map_or((0, 0), |session| session.section_score(section));
+            let heading = format!("➡️ {} section: {} questions", section, questions);
+            if let Err(e) = self.send_message(chat_id, &heading).await {
+                error!("Failed to send mock section heading: {}", e);
+            }
+        }
+        info!("Mock question {}/{} for chat: {}", number, total, chat_id);
+        let sent = self
+            .send_random_question(
+                chat_id,
+                &user_id,
+                Some(question_type),
+                false,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+        if !sent {
+            self.finish_mock(chat_id, output_dir, image_host).await;
+            return;
+        }
+
+        // Start the clock once the question has actually landed
+        let question_id = self
+            .pending_questions
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .map(|pending| pending.content.id.clone());
+        if let Some(question_id) = question_id
+            && let Some(session) = self.mock_sessions.lock().unwrap().get_mut(chat_id)
+        {
+            session.question_sent(&question_id);
+        }
+    }
+
+    /// Whether `question_id` is the mock test question waiting for the chat's reply
+    fn is_mock_question(&self, chat_id: &str, question_id: &str) -> bool {
+        self.mock_sessions
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .is_some_and(|session| session.current.as_deref() == Some(question_id))
+    }
+
+    /// Records a mock test reply, or `None` when the question ran out of time, then
+    /// moves on without a verdict
+    async fn continue_mock(
+        &self,
+        chat_id: &str,
+        pending: &PendingQuestion,
+        answer: Option<char>,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let progress = {
+            let mut sessions = self.mock_sessions.lock().unwrap();
+            let Some(session) = sessions.get_mut(chat_id) else {
+                return;
+            };
+            session.record(pending, answer);
+            (!session.is_finished()).then(|| session.progress())
+        };
+        let Some(progress) = progress else {
+            self.finish_mock(chat_id, output_dir, image_host).await;
+            return;
+        };
+
+        let note = match answer {
+            Some(_) => format!("📝 Answer recorded ({}).", progress),
+            None => format!("⏰ Time's up for this question ({}).", progress),
+        };
+        if let Err(e) = self.send_message(chat_id, &note).await {
+            error!("Failed to send message: {}", e);
+        }
+        self.send_next_mock_question(chat_id, database, output_dir, image_host)
+            .await;
+    }
+
+    /// Ends the chat's mock test: sends the score report, then the explanations
+    /// of every question that wasn't answered correctly
+    async fn finish_mock(&self, chat_id: &str, output_dir: &str, image_host: &dyn ImageHost) {
+        let Some(session) = self.mock_sessions.lock().unwrap().remove(chat_id) else {
+            return;
+        };
+        // A question still on screen is no longer graded
+        let on_screen = session.current.as_deref().is_some_and(|question_id| {
+            self.pending_questions
+                .lock()
+                .unwrap()
+                .get(chat_id)
+                .is_some_and(|pending| pending.content.id == question_id)
+        });
+        if on_screen {
+            self.take_pending_question(chat_id);
+        }
+        info!(
+            "Mock test finished in chat: {} ({}/{} answered)",
+            chat_id,
+            session.answers.len(),
+            session.plan.len()
+        );
+
+        let html = generate_mock_report_html(&session);
+        let result = self
+            .send_card(
+                chat_id,
+                &html,
+                &format!("mock_{}", chat_id),
+                "Your mock test results 📋",
+                output_dir,
+                image_host,
+            )
+            .await;
+        if let Err(e) = result {
+            warn!("Sending mock test results as text instead: {}", e);
+            if let Err(e) = self.send_message(chat_id, &session.summary()).await {
+                error!("Failed to send mock test results: {}", e);
+            }
+        }
+
+        for (i, answer) in session.answers.iter().enumerate() {
+            if answer.correct() == Some(true) {
+                continue;
+            }
+            let caption = format!("Explanations for question {} 📖", i + 1);
+            let result = self
+                .send_question_image(
+                    chat_id,
+                    &answer.content,
+                    &answer.question_type,
+                    true,
+                    None,
+                    &caption,
+                    output_dir,
+                    image_host,
+                )
+                .await;
+            if let Err(e) = result {
+                error!("Failed to send explanations: {}", e);
+            }
+        }
+    }
+
+    /// Sends one unseen question of the given difficulty, of any type when `question_type` is `None`
+    #[allow(clippy::too_many_arguments)]
+    async fn send_question_by_difficulty(
+        &self,
+        chat_id: &str,
+        question_type: Option<QuestionType>,
+        difficulty: Difficulty,
+        show_explanations: bool,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let seen = self.seen_question_ids(chat_id);
+        let picked = pick_random_questions_by_difficulty(
+            database,
+            &self.exclusions,
+            &question_type,
+            difficulty,
+            1,
+            &seen,
+            &self.database_source,
+            self.question_cache.as_ref(),
+        )
+        .await;
+
+        let Some((q_type, content)) = picked.into_iter().next() else {
+            let msg = format!(
+                "😕 I couldn't find a {} {} question right now. Try again, or send the type alone for any difficulty.",
+                difficulty,
+                type_label(question_type)
+            );
+            if let Err(e) = self.send_message(chat_id, &msg).await {
+                error!("Failed to send message: {}", e);
+            }
+            return;
+        };
+
+        if let Err(e) = self
+            .send_question(
+                chat_id,
+                &content,
+                Some(&q_type),
+                output_dir,
+                image_host,
+                show_explanations,
+            )
+            .await
+        {
+            error!("Failed to send question: {}", e);
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "❌ Failed to process the requested question. Please try again later.",
+                )
+                .await;
+        }
+    }
+
+    /// Sends the next question of the chat's quiz, ending the quiz if none can be delivered
+    async fn send_next_quiz_question(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let question_type = match self.quiz_sessions.lock().unwrap().get(chat_id) {
+            Some(session) => {
+                info!(
+                    "Quiz question {}/{} for chat: {}",
+                    session.next_number(),
+                    session.total,
+                    chat_id
+                );
+                session.question_type
+            }
+            None => return,
+        };
+
+        let sent = self
+            .send_random_question(
+                chat_id,
+                sender_id,
+                question_type,
+                false,
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+
+        let mut sessions = self.quiz_sessions.lock().unwrap();
+        if sent {
+            // Start the clock once the question has actually landed
+            if let Some(session) = sessions.get_mut(chat_id) {
+                session.question_started = std::time::Instant::now();
+            }
+        } else {
+            sessions.remove(chat_id);
+        }
+    }
+
+    /// A user's defaults, falling back to the bot's when none are stored
+    fn user_settings(&self, user_id: &str) -> UserSettings {
+        let Some(storage) = &self.storage else {
+            return UserSettings::default();
+        };
+        storage
+            .get_user_settings(user_id)
+            .unwrap_or_else(|e| {
+                warn!("Failed to load user settings: {}", e);
+                None
+            })
+            .unwrap_or_default()
+    }
+
+    /// Records a "flag" or "report broken" against the last question sent to the chat
+    async fn flag_last_question(&self, chat_id: &str, sender_id: &str, note: &str) {
+        let reply = match &self.storage {
+            None => "🤷 Flags can't be saved because nothing is being stored.".to_string(),
+            Some(storage) => match record_flag(storage, chat_id, sender_id, note) {
+                Ok(Some(question_id)) => {
+                    info!(question_id, "Question flagged");
+                    format!(
+                        "🚩 Thanks! Question #{} has been flagged for review.",
+                        question_id
+                    )
+                }
+                Ok(None) => "🤔 There's no question here to flag yet.".to_string(),
+                Err(e) => {
+                    error!("Failed to save question report: {}", e);
+                    "❌ Failed to save your report. Please try again later.".to_string()
+                }
+            },
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send flag reply: {}", e);
+        }
+    }
+
+    /// Replies to "settings" with the sender's defaults, or changes one with "settings <name> <value>"
+    async fn handle_settings_command(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        is_group: bool,
+        args: &str,
+    ) {
+        let reply = match &self.storage {
+            None => "🤷 Settings can't be saved because nothing is being stored.".to_string(),
+            Some(storage) => self
+                .apply_settings_command(storage, chat_id, sender_id, is_group, args)
+                .unwrap_or_else(|e| {
+                    error!("Failed to save user settings: {}", e);
+                    "❌ Failed to save your settings. Please try again later.".to_string()
+                }),
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send settings reply: {}", e);
+        }
+    }
+
+    fn apply_settings_command(
+        &self,
+        storage: &Storage,
+        chat_id: &str,
+        sender_id: &str,
+        is_group: bool,
+        args: &str,
+    ) -> Result<String> {
+        // The question of the day goes to the sender's private chat, even when asked from a group
+        let private_chat_id = if is_group {
+            storage.private_chat_id(sender_id)?
+        } else {
+            Some(chat_id.to_string())
+        };
+        let mut settings = self.user_settings(sender_id);
+        settings.daily = match &private_chat_id {
+            Some(private_chat_id) => storage.is_subscribed(private_chat_id)?,
+            None => false,
+        };
+        let was_daily = settings.daily;
+        let mut summary = match settings.apply_command(args) {
+            Ok(summary) => summary,
+            Err(usage) => return Ok(format!("❌ {}", usage)),
+        };
+
+        if settings.daily != was_daily {
+            let Some(private_chat_id) = &private_chat_id else {
+                return Ok(
+                    "💬 Send me a private message first, so I know where to post your question of the day."
+                        .to_string(),
+                );
+            };
+            if settings.daily {
+                storage.subscribe(private_chat_id)?;
+            } else {
+                storage.unsubscribe(private_chat_id)?;
+            }
+        }
+        storage.save_user_settings(sender_id, &settings)?;
+        if settings.daily && self.daily_schedule.is_none() {
+            summary.push_str("\n(The question of the day isn't being posted at the moment.)");
+        }
+        Ok(summary)
+    }
+
+    /// Settings for a chat, falling back to defaults when none are stored
+    fn chat_config(&self, chat_id: &str) -> ChatConfig {
+        let Some(storage) = &self.storage else {
+            return ChatConfig::default();
+        };
+        storage
+            .get_chat_config(chat_id)
+            .unwrap_or_else(|e| {
+                warn!("Failed to load chat config: {}", e);
+                None
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sends the user's most overdue missed question, if any is due
+    async fn send_review(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some(storage) = &self.storage else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤷 Reviews aren't available because answers aren't being stored.",
+                )
+                .await;
+            return;
+        };
+
+        let due = match storage.next_due_review(sender_id) {
+            Ok(due) => due,
+            Err(e) => {
+                error!("Failed to load due reviews: {}", e);
+                None
+            }
+        };
+        let Some((question_type, question_id)) = due else {
+            let reply = match storage.next_review_time(sender_id) {
+                Ok(Some(due_at)) => format!(
+                    "🎉 Nothing to review right now! Your next review is due {}.",
+                    chrono::DateTime::from_timestamp(due_at, 0)
+                        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+                        .unwrap_or_else(|| "soon".to_string())
+                ),
+                _ => "🎉 Nothing to review! Questions you answer wrong will come back here."
+                    .to_string(),
+            };
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send review message: {}", e);
+            }
+            return;
+        };
+
+        info!("Sending review question {}", question_id);
+        let result = match fetch_question_content(
+            &self.database_source,
+            &question_id,
+            self.question_cache.as_ref(),
+        )
+        .await
+        {
+            Ok(content) => {
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        &format!(
+                            "🔁 Review time! You missed question #{} before.",
+                            question_id
+                        ),
+                    )
+                    .await;
+                self.send_question(
+                    chat_id,
+                    &content,
+                    Some(&question_type),
+                    output_dir,
+                    image_host,
+                    false,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("Failed to send review question: {}", e);
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "❌ Failed to send your review question. Please try again later.",
+                )
+                .await;
+        }
+    }
+
+    /// Re-sends the explanations image for the question the user last answered
+    async fn send_last_explanation(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let last = match &self.storage {
+            Some(storage) => storage.last_answered_question(sender_id),
+            None => {
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "🤷 Explanations on demand aren't available because answers aren't being stored.",
+                    )
+                    .await;
+                return;
+            }
+        };
+        let (question_id, question_type) = match last {
+            Ok(Some(last)) => last,
+            Ok(None) => {
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "🤔 You haven't answered a question yet. Send a type like 'PS' to get one!",
+                    )
+                    .await;
+                return;
+            }
+            Err(e) => {
+                error!("Failed to load last answered question: {}", e);
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "❌ Failed to find your last question. Please try again later.",
+                    )
+                    .await;
+                return;
+            }
+        };
+        // Answers recorded before types were stored fall back to the database index
+        let question_type = question_type
+            .or_else(|| database.find_question_type(&question_id))
+            .unwrap_or(QuestionType::PS);
+
+        info!("Sending explanations for question {}", question_id);
+        let result = match fetch_question_content(
+            &self.database_source,
+            &question_id,
+            self.question_cache.as_ref(),
+        )
+        .await
+        {
+            Ok(content) => {
+                self.send_question_image(
+                    chat_id,
+                    &content,
+                    &question_type,
+                    true,
+                    None,
+                    &self.explanations_caption(chat_id, &content, question_type),
+                    output_dir,
+                    image_host,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("Failed to send explanations: {}", e);
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "❌ Failed to load the explanations. Please try again later.",
+                )
+                .await;
+        }
+    }
+
+    /// Lists the questions sent to a chat, one page per "history more"
+    async fn send_history(&self, chat_id: &str, command: HistoryCommand) {
+        let Some(storage) = &self.storage else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤷 History isn't available because questions aren't being stored.",
+                )
+                .await;
+            return;
+        };
+        let offset = match command {
+            HistoryCommand::Latest => 0,
+            HistoryCommand::More => self
+                .history_offsets
+                .lock()
+                .unwrap()
+                .get(chat_id)
+                .copied()
+                .unwrap_or(0),
+        };
+        // One extra row tells whether there is an older page
+        let reply = match storage.question_history(chat_id, offset, HISTORY_PAGE_SIZE + 1) {
+            Ok(mut entries) => {
+                let has_more = entries.len() > HISTORY_PAGE_SIZE;
+                entries.truncate(HISTORY_PAGE_SIZE);
+                self.history_offsets
+                    .lock()
+                    .unwrap()
+                    .insert(chat_id.to_string(), offset + entries.len());
+                format_history(&entries, offset, has_more)
+            }
+            Err(e) => {
+                error!("Failed to load question history: {}", e);
+                "❌ Failed to load the question history. Please try again later.".to_string()
+            }
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send question history: {}", e);
+        }
+    }
+
+    async fn send_leaderboard(&self, chat_id: &str) {
+        let reply = match &self.storage {
+            Some(storage) => match storage.leaderboard(chat_id, week_start(), LEADERBOARD_SIZE) {
+                Ok(entries) => format_leaderboard(&entries),
+                Err(e) => {
+                    error!("Failed to load leaderboard: {}", e);
+                    "❌ Failed to load the leaderboard. Please try again later.".to_string()
+                }
+            },
+            None => "🤷 The leaderboard isn't available because answers aren't being stored."
+                .to_string(),
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send leaderboard: {}", e);
+        }
+    }
+
+    async fn handle_admin_command(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        command: AdminCommand<'_>,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        info!(?command, "Admin command");
+        // The exclusion list has its own file, so it works without storage
+        if let AdminCommand::Exclude(command) = command {
+            let reply = run_exclusion_command(&self.exclusions, command);
+            if let Err(e) = self.send_message(chat_id, &reply).await {
+                error!("Failed to send admin reply: {}", e);
+            }
+            return;
+        }
+        let Some(storage) = &self.storage else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤷 Admin commands need storage for the list of known users.",
+                )
+                .await;
+            return;
+        };
+
+        let reply = match command {
+            AdminCommand::Usage => {
+                let since =
+                    chrono::Utc::now().timestamp() - admin::USAGE_WINDOW_DAYS * 24 * 60 * 60;
+                match storage.usage_stats(since) {
+                    Ok(stats) => format_usage(&stats),
+                    Err(e) => {
+                        error!("Failed to load usage stats: {}", e);
+                        "❌ Failed to load usage stats.".to_string()
+                    }
+                }
+            }
+            AdminCommand::Ban(user_id) if self.admin_ids.contains(user_id) => {
+                "🙅 Admins can't be banned.".to_string()
+            }
+            AdminCommand::Ban(user_id) | AdminCommand::Unban(user_id) => {
+                let banned = matches!(command, AdminCommand::Ban(_));
+                match storage.set_banned(user_id, banned) {
+                    Ok(()) if banned => format!("🚫 User {} is banned.", user_id),
+                    Ok(()) => format!("✅ User {} is unbanned.", user_id),
+                    Err(e) => {
+                        error!("Failed to update ban list: {}", e);
+                        "❌ Failed to update the ban list.".to_string()
+                    }
+                }
+            }
+            AdminCommand::Broadcast(payload) => {
+                self.broadcast(storage, payload, database, output_dir, image_host)
+                    .await
+            }
+            AdminCommand::Assign(args) => {
+                self.create_assignment(storage, sender_id, args, database, output_dir, image_host)
+                    .await
+            }
+            AdminCommand::Assignments(id) => assignments_report(storage, id),
+            AdminCommand::Exclude(_) => unreachable!("handled above"),
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send admin reply: {}", e);
+        }
+    }
+
+    /// Sends a text, or the question with a numeric ID, to every known user
+    async fn broadcast(
+        &self,
+        storage: &Storage,
+        payload: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> String {
+        let chat_ids = match storage.broadcast_chat_ids() {
+            Ok(chat_ids) => chat_ids,
+            Err(e) => {
+                error!("Failed to load known users: {}", e);
+                return "❌ Failed to load the list of known users.".to_string();
+            }
+        };
+        let question = match self.broadcast_question(payload, database).await {
+            Ok(question) => question,
+            Err(e) => {
+                error!("Failed to fetch question: {}", e);
+                return format!("❌ Failed to fetch question #{}.", payload);
+            }
+        };
+        let summary = self
+            .deliver_broadcast(
+                storage,
+                &chat_ids,
+                payload,
+                question.as_ref(),
+                output_dir,
+                image_host,
+            )
+            .await;
+        format_broadcast(&summary)
+    }
+
+    /// Sends a broadcast to each chat, or holds it for chats in quiet hours
+    async fn deliver_broadcast(
+        &self,
+        storage: &Storage,
+        chat_ids: &[String],
+        payload: &str,
+        question: Option<&(QuestionContent, QuestionType)>,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> BroadcastSummary {
+        let mut summary = BroadcastSummary {
+            recipients: chat_ids.len(),
+            ..BroadcastSummary::default()
+        };
+        for chat_id in chat_ids {
+            if self.chat_in_quiet_hours(chat_id) {
+                self.hold_for_quiet_hours(chat_id, QueuedPost::Broadcast(payload.to_string()));
+                summary.held += 1;
+                continue;
+            }
+            match self
+                .send_broadcast(chat_id, payload, question, output_dir, image_host)
+                .await
+            {
+                Ok(()) => summary.delivered += 1,
+                Err(e) => {
+                    warn!("Broadcast to {} failed: {}", chat_id, e);
+                    if mark_inactive_if_blocked(storage, chat_id, &e) {
+                        summary.blocked += 1;
+                    }
+                }
+            }
+        }
+        summary
+    }
+
+    /// The question a broadcast payload names by ID, or `None` when it is plain text
+    async fn broadcast_question(
+        &self,
+        payload: &str,
+        database: &GmatDatabase,
+    ) -> Result<Option<(QuestionContent, QuestionType)>> {
+        let Some(question_type) = database.find_question_type(payload) else {
+            return Ok(None);
+        };
+        let content =
+            fetch_question_content(&self.database_source, payload, self.question_cache.as_ref())
+                .await?;
+        Ok(Some((content, question_type)))
+    }
+
+    async fn send_broadcast(
+        &self,
+        chat_id: &str,
+        payload: &str,
+        question: Option<&(QuestionContent, QuestionType)>,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        match question {
+            Some((content, question_type)) => {
+                self.send_question(
+                    chat_id,
+                    content,
+                    Some(question_type),
+                    output_dir,
+                    image_host,
+                    false,
+                )
+                .await
+            }
+            None => self.send_message(chat_id, payload).await,
+        }
+    }
+
+    /// Saves the assignment "assign <ids> to <users>" describes and sends it to the
+    /// students the bot can already reach
+    async fn create_assignment(
+        &self,
+        storage: &Storage,
+        admin_id: &str,
+        args: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> String {
+        let Some((question_ids, user_ids)) = parse_assign_command(args) else {
+            return "❌ Usage: assign <question ids> to <user ids>, e.g. 'assign 101,102 to user-1,user-2'"
+                .to_string();
+        };
+        if question_ids.len() > MAX_ASSIGNMENT_QUESTIONS {
+            return format!(
+                "❌ An assignment holds at most {} questions.",
+                MAX_ASSIGNMENT_QUESTIONS
+            );
+        }
+        let unknown: Vec<_> = question_ids
+            .iter()
+            .filter(|id| database.find_question_type(id).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return format!("❌ Unknown question ID(s): {}", unknown.join(", "));
+        }
+        let id = match storage.create_assignment(None, Some(admin_id), &question_ids, &user_ids) {
+            Ok(id) => id,
+            Err(e) => {
+                error!("Failed to save assignment: {}", e);
+                return "❌ Failed to save the assignment.".to_string();
+            }
+        };
+        let delivered = self
+            .deliver_assignments(storage, database, output_dir, image_host)
+            .await;
+        format!(
+            "📋 Assignment #{} saved: {} question(s) for {} student(s). {} got it now; the rest get it once they message me privately. Send 'assignments status {}' to follow their progress.",
+            id,
+            question_ids.len(),
+            user_ids.len(),
+            delivered,
+            id
+        )
+    }
+
+    /// Sends each assignment to the students waiting for it whose private chat the
+    /// bot knows, outside their quiet hours; returns how many got one
+    async fn deliver_assignments(
+        &self,
+        storage: &Storage,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> usize {
+        let waiting = match storage.undelivered_assignments() {
+            Ok(waiting) => waiting,
+            Err(e) => {
+                warn!("Failed to list undelivered assignments: {}", e);
+                return 0;
+            }
+        };
+        let mut delivered = 0;
+        for (assignment_id, user_id, chat_id) in waiting {
+            if self.chat_in_quiet_hours(&chat_id) {
+                continue;
+            }
+            let assignment = match storage.assignment(assignment_id) {
+                Ok(Some(assignment)) => assignment,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to load assignment {}: {}", assignment_id, e);
+                    continue;
+                }
+            };
+            let intro = format!(
+                "📚 New assignment {} from your tutor: {} question(s). Answer each one to get the next, or send 'homework' to see where you are.",
+                assignment.title(),
+                assignment.question_ids.len()
+            );
+            if let Err(e) = self.send_message(&chat_id, &intro).await {
+                warn!("Failed to deliver assignment to {}: {}", chat_id, e);
+                mark_inactive_if_blocked(storage, &chat_id, &e);
+                continue;
+            }
+            if let Err(e) = storage.mark_assignment_delivered(assignment_id, &user_id) {
+                warn!("Failed to mark assignment delivered: {}", e);
+            }
+            delivered += 1;
+            // A question the student is busy with isn't replaced; the assignment
+            // carries on after it, or with "homework"
+            let busy = self
+                .pending_questions
+                .lock()
+                .unwrap()
+                .contains_key(&chat_id)
+                || self.quiz_sessions.lock().unwrap().contains_key(&chat_id)
+                || self.mock_sessions.lock().unwrap().contains_key(&chat_id)
+                || self.in_challenge(&chat_id);
+            if !busy {
+                self.send_next_assignment_question(
+                    storage, &chat_id, &user_id, database, output_dir, image_host,
+                )
+                .await;
+            }
+        }
+        delivered
+    }
+
+    /// Sends the first question the student hasn't answered in their oldest open
+    /// assignment; returns false when every assignment is done
+    async fn send_next_assignment_question(
+        &self,
+        storage: &Storage,
+        chat_id: &str,
+        user_id: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> bool {
+        let assignments = match storage.student_assignments(user_id) {
+            Ok(assignments) => assignments,
+            Err(e) => {
+                warn!("Failed to load assignments: {}", e);
+                return false;
+            }
+        };
+        let next = assignments.iter().find_map(|(assignment, progress)| {
+            assignment
+                .next_question(progress)
+                .map(|(position, id)| (position, assignment.question_ids.len(), id))
+        });
+        let Some((position, total, question_id)) = next else {
+            return false;
+        };
+        let Some(question_type) = database.find_question_type(question_id) else {
+            warn!(
+                "Assignment question {} is no longer in the index",
+                question_id
+            );
+            let _ = self
+                .send_message(
+                    chat_id,
+                    &format!(
+                        "⚠️ Question #{} of your assignment is no longer available. Please tell your tutor.",
+                        question_id
+                    ),
+                )
+                .await;
+            return true;
+        };
+        let result = match fetch_question_content(
+            &self.database_source,
             question_id,
-            response.status()
+            self.question_cache.as_ref(),
+        )
+        .await
+        {
+            Ok(content) => {
+                self.send_question_in(
+                    Some((CaptionContext::Assignment, position, total)),
+                    chat_id,
+                    &content,
+                    Some(&question_type),
+                    output_dir,
+                    image_host,
+                    false,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            error!("Failed to send assignment question {}: {}", question_id, e);
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "❌ Failed to send your next assignment question. Send 'homework' to try again.",
+                )
+                .await;
+        }
+        true
+    }
+
+    /// After a student's first answer to an assignment question in their private
+    /// chat, cheers a finished assignment and sends the next question
+    async fn continue_assignment(
+        &self,
+        chat_id: &str,
+        user_id: &str,
+        question_id: &str,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        if !matches!(storage.private_chat_user(chat_id), Ok(Some(id)) if id == user_id) {
+            return;
+        }
+        let assignments = match storage.student_assignments(user_id) {
+            Ok(assignments) => assignments,
+            Err(e) => {
+                warn!("Failed to load assignments: {}", e);
+                return;
+            }
+        };
+        let Some((assignment, progress)) = assignments
+            .iter()
+            .find(|(_, progress)| progress.just_answered(question_id))
+        else {
+            return;
+        };
+        if assignment.is_complete(progress) {
+            let message = format!(
+                "🎉 Assignment {} done: {}/{} correct. Nice work!",
+                assignment.title(),
+                progress.correct(),
+                assignment.question_ids.len()
+            );
+            if let Err(e) = self.send_message(chat_id, &message).await {
+                error!("Failed to send message: {}", e);
+            }
+        }
+        self.send_next_assignment_question(
+            storage, chat_id, user_id, database, output_dir, image_host,
+        )
+        .await;
+    }
+
+    /// "homework": the sender's open assignments, then their next question
+    async fn send_homework(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        is_group: bool,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some(storage) = &self.storage else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤷 Assignments aren't available because nothing is being stored.",
+                )
+                .await;
+            return;
+        };
+        if is_group {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "📚 Assignments come to your private chat. Send me 'homework' there!",
+                )
+                .await;
+            return;
+        }
+        let open: Vec<_> = match storage.student_assignments(sender_id) {
+            Ok(assignments) => assignments
+                .into_iter()
+                .filter(|(assignment, progress)| !assignment.is_complete(progress))
+                .collect(),
+            Err(e) => {
+                error!("Failed to load assignments: {}", e);
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "❌ Failed to load your assignments. Please try again later.",
+                    )
+                    .await;
+                return;
+            }
+        };
+        let reply = if open.is_empty() {
+            "✅ You have no assignments to do. Send a type like 'PS' to keep practising!"
+                .to_string()
+        } else {
+            let lines: Vec<_> = open
+                .iter()
+                .map(|(assignment, progress)| {
+                    format!(
+                        "📚 Assignment {}: {}/{} done",
+                        assignment.title(),
+                        progress.answered(),
+                        assignment.question_ids.len()
+                    )
+                })
+                .collect();
+            lines.join("\n")
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send message: {}", e);
+        }
+        if !open.is_empty() {
+            self.send_next_assignment_question(
+                storage, chat_id, sender_id, database, output_dir, image_host,
+            )
+            .await;
+        }
+    }
+
+    /// Sends assignments to students who became reachable since they were made; never returns
+    async fn run_assignment_deliveries(
+        &self,
+        database: &LiveDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some(storage) = &self.storage else {
+            return std::future::pending().await;
+        };
+        let mut ticker = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            ticker.tick().await;
+            let database = database.current();
+            let delivered = self
+                .deliver_assignments(storage, &database, output_dir, image_host)
+                .await;
+            if delivered > 0 {
+                info!("Delivered {} assignment(s)", delivered);
+            }
+        }
+    }
+
+    /// Renders the sender's progress card and sends it to the chat
+    async fn send_stats(
+        &self,
+        chat_id: &str,
+        sender: &ZaloSender,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some(storage) = &self.storage else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤷 Stats aren't available because answers aren't being stored.",
+                )
+                .await;
+            return;
+        };
+        let stats = match storage.user_stats(&sender.id) {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("Failed to load user stats: {}", e);
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "❌ Failed to load your stats. Please try again later.",
+                    )
+                    .await;
+                return;
+            }
+        };
+        if stats.attempted == 0 {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "📊 You haven't answered any questions yet. Send a type like 'PS' to get started!",
+                )
+                .await;
+            return;
+        }
+
+        let html = generate_stats_card_html(sender.display_name.as_deref(), &stats);
+        let result = self
+            .send_card(
+                chat_id,
+                &html,
+                &format!("stats_{}", sender.id),
+                "Your progress 📊",
+                output_dir,
+                image_host,
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Failed to send stats card: {}", e);
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "❌ Failed to create your stats card. Please try again later.",
+                )
+                .await;
+        }
+    }
+
+    /// Replies to "mydata" with everything stored about the sender, split into messages
+    async fn send_user_data(
+        &self,
+        chat_id: &str,
+        user_id: &str,
+        is_group: bool,
+        format: UserDataFormat,
+    ) {
+        let messages = match &self.storage {
+            _ if is_group => vec![
+                "🔒 Send 'mydata' in a private chat with me, so your data isn't shared with the group."
+                    .to_string(),
+            ],
+            None => vec!["🤷 Nothing is stored about you: this bot keeps no records.".to_string()],
+            Some(storage) => match storage.export_user_data(user_id) {
+                Ok(data) => {
+                    let format_name = match format {
+                        UserDataFormat::Json => "JSON",
+                        UserDataFormat::Csv => "CSV",
+                    };
+                    let mut messages = vec![format!(
+                        "📦 Here's everything stored about you: {} record(s) as {}. Send 'forgetme' to delete it.",
+                        data.records(),
+                        format_name
+                    )];
+                    messages.extend(split_into_messages(
+                        &format_user_data(&data, format),
+                        MAX_EXPORT_MESSAGE_CHARS,
+                    ));
+                    messages
+                }
+                Err(e) => {
+                    error!("Failed to export user data: {}", e);
+                    vec!["❌ Failed to export your data. Please try again later.".to_string()]
+                }
+            },
+        };
+        for message in messages {
+            if let Err(e) = self.send_message(chat_id, &message).await {
+                error!("Failed to send data export: {}", e);
+                return;
+            }
+        }
+    }
+
+    /// "forgetme" says what would go; "forgetme confirm" deletes everything stored about the sender
+    async fn handle_forgetme(&self, chat_id: &str, user_id: &str, args: &str) {
+        let reply = match &self.storage {
+            None => "🤷 Nothing is stored about you: this bot keeps no records.".to_string(),
+            Some(_) if !args.eq_ignore_ascii_case("confirm") => {
+                "⚠️ This deletes your answers, streaks, badges, reviews and settings for good, and stops the question of the day in your private chat. Send 'forgetme confirm' to go ahead."
+                    .to_string()
+            }
+            Some(_) => match self.forget_user(user_id) {
+                Ok(deleted) => {
+                    info!("Deleted {} record(s) about user {}", deleted, user_id);
+                    format!("🧹 Done! I deleted {} record(s) about you.", deleted)
+                }
+                Err(e) => {
+                    error!("Failed to delete user data: {}", e);
+                    "❌ Failed to delete your data. Please try again later.".to_string()
+                }
+            },
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send message: {}", e);
+        }
+    }
+
+    /// Deletes everything about `user_id`: their stored records, and the questions,
+    /// sessions, queued posts and recent sends the bot holds for their private chat.
+    /// Challenges they play in are called off. Returns how many records were deleted
+    pub fn forget_user(&self, user_id: &str) -> Result<usize> {
+        // Private chats share the user's ID on every platform, even before one is stored
+        let mut chat_ids = vec![user_id.to_string()];
+        let deleted = match &self.storage {
+            Some(storage) => {
+                chat_ids.extend(storage.private_chat_id(user_id)?);
+                storage.forget_user(user_id)?
+            }
+            None => 0,
+        };
+        for chat_id in &chat_ids {
+            self.pending_questions.lock().unwrap().remove(chat_id);
+            self.question_timers.lock().unwrap().remove(chat_id);
+            self.quiz_sessions.lock().unwrap().remove(chat_id);
+            self.history_offsets.lock().unwrap().remove(chat_id);
+            self.quiet_queue.lock().unwrap().remove(chat_id);
+            self.recent_sends.forget(chat_id);
+        }
+        self.mock_sessions
+            .lock()
+            .unwrap()
+            .retain(|chat_id, session| session.user_id != user_id && !chat_ids.contains(chat_id));
+        self.challenges.lock().unwrap().retain(|challenge| {
+            challenge
+                .players
+                .iter()
+                .all(|player| player.user_id != user_id)
+        });
+        Ok(deleted)
+    }
+
+    /// Renders the sender's badge collection and level and sends it to the chat
+    async fn send_badges(
+        &self,
+        chat_id: &str,
+        sender: &ZaloSender,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        let Some(storage) = &self.storage else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤷 Badges aren't available because answers aren't being stored.",
+                )
+                .await;
+            return;
+        };
+        let loaded = storage
+            .badge_progress(&sender.id)
+            .and_then(|progress| Ok((progress, storage.user_badges(&sender.id)?)));
+        let (progress, awarded) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                error!("Failed to load badges: {}", e);
+                let _ = self
+                    .send_message(
+                        chat_id,
+                        "❌ Failed to load your badges. Please try again later.",
+                    )
+                    .await;
+                return;
+            }
+        };
+
+        let timezone = self.chat_config(chat_id).timezone;
+        let earned: Vec<_> = awarded
+            .iter()
+            .filter_map(|(id, awarded_at)| {
+                let date = chrono::DateTime::from_timestamp(*awarded_at, 0)?
+                    .with_timezone(&timezone)
+                    .date_naive();
+                Some((find_badge(id)?, date))
+            })
+            .collect();
+        let html =
+            generate_badges_card_html(sender.display_name.as_deref(), progress.correct, &earned);
+        let caption = format!("Your badges: {} of {} 🏅", earned.len(), BADGES.len());
+        let result = self
+            .send_card(
+                chat_id,
+                &html,
+                &format!("badges_{}", sender.id),
+                &caption,
+                output_dir,
+                image_host,
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Failed to send badges card: {}", e);
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "❌ Failed to create your badges card. Please try again later.",
+                )
+                .await;
+        }
+    }
+
+    /// Awards the badges the sender's latest answer earned and announces them, along
+    /// with a new level when a correct answer reached one
+    async fn celebrate_progress(&self, chat_id: &str, sender: &ZaloSender, correct: bool) {
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let (progress, awarded) = match self.award_badges(storage, chat_id, &sender.id) {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Failed to award badges: {}", e);
+                return;
+            }
+        };
+        let name = sender.display_name.as_deref();
+        let mut messages: Vec<_> = awarded
+            .into_iter()
+            .map(|badge| format_badge_award(name, badge))
+            .collect();
+        let level = level_for(progress.correct);
+        if correct && progress.correct == level.min_correct && level.number > 1 {
+            messages.push(format_level_up(name, level));
+        }
+        for message in messages {
+            if let Err(e) = self.send_message(chat_id, &message).await {
+                error!("Failed to send badge message: {}", e);
+            }
+        }
+    }
+
+    /// Moves the sender up a band in `question_type` once their answers in the current
+    /// one are accurate enough, and tells them
+    async fn advance_progression(
+        &self,
+        chat_id: &str,
+        sender: &ZaloSender,
+        question_type: QuestionType,
+    ) {
+        let (Some(progression), Some(storage)) = (&self.progression, &self.storage) else {
+            return;
+        };
+        let advanced = storage
+            .progression_band(&sender.id, &question_type)
+            .and_then(|band| {
+                let band = band.unwrap_or(STARTING_BAND);
+                let results = storage.band_results(
+                    &sender.id,
+                    &question_type,
+                    progression.answers as usize,
+                )?;
+                let Some(next) = next_band(band).filter(|_| progression.levels_up(&results)) else {
+                    return Ok(None);
+                };
+                storage.set_progression_band(&sender.id, &question_type, next)?;
+                Ok(Some(next))
+            });
+        let next = match advanced {
+            Ok(Some(next)) => next,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to update progression band: {}", e);
+                return;
+            }
+        };
+        info!(
+            "User {} moved up to {} {} questions",
+            sender.id, next, question_type
+        );
+        let message = format_band_up(
+            sender.display_name.as_deref(),
+            question_type,
+            next,
+            progression,
+        );
+        if let Err(e) = self.send_message(chat_id, &message).await {
+            error!("Failed to send level up message: {}", e);
+        }
+    }
+
+    /// The user's progress and the badges it earned that they didn't have yet
+    fn award_badges(
+        &self,
+        storage: &Storage,
+        chat_id: &str,
+        user_id: &str,
+    ) -> Result<(BadgeProgress, Vec<&'static Badge>)> {
+        let mut progress = storage.badge_progress(user_id)?;
+        progress.practice_streak = self.practice_streak(user_id, chat_id)?;
+        let mut awarded = Vec::new();
+        for badge in earned_badges(&progress) {
+            if storage.award_badge(user_id, badge.id)? {
+                info!("User {} earned the {} badge", user_id, badge.id);
+                awarded.push(badge);
+            }
+        }
+        Ok((progress, awarded))
+    }
+
+    async fn handle_answer(
+        &self,
+        chat_id: &str,
+        sender: &ZaloSender,
+        answer: char,
+        database: &GmatDatabase,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) {
+        self.question_timers.lock().unwrap().remove(chat_id);
+        let Some(pending) = self.take_pending_question(chat_id) else {
+            let _ = self
+                .send_message(
+                    chat_id,
+                    "🤔 There is no question waiting for an answer. Send a type like 'PS' to get one!",
+                )
+                .await;
+            return;
+        };
+
+        info!(
+            "Grading answer {} for question {} in chat: {}",
+            answer, pending.content.id, chat_id
+        );
+        // Grade and record the choice under its original letter, as the explanations name it
+        let shown = answer;
+        let answer = pending.original_answer(shown);
+        let correct = pending.correct_answer.map(|c| c == answer);
+        self.webhooks.emit(WebhookEvent::AnswerRecorded {
+            chat_id: chat_id.to_string(),
+            user_id: sender.id.clone(),
+            question_id: pending.content.id.clone(),
+            question_type: pending.question_type,
+            answer,
+            correct,
+            response_secs: chrono::Utc::now().timestamp() - pending.sent_at,
+        });
+
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.record_answer(
+                chat_id,
+                &sender.id,
+                sender.display_name.as_deref(),
+                &pending,
+                answer,
+                correct,
+            ) {
+                warn!("Failed to record answer: {}", e);
+            }
+            if let Some(correct) = correct
+                && let Err(e) = storage.record_review(
+                    &sender.id,
+                    &pending.content.id,
+                    &pending.question_type,
+                    correct,
+                )
+            {
+                warn!("Failed to update review schedule: {}", e);
+            }
+        }
+
+        // Mock tests hold back verdicts and explanations until the end
+        if self.is_mock_question(chat_id, &pending.content.id) {
+            self.continue_mock(
+                chat_id,
+                &pending,
+                Some(answer),
+                database,
+                output_dir,
+                image_host,
+            )
+            .await;
+            return;
+        }
+
+        let mut verdict = match pending.correct_answer_label() {
+            Some(label) if pending.correct_answer == Some(answer) => {
+                format!("✅ Correct! The answer is {}.", label)
+            }
+            Some(label) => format!(
+                "❌ Not quite. You answered ({}), the correct answer is {}.",
+                shown, label
+            ),
+            None => format!(
+                "🤷 I couldn't find the official answer for question #{}. Check the explanations below.",
+                pending.content.id
+            ),
+        };
+        if pending
+            .content
+            .answer_key()
+            .is_some_and(|key| key.confidence == AnswerConfidence::Low)
+        {
+            verdict.push_str(
+                "\n⚠️ I'm not sure of this answer key, so check the explanations. Send 'flag wrong answer key' if it's off.",
+            );
+        }
+        if let Err(e) = self.send_message(chat_id, &verdict).await {
+            error!("Failed to send grading message: {}", e);
+        }
+        self.celebrate_progress(chat_id, sender, pending.correct_answer == Some(answer))
+            .await;
+        if correct.is_some() {
+            self.advance_progression(chat_id, sender, pending.question_type)
+                .await;
+        }
+
+        // Challenges move on like quizzes, and keep score for both players
+        if self.is_challenge_question(chat_id, &pending.content.id) {
+            let correct = pending.correct_answer.map(|c| c == answer);
+            self.with_challenge(chat_id, |session| session.record(chat_id, correct));
+            self.advance_challenge(chat_id, output_dir, image_host)
+                .await;
+            return;
+        }
+
+        // During a quiz, move straight on to the next question instead of explaining
+        let quiz_progress = {
+            let mut sessions = self.quiz_sessions.lock().unwrap();
+            sessions.get_mut(chat_id).map(|session| {
+                session.record(
+                    &pending.content.id,
+                    pending.question_type,
+                    pending.correct_answer.map(|c| c == answer),
+                );
+                session.is_finished()
+            })
+        };
+        match quiz_progress {
+            Some(true) => {
+                let session = self.quiz_sessions.lock().unwrap().remove(chat_id);
+                if let Some(session) = session
+                    && let Err(e) = self.send_message(chat_id, &session.summary()).await
+                {
+                    error!("Failed to send quiz summary: {}", e);
+                }
+                return;
+            }
+            Some(false) => {
+                self.send_next_quiz_question(chat_id, &sender.id, database, output_dir, image_host)
+                    .await;
+                return;
+            }
+            None => {}
+        }
+
+        // Follow up with the explanations image
+        let result = self
+            .send_question_image(
+                chat_id,
+                &pending.content,
+                &pending.question_type,
+                true,
+                None,
+                &self.explanations_caption(chat_id, &pending.content, pending.question_type),
+                output_dir,
+                image_host,
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Failed to send explanations: {}", e);
+        }
+
+        self.continue_assignment(
+            chat_id,
+            &sender.id,
+            &pending.content.id,
+            database,
+            output_dir,
+            image_host,
         )
-        .into());
+        .await;
+    }
+
+    pub async fn send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()> {
+        match &self.platform {
+            Some(platform) => platform.send_photo(chat_id, photo, caption).await,
+            None => self.zalo_send_photo(chat_id, photo, caption).await,
+        }
+        .inspect(|_| self.health.send_succeeded())
+    }
+
+    /// Sends a local image as a file upload, so it needs no image host
+    pub async fn send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()> {
+        match &self.platform {
+            Some(platform) => platform.send_photo_file(chat_id, path, caption).await,
+            None => self.zalo_send_photo_file(chat_id, path, caption).await,
+        }
+        .inspect(|_| self.health.send_succeeded())
+    }
+
+    async fn zalo_send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()> {
+        self.api.send_photo_file(chat_id, path, caption).await?;
+        Ok(())
+    }
+
+    async fn zalo_send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()> {
+        self.api.send_photo(chat_id, photo, caption).await?;
+        Ok(())
+    }
+
+    /// The theme a chat's images use: its user's preference, else the bot's default
+    fn theme_for(&self, chat_id: &str) -> Theme {
+        let preference = self
+            .storage
+            .as_ref()
+            .and_then(|storage| storage.get_preference(chat_id, THEME_PREFERENCE).ok())
+            .flatten();
+        preference
+            .as_deref()
+            .and_then(parse_theme)
+            .unwrap_or(self.theme)
+    }
+
+    /// Replies to "theme" with the current theme, or saves "theme <name>" as the sender's preference
+    async fn handle_theme_command(&self, chat_id: &str, sender_id: &str, args: &str) {
+        let reply = match (&self.storage, args.trim()) {
+            (None, _) => "🤷 Themes can't be saved because nothing is being stored.".to_string(),
+            (Some(_), "") => format!(
+                "🎨 Your theme is {}. Send 'theme light', 'theme dark', 'theme high-contrast' or 'theme print' to change it.",
+                self.theme_for(sender_id)
+            ),
+            (Some(storage), name) => match parse_theme(name) {
+                None => "❌ Unknown theme. Choose light, dark, high-contrast or print.".to_string(),
+                Some(theme) => {
+                    match storage.set_preference(sender_id, THEME_PREFERENCE, &theme.to_string()) {
+                        Ok(()) => format!("🎨 Theme set to {}.", theme),
+                        Err(e) => {
+                            error!("Failed to save theme: {}", e);
+                            "❌ Failed to save your theme. Please try again later.".to_string()
+                        }
+                    }
+                }
+            },
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send theme reply: {}", e);
+        }
+    }
+
+    /// Shows, sets or clears the sender's quiet hours
+    async fn handle_dnd_command(
+        &self,
+        chat_id: &str,
+        sender_id: &str,
+        command: std::result::Result<DndCommand, String>,
+    ) {
+        let timezone = self.chat_config(chat_id).timezone;
+        let reply = match (&self.storage, command) {
+            (None, _) => {
+                "🤷 Quiet hours can't be saved because nothing is being stored.".to_string()
+            }
+            (Some(_), Err(usage)) => format!("❌ {}. Send 'dnd off' to turn them off.", usage),
+            (Some(_), Ok(DndCommand::Show)) => match self.quiet_hours(sender_id) {
+                Some(quiet_hours) => format!(
+                    "🌙 Your quiet hours are {} ({}). Send 'dnd off' to turn them off.",
+                    quiet_hours, timezone
+                ),
+                None => "🌙 Quiet hours are off. Send 'dnd 22:00-07:00' to hold reminders, reports and daily questions overnight.".to_string(),
+            },
+            (Some(storage), Ok(command)) => {
+                let (value, reply) = match command {
+                    DndCommand::Set(quiet_hours) => (
+                        quiet_hours.to_string(),
+                        format!(
+                            "🌙 Quiet hours set to {} ({}). Reminders, reports and daily questions wait until they end; change the timezone with 'config timezone'.",
+                            quiet_hours, timezone
+                        ),
+                    ),
+                    _ => (String::new(), "🔔 Quiet hours are off.".to_string()),
+                };
+                match storage.set_preference(sender_id, QUIET_HOURS_PREFERENCE, &value) {
+                    Ok(()) => reply,
+                    Err(e) => {
+                        error!("Failed to save quiet hours: {}", e);
+                        "❌ Failed to save your quiet hours. Please try again later.".to_string()
+                    }
+                }
+            }
+        };
+        if let Err(e) = self.send_message(chat_id, &reply).await {
+            error!("Failed to send quiet hours reply: {}", e);
+        }
+    }
+
+    /// Sends a question's image, or one image per part for multi-part questions
+    #[allow(clippy::too_many_arguments)]
+    async fn send_question_image(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: &QuestionType,
+        show_explanations: bool,
+        choice_order: Option<&[usize]>,
+        caption: &str,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        let content = &self.enriched(content).await;
+        // Multi-part questions go out as a numbered sequence: the passage, then each sub-question
+        let parts = match choice_order {
+            Some(order) => vec![reorder_choices(content, order)],
+            None => content.parts(),
+        };
+        let total = parts.len();
+        let send_parts = async {
+            for (i, part) in parts.iter().enumerate() {
+                let caption = part_caption(caption, i, total);
+                let sent = self
+                    .send_question_part(
+                        chat_id,
+                        part,
+                        question_type,
+                        show_explanations,
+                        choice_order,
+                        &caption,
+                        output_dir,
+                        image_host,
+                    )
+                    .await;
+                if let Err(e) = sent {
+                    // A plain-text copy is better than nothing when the image can't be made
+                    warn!("Sending question {} as text instead: {}", part.id, e);
+                    let text = format!("{}\n\n{}", question_text(part, show_explanations), caption);
+                    if let Err(text_error) = self.send_message(chat_id, &text).await {
+                        warn!(
+                            "Failed to send question {} as text: {}",
+                            part.id, text_error
+                        );
+                        return Err(e);
+                    }
+                }
+            }
+            Ok(())
+        };
+        // Rendering and uploading can take a while, so show that the bot is busy
+        self.while_typing(chat_id, send_parts).await
+    }
+
+    /// URLs of an image hosted by an earlier send, when they are still good to hand out;
+    /// expiring URLs are only reused for the first half of their lifetime, so chats have
+    /// time to fetch them
+    pub(crate) fn hosted_copy(
+        &self,
+        image_key: &str,
+        show_explanations: bool,
+        image_host: &dyn ImageHost,
+    ) -> Option<Vec<String>> {
+        let max_age = image_host.url_lifetime().map(|lifetime| lifetime / 2);
+        self.storage
+            .as_ref()?
+            .hosted_image_urls(image_key, show_explanations, max_age)
+            .inspect_err(|e| warn!("Failed to look up cached image: {}", e))
+            .ok()
+            .flatten()
+    }
+
+    /// Sends one part's image, reusing the hosted copy from an earlier send when there is
+    /// one; an image split into pages goes out as its own numbered sequence
+    #[allow(clippy::too_many_arguments)]
+    async fn send_question_part(
+        &self,
+        chat_id: &str,
+        content: &QuestionContent,
+        question_type: &QuestionType,
+        show_explanations: bool,
+        choice_order: Option<&[usize]>,
+        caption: &str,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        let theme = self.theme_for(chat_id);
+        let image_key = hosted_image_key(&content.id, theme, choice_order);
+        if let Some(manifest) = &self.manifest {
+            return match manifest.urls(&image_key, show_explanations) {
+                Some(image_urls) => self.send_photo_pages(chat_id, image_urls, caption).await,
+                None => Err(BotError::Render(format!(
+                    "{} isn't in the image manifest",
+                    image_key
+                ))),
+            };
+        }
+        if let Some(storage) = &self.storage
+            && let Some(image_urls) = self.hosted_copy(&image_key, show_explanations, image_host)
+        {
+            debug!("Reusing hosted image for question {}", content.id);
+            match self.send_photo_pages(chat_id, &image_urls, caption).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    // The hosted copy may have been deleted; render a fresh one
+                    warn!("Cached image {} failed, re-rendering: {}", image_key, e);
+                    if let Err(e) = storage.forget_hosted_image(&image_key, show_explanations) {
+                        warn!("Failed to forget cached image: {}", e);
+                    }
+                }
+            }
+        }
+
+        let scratch = render_scratch_dir(output_dir)?;
+        let scratch_dir = scratch.path().to_string_lossy();
+        let render = || {
+            render_question_to_image(
+                content,
+                question_type,
+                show_explanations,
+                theme,
+                &self.templates,
+                &scratch_dir,
+                self.renderer.as_ref(),
+            )
+        };
+        // A hung render is usually a one-off; a second hang falls back to text
+        let rendered = match render().await {
+            Err(e @ BotError::RenderTimeout { .. }) => {
+                warn!("{}; rendering question {} once more", e, content.id);
+                render().await
+            }
+            rendered => rendered,
+        };
+        let image_paths = rendered.inspect_err(|e| {
+            self.webhooks.emit(WebhookEvent::RenderFailed {
+                chat_id: Some(chat_id.to_string()),
+                question_id: content.id.clone(),
+                error: e.to_string(),
+            })
+        })?;
+        let total = image_paths.len();
+        // Nothing to cache when the file goes straight to the chat
+        if image_host.uploads_directly() {
+            for (i, image_path) in image_paths.iter().enumerate() {
+                let caption = part_caption(caption, i, total);
+                self.upload_and_send(chat_id, image_path, &caption, image_host)
+                    .await?;
+            }
+            return Ok(());
+        }
+        let uploaded = async {
+            let mut image_urls = Vec::new();
+            for image_path in &image_paths {
+                image_urls.push(image_host.upload(image_path).await?);
+            }
+            Ok::<_, BotError>(image_urls)
+        }
+        .await;
+        drop(scratch);
+        let image_urls = uploaded?;
+
+        if let Some(storage) = &self.storage
+            && let Err(e) =
+                storage.save_hosted_image_urls(&image_key, show_explanations, &image_urls)
+        {
+            warn!("Failed to cache hosted image URL: {}", e);
+        }
+        self.send_photo_pages(chat_id, &image_urls, caption).await
+    }
+
+    /// Sends the pages of an image in order, numbered when there are several
+    async fn send_photo_pages(
+        &self,
+        chat_id: &str,
+        image_urls: &[String],
+        caption: &str,
+    ) -> Result<()> {
+        let total = image_urls.len();
+        for (i, image_url) in image_urls.iter().enumerate() {
+            self.send_photo(chat_id, image_url, &part_caption(caption, i, total))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Renders a card and sends it, from a scratch directory of its own
+    async fn send_card(
+        &self,
+        chat_id: &str,
+        html: &str,
+        stem: &str,
+        caption: &str,
+        output_dir: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        let scratch = render_scratch_dir(output_dir)?;
+        let image_path = rendered_image_path(
+            &scratch.path().to_string_lossy(),
+            stem,
+            self.renderer.as_ref(),
+        );
+        render_html_to_image(html, &image_path, self.renderer.as_ref()).await?;
+        self.upload_and_send(chat_id, &image_path.to_string_lossy(), caption, image_host)
+            .await
+    }
+
+    pub async fn upload_and_send(
+        &self,
+        chat_id: &str,
+        image_path: &str,
+        caption: &str,
+        image_host: &dyn ImageHost,
+    ) -> Result<()> {
+        // Send the file itself, or upload it to the image host first and send the URL
+        let result = if image_host.uploads_directly() {
+            self.send_photo_file(chat_id, Path::new(image_path), caption)
+                .await
+        } else {
+            match image_host.upload(image_path).await {
+                Ok(image_url) => self.send_photo(chat_id, &image_url, caption).await,
+                Err(e) => Err(e),
+            }
+        };
+
+        if let Err(e) = std::fs::remove_file(image_path) {
+            warn!("Failed to remove temporary file {}: {}", image_path, e);
+        }
+        result
+    }
+
+    /// Offers tap-to-reply buttons where the platform supports them
+    pub async fn send_chat_action(&self, chat_id: &str, action: &str) -> Result<()> {
+        match &self.platform {
+            Some(platform) => platform.send_chat_action(chat_id, action).await,
+            None => self.api.send_chat_action(chat_id, action).await,
+        }
+    }
+
+    /// Runs `work` while the chat shows the typing indicator, refreshing it
+    /// before it expires; a failed refresh only stops the indicator
+    async fn while_typing<F: Future>(&self, chat_id: &str, work: F) -> F::Output {
+        let typing = async {
+            let mut ticker = tokio::time::interval(TYPING_REFRESH_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.send_chat_action(chat_id, "typing").await {
+                    debug!("Typing indicator unavailable: {}", e);
+                    return std::future::pending().await;
+                }
+            }
+        };
+        tokio::select! {
+            output = work => output,
+            _ = typing => unreachable!("the typing loop never returns"),
+        }
+    }
+
+    pub async fn send_choices(&self, chat_id: &str, text: &str, choices: &[&str]) -> Result<()> {
+        match &self.platform {
+            Some(platform) => platform.send_choices(chat_id, text, choices).await,
+            // The Zalo Bot API has no reply buttons; users type the letter
+            None => Ok(()),
+        }
+    }
+
+    pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<()> {
+        self.send_message_with_id(chat_id, text).await.map(|_| ())
+    }
+
+    /// Sends a text message, returning its message ID so it can be edited later
+    pub async fn send_message_with_id(&self, chat_id: &str, text: &str) -> Result<String> {
+        match &self.platform {
+            Some(platform) => platform.send_message(chat_id, text).await,
+            None => Ok(self.api.send_message(chat_id, text).await?.message_id),
+        }
+        .inspect(|_| self.health.send_succeeded())
+    }
+
+    pub async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()> {
+        match &self.platform {
+            Some(platform) => platform.edit_message(chat_id, message_id, text).await,
+            None => self.api.edit_message(chat_id, message_id, text).await,
+        }
+    }
+
+    /// Sends a "⏳ ..." progress message, returning its ID if it was delivered
+    async fn send_status(&self, chat_id: &str, text: &str) -> Option<String> {
+        match self.send_message_with_id(chat_id, text).await {
+            Ok(message_id) => Some(message_id),
+            Err(e) => {
+                error!("Failed to send processing message: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()> {
+        match &self.platform {
+            Some(platform) => platform.delete_message(chat_id, message_id).await,
+            None => self.api.delete_message(chat_id, message_id).await,
+        }
+    }
+
+    /// Replaces a progress message with the outcome; when it can't be edited the
+    /// outcome is sent as a new message
+    async fn update_status(&self, chat_id: &str, status: Option<&str>, text: &str) {
+        if let Some(message_id) = status {
+            match self.edit_message(chat_id, message_id, text).await {
+                Ok(()) => return,
+                Err(e) => debug!("Failed to edit processing message: {}", e),
+            }
+        }
+        if let Err(e) = self.send_message(chat_id, text).await {
+            error!("Failed to send message: {}", e);
+        }
+    }
+
+    /// Removes a progress message once it has served its purpose
+    async fn clear_status(&self, chat_id: &str, status: Option<&str>) {
+        if let Some(message_id) = status
+            && let Err(e) = self.delete_message(chat_id, message_id).await
+        {
+            debug!("Failed to delete processing message: {}", e);
+        }
+    }
+}
+
+/// The Zalo Bot API transport itself
+#[async_trait::async_trait]
+impl ChatPlatform for ZaloBot {
+    async fn get_me(&self) -> Result<Option<BotIdentity>> {
+        let me = self.api.get_me().await?;
+        Ok(Some(BotIdentity {
+            id: me.id,
+            name: me.account_name,
+        }))
+    }
+
+    async fn get_updates(&self) -> Result<Vec<ChatMessage>> {
+        self.zalo_get_updates().await
+    }
+
+    async fn send_message(&self, chat_id: &str, text: &str) -> Result<String> {
+        Ok(self.api.send_message(chat_id, text).await?.message_id)
+    }
+
+    async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()> {
+        self.api.edit_message(chat_id, message_id, text).await
+    }
+
+    async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()> {
+        self.api.delete_message(chat_id, message_id).await
+    }
+
+    async fn send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()> {
+        self.zalo_send_photo(chat_id, photo, caption).await
+    }
+
+    async fn send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()> {
+        self.zalo_send_photo_file(chat_id, path, caption).await
+    }
+
+    async fn send_chat_action(&self, chat_id: &str, action: &str) -> Result<()> {
+        self.api.send_chat_action(chat_id, action).await
+    }
+}
+
+/// Parses a question type abbreviation like "ps" or "DS"
+pub fn parse_question_type(text: &str) -> Option<QuestionType> {
+    match text.trim().to_lowercase().as_str() {
+        "rc" => Some(QuestionType::RC),
+        "sc" => Some(QuestionType::SC),
+        "cr" => Some(QuestionType::CR),
+        "ps" => Some(QuestionType::PS),
+        "ds" => Some(QuestionType::DS),
+        _ => None,
+    }
+}
+
+/// Flags the last question sent to a chat, returning its ID; `None` when none was sent
+fn record_flag(
+    storage: &Storage,
+    chat_id: &str,
+    sender_id: &str,
+    note: &str,
+) -> Result<Option<String>> {
+    let Some(last) = storage.question_history(chat_id, 0, 1)?.pop() else {
+        return Ok(None);
+    };
+    storage.record_question_report(
+        &last.question_id,
+        last.question_type,
+        chat_id,
+        sender_id,
+        note,
+    )?;
+    Ok(Some(last.question_id))
+}
+
+/// Name of a question type in messages, "GMAT" when any type will do
+fn type_label(question_type: Option<QuestionType>) -> String {
+    question_type
+        .map(|t| t.to_string())
+        .unwrap_or_else(|| "GMAT".to_string())
+}
+
+/// Type for a "next" request: one of the sender's default types the chat allows,
+/// or any allowed type; the error is the reply when none qualifies
+fn next_question_type(
+    settings: &UserSettings,
+    config: &ChatConfig,
+) -> std::result::Result<Option<QuestionType>, String> {
+    let candidates: Vec<QuestionType> = match (&settings.default_types, &config.allowed_types) {
+        (Some(types), _) => types
+            .iter()
+            .copied()
+            .filter(|t| config.allows(*t))
+            .collect(),
+        (None, Some(allowed)) => allowed.clone(),
+        (None, None) => return Ok(None),
+    };
+    candidates
+        .choose(&mut rand::thread_rng())
+        .copied()
+        .map(Some)
+        .ok_or_else(|| {
+            "🚫 None of your default question types are turned on in this chat. Change them with 'settings types ps,ds'."
+                .to_string()
+        })
+}
+
+/// Answer letters offered as buttons under each question
+pub const ANSWER_CHOICES: [&str; 5] = ["A", "B", "C", "D", "E"];
+
+/// Parses a reply like "A", "b" or "(C)" into an answer choice letter
+pub fn parse_answer_choice(text: &str) -> Option<char> {
+    let trimmed = text.trim().trim_start_matches('(').trim_end_matches(')');
+    let mut chars = trimmed.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if ('A'..='E').contains(&letter.to_ascii_uppercase()) => {
+            Some(letter.to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
+
+/// Removes HTML tags, keeping only the text content
+fn strip_html_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+pub async fn fetch_gmat_database(source: &DatabaseSource) -> Result<GmatDatabase> {
+    source.fetch_index().await
+}
+
+/// Fetches a question from `source`, consulting `cache` first and filling it on a miss
+pub async fn fetch_question_content(
+    source: &DatabaseSource,
+    question_id: &str,
+    cache: Option<&QuestionCache>,
+) -> Result<QuestionContent> {
+    if let Some(content) = cache.and_then(|c| c.get(question_id)) {
+        debug!("Serving question {} from cache", question_id);
+        return Ok(content);
     }
 
-    let content: QuestionContent = response.json().await?;
+    info!("Fetching question content for ID: {}", question_id);
+    let content = source.fetch_question(question_id).await?;
+    if let Some(cache) = cache
+        && let Err(e) = cache.put(question_id, &content)
+    {
+        warn!("Failed to cache question {}: {}", question_id, e);
+    }
     Ok(content)
 }
 
+/// Picks up to `count` random questions, of `question_type` or any type, leaving out
+/// those on `exclusions`
 pub fn pick_random_questions(
     database: &GmatDatabase,
+    exclusions: &ExclusionList,
+    question_type: &Option<QuestionType>,
+    count: usize,
+) -> Vec<(QuestionType, String)> {
+    pick_random_questions_excluding(database, exclusions, question_type, count, &HashSet::new())
+}
+
+/// How many random candidates are fetched while looking for a given difficulty
+const MAX_DIFFICULTY_PROBES: usize = 30;
+
+/// Picks questions of the given difficulty, fetching candidates to read their level tag
+///
+/// Difficulty isn't part of the database index, so this fetches content (through
+/// `cache` when given) and returns it alongside the type.
+#[allow(clippy::too_many_arguments)]
+pub async fn pick_random_questions_by_difficulty(
+    database: &GmatDatabase,
+    exclusions: &ExclusionList,
+    question_type: &Option<QuestionType>,
+    difficulty: Difficulty,
+    count: usize,
+    exclude: &HashSet<String>,
+    source: &DatabaseSource,
+    cache: Option<&QuestionCache>,
+) -> Vec<(QuestionType, QuestionContent)> {
+    let mut results = Vec::new();
+    let candidates = pick_random_questions_excluding(
+        database,
+        exclusions,
+        question_type,
+        MAX_DIFFICULTY_PROBES,
+        exclude,
+    );
+
+    for (qtype, question_id) in candidates {
+        if results.len() >= count {
+            break;
+        }
+        match fetch_question_content(source, &question_id, cache).await {
+            Ok(content) if Difficulty::of(&content) == Some(difficulty) => {
+                results.push((qtype, content));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Skipping question {}: {}", question_id, e),
+        }
+    }
+
+    results
+}
+
+/// Same as [`pick_random_questions`], but never picks an ID in `exclude`
+pub fn pick_random_questions_excluding(
+    database: &GmatDatabase,
+    exclusions: &ExclusionList,
     question_type: &Option<QuestionType>,
     count: usize,
+    exclude: &HashSet<String>,
 ) -> Vec<(QuestionType, String)> {
     let mut rng = rand::thread_rng();
     let mut results = Vec::new();
@@ -680,20 +6196,22 @@ pub fn pick_random_questions(
         Some(qtype) => {
             // Skip RC questions as they have a different JSON structure
             if *qtype == QuestionType::RC {
-                eprintln!(
-                    "⚠️  RC questions are currently not supported due to different JSON structure"
-                );
+                warn!("RC questions are currently not supported due to different JSON structure");
                 return results;
             }
 
-            let questions = database.get_questions_by_type(qtype);
+            let questions: Vec<_> = database
+                .get_questions_by_type(qtype)
+                .iter()
+                .filter(|id| !exclude.contains(*id) && !exclusions.contains(id))
+                .collect();
             let selected: Vec<_> = questions
                 .choose_multiple(&mut rng, count.min(questions.len()))
-                .cloned()
+                .map(|id| id.to_string())
                 .collect();
 
             for question_id in selected {
-                results.push((qtype.clone(), question_id));
+                results.push((*qtype, question_id));
             }
         }
         None => {
@@ -703,7 +6221,9 @@ pub fn pick_random_questions(
 
             for (qtype, questions) in all_questions {
                 for question_id in questions {
-                    all_items.push((qtype, question_id.clone()));
+                    if !exclude.contains(question_id) && !exclusions.contains(question_id) {
+                        all_items.push((qtype, question_id.clone()));
+                    }
                 }
             }
 
@@ -719,398 +6239,205 @@ pub fn pick_random_questions(
     results
 }
 
-/// Generates HTML content for a question without explanations
+/// Generates HTML content for a question without explanations, from the bundled template
 pub fn generate_html_content_without_explanations(
     content: &QuestionContent,
     question_type: &QuestionType,
-) -> String {
-    generate_html_content_impl(content, question_type, false)
+    theme: Theme,
+) -> Result<String> {
+    bundled_templates().render_question(content, question_type, false, theme)
 }
 
-/// Generates HTML content for a question with optional explanations
-pub fn generate_html_content(content: &QuestionContent, question_type: &QuestionType) -> String {
-    generate_html_content_impl(content, question_type, true)
+/// Generates HTML content for a question with explanations, from the bundled template
+pub fn generate_html_content(
+    content: &QuestionContent,
+    question_type: &QuestionType,
+    theme: Theme,
+) -> Result<String> {
+    bundled_templates().render_question(content, question_type, true, theme)
 }
 
-/// Internal implementation of HTML content generation
-fn generate_html_content_impl(
+/// Renders a question to an image, or to several pages when it is taller than the
+/// renderer allows; returns the image paths top to bottom
+#[instrument(skip_all, fields(question_id = %content.id, show_explanations))]
+pub async fn render_question_to_image(
     content: &QuestionContent,
     question_type: &QuestionType,
     show_explanations: bool,
-) -> String {
-    let type_color = "#0068ff";
-
-    // Format answers with proper LaTeX delimiters
-    let answers_html = if !content.answers.is_empty() {
-        let options = content
-            .answers
-            .iter()
-            .enumerate()
-            .map(|(i, answer)| {
-                let label = match i {
-                    0 => "A",
-                    1 => "B",
-                    2 => "C",
-                    3 => "D",
-                    4 => "E",
-                    _ => &format!("{}", i + 1),
-                };
-                // Format answer text with proper LaTeX delimiters
-                let formatted_answer = answer
-                    .replace("$$", "$$") // Block math
-                    .replace("$", "$"); // Inline math
-
-                format!(
-                    "<div class=\"answer-option\"><strong>{})</strong> {}</div>",
-                    label, formatted_answer
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        format!(
-            r#"
-        <div class="answers-section">
-            <h3>Answer Choices:</h3>
-            {}
-        </div>
-        "#,
-            options
-        )
-    } else {
-        String::new()
-    };
-
-    // Include explanations only if show_explanations is true
-    let explanations_html = if show_explanations && !content.explanations.is_empty() {
-        let explanations = content
-            .explanations
-            .iter()
-            .enumerate()
-            .map(|(i, explanation)| {
-                format!(
-                    "<div class=\"explanation\"><h4>Explanation {}:</h4>{}</div>",
-                    i + 1,
-                    explanation
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        format!(
-            r#"
-        <div class="explanations-section">
-            <h3>Explanations:</h3>
-            {}
-        </div>
-        "#,
-            explanations
-        )
-    } else {
-        String::new()
-    };
-
-    format!(
-        r#"
-<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>GMAT Question {}</title>
-    <script>
-        // Set initial window status
-        window.status = 'loading';
-        
-        // Function to set ready status
-        function setReady() {{
-            window.status = 'ready_to_print';
-            console.log('Page is ready for printing');
-        }}
-        
-        // Configure MathJax
-        window.MathJax = {{
-            startup: {{
-                ready: function() {{
-                    // When MathJax is ready, set the page as ready
-                    MathJax.startup.defaultReady().then(function() {{
-                        console.log('MathJax rendering complete');
-                        setReady();
-                    }});
-                }}
-            }}
-        }};
-        
-        // Fallback in case MathJax fails to load
-        setTimeout(setReady, 3000);
-    </script>
-    <script id="MathJax-script" async src="https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-mml-chtml.js"></script>
-    <style>
-        body {{
-            font-family: Georgia, 'Times New Roman', Times, serif;
-            max-width: 1000px;
-            margin: 0 auto;
-            padding: 30px;
-            line-height: 1.6;
-            background-color: #ffffff;
-            color: #333;
-        }}
-
-        .question-header {{
-            background: {};
-            color: white;
-            padding: 25px;
-            border-radius: 8px;
-            margin-bottom: 30px;
-        }}
-
-        .question-id {{
-            font-size: 1.1em;
-            font-weight: 600;
-            opacity: 0.9;
-            margin-bottom: 5px;
-        }}
-
-        .question-type {{
-            font-size: 1.8em;
-            font-weight: 700;
-            margin: 0;
-        }}
-
-        .question-content {{
-            background: white;
-            padding: 30px;
-            margin-bottom: 25px;
-        }}
-
-        .question-text {{
-            font-size: 1.2em;
-            line-height: 1.7;
-            margin-bottom: 25px;
-            color: #2c3e50;
-        }}
-
-        .answers-section {{
-            background: #f9f9f9;
-            padding: 25px;
-            margin-bottom: 25px;
-        }}
-
-        .answers-section h3 {{
-            color: {};
-            margin-top: 0;
-            margin-bottom: 20px;
-            font-size: 1.3em;
-        }}
-
-        .answer-option {{
-            padding: 12px 15px;
-            margin: 8px 0;
-            background: white;
-            font-size: 1.1em;
-        }}
-
-        .explanations-section {{
-            background: white;
-            padding: 25px;
-        }}
-
-        .explanations-section h3 {{
-            color: {};
-            margin-top: 0;
-            margin-bottom: 20px;
-            font-size: 1.3em;
-        }}
-
-        .explanation {{
-            margin-bottom: 25px;
-            padding: 20px;
-            background: #f9f9f9;
-        }}
-
-        .explanation h4 {{
-            color: {};
-            margin-top: 0;
-            margin-bottom: 15px;
-        }}
-
-        .source-link {{
-            margin-top: 30px;
-            padding: 15px;
-            background: #f9f9f9;
-            font-size: 0.9em;
-        }}
-
-        .source-link a {{
-            color: {};
-            text-decoration: none;
-        }}
-
-        .source-link a:hover {{
-            text-decoration: underline;
-        }}
-
-        /* LaTeX Math styling */
-        .MathJax {{
-            font-size: 1.1em !important;
-        }}
-
-        /* Table styling for better readability */
-        table {{
-            border-collapse: collapse;
-            width: 100%;
-            margin: 15px 0;
-        }}
-
-        th, td {{
-            padding: 12px;
-            text-align: left;
-            border-bottom: 1px solid #eee;
-        }}
-
-        th {{
-            background-color: #f9f9f9;
-            font-weight: bold;
-        }}
-
-        /* List styling */
-        ul, ol {{
-            padding-left: 25px;
-        }}
-
-        li {{
-            margin: 8px 0;
-        }}
-
-        /* Code blocks */
-        code {{
-            background-color: #f9f9f9;
-            padding: 2px 6px;
-            font-family: 'Courier New', monospace;
-        }}
-
-        /* Emphasis */
-        strong {{
-            color: #2c3e50;
-        }}
-
-        em {{
-            color: #7f8c8d;
-        }}
-    </style>
-</head>
-<body>
-    <div class="question-header">
-        <div class="question-id">Question ID: {}</div>
-        <h1 class="question-type">{}</h1>
-    </div>
-
-    <div class="question-content">
-        <div class="question-text">
-            {}
-        </div>
-
-        {}
-
-        {}
-    </div>
-
-    <div class="source-link">
-        <strong>Source:</strong> <a href="{}" target="_blank">{}</a>
-    </div>
-</body>
-</html>
-    "#,
-        content.id,
-        type_color, // header background
-        type_color, // answers section title
-        type_color, // explanations section title
-        type_color, // explanation title
-        type_color, // source link
-        content.id,
-        question_type,
-        content.question,
-        answers_html,
-        explanations_html,
-        content.src,
-        content.src
-    )
-}
+    theme: Theme,
+    templates: &QuestionTemplates,
+    output_dir: &str,
+    renderer: &dyn Renderer,
+) -> Result<Vec<String>> {
+    // Generate HTML content with or without explanations
+    let html_content =
+        templates.render_question(content, question_type, show_explanations, theme)?;
 
-pub fn check_wkhtmltoimage() -> Result<(), Box<dyn std::error::Error>> {
-    match Command::new("wkhtmltoimage").arg("--version").output() {
-        Ok(_) => Ok(()),
-        Err(_) => Err("wkhtmltoimage is not installed or not in PATH. Please install it first. Visit: https://wkhtmltopdf.org/downloads.html".into()),
+    // Generate output path
+    let output_path =
+        rendered_image_path(output_dir, &format!("question_{}", content.id), renderer);
+
+    info!("Rendering question to image...");
+    let pages = render_html_to_pages(&html_content, &output_path, renderer).await?;
+    if let Some(kept_html_dir) = templates.kept_html_dir() {
+        // The images are what matters; a page that can't be kept is only logged
+        if let Err(e) = keep_html(
+            &html_content,
+            kept_html_dir,
+            &output_path,
+            &content.id,
+            show_explanations,
+            &theme.to_string(),
+            &pages,
+        ) {
+            warn!("Failed to keep the HTML of question {}: {}", content.id, e);
+        }
     }
+    Ok(pages
+        .iter()
+        .map(|page| page.to_string_lossy().to_string())
+        .collect())
 }
 
-pub async fn render_question_to_image(
+/// Renders every part of a question (see [`QuestionContent::parts`]) to its own images
+pub async fn render_question_parts(
     content: &QuestionContent,
     question_type: &QuestionType,
     show_explanations: bool,
+    theme: Theme,
+    templates: &QuestionTemplates,
     output_dir: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    // Ensure the output directory exists
-    std::fs::create_dir_all(output_dir)?;
-    check_wkhtmltoimage()?;
+    renderer: &dyn Renderer,
+) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for part in content.parts() {
+        paths.extend(
+            render_question_to_image(
+                &part,
+                question_type,
+                show_explanations,
+                theme,
+                templates,
+                output_dir,
+                renderer,
+            )
+            .await?,
+        );
+    }
+    Ok(paths)
+}
 
-    // Generate HTML content with or without explanations
-    let html_content = if show_explanations {
-        generate_html_content(content, question_type)
-    } else {
-        generate_html_content_without_explanations(content, question_type)
-    };
+/// A spawned task that is aborted when dropped, so a handler abandoned at shutdown stops
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
 
-    // Write HTML to a temporary file for debugging if needed
-    #[cfg(debug_assertions)]
-    {
-        use std::fs::File;
-        use std::io::Write;
-        let mut file = File::create("debug_question.html")?;
-        file.write_all(html_content.as_bytes())?;
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
     }
+}
+
+/// A directory of its own under `output_dir` for one render, removed with everything in
+/// it when dropped, so renders of the same question running at once never share a file
+pub fn render_scratch_dir(output_dir: &str) -> Result<TempDir> {
+    fs::create_dir_all(output_dir)?;
+    Ok(tempfile::Builder::new()
+        .prefix(".render-")
+        .tempdir_in(output_dir)?)
+}
+
+/// Where an image named `stem` goes in `output_dir`, with the extension of the renderer's format
+pub fn rendered_image_path(output_dir: &str, stem: &str, renderer: &dyn Renderer) -> PathBuf {
+    Path::new(output_dir).join(format!(
+        "{}.{}",
+        stem,
+        renderer.options().format.extension()
+    ))
+}
+
+/// Renders a complete HTML page to an image at `output_path`
+pub async fn render_html_to_image(
+    html_content: &str,
+    output_path: &Path,
+    renderer: &dyn Renderer,
+) -> Result<()> {
+    render_html(html_content, output_path, renderer, false)
+        .await
+        .map(|_| ())
+}
+
+/// Renders a complete HTML page to images, split into pages when it is taller than
+/// the renderer's maximum height; returns them top to bottom
+pub async fn render_html_to_pages(
+    html_content: &str,
+    output_path: &Path,
+    renderer: &dyn Renderer,
+) -> Result<Vec<PathBuf>> {
+    render_html(html_content, output_path, renderer, true).await
+}
+
+async fn render_html(
+    html_content: &str,
+    output_path: &Path,
+    renderer: &dyn Renderer,
+    split: bool,
+) -> Result<Vec<PathBuf>> {
+    // Fall back to the built-in renderer when the configured tool isn't installed
+    let fallback;
+    let renderer = match renderer.check_available().await {
+        Ok(()) => renderer,
+        Err(e) => {
+            warn!("{} Falling back to the built-in SVG renderer.", e);
+            fallback = SvgRenderer::new(renderer.options());
+            &fallback as &dyn Renderer
+        }
+    };
 
     // Create a temporary directory for the HTML file
     let temp_dir = TempDir::new()?;
-    let html_path = temp_dir.path().join("question.html");
+    let html_path = temp_dir.path().join("page.html");
 
     // Write HTML to temporary file
     fs::write(&html_path, html_content)?;
 
     // Create output directory if it doesn't exist
-    fs::create_dir_all(output_dir)?;
+    if let Some(output_dir) = output_path.parent() {
+        fs::create_dir_all(output_dir)?;
+    }
 
-    // Generate output path
-    let output_path = Path::new(output_dir).join(format!("question_{}.png", content.id));
-
-    println!("  🖼️  Rendering question to image...");
-
-    // Run wkhtmltoimage command with window status for better page load detection
-    let output = Command::new("wkhtmltoimage")
-        .arg("--format")
-        .arg("jpg")
-        .arg("--width")
-        .arg("1200")
-        .arg("--disable-smart-width")
-        .arg("--quality")
-        .arg("70")
-        .arg("--enable-javascript")
-        .arg("--window-status")
-        .arg("ready_to_print")
-        .arg(html_path.to_str().unwrap())
-        .arg(&output_path)
-        .output()?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "wkhtmltoimage failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )
-        .into());
+    let pages = if split {
+        renderer.render_pages(&html_path, output_path).await?
+    } else {
+        renderer.render(&html_path, output_path).await?;
+        vec![output_path.to_path_buf()]
+    };
+
+    for page in &pages {
+        info!("Image saved: {}", page.display());
+    }
+    Ok(pages)
+}
+
+/// Prints a complete HTML page to a PDF at `output_path`
+pub async fn render_html_to_pdf(
+    html_content: &str,
+    output_path: &Path,
+    renderer: &dyn Renderer,
+) -> Result<()> {
+    // No fallback here: the built-in SVG renderer can't paginate
+    renderer.check_available().await?;
+
+    let temp_dir = TempDir::new()?;
+    let html_path = temp_dir.path().join("page.html");
+    fs::write(&html_path, html_content)?;
+
+    if let Some(output_dir) = output_path.parent() {
+        fs::create_dir_all(output_dir)?;
     }
 
-    println!("  ✅ Image saved: {}", output_path.display());
-    Ok(output_path.to_string_lossy().to_string())
+    renderer.render_pdf(&html_path, output_path).await?;
+
+    info!("PDF saved: {}", output_path.display());
+    Ok(())
 }
 
 pub fn show_database_stats(database: &GmatDatabase) {
@@ -1146,242 +6473,161 @@ pub fn show_database_stats(database: &GmatDatabase) {
     println!();
 }
 
-#[derive(Debug)]
-pub struct GitHubConfig {
-    pub repo: String,
-    pub release_id: u64,
-    pub token: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct GitHubReleaseResponse {
-    upload_url: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct GitHubAssetResponse {
-    browser_download_url: String,
-}
-
-pub async fn create_github_release(
-    repo: &str,
-    token: &str,
-    tag_name: &str,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    println!("  🏷️  Creating GitHub release with tag: {}", tag_name);
-
-    let client = reqwest::Client::new();
-    let url = format!("https://api.github.com/repos/{}/releases", repo);
-
-    let release_data = serde_json::json!({
-        "tag_name": tag_name,
-        "name": format!("GMAT Bot Images - {}", tag_name),
-        "body": "Automated release for GMAT question images",
-        "draft": false,
-        "prerelease": false
-    });
-
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("token {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "gmat-zalo-bot")
-        .json(&release_data)
+/// Downloads a photo next to the rendered images, reads it with tesseract, then deletes it
+async fn read_photo(
+    client: &reqwest::Client,
+    photo_url: &str,
+    message_id: &str,
+    output_dir: &str,
+) -> Result<String> {
+    let file_stem: String = message_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    fs::create_dir_all(output_dir)?;
+    let path = Path::new(output_dir).join(format!("screenshot_{}", file_stem));
+    let image = client
+        .get(photo_url)
         .send()
+        .await?
+        .error_for_status()?
+        .bytes()
         .await?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Failed to create release: {} - {}", status, error_text).into());
+    fs::write(&path, &image)?;
+    let text = recognize_text(&path).await;
+    if let Err(e) = fs::remove_file(&path) {
+        warn!("Failed to remove screenshot {}: {}", path.display(), e);
     }
-
-    let release_response: serde_json::Value = response.json().await?;
-    let release_id = release_response["id"]
-        .as_u64()
-        .ok_or("Failed to get release ID from response")?;
-
-    println!("  ✅ Created release with ID: {}", release_id);
-    Ok(release_id)
+    text
 }
 
-pub async fn get_latest_release_id(
-    repo: &str,
-    token: &str,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    println!("  🔍 Getting latest release ID...");
-
-    let client = reqwest::Client::new();
-    let url = format!("https://api.github.com/repos/{}/releases/latest", repo);
-
-    let response = client
-        .get(&url)
-        .header("Authorization", format!("token {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "gmat-zalo-bot")
-        .send()
-        .await?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Failed to get latest release: {} - {}", status, error_text).into());
+/// Reply when a screenshot matches no question clearly: the closest cached ones, if any
+fn format_suggestions(matches: &[QuestionMatch]) -> String {
+    if matches.is_empty() {
+        return "🤔 I couldn't find that question. Send its number if you know it, or a type like 'PS' for a random one.".to_string();
     }
-
-    let release_response: serde_json::Value = response.json().await?;
-    let release_id = release_response["id"]
-        .as_u64()
-        .ok_or("Failed to get release ID from response")?;
-
-    println!("  ✅ Found latest release ID: {}", release_id);
-    Ok(release_id)
+    let mut reply =
+        "🤔 I couldn't find that question for sure. Closest matches, send a number to open it:\n"
+            .to_string();
+    for question in matches.iter().take(MAX_SUGGESTIONS) {
+        reply.push_str(&format!(
+            "\n{} - {}",
+            question.question_id, question.preview
+        ));
+    }
+    reply
 }
 
-pub async fn upload_to_github_release(
-    repo: &str,
-    release_id: u64,
-    token: &str,
-    image_path: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    println!("  📤 Uploading image to GitHub release...");
-
-    let client = reqwest::Client::new();
-
-    // First, get the release info to obtain the upload_url
-    println!("  🔍 Getting release upload URL...");
-    let release_url = format!(
-        "https://api.github.com/repos/{}/releases/{}",
-        repo, release_id
-    );
-
-    let release_response = client
-        .get(&release_url)
-        .header("Authorization", format!("token {}", token))
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "gmat-zalo-bot")
-        .send()
-        .await?;
-
-    if !release_response.status().is_success() {
-        let status = release_response.status();
-        let error_text = release_response.text().await.unwrap_or_default();
-
-        if status == 404 {
-            return Err(format!(
-                "Release not found. Please create a release first or use --github-release-id with a valid release ID.\n\
-                You can create a release manually on GitHub or the bot can auto-create one.\n\
-                Repository: {}, Release ID: {}",
-                repo, release_id
-            ).into());
-        }
-
-        return Err(format!("Failed to get release info: {} - {}", status, error_text).into());
-    }
-
-    let release_info: GitHubReleaseResponse = release_response.json().await?;
-
-    // Extract the base upload URL (remove the {?name,label} template part)
-    let upload_url = release_info
-        .upload_url
-        .split('{')
-        .next()
-        .unwrap_or(&release_info.upload_url);
-
-    // Read the image file
-    let file_bytes = fs::read(image_path)?;
-    println!("  📏 Image size: {} bytes", file_bytes.len());
-
-    // Generate unique filename based on timestamp and question ID
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_secs();
-    let path = Path::new(image_path);
-    let base_name = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("question");
-    let file_name = format!("{}__{}.png", base_name, timestamp);
-
-    // Upload the asset using the upload_url
-    let upload_url_with_name = format!("{}?name={}", upload_url, file_name);
-    println!("  📤 Uploading {} to GitHub...", file_name);
-
-    let response = client
-        .post(&upload_url_with_name)
-        .header("Authorization", format!("token {}", token))
-        .header("Content-Type", "image/png")
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "gmat-zalo-bot")
-        .body(file_bytes)
-        .send()
-        .await?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-
-        if status == 422 {
-            return Err(format!(
-                "Asset upload failed - likely due to duplicate filename: {}\n\
-                GitHub returns 422 when an asset with the same name already exists.\n\
-                Error details: {}",
-                file_name, error_text
-            )
-            .into());
+/// Reply to the admin "assignments status [id]": one assignment's report, or every one's
+fn assignments_report(storage: &Storage, id: Option<i64>) -> String {
+    let ids = match id {
+        Some(id) => Ok(vec![id]),
+        None => storage
+            .assignments()
+            .map(|assignments| assignments.iter().map(|a| a.id).collect()),
+    };
+    let statuses = ids.and_then(|ids| {
+        ids.into_iter()
+            .filter_map(|id| storage.assignment_status(id).transpose())
+            .collect::<Result<Vec<_>>>()
+    });
+    match statuses {
+        Ok(statuses) if statuses.is_empty() => match id {
+            Some(id) => format!("🤷 There is no assignment #{}.", id),
+            None => {
+                "🤷 No assignments yet. Send 'assign <question ids> to <user ids>' to make one."
+                    .to_string()
+            }
+        },
+        Ok(statuses) => statuses
+            .iter()
+            .map(format_assignment_status)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        Err(e) => {
+            error!("Failed to load assignments: {}", e);
+            "❌ Failed to load the assignments.".to_string()
         }
+    }
+}
 
-        return Err(format!(
-            "GitHub upload failed: {} - {}\n\
-            Make sure your GitHub token has the 'repo' scope and write access to the repository.",
-            status, error_text
-        )
-        .into());
+/// Marks the chat's user inactive when `error` says they blocked the bot, so
+/// scheduled sends stop retrying them; returns whether it did
+fn mark_inactive_if_blocked(storage: &Storage, chat_id: &str, error: &BotError) -> bool {
+    if !matches!(error.root(), BotError::ZaloUserBlocked { .. }) {
+        return false;
     }
+    info!(
+        "Chat {} blocked the bot, marking its user inactive",
+        chat_id
+    );
+    if let Err(e) = storage.mark_inactive(chat_id) {
+        warn!("Failed to mark chat {} inactive: {}", chat_id, e);
+    }
+    true
+}
 
-    let github_response: GitHubAssetResponse = response.json().await?;
+/// Reply to anything that isn't a command: what the bot can do, plus how to
+/// address it in groups
+fn help_message(is_group: bool, command_prefix: &str) -> String {
+    let mut help_message = "Hello! 👋 I'm your GMAT practice bot.\n\n\
+        To get a question, please send one of these types:\n\n\
+        ✏️ **SC** - Sentence Correction\n\
+        🧠 **CR** - Critical Reasoning\n\
+        🔢 **PS** - Problem Solving\n\
+        📊 **DS** - Data Sufficiency\n\n\
+        Just type the abbreviation (like 'PS' or 'ds') to get a random question of that type!\n\
+        Add a difficulty (easy, medium or hard), like 'ps hard', to pick the level.\n\
+        Send 'topic geometry' (or algebra, assumption, bold-face...) for a question on one topic.\n\
+        Send 'next' for a question of your default types, or 'question' for one of the types you find hardest.\n\n\
+        Once a question arrives, reply with A, B, C, D or E to check your answer, then 'why' to see the explanations again.\n\n\
+        Send 'review' to retry questions you missed, spaced out so they stick.\n\
+        Send 'history' to list the questions sent here ('history more' for older ones).\n\
+        Send 'flag' (or 'flag wrong answer key') if the last question looks broken.\n\
+        Send 'leaderboard' to see this week's top scorers in this chat.\n\
+        Send 'stats' for a card with your accuracy, streak and pace, or 'badges' for the badges you've earned.\n\
+        Send 'mydata' (or 'mydata csv') for everything stored about you, and 'forgetme' to delete it.\n\
+        Send 'subscribe' to get a question of the day every morning ('unsubscribe' stops it).\n\
+        Send 'homework' to see the assignments your tutor gave you and get the next question.\n\n\
+        Want a challenge? Send 'quiz 5 ps' for a timed 5-question quiz ('quiz stop' ends it early),\n\
+        or 'mock' for a 45-minute test of 10 quant and 10 verbal questions with a score report at the end.\n\
+        Send 'challenge @name 5 ps' to race a friend on the same questions ('challenge stop' ends it).\n\n\
+        👤 Send 'settings' to pick your default types, difficulty and explanations.\n\
+        🎨 Send 'theme dark' (or light, high-contrast, print) to change how questions look.\n\
+        🌙 Send 'dnd 22:00-07:00' to hold reminders and daily questions overnight ('dnd off' stops it).\n\
+        ⚙️ Send 'config' to see or change this chat's settings."
+        .to_string();
+    if is_group {
+        help_message.push_str(&format!(
+            "\n\n👥 In groups, start commands with '{0}' (like '{0}ps') or mention me.",
+            command_prefix
+        ));
+    }
+    help_message
+}
 
-    println!(
-        "  ✅ Image uploaded to GitHub: {}",
-        github_response.browser_download_url
-    );
-    Ok(github_response.browser_download_url)
+/// Caption of one image of a multi-part question, e.g. "You can do it! 💪 (2/4)"
+fn part_caption(caption: &str, index: usize, total: usize) -> String {
+    if total > 1 {
+        format!("{} ({}/{})", caption, index + 1, total)
+    } else {
+        caption.to_string()
+    }
 }
 
-/// Send questions to specified users with retry logic
-pub async fn send_question_to_users(
-    zalo_bot: &ZaloBot,
-    users: &[String],
+/// Key a hosted image is cached under; copies are per theme and choice order, and
+/// light ones in the original order keep the plain question ID
+pub(crate) fn hosted_image_key(
     question_id: &str,
-    question_type: &QuestionType,
-    output_dir: &str,
-    github_config: &GitHubConfig,
-    show_explanations: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    match fetch_question_content(question_id).await {
-        Ok(content) => {
-            for user_id in users {
-                println!("📤 Sending question to user: {}", user_id);
-                if let Err(e) = zalo_bot
-                    .send_question(
-                        user_id,
-                        &content,
-                        Some(question_type),
-                        output_dir,
-                        github_config,
-                        show_explanations,
-                    )
-                    .await
-                {
-                    eprintln!("❌ Failed to send to user {}: {}", user_id, e);
-                } else {
-                    println!("✅ Successfully sent to user: {}", user_id);
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("❌ Failed to fetch question content: {}", e);
-            return Err(e.into());
-        }
+    theme: Theme,
+    choice_order: Option<&[usize]>,
+) -> String {
+    let key = match theme {
+        Theme::Light => question_id.to_string(),
+        theme => format!("{}@{}", question_id, theme),
+    };
+    match choice_order {
+        Some(order) => format!("{}~{}", key, order_code(order)),
+        None => key,
     }
-    Ok(())
 }
@@ -0,0 +1,28 @@
+use crate::{BotError, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+/// Output format for log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines for a terminal
+    Text,
+    /// One JSON object per line for log collectors
+    Json,
+}
+
+/// Installs the global subscriber; `RUST_LOG` takes precedence over `level`
+pub fn init_logging(level: &str, format: LogFormat) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(level))
+        .map_err(|e| BotError::Config(format!("Invalid log level '{}': {}", level, e)))?;
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+    result.map_err(|e| BotError::Config(format!("Failed to initialize logging: {}", e)))
+}
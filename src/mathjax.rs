@@ -0,0 +1,52 @@
+use crate::{BotError, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// MathJax loaded from the jsDelivr CDN
+pub const MATHJAX_CDN_URL: &str = "https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-mml-chtml.js";
+
+/// Entry script inside a MathJax 3 distribution, e.g. the unpacked `mathjax` npm package
+pub const MATHJAX_SCRIPT: &str = "es5/tex-mml-chtml.js";
+
+/// Default location of the local MathJax copy, `~/.gmat-zalo-bot/mathjax`
+pub fn default_mathjax_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".gmat-zalo-bot")
+        .join("mathjax")
+}
+
+/// Where rendered pages load MathJax from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MathJaxSource {
+    #[default]
+    Cdn,
+    /// A MathJax copy on disk, so rendering works without network access
+    Bundled,
+}
+
+/// Resolves the `src` of the MathJax script tag; `Bundled` reads from `dir`
+/// (default `~/.gmat-zalo-bot/mathjax`) and fails if the entry script is missing
+pub fn mathjax_script_url(source: MathJaxSource, dir: Option<&Path>) -> Result<String> {
+    match source {
+        MathJaxSource::Cdn => Ok(MATHJAX_CDN_URL.to_string()),
+        MathJaxSource::Bundled => {
+            let dir = dir
+                .map(Path::to_path_buf)
+                .unwrap_or_else(default_mathjax_dir);
+            let script = dir.join(MATHJAX_SCRIPT);
+            if !script.is_file() {
+                return Err(BotError::Config(format!(
+                    "MathJax not found at {}. Unpack the mathjax@3 npm package into {} or pass --mathjax-dir",
+                    script.display(),
+                    dir.display()
+                )));
+            }
+            // Pages are rendered from a temp dir, so the script needs an absolute URL
+            let script = script.canonicalize()?;
+            Ok(format!("file://{}", script.display()))
+        }
+    }
+}
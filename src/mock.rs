@@ -0,0 +1,235 @@
+use crate::quiz::format_duration;
+use crate::{PendingQuestion, QuestionContent, QuestionType};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::time::{Duration, Instant};
+
+/// Questions in each section of a mock test
+pub const MOCK_SECTION_QUESTIONS: usize = 10;
+
+/// Running total for the whole mock test
+pub const MOCK_TIME_LIMIT: Duration = Duration::from_secs(45 * 60);
+
+/// Longest a single question may take before the test moves on without an answer
+pub const MOCK_QUESTION_CAP: Duration = Duration::from_secs(3 * 60);
+
+/// The two halves of a mock test, taken in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockSection {
+    Quant,
+    Verbal,
+}
+
+impl MockSection {
+    pub const ALL: [MockSection; 2] = [MockSection::Quant, MockSection::Verbal];
+
+    /// Types asked in the section; RC is left out as its passages come in several parts
+    pub fn question_types(self) -> &'static [QuestionType] {
+        match self {
+            MockSection::Quant => &[QuestionType::PS, QuestionType::DS],
+            MockSection::Verbal => &[QuestionType::CR, QuestionType::SC],
+        }
+    }
+
+    /// Section a question type belongs to
+    pub fn of(question_type: QuestionType) -> Self {
+        match question_type {
+            QuestionType::PS | QuestionType::DS => MockSection::Quant,
+            QuestionType::RC | QuestionType::SC | QuestionType::CR => MockSection::Verbal,
+        }
+    }
+}
+
+impl std::fmt::Display for MockSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockSection::Quant => write!(f, "Quant"),
+            MockSection::Verbal => write!(f, "Verbal"),
+        }
+    }
+}
+
+/// A question of a mock test, answered or run out of time
+#[derive(Debug, Clone)]
+pub struct MockAnswer {
+    pub content: QuestionContent,
+    pub question_type: QuestionType,
+    /// The original letter chosen; `None` when the question's time ran out
+    pub answer: Option<char>,
+    /// `None` when the official answer couldn't be determined
+    pub correct_answer: Option<char>,
+    pub elapsed: Duration,
+}
+
+impl MockAnswer {
+    /// Whether the answer was right; a question that ran out of time counts as wrong
+    pub fn correct(&self) -> Option<bool> {
+        let correct_answer = self.correct_answer?;
+        Some(self.answer == Some(correct_answer))
+    }
+}
+
+/// An in-progress mock test for one chat
+#[derive(Debug)]
+pub struct MockSession {
+    /// User who started the test, for picking the questions that follow
+    pub user_id: String,
+    /// Type of each question in the order they're asked
+    pub plan: Vec<QuestionType>,
+    pub answers: Vec<MockAnswer>,
+    pub started: Instant,
+    /// When the current question was delivered
+    pub question_started: Instant,
+    /// ID of the question on screen, so a reply and its time cap can't both count
+    pub current: Option<String>,
+}
+
+impl MockSession {
+    /// A test of [`MOCK_SECTION_QUESTIONS`] per section, drawn from the `allowed`
+    /// types; sections with no allowed type are skipped. `None` when both are.
+    pub fn new<R: Rng + ?Sized>(
+        user_id: &str,
+        allowed: impl Fn(QuestionType) -> bool,
+        rng: &mut R,
+    ) -> Option<Self> {
+        let mut plan = Vec::new();
+        for section in MockSection::ALL {
+            let types: Vec<QuestionType> = section
+                .question_types()
+                .iter()
+                .copied()
+                .filter(|t| allowed(*t))
+                .collect();
+            if types.is_empty() {
+                continue;
+            }
+            plan.extend((0..MOCK_SECTION_QUESTIONS).filter_map(|_| types.choose(rng).copied()));
+        }
+        if plan.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        Some(Self {
+            user_id: user_id.to_string(),
+            plan,
+            answers: Vec::new(),
+            started: now,
+            question_started: now,
+            current: None,
+        })
+    }
+
+    /// Type of the question about to be sent
+    pub fn next_type(&self) -> Option<QuestionType> {
+        self.plan.get(self.answers.len()).copied()
+    }
+
+    /// 1-based number of the question about to be sent
+    pub fn next_number(&self) -> usize {
+        self.answers.len() + 1
+    }
+
+    /// Whether the next question starts a new section
+    pub fn starts_section(&self) -> Option<MockSection> {
+        let next = MockSection::of(self.next_type()?);
+        let previous = self
+            .answers
+            .last()
+            .map(|a| MockSection::of(a.question_type));
+        (previous != Some(next)).then_some(next)
+    }
+
+    /// Marks a delivered question as the one on screen and starts its clock
+    pub fn question_sent(&mut self, question_id: &str) {
+        self.current = Some(question_id.to_string());
+        self.question_started = Instant::now();
+    }
+
+    /// Records the reply to the question on screen, or `None` when its time ran out
+    pub fn record(&mut self, pending: &PendingQuestion, answer: Option<char>) {
+        self.answers.push(MockAnswer {
+            content: pending.content.clone(),
+            question_type: pending.question_type,
+            answer,
+            correct_answer: pending.correct_answer,
+            elapsed: self.question_started.elapsed().min(MOCK_QUESTION_CAP),
+        });
+        self.current = None;
+    }
+
+    pub fn time_left(&self) -> Duration {
+        MOCK_TIME_LIMIT.saturating_sub(self.started.elapsed())
+    }
+
+    /// Every question is done, or the total time ran out
+    pub fn is_finished(&self) -> bool {
+        self.answers.len() >= self.plan.len() || self.time_left().is_zero()
+    }
+
+    /// When the question on screen runs out of time: its own cap or the test's end
+    pub fn question_deadline(&self) -> Instant {
+        (self.question_started + MOCK_QUESTION_CAP).min(self.started + MOCK_TIME_LIMIT)
+    }
+
+    /// (correct, asked) in a section; questions never reached count as asked
+    pub fn section_score(&self, section: MockSection) -> (usize, usize) {
+        let asked = self
+            .plan
+            .iter()
+            .filter(|t| MockSection::of(**t) == section)
+            .count();
+        let correct = self
+            .answers
+            .iter()
+            .filter(|a| MockSection::of(a.question_type) == section && a.correct() == Some(true))
+            .count();
+        (correct, asked)
+    }
+
+    /// Time spent from the first question to the last answer
+    pub fn total_time(&self) -> Duration {
+        self.answers.iter().map(|a| a.elapsed).sum()
+    }
+
+    /// Progress line after an answer, e.g. "3/20 done, 38m12s left"
+    pub fn progress(&self) -> String {
+        format!(
+            "{}/{} done, {} left",
+            self.answers.len(),
+            self.plan.len(),
+            format_duration(self.time_left())
+        )
+    }
+
+    /// Plain-text results, sent when the report image can't be made
+    pub fn summary(&self) -> String {
+        let mut summary = "🏁 Mock test finished!".to_string();
+        for section in MockSection::ALL {
+            let (correct, asked) = self.section_score(section);
+            if asked > 0 {
+                summary.push_str(&format!("\n{}: {}/{}", section, correct, asked));
+            }
+        }
+        summary.push_str(&format!("\nTime: {}", format_duration(self.total_time())));
+        for (i, answer) in self.answers.iter().enumerate() {
+            let mark = match (answer.answer, answer.correct()) {
+                (None, _) => "⏰",
+                (_, Some(true)) => "✅",
+                (_, Some(false)) => "❌",
+                (_, None) => "❔",
+            };
+            summary.push_str(&format!(
+                "\n{}. #{} {} {}",
+                i + 1,
+                answer.content.id,
+                format_duration(answer.elapsed),
+                mark
+            ));
+        }
+        let unreached = self.plan.len() - self.answers.len();
+        if unreached > 0 {
+            summary.push_str(&format!("\n{} question(s) not reached", unreached));
+        }
+        summary
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{BotError, QuestionContent, Result, strip_html_tags};
+use std::collections::HashSet;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Keywords a cached question must share with a screenshot to count as the same question
+pub const MIN_KEYWORD_MATCHES: usize = 6;
+
+/// Closest questions listed when a screenshot matches none well enough
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Longest preview of a suggested question, in characters
+const PREVIEW_LENGTH: usize = 60;
+
+/// Words too common in GMAT questions to tell them apart
+const STOP_WORDS: &str = "about above after also answer because been before being both could \
+    does each following from have into more most much only other question same should some \
+    than that their them then there these they this those what when which while will with \
+    would your";
+
+/// A cached question that shares keywords with a screenshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuestionMatch {
+    pub question_id: String,
+    /// How many of the screenshot's keywords appear in the question
+    pub score: usize,
+    /// The opening of the question text
+    pub preview: String,
+}
+
+/// Fails early with an installation hint when the `tesseract` binary is missing
+pub fn check_tesseract() -> Result<()> {
+    match std::process::Command::new("tesseract")
+        .arg("--version")
+        .output()
+    {
+        Ok(_) => Ok(()),
+        Err(_) => Err(BotError::Ocr(
+            "tesseract is not installed or not in PATH. Install tesseract-ocr to read screenshots."
+                .to_string(),
+        )),
+    }
+}
+
+/// Reads the text in an image with the `tesseract` binary
+pub async fn recognize_text(image_path: &Path) -> Result<String> {
+    let output = Command::new("tesseract")
+        .arg(image_path)
+        .arg("stdout")
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(BotError::Ocr(format!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Numbers in the text that could be question IDs, most likely first: ones
+/// written as `#123` come before bare numbers
+pub fn question_id_candidates(text: &str) -> Vec<String> {
+    let mut tagged = Vec::new();
+    let mut bare = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        let digits = rest[start..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(&rest[start..], |end| &rest[start..start + end]);
+        let after_hash = rest[..start].ends_with('#');
+        // Short numbers are far more likely to be quantities in the question
+        if after_hash {
+            tagged.push(digits.to_string());
+        } else if digits.len() >= 3 {
+            bare.push(digits.to_string());
+        }
+        rest = &rest[start + digits.len()..];
+    }
+    let mut seen = HashSet::new();
+    tagged
+        .into_iter()
+        .chain(bare)
+        .filter(|id| seen.insert(id.clone()))
+        .collect()
+}
+
+/// Distinct lowercase words of four letters or more, minus common ones
+pub fn keywords(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| word.chars().count() >= 4)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.split_whitespace().any(|stop| stop == word))
+        .filter(|word| seen.insert(word.clone()))
+        .collect()
+}
+
+/// Questions sharing at least one keyword, best match first
+pub fn rank_matches(
+    keywords: &[String],
+    questions: impl IntoIterator<Item = QuestionContent>,
+) -> Vec<QuestionMatch> {
+    let mut matches: Vec<QuestionMatch> = questions
+        .into_iter()
+        .filter_map(|content| {
+            let text = strip_html_tags(&content.question);
+            let words: HashSet<String> = self::keywords(&text).into_iter().collect();
+            let score = keywords.iter().filter(|k| words.contains(*k)).count();
+            (score > 0).then(|| QuestionMatch {
+                question_id: content.id,
+                score,
+                preview: preview(&text),
+            })
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+    matches
+}
+
+/// The question's opening words, cut short with an ellipsis
+fn preview(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(PREVIEW_LENGTH) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text,
+    }
+}
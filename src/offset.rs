@@ -0,0 +1,115 @@
+use crate::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Default location of the persisted offset, `~/.gmat-zalo-bot/offset`
+pub fn default_offset_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".gmat-zalo-bot")
+        .join("offset")
+}
+
+/// The next `getUpdates` offset, persisted so restarts don't replay handled messages.
+/// Platforms read channel by channel keep a cursor per channel instead
+pub struct OffsetStore {
+    path: PathBuf,
+    offset: Mutex<Option<u64>>,
+    /// Newest message handled in each channel, saved as a JSON object
+    cursors: Mutex<BTreeMap<String, u64>>,
+}
+
+impl OffsetStore {
+    /// Loads the offset or cursors saved at `path`; a missing or unreadable file starts fresh
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let saved = fs::read_to_string(&path).unwrap_or_default();
+        let offset = saved.trim().parse().ok();
+        let cursors = serde_json::from_str(&saved).unwrap_or_default();
+        Self {
+            path,
+            offset: Mutex::new(offset),
+            cursors: Mutex::new(cursors),
+        }
+    }
+
+    /// Forgets the saved offset and cursors and deletes their file
+    pub fn reset(&self) -> Result<()> {
+        *self.offset.lock().unwrap() = None;
+        self.cursors.lock().unwrap().clear();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn current(&self) -> Option<u64> {
+        *self.offset.lock().unwrap()
+    }
+
+    /// Whether an update has already been acknowledged
+    pub fn is_handled(&self, update_id: u64) -> bool {
+        self.current().is_some_and(|offset| update_id < offset)
+    }
+
+    /// Acknowledges every update up to and including `update_id`
+    pub fn advance(&self, update_id: u64) -> Result<()> {
+        let next = update_id + 1;
+        {
+            let mut offset = self.offset.lock().unwrap();
+            if offset.is_some_and(|current| current >= next) {
+                return Ok(());
+            }
+            *offset = Some(next);
+        }
+        self.write(next.to_string())
+    }
+
+    /// The newest message handled in `channel`
+    pub fn cursor(&self, channel: &str) -> Option<u64> {
+        self.cursors.lock().unwrap().get(channel).copied()
+    }
+
+    /// Every channel with a saved cursor
+    pub fn cursors(&self) -> Vec<(String, u64)> {
+        let cursors = self.cursors.lock().unwrap();
+        cursors
+            .iter()
+            .map(|(channel, id)| (channel.clone(), *id))
+            .collect()
+    }
+
+    /// Records every message in `channel` up to and including `message_id` as handled
+    pub fn advance_cursor(&self, channel: &str, message_id: u64) -> Result<()> {
+        let mut cursors = self.cursors.lock().unwrap();
+        let cursor = cursors.entry(channel.to_string()).or_default();
+        if *cursor >= message_id {
+            return Ok(());
+        }
+        *cursor = message_id;
+        // Written under the lock so an older set of cursors never lands last
+        self.write(serde_json::to_string(&*cursors)?)
+    }
+
+    /// Rewrites the current offset or cursors, in case an earlier save failed
+    pub fn flush(&self) -> Result<()> {
+        let cursors = self.cursors.lock().unwrap().clone();
+        if !cursors.is_empty() {
+            return self.write(serde_json::to_string(&cursors)?);
+        }
+        match self.current() {
+            Some(offset) => self.write(offset.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&self, saved: String) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, saved)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,548 @@
+use crate::{
+    BotError, Campaign, GmatDatabase, ImageHost, QuestionContent, QuestionType, Result, Storage,
+    Theme, Throttle, WebhookEvent, ZaloBot, fetch_question_content, hosted_image_key, part_caption,
+    question_text, render_question_to_image, render_scratch_dir, reorder_choices, shuffle_order,
+};
+use futures_util::stream::{self, StreamExt};
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tracing::{debug, error, info, warn};
+
+/// Questions fetched at once unless configured otherwise
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+/// Question images rendered at once unless configured otherwise
+pub const DEFAULT_RENDER_CONCURRENCY: usize = 2;
+/// Uploads and deliveries in flight at once unless configured otherwise
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// How much work each stage of [`send_questions_to_users`] does at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PipelineLimits {
+    pub fetch: usize,
+    pub render: usize,
+    /// Shared by uploads to the image host and deliveries to recipients
+    pub upload: usize,
+    /// Most messages sent per second across all recipients; unlimited when `None`
+    pub send_rate: Option<f64>,
+}
+
+impl Default for PipelineLimits {
+    fn default() -> Self {
+        Self {
+            fetch: DEFAULT_FETCH_CONCURRENCY,
+            render: DEFAULT_RENDER_CONCURRENCY,
+            upload: DEFAULT_UPLOAD_CONCURRENCY,
+            send_rate: None,
+        }
+    }
+}
+
+/// The image of one part of a question, before or after it reached the image host;
+/// an image too tall for one picture holds several pages
+enum QuestionImage {
+    Hosted(Vec<String>),
+    File(Vec<String>),
+    /// Couldn't be rendered or uploaded, so it goes out as plain text
+    Text(String),
+}
+
+/// A question rendered in one theme, for the recipients who use that theme
+struct RenderedQuestion {
+    content: QuestionContent,
+    question_type: QuestionType,
+    theme: Theme,
+    /// Order the choices are shown in, for recipients who get them shuffled
+    choice_order: Option<Vec<usize>>,
+    recipients: Vec<String>,
+    images: Vec<QuestionImage>,
+    /// Where the images are rendered; removed with them once the last recipient has them
+    scratch: TempDir,
+}
+
+impl RenderedQuestion {
+    /// What each image shows: the question's parts, or the question with its choices reordered
+    fn parts(&self) -> Vec<QuestionContent> {
+        match &self.choice_order {
+            Some(order) => vec![reorder_choices(&self.content, order)],
+            None => self.content.parts(),
+        }
+    }
+}
+
+/// Sends each question to every user. Fetching, rendering and uploading run as
+/// overlapping stages, each bounded by `limits`; a question is rendered and
+/// uploaded once per theme in use rather than once per user, and recipients who
+/// get shuffled choices all see the same order.
+///
+/// Returns the outcome of each question in the order given. A question fails
+/// when it can't be fetched; images that can't be rendered or uploaded are sent
+/// as plain text instead, and failed deliveries to individual users are only logged.
+///
+/// With a `campaign`, deliveries it already recorded are skipped and each new
+/// one is checkpointed, so an interrupted send can be resumed.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_questions_to_users(
+    zalo_bot: &ZaloBot,
+    users: &[String],
+    questions: &[(QuestionType, String)],
+    output_dir: &str,
+    image_host: &dyn ImageHost,
+    show_explanations: bool,
+    limits: PipelineLimits,
+    campaign: Option<&Campaign>,
+) -> Vec<(String, Result<()>)> {
+    let delivered = |question_id: &str, user_id: &str| {
+        campaign.is_some_and(|campaign| campaign.is_delivered(question_id, user_id))
+    };
+    let throttle = limits.send_rate.map(Throttle::new);
+    let throttle = throttle.as_ref();
+
+    // Recipients grouped by theme and shuffling, so each variant is rendered once
+    let mut variants: Vec<((Theme, bool), Vec<String>)> = Vec::new();
+    for user_id in users {
+        let variant = (
+            zalo_bot.theme_for(user_id),
+            !show_explanations && zalo_bot.shuffles_answers(user_id),
+        );
+        match variants.iter_mut().find(|(v, _)| *v == variant) {
+            Some((_, recipients)) => recipients.push(user_id.clone()),
+            None => variants.push((variant, vec![user_id.clone()])),
+        }
+    }
+    let variants = &variants;
+
+    // Questions every user already has aren't fetched or rendered again
+    let pending = questions
+        .iter()
+        .filter(|(_, question_id)| users.iter().any(|user_id| !delivered(question_id, user_id)));
+
+    let outcomes: Vec<(&str, Result<()>)> = stream::iter(pending)
+        // Fetch
+        .map(|(question_type, question_id)| async move {
+            let content = fetch_question_content(
+                &zalo_bot.database_source,
+                question_id,
+                zalo_bot.question_cache.as_ref(),
+            )
+            .await;
+            (
+                question_id.as_str(),
+                content.map(|content| (*question_type, content)),
+            )
+        })
+        .buffer_unordered(limits.fetch.max(1))
+        // Render, once per variant
+        .flat_map(|(question_id, fetched)| {
+            let jobs: Vec<_> = match fetched {
+                Ok((question_type, content)) => variants
+                    .iter()
+                    .map(|(variant, recipients)| {
+                        let job = (question_type, content.clone(), *variant, recipients.clone());
+                        (question_id, Ok(job))
+                    })
+                    .collect(),
+                Err(e) => vec![(question_id, Err(e))],
+            };
+            stream::iter(jobs)
+        })
+        .map(|(question_id, job)| async move {
+            let rendered = match job {
+                Ok((question_type, content, (theme, shuffle), recipients)) => {
+                    let choice_order = if shuffle {
+                        shuffle_order(&content, question_type, &mut rand::thread_rng())
+                    } else {
+                        None
+                    };
+                    render_for_theme(
+                        zalo_bot,
+                        content,
+                        question_type,
+                        theme,
+                        choice_order,
+                        recipients,
+                        show_explanations,
+                        output_dir,
+                        image_host,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            (question_id, rendered)
+        })
+        .buffer_unordered(limits.render.max(1))
+        // Upload
+        .map(|(question_id, rendered)| async move {
+            let uploaded = match rendered {
+                Ok(rendered) => {
+                    upload_rendered(zalo_bot, rendered, show_explanations, image_host).await
+                }
+                Err(e) => Err(e),
+            };
+            (question_id, uploaded)
+        })
+        .buffer_unordered(limits.upload.max(1))
+        // Deliver, one send per recipient
+        .flat_map(|(question_id, uploaded)| {
+            let deliveries: Vec<_> = match uploaded {
+                Ok(rendered) => {
+                    let rendered = Arc::new(rendered);
+                    (0..rendered.recipients.len())
+                        .filter(|&i| !delivered(question_id, &rendered.recipients[i]))
+                        .map(|i| (question_id, Ok((rendered.clone(), i))))
+                        .collect()
+                }
+                Err(e) => vec![(question_id, Err(e))],
+            };
+            stream::iter(deliveries)
+        })
+        .map(|(question_id, delivery)| async move {
+            let (rendered, recipient) = match delivery {
+                Ok(delivery) => delivery,
+                Err(e) => return (question_id, Err(e)),
+            };
+            let user_id = &rendered.recipients[recipient];
+            info!("Sending question to user: {}", user_id);
+            let index = questions
+                .iter()
+                .position(|(_, id)| id == question_id)
+                .map_or(1, |i| i + 1);
+            let position = (index, questions.len());
+            match deliver(zalo_bot, user_id, &rendered, position, throttle).await {
+                Ok(()) => {
+                    info!("Successfully sent to user: {}", user_id);
+                    if let Some(campaign) = campaign
+                        && let Err(e) = campaign.record(question_id, user_id)
+                    {
+                        warn!("Failed to checkpoint delivery to {}: {}", user_id, e);
+                    }
+                }
+                Err(e) => error!("Failed to send to user {}: {}", user_id, e),
+            }
+            (question_id, Ok(()))
+        })
+        .buffer_unordered(limits.upload.max(1))
+        .collect()
+        .await;
+
+    // Each question counts once; its first error decides the outcome
+    let mut results: Vec<(String, Result<()>)> = questions
+        .iter()
+        .map(|(_, question_id)| (question_id.clone(), Ok(())))
+        .collect();
+    for (question_id, outcome) in outcomes {
+        if let Err(e) = outcome
+            && let Some((_, result)) = results
+                .iter_mut()
+                .find(|(id, result)| id == question_id && result.is_ok())
+        {
+            *result = Err(e);
+        }
+    }
+    results
+}
+
+/// Questions whose images `serve` renders and uploads at startup, so the first
+/// requests for them reuse the hosted copies
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarmCache {
+    /// How many of the most often sent questions to warm
+    pub most_sent: usize,
+    /// Questions to warm by ID, before the most sent ones
+    pub question_ids: Vec<String>,
+}
+
+impl WarmCache {
+    /// The listed questions that exist, then the most sent ones, without repeats
+    pub fn questions(
+        &self,
+        database: &GmatDatabase,
+        storage: &Storage,
+    ) -> Result<Vec<(QuestionType, String)>> {
+        let mut questions: Vec<(QuestionType, String)> = Vec::new();
+        for question_id in &self.question_ids {
+            match database.find_question_type(question_id) {
+                Some(question_type) => questions.push((question_type, question_id.clone())),
+                None => warn!("Question {} to warm isn't in the database", question_id),
+            }
+        }
+        if self.most_sent > 0 {
+            for question in storage.most_sent_questions(self.most_sent)? {
+                if !questions.iter().any(|(_, id)| *id == question.1) {
+                    questions.push(question);
+                }
+            }
+        }
+        Ok(questions)
+    }
+}
+
+/// Renders and uploads the images of `questions` in the bot's default theme,
+/// with and without explanations, caching their URLs for later sends; ones
+/// already hosted are skipped. Returns how many questions are fully hosted.
+pub async fn warm_question_images(
+    zalo_bot: &ZaloBot,
+    questions: &[(QuestionType, String)],
+    output_dir: &str,
+    image_host: &dyn ImageHost,
+    limits: PipelineLimits,
+) -> usize {
+    let theme = zalo_bot.theme;
+    let warmed: Vec<(&str, bool)> = stream::iter(questions)
+        // Fetch
+        .map(|(question_type, question_id)| async move {
+            let content = fetch_question_content(
+                &zalo_bot.database_source,
+                question_id,
+                zalo_bot.question_cache.as_ref(),
+            )
+            .await
+            .inspect_err(|e| warn!("Failed to fetch question {} to warm: {}", question_id, e));
+            (
+                question_id.as_str(),
+                content.ok().map(|c| (*question_type, c)),
+            )
+        })
+        .buffer_unordered(limits.fetch.max(1))
+        // Render, once with and once without explanations
+        .flat_map(|(question_id, fetched)| {
+            let jobs: Vec<_> = [false, true]
+                .into_iter()
+                .map(|show_explanations| (question_id, fetched.clone(), show_explanations))
+                .collect();
+            stream::iter(jobs)
+        })
+        .map(|(question_id, fetched, show_explanations)| async move {
+            let rendered = match fetched {
+                Some((question_type, content)) => render_for_theme(
+                    zalo_bot,
+                    content,
+                    question_type,
+                    theme,
+                    None,
+                    Vec::new(),
+                    show_explanations,
+                    output_dir,
+                    image_host,
+                )
+                .await
+                .ok(),
+                None => None,
+            };
+            (question_id, rendered, show_explanations)
+        })
+        .buffer_unordered(limits.render.max(1))
+        // Upload
+        .map(|(question_id, rendered, show_explanations)| async move {
+            let hosted = match rendered {
+                Some(rendered) => {
+                    upload_rendered(zalo_bot, rendered, show_explanations, image_host)
+                        .await
+                        .is_ok_and(|rendered| {
+                            rendered
+                                .images
+                                .iter()
+                                .all(|image| matches!(image, QuestionImage::Hosted(_)))
+                        })
+                }
+                None => false,
+            };
+            (question_id, hosted)
+        })
+        .buffer_unordered(limits.upload.max(1))
+        .collect()
+        .await;
+
+    questions
+        .iter()
+        .filter(|(_, question_id)| {
+            warmed
+                .iter()
+                .filter(|(id, _)| id == question_id)
+                .all(|(_, hosted)| *hosted)
+        })
+        .count()
+}
+
+/// Renders every part of a question in one theme and choice order, reusing hosted copies from earlier sends
+#[allow(clippy::too_many_arguments)]
+async fn render_for_theme(
+    zalo_bot: &ZaloBot,
+    content: QuestionContent,
+    question_type: QuestionType,
+    theme: Theme,
+    choice_order: Option<Vec<usize>>,
+    recipients: Vec<String>,
+    show_explanations: bool,
+    output_dir: &str,
+    image_host: &dyn ImageHost,
+) -> Result<RenderedQuestion> {
+    let content = zalo_bot.enriched(&content).await;
+    // Themes and choice orders render side by side, so each writes to its own directory
+    let mut rendered = RenderedQuestion {
+        content,
+        question_type,
+        theme,
+        choice_order,
+        recipients,
+        images: Vec::new(),
+        scratch: render_scratch_dir(output_dir)?,
+    };
+    let output_dir = rendered.scratch.path().to_string_lossy().to_string();
+    let choice_order = rendered.choice_order.as_deref();
+    for part in rendered.parts() {
+        let hosted = zalo_bot.hosted_copy(
+            &hosted_image_key(&part.id, theme, choice_order),
+            show_explanations,
+            image_host,
+        );
+        let image = match hosted {
+            Some(image_urls) => {
+                debug!("Reusing hosted image for question {}", part.id);
+                QuestionImage::Hosted(image_urls)
+            }
+            None => match render_question_to_image(
+                &part,
+                &question_type,
+                show_explanations,
+                theme,
+                &zalo_bot.templates,
+                &output_dir,
+                zalo_bot.renderer.as_ref(),
+            )
+            .await
+            {
+                Ok(paths) => QuestionImage::File(paths),
+                Err(e) => {
+                    warn!("Sending question {} as text instead: {}", part.id, e);
+                    zalo_bot.webhooks.emit(WebhookEvent::RenderFailed {
+                        chat_id: None,
+                        question_id: part.id.clone(),
+                        error: e.to_string(),
+                    });
+                    QuestionImage::Text(question_text(&part, show_explanations))
+                }
+            },
+        };
+        rendered.images.push(image);
+    }
+    Ok(rendered)
+}
+
+/// Uploads rendered files to the image host and remembers their URLs; hosts
+/// that take the file with each message keep them as files
+async fn upload_rendered(
+    zalo_bot: &ZaloBot,
+    mut rendered: RenderedQuestion,
+    show_explanations: bool,
+    image_host: &dyn ImageHost,
+) -> Result<RenderedQuestion> {
+    if image_host.uploads_directly() {
+        return Ok(rendered);
+    }
+    let parts = rendered.parts();
+    for (image, part) in rendered.images.iter_mut().zip(&parts) {
+        let QuestionImage::File(paths) = image else {
+            continue;
+        };
+        let uploaded = async {
+            let mut image_urls = Vec::new();
+            for path in paths.iter() {
+                image_urls.push(image_host.upload(path).await?);
+            }
+            Ok::<_, BotError>(image_urls)
+        }
+        .await;
+        remove_files(paths);
+        let image_urls = match uploaded {
+            Ok(image_urls) => image_urls,
+            Err(e) => {
+                warn!("Sending question {} as text instead: {}", part.id, e);
+                *image = QuestionImage::Text(question_text(part, show_explanations));
+                continue;
+            }
+        };
+        if let Some(storage) = &zalo_bot.storage
+            && let Err(e) = storage.save_hosted_image_urls(
+                &hosted_image_key(&part.id, rendered.theme, rendered.choice_order.as_deref()),
+                show_explanations,
+                &image_urls,
+            )
+        {
+            warn!("Failed to cache hosted image URL: {}", e);
+        }
+        *image = QuestionImage::Hosted(image_urls);
+    }
+    Ok(rendered)
+}
+
+fn remove_files(paths: &[String]) {
+    for path in paths {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Failed to remove temporary file {}: {}", path, e);
+        }
+    }
+}
+
+/// Sends a rendered question, number `index` of the `count` in the batch, to one
+/// recipient and waits for their answer
+async fn deliver(
+    zalo_bot: &ZaloBot,
+    chat_id: &str,
+    rendered: &RenderedQuestion,
+    (index, count): (usize, usize),
+    throttle: Option<&Throttle>,
+) -> Result<()> {
+    let caption = zalo_bot.send_caption(
+        chat_id,
+        &rendered.content,
+        rendered.question_type,
+        index,
+        count,
+    );
+    let total = rendered.images.len();
+    for (i, image) in rendered.images.iter().enumerate() {
+        let caption = part_caption(&caption, i, total);
+        match image {
+            QuestionImage::Hosted(image_urls) => {
+                for (page, image_url) in image_urls.iter().enumerate() {
+                    if let Some(throttle) = throttle {
+                        throttle.wait().await;
+                    }
+                    let caption = part_caption(&caption, page, image_urls.len());
+                    zalo_bot.send_photo(chat_id, image_url, &caption).await?
+                }
+            }
+            // Left as files only for hosts that take them with each message
+            QuestionImage::File(paths) => {
+                for (page, path) in paths.iter().enumerate() {
+                    if let Some(throttle) = throttle {
+                        throttle.wait().await;
+                    }
+                    let caption = part_caption(&caption, page, paths.len());
+                    zalo_bot
+                        .send_photo_file(chat_id, Path::new(path), &caption)
+                        .await?
+                }
+            }
+            QuestionImage::Text(text) => {
+                if let Some(throttle) = throttle {
+                    throttle.wait().await;
+                }
+                zalo_bot
+                    .send_message(chat_id, &format!("{}\n\n{}", text, caption))
+                    .await?
+            }
+        }
+    }
+    zalo_bot
+        .question_delivered(
+            chat_id,
+            &rendered.content,
+            &rendered.question_type,
+            rendered.choice_order.clone(),
+        )
+        .await;
+    Ok(())
+}
@@ -0,0 +1,793 @@
+use super::{BotIdentity, ChatMessage, ChatPlatform};
+use crate::http::default_client;
+use crate::retry::{RetryPolicy, retry};
+use crate::zalo::DEFAULT_POLL_TIMEOUT;
+use crate::{
+    BotError, ImageFormat, MessageKind, OffsetStore, Result, ZaloChat, ZaloSender,
+    split_into_messages,
+};
+use futures_util::StreamExt;
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use twilight_gateway::{ConfigBuilder, Intents, Message as GatewayMessage, Shard, ShardId};
+
+/// Discord HTTP API host and version
+pub const DISCORD_API_URL: &str = "https://discord.com/api/v10";
+/// Longest message Discord accepts, in characters
+pub const DISCORD_MESSAGE_LIMIT: usize = 2000;
+/// Milliseconds from the Unix epoch to the first Discord snowflake
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+/// Text channels in a server; the rest are voice channels, categories and threads
+const GUILD_TEXT: u8 = 0;
+/// A "user joined the server" system message
+const GUILD_MEMBER_JOIN: u8 = 7;
+/// Most messages Discord lists per request when catching up on a chat
+const CATCH_UP_PAGE: usize = 100;
+/// Cursor keys of direct message chats start with this, followed by the user ID
+const DM_CURSOR_PREFIX: &str = "dm:";
+
+/// Server channels and their messages, direct messages, and message text
+fn gateway_intents() -> Intents {
+    Intents::GUILDS | Intents::GUILD_MESSAGES | Intents::DIRECT_MESSAGES | Intents::MESSAGE_CONTENT
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordUser {
+    id: String,
+    username: String,
+    global_name: Option<String>,
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordGuild {
+    #[serde(default)]
+    channels: Vec<DiscordChannel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordChannel {
+    id: String,
+    #[serde(rename = "type")]
+    channel_type: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordAttachment {
+    url: String,
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordMessage {
+    id: String,
+    #[serde(default)]
+    channel_id: String,
+    /// Only set on messages from the gateway, and only in servers
+    #[serde(default)]
+    guild_id: Option<String>,
+    #[serde(rename = "type", default)]
+    message_type: u8,
+    author: DiscordUser,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    mentions: Vec<DiscordUser>,
+    #[serde(default)]
+    attachments: Vec<DiscordAttachment>,
+    #[serde(default)]
+    sticker_items: Vec<Value>,
+}
+
+/// A gateway payload; only dispatches carry an event name
+#[derive(Debug, Deserialize)]
+struct GatewayPayload {
+    t: Option<String>,
+    #[serde(default)]
+    d: Value,
+}
+
+/// What the gateway task passes on to `get_updates`
+#[derive(Debug)]
+enum GatewayEvent {
+    Message(Box<DiscordMessage>),
+    /// Text channels the bot can post in, from joining or creating them
+    Channels(Vec<String>),
+    /// The connection ended for good, e.g. over a rejected token or intent
+    Closed(String),
+}
+
+impl DiscordUser {
+    fn display_name(&self) -> String {
+        self.global_name
+            .clone()
+            .unwrap_or_else(|| self.username.clone())
+    }
+}
+
+/// Where a message was sent: a server channel, or the direct messages with one user
+#[derive(Debug, Clone)]
+struct DiscordChat {
+    channel_id: String,
+    /// The other side of a direct message channel
+    user_id: Option<String>,
+}
+
+impl DiscordChat {
+    /// The chat's key in the offset store. Direct messages are keyed by user, since
+    /// their channel is looked up again after a restart
+    fn cursor_key(&self) -> String {
+        match &self.user_id {
+            Some(user_id) => format!("{}{}", DM_CURSOR_PREFIX, user_id),
+            None => self.channel_id.clone(),
+        }
+    }
+}
+
+/// Seconds since the Unix epoch a snowflake ID was made at
+fn snowflake_secs(id: u64) -> u64 {
+    ((id >> 22) + DISCORD_EPOCH_MS) / 1000
+}
+
+impl DiscordMessage {
+    fn snowflake(&self) -> u64 {
+        self.id.parse().unwrap_or_default()
+    }
+
+    /// The message as the bot sees it in `chat`; `None` for other bots, including
+    /// this one, so the bot never answers itself
+    fn into_chat_message(self, chat: &DiscordChat, bot_id: &str) -> Option<ChatMessage> {
+        if self.author.bot {
+            return None;
+        }
+        let snowflake = self.id.parse::<u64>().ok()?;
+        let chat = match &chat.user_id {
+            Some(user_id) => ZaloChat {
+                id: user_id.clone(),
+                chat_type: "PRIVATE".to_string(),
+            },
+            None => ZaloChat {
+                id: chat.channel_id.clone(),
+                chat_type: "GROUP".to_string(),
+            },
+        };
+        // A mention addresses the bot as directly as a tapped button
+        let mentioned = self.mentions.iter().any(|user| user.id == bot_id);
+        let mut text = self.content;
+        if mentioned {
+            for mention in [format!("<@{}>", bot_id), format!("<@!{}>", bot_id)] {
+                text = text.replace(&mention, "");
+            }
+            text = text.trim().to_string();
+        }
+        let image = self.attachments.into_iter().find(|attachment| {
+            attachment
+                .content_type
+                .as_deref()
+                .is_some_and(|content_type| content_type.starts_with("image/"))
+        });
+        let (kind, text, photo, caption) = if self.message_type == GUILD_MEMBER_JOIN {
+            (MessageKind::MemberJoined, None, None, None)
+        } else if let Some(image) = image {
+            let caption = (!text.is_empty()).then_some(text);
+            (MessageKind::Photo, None, Some(image.url), caption)
+        } else if !self.sticker_items.is_empty() {
+            (MessageKind::Sticker, None, None, None)
+        } else {
+            (MessageKind::Text, Some(text), None, None)
+        };
+        Some(ChatMessage {
+            sender: ZaloSender {
+                display_name: Some(self.author.display_name()),
+                id: self.author.id,
+                is_bot: false,
+            },
+            chat,
+            text,
+            photo,
+            caption,
+            message_id: self.id,
+            date: snowflake_secs(snowflake),
+            from_button: mentioned,
+            kind,
+        })
+    }
+}
+
+/// Parses a gateway message into the events the bot acts on
+fn parse_gateway_event(text: &str) -> Option<GatewayEvent> {
+    let payload: GatewayPayload = serde_json::from_str(text).ok()?;
+    let parsed = match payload.t.as_deref()? {
+        "MESSAGE_CREATE" => serde_json::from_value(payload.d).map(GatewayEvent::Message),
+        "GUILD_CREATE" => serde_json::from_value(payload.d)
+            .map(|guild: DiscordGuild| GatewayEvent::Channels(text_channels(guild.channels))),
+        "CHANNEL_CREATE" => serde_json::from_value(payload.d)
+            .map(|channel| GatewayEvent::Channels(text_channels(vec![channel]))),
+        _ => return None,
+    };
+    parsed
+        .inspect_err(|e| debug!("Ignoring unreadable Discord gateway event: {}", e))
+        .ok()
+}
+
+fn text_channels(channels: Vec<DiscordChannel>) -> Vec<String> {
+    channels
+        .into_iter()
+        .filter(|channel| channel.channel_type == GUILD_TEXT)
+        .map(|channel| channel.id)
+        .collect()
+}
+
+/// Reads `shard` until the connection ends for good, passing events on to `events`.
+/// Dropped connections are resumed by the shard, which replays what was missed
+async fn run_gateway(mut shard: Shard, events: mpsc::UnboundedSender<GatewayEvent>) {
+    let mut closed = "the connection ended".to_string();
+    while let Some(received) = shard.next().await {
+        let event = match received {
+            Ok(GatewayMessage::Text(text)) => parse_gateway_event(&text),
+            Ok(GatewayMessage::Close(frame)) => {
+                if let Some(frame) = frame {
+                    debug!(code = frame.code, "Discord gateway closed the connection");
+                    closed = format!("closed with code {}: {}", frame.code, frame.reason);
+                }
+                None
+            }
+            Err(e) => {
+                warn!("Discord gateway error: {}", e);
+                None
+            }
+        };
+        // The receiving end goes away with the bot
+        if let Some(event) = event
+            && events.send(event).is_err()
+        {
+            return;
+        }
+    }
+    let _ = events.send(GatewayEvent::Closed(closed));
+}
+
+/// What the bot has learned about its servers and chats
+#[derive(Debug, Default)]
+struct DiscordState {
+    /// The bot's own user ID, from `GET /users/@me`
+    bot_id: Option<String>,
+    /// Text channels in the bot's servers
+    channels: HashSet<String>,
+    /// The newest message handled in each chat, by [`DiscordChat::cursor_key`]
+    cursors: HashMap<String, u64>,
+    /// Direct message channel of each user the bot has talked to
+    dm_channels: HashMap<String, String>,
+    /// Chats to catch up on over the HTTP API, e.g. after a restart; a chat that
+    /// fails is tried again on the next `get_updates`
+    catch_up: Vec<String>,
+}
+
+/// Discord transport: messages arrive over the gateway and everything else goes
+/// through the HTTP API.
+///
+/// Chats the bot has handled messages in are caught up on over the HTTP API when
+/// the gateway connects, so nothing sent while the bot was down is missed. Chat
+/// IDs are channel IDs in servers and user IDs in direct messages
+pub struct DiscordBot {
+    bot_token: String,
+    api_url: String,
+    /// Gateway to connect to instead of Discord's, e.g. a mock server
+    gateway_url: Option<String>,
+    client: reqwest::Client,
+    timeout: Option<Duration>,
+    /// Seconds `get_updates` waits for a message before returning empty-handed
+    poll_timeout: u64,
+    retry_policy: RetryPolicy,
+    offset_store: Option<OffsetStore>,
+    state: Mutex<DiscordState>,
+    /// Events from the gateway, connected on the first `get_updates`
+    gateway: tokio::sync::Mutex<Option<mpsc::UnboundedReceiver<GatewayEvent>>>,
+}
+
+impl DiscordBot {
+    pub fn new(bot_token: String) -> Self {
+        Self {
+            bot_token,
+            api_url: DISCORD_API_URL.to_string(),
+            gateway_url: None,
+            client: default_client(),
+            timeout: None,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            offset_store: None,
+            state: Mutex::new(DiscordState::default()),
+            gateway: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Talks to another host, e.g. a mock server
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Connects to another gateway, e.g. `ws://127.0.0.1:8080` for a mock server
+    pub fn with_gateway_url(mut self, gateway_url: impl Into<String>) -> Self {
+        self.gateway_url = Some(gateway_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Gives up on a request after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Seconds `get_updates` waits for messages before returning none
+    pub fn with_poll_timeout(mut self, poll_timeout: u64) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sends requests through `client`, e.g. the chat route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Persists the newest message handled in each chat, so restarts neither handle
+    /// messages twice nor miss those sent in between
+    pub fn with_offset_store(mut self, offset_store: OffsetStore) -> Self {
+        self.offset_store = Some(offset_store);
+        self
+    }
+
+    /// Calls an API route and parses its JSON reply; routes answering 204 give `null`
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        route: &str,
+        payload: Option<&Value>,
+    ) -> Result<T> {
+        let url = format!("{}{}", self.api_url, route);
+        let mut request = self
+            .client
+            .request(method.clone(), &url)
+            .header("Authorization", format!("Bot {}", self.bot_token));
+        if let Some(payload) = payload {
+            request = request.json(payload);
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        parse_response(response, &format!("Discord {} {}", method, route)).await
+    }
+
+    /// The bot's own user ID, looked up once
+    async fn bot_id(&self) -> Result<String> {
+        if let Some(bot_id) = self.state.lock().unwrap().bot_id.clone() {
+            return Ok(bot_id);
+        }
+        let me: DiscordUser = self.call(Method::GET, "/users/@me", None).await?;
+        self.state.lock().unwrap().bot_id = Some(me.id.clone());
+        Ok(me.id)
+    }
+
+    /// Opens the gateway connection in a background task, and queues every chat
+    /// with a cursor to be caught up on
+    fn connect(&self) -> mpsc::UnboundedReceiver<GatewayEvent> {
+        let mut chats: HashSet<String> =
+            self.state.lock().unwrap().cursors.keys().cloned().collect();
+        if let Some(offset_store) = &self.offset_store {
+            chats.extend(offset_store.cursors().into_iter().map(|(chat, _)| chat));
+        }
+        self.state.lock().unwrap().catch_up = chats.into_iter().collect();
+
+        let mut config = ConfigBuilder::new(self.bot_token.clone(), gateway_intents());
+        if let Some(gateway_url) = &self.gateway_url {
+            config = config.proxy_url(gateway_url.clone());
+        }
+        let shard = Shard::with_config(ShardId::ONE, config.build());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run_gateway(shard, sender));
+        info!("Connecting to the Discord gateway");
+        receiver
+    }
+
+    /// The newest message handled in the chat with `cursor_key`
+    fn cursor(&self, cursor_key: &str) -> Option<u64> {
+        let cursor = self.state.lock().unwrap().cursors.get(cursor_key).copied();
+        cursor.or_else(|| self.offset_store.as_ref()?.cursor(cursor_key))
+    }
+
+    /// Fetches what each queued chat got since its cursor. A chat the bot can no
+    /// longer read is dropped; any other failure is logged and tried again later
+    async fn catch_up(&self) -> Vec<(DiscordChat, DiscordMessage)> {
+        let queued = std::mem::take(&mut self.state.lock().unwrap().catch_up);
+        let mut received = Vec::new();
+        let mut failed = Vec::new();
+        for cursor_key in queued {
+            match self.catch_up_chat(&cursor_key).await {
+                Ok(messages) => received.extend(messages),
+                Err(e)
+                    if matches!(
+                        e.status(),
+                        Some(StatusCode::FORBIDDEN | StatusCode::NOT_FOUND)
+                    ) =>
+                {
+                    debug!("Skipping Discord chat {}: {}", cursor_key, e);
+                }
+                Err(e) => {
+                    warn!("Failed to catch up on Discord chat {}: {}", cursor_key, e);
+                    failed.push(cursor_key);
+                }
+            }
+        }
+        self.state.lock().unwrap().catch_up.extend(failed);
+        received
+    }
+
+    /// Every message in one chat newer than its cursor, page by page
+    async fn catch_up_chat(&self, cursor_key: &str) -> Result<Vec<(DiscordChat, DiscordMessage)>> {
+        let chat = match cursor_key.strip_prefix(DM_CURSOR_PREFIX) {
+            Some(user_id) => DiscordChat {
+                channel_id: self.channel_for(user_id).await?,
+                user_id: Some(user_id.to_string()),
+            },
+            None => {
+                self.state
+                    .lock()
+                    .unwrap()
+                    .channels
+                    .insert(cursor_key.to_string());
+                DiscordChat {
+                    channel_id: cursor_key.to_string(),
+                    user_id: None,
+                }
+            }
+        };
+        let mut after = self.cursor(cursor_key).unwrap_or_default();
+        let mut received = Vec::new();
+        loop {
+            let route = format!(
+                "/channels/{}/messages?after={}&limit={}",
+                chat.channel_id, after, CATCH_UP_PAGE
+            );
+            let mut page: Vec<DiscordMessage> = self.call(Method::GET, &route, None).await?;
+            // Discord lists the newest message first
+            page.sort_by_key(DiscordMessage::snowflake);
+            let full = page.len() == CATCH_UP_PAGE;
+            let Some(newest) = page.last() else {
+                break;
+            };
+            after = newest.snowflake();
+            received.extend(page.into_iter().map(|message| (chat.clone(), message)));
+            if !full {
+                break;
+            }
+        }
+        Ok(received)
+    }
+
+    /// Takes in one gateway event, adding messages to `received`; errors once the
+    /// gateway has closed
+    fn receive(
+        &self,
+        event: Option<GatewayEvent>,
+        received: &mut Vec<(DiscordChat, DiscordMessage)>,
+    ) -> Result<()> {
+        match event {
+            Some(GatewayEvent::Message(message)) => {
+                // The bot's own messages are left out, so its direct messages are
+                // only ever tied to the user it talks to
+                if message.author.bot {
+                    return Ok(());
+                }
+                let mut state = self.state.lock().unwrap();
+                let chat = match message.guild_id {
+                    Some(_) => {
+                        state.channels.insert(message.channel_id.clone());
+                        DiscordChat {
+                            channel_id: message.channel_id.clone(),
+                            user_id: None,
+                        }
+                    }
+                    None => {
+                        state
+                            .dm_channels
+                            .insert(message.author.id.clone(), message.channel_id.clone());
+                        DiscordChat {
+                            channel_id: message.channel_id.clone(),
+                            user_id: Some(message.author.id.clone()),
+                        }
+                    }
+                };
+                received.push((chat, *message));
+                Ok(())
+            }
+            Some(GatewayEvent::Channels(channels)) => {
+                self.state.lock().unwrap().channels.extend(channels);
+                Ok(())
+            }
+            Some(GatewayEvent::Closed(reason)) => Err(BotError::discord(
+                "Discord gateway connection failed",
+                None,
+                reason,
+            )),
+            None => Err(BotError::discord(
+                "Discord gateway connection failed",
+                None,
+                "the gateway task stopped",
+            )),
+        }
+    }
+
+    /// Turns a batch into chat messages, oldest first, leaving out any already
+    /// handled, e.g. one both caught up on and delivered by the gateway. Cursors
+    /// only move once the whole batch is in hand
+    fn acknowledge(
+        &self,
+        mut received: Vec<(DiscordChat, DiscordMessage)>,
+        bot_id: &str,
+    ) -> Vec<ChatMessage> {
+        received.sort_by_key(|(_, message)| message.snowflake());
+        let mut messages = Vec::new();
+        for (chat, message) in received {
+            let cursor_key = chat.cursor_key();
+            let snowflake = message.snowflake();
+            if self
+                .cursor(&cursor_key)
+                .is_some_and(|cursor| snowflake <= cursor)
+            {
+                continue;
+            }
+            self.state
+                .lock()
+                .unwrap()
+                .cursors
+                .insert(cursor_key.clone(), snowflake);
+            if let Some(offset_store) = &self.offset_store
+                && let Err(e) = offset_store.advance_cursor(&cursor_key, snowflake)
+            {
+                warn!("Failed to save update offset: {}", e);
+            }
+            messages.extend(message.into_chat_message(&chat, bot_id));
+        }
+        messages
+    }
+
+    /// The channel a chat ID sends to: server channels are used as they are, any
+    /// other ID is a user whose direct messages are opened on first use
+    async fn channel_for(&self, chat_id: &str) -> Result<String> {
+        {
+            let state = self.state.lock().unwrap();
+            if state.channels.contains(chat_id) {
+                return Ok(chat_id.to_string());
+            }
+            if let Some(channel_id) = state.dm_channels.get(chat_id) {
+                return Ok(channel_id.clone());
+            }
+        }
+        let payload = json!({ "recipient_id": chat_id });
+        let channel: DiscordChannel = retry(&self.retry_policy, "Discord open DM", || {
+            self.call(Method::POST, "/users/@me/channels", Some(&payload))
+        })
+        .await?;
+        self.state
+            .lock()
+            .unwrap()
+            .dm_channels
+            .insert(chat_id.to_string(), channel.id.clone());
+        Ok(channel.id)
+    }
+
+    /// Posts a message to a channel, returning its ID
+    async fn create_message(&self, channel_id: &str, payload: &Value) -> Result<String> {
+        let route = format!("/channels/{}/messages", channel_id);
+        let sent: DiscordMessageId = retry(&self.retry_policy, "Discord create message", || {
+            self.call(Method::POST, &route, Some(payload))
+        })
+        .await?;
+        Ok(sent.id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordMessageId {
+    id: String,
+}
+
+/// Parses a reply, turning an error status into [`BotError::DiscordApi`] with
+/// Discord's own message
+async fn parse_response<T: DeserializeOwned>(
+    response: reqwest::Response,
+    context: &str,
+) -> Result<T> {
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        let message = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|body| body.get("message")?.as_str().map(String::from))
+            .unwrap_or(text);
+        return Err(BotError::discord(
+            format!("{} failed", context),
+            Some(status),
+            message,
+        ));
+    }
+    let body = if text.is_empty() { "null" } else { &text };
+    serde_json::from_str(body).map_err(|e| {
+        BotError::discord(
+            format!("Failed to parse {} response: {}", context, e),
+            Some(status),
+            text.clone(),
+        )
+    })
+}
+
+#[async_trait::async_trait]
+impl ChatPlatform for DiscordBot {
+    async fn get_me(&self) -> Result<Option<BotIdentity>> {
+        let me: DiscordUser =
+            self.call(Method::GET, "/users/@me", None)
+                .await
+                .map_err(|e| match e {
+                    BotError::DiscordApi {
+                        context,
+                        status: Some(StatusCode::UNAUTHORIZED),
+                        body,
+                    } => BotError::DiscordInvalidToken {
+                        context,
+                        description: body,
+                    },
+                    e => e,
+                })?;
+        self.state.lock().unwrap().bot_id = Some(me.id.clone());
+        Ok(Some(BotIdentity {
+            name: Some(me.display_name()),
+            id: me.id,
+        }))
+    }
+
+    async fn get_updates(&self) -> Result<Vec<ChatMessage>> {
+        let bot_id = self.bot_id().await?;
+        let mut gateway = self.gateway.lock().await;
+        let events = match gateway.as_mut() {
+            Some(events) => events,
+            None => gateway.insert(self.connect()),
+        };
+        let mut received = self.catch_up().await;
+        // Only wait for the gateway when there is nothing to return yet
+        if received.is_empty()
+            && let Ok(event) =
+                tokio::time::timeout(Duration::from_secs(self.poll_timeout), events.recv()).await
+            && let Err(e) = self.receive(event, &mut received)
+        {
+            *gateway = None;
+            return Err(e);
+        }
+        while let Ok(event) = events.try_recv() {
+            if let Err(e) = self.receive(Some(event), &mut received) {
+                // Hand over what came first; the next call reconnects and catches up
+                warn!("{}", e);
+                *gateway = None;
+                break;
+            }
+        }
+        Ok(self.acknowledge(received, &bot_id))
+    }
+
+    async fn send_message(&self, chat_id: &str, text: &str) -> Result<String> {
+        let channel_id = self.channel_for(chat_id).await?;
+        // Long replies such as leaderboards go out in several messages
+        let mut message_id = String::new();
+        for part in split_into_messages(text, DISCORD_MESSAGE_LIMIT) {
+            message_id = self
+                .create_message(&channel_id, &json!({ "content": part }))
+                .await?;
+        }
+        info!("Message sent successfully to chat: {}", chat_id);
+        Ok(message_id)
+    }
+
+    async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()> {
+        let route = format!(
+            "/channels/{}/messages/{}",
+            self.channel_for(chat_id).await?,
+            message_id
+        );
+        let payload = json!({ "content": text });
+        retry(&self.retry_policy, "Discord edit message", || async {
+            self.call::<Value>(Method::PATCH, &route, Some(&payload))
+                .await
+                .map(|_| ())
+        })
+        .await
+    }
+
+    async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()> {
+        let route = format!(
+            "/channels/{}/messages/{}",
+            self.channel_for(chat_id).await?,
+            message_id
+        );
+        retry(&self.retry_policy, "Discord delete message", || async {
+            self.call::<Value>(Method::DELETE, &route, None)
+                .await
+                .map(|_| ())
+        })
+        .await
+    }
+
+    /// Discord has no "send photo by URL", so the image goes in an embed
+    async fn send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()> {
+        let channel_id = self.channel_for(chat_id).await?;
+        let payload = json!({ "content": caption, "embeds": [{ "image": { "url": photo } }] });
+        self.create_message(&channel_id, &payload).await?;
+        info!("Photo sent successfully to chat: {}", chat_id);
+        Ok(())
+    }
+
+    async fn send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()> {
+        let channel_id = self.channel_for(chat_id).await?;
+        let bytes = std::fs::read(path)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "question.png".to_string());
+        let payload = json!({
+            "content": caption,
+            "attachments": [{ "id": 0, "filename": file_name }],
+        });
+        let url = format!("{}/channels/{}/messages", self.api_url, channel_id);
+        retry(&self.retry_policy, "Discord upload", || async {
+            // A multipart form can only be sent once, so each attempt builds its own
+            let form = reqwest::multipart::Form::new()
+                .text("payload_json", payload.to_string())
+                .part(
+                    "files[0]",
+                    reqwest::multipart::Part::bytes(bytes.clone())
+                        .file_name(file_name.clone())
+                        .mime_str(ImageFormat::mime_type_of(&bytes))?,
+                );
+            let mut request = self
+                .client
+                .post(&url)
+                .header("Authorization", format!("Bot {}", self.bot_token))
+                .multipart(form);
+            if let Some(timeout) = self.timeout {
+                request = request.timeout(timeout);
+            }
+            parse_response::<Value>(request.send().await?, "Discord upload")
+                .await
+                .map(|_| ())
+        })
+        .await?;
+        info!("Photo uploaded successfully to chat: {}", chat_id);
+        Ok(())
+    }
+
+    /// Discord only has a typing indicator, shown for whatever `action` is asked for
+    async fn send_chat_action(&self, chat_id: &str, _action: &str) -> Result<()> {
+        let route = format!("/channels/{}/typing", self.channel_for(chat_id).await?);
+        self.call::<Value>(Method::POST, &route, None)
+            .await
+            .map(|_| ())
+    }
+
+    fn offset_store(&self) -> Option<&OffsetStore> {
+        self.offset_store.as_ref()
+    }
+}
@@ -0,0 +1,69 @@
+use super::{ChatMessage, ChatPlatform};
+use crate::Result;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::info;
+
+/// Logs every message it is asked to send instead of calling a chat API
+#[derive(Debug, Default)]
+pub struct DryRunPlatform {
+    next_message_id: AtomicU64,
+}
+
+#[async_trait::async_trait]
+impl ChatPlatform for DryRunPlatform {
+    /// Nobody can message a dry run, so this never returns
+    async fn get_updates(&self) -> Result<Vec<ChatMessage>> {
+        std::future::pending().await
+    }
+
+    async fn send_message(&self, chat_id: &str, text: &str) -> Result<String> {
+        info!("[dry run] Would send to {}: {:?}", chat_id, text);
+        let message_id = self.next_message_id.fetch_add(1, Ordering::Relaxed);
+        Ok(format!("dry-run-{}", message_id))
+    }
+
+    async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()> {
+        info!(
+            "[dry run] Would edit message {} in {}: {:?}",
+            message_id, chat_id, text
+        );
+        Ok(())
+    }
+
+    async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()> {
+        info!(
+            "[dry run] Would delete message {} in {}",
+            message_id, chat_id
+        );
+        Ok(())
+    }
+
+    async fn send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()> {
+        info!(
+            "[dry run] Would send photo {} to {} with caption {:?}",
+            photo, chat_id, caption
+        );
+        Ok(())
+    }
+
+    async fn send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()> {
+        let size = std::fs::metadata(path)?.len();
+        info!(
+            "[dry run] Would upload {} ({} bytes) to {} with caption {:?}",
+            path.display(),
+            size,
+            chat_id,
+            caption
+        );
+        Ok(())
+    }
+
+    async fn send_choices(&self, chat_id: &str, text: &str, choices: &[&str]) -> Result<()> {
+        info!(
+            "[dry run] Would offer {:?} to {} under {:?}",
+            choices, chat_id, text
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{OffsetStore, Result, ZaloMessage};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+pub mod discord;
+pub mod dry_run;
+pub mod telegram;
+
+pub use discord::{DISCORD_API_URL, DISCORD_MESSAGE_LIMIT, DiscordBot};
+pub use dry_run::DryRunPlatform;
+pub use telegram::{TELEGRAM_API_URL, TelegramBot};
+
+/// Incoming chat message; every platform converts its updates into this shape
+pub type ChatMessage = ZaloMessage;
+
+/// The account the bot is signed in as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotIdentity {
+    pub id: String,
+    /// Display name, when the platform gives one
+    pub name: Option<String>,
+}
+
+impl fmt::Display for BotIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} (ID {})", name, self.id),
+            None => write!(f, "ID {}", self.id),
+        }
+    }
+}
+
+/// The messaging transport the bot runs on
+#[async_trait::async_trait]
+pub trait ChatPlatform: Send + Sync {
+    /// Looks up the bot's own account, which also checks its token. Platforms without
+    /// an account, like a dry run, return `None`
+    async fn get_me(&self) -> Result<Option<BotIdentity>> {
+        Ok(None)
+    }
+
+    /// Long-polls for new messages
+    async fn get_updates(&self) -> Result<Vec<ChatMessage>>;
+
+    /// Sends a text message, returning its message ID
+    async fn send_message(&self, chat_id: &str, text: &str) -> Result<String>;
+
+    /// Replaces the text of a message the bot sent earlier
+    async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()>;
+
+    /// Removes a message the bot sent earlier
+    async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()>;
+
+    /// Sends an image by public URL
+    async fn send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()>;
+
+    /// Sends a local image as a multipart file upload
+    async fn send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()>;
+
+    /// Offers `choices` as tap-to-reply buttons under `text`; a tap comes back as a
+    /// message with `from_button` set. Platforms without buttons send nothing, so
+    /// users type their reply instead
+    async fn send_choices(&self, _chat_id: &str, _text: &str, _choices: &[&str]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Shows an activity indicator such as "typing" in the chat for a few seconds.
+    /// Platforms without one do nothing
+    async fn send_chat_action(&self, _chat_id: &str, _action: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// The platform's own update offset, flushed on shutdown
+    fn offset_store(&self) -> Option<&OffsetStore> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    /// Zalo Bot API
+    Zalo,
+    /// Telegram Bot API
+    Telegram,
+    /// Discord gateway and HTTP API
+    Discord,
+}
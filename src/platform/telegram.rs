@@ -0,0 +1,431 @@
+use super::{BotIdentity, ChatMessage, ChatPlatform};
+use crate::http::{DEFAULT_REQUEST_TIMEOUT, default_client};
+use crate::retry::{RetryPolicy, retry};
+use crate::zalo::DEFAULT_POLL_TIMEOUT;
+use crate::{BotError, ImageFormat, MessageKind, OffsetStore, Result, ZaloChat, ZaloSender};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Telegram Bot API host
+pub const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+#[derive(Debug, Deserialize)]
+struct TelegramResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramUpdate {
+    update_id: u64,
+    message: Option<TelegramMessage>,
+    callback_query: Option<TelegramCallbackQuery>,
+}
+
+/// A tap on an inline keyboard button
+#[derive(Debug, Deserialize)]
+struct TelegramCallbackQuery {
+    id: String,
+    from: TelegramUser,
+    /// The message the button was attached to
+    message: Option<TelegramMessage>,
+    data: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramMessage {
+    message_id: i64,
+    from: Option<TelegramUser>,
+    chat: TelegramChat,
+    date: u64,
+    text: Option<String>,
+    caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramUser {
+    id: i64,
+    is_bot: bool,
+    first_name: String,
+    last_name: Option<String>,
+}
+
+/// The bot's own account, from `getMe`
+#[derive(Debug, Deserialize)]
+struct TelegramBotUser {
+    id: i64,
+    first_name: String,
+    username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelegramChat {
+    id: i64,
+    #[serde(rename = "type")]
+    chat_type: String,
+}
+
+impl From<TelegramMessage> for ChatMessage {
+    fn from(message: TelegramMessage) -> Self {
+        // Channel posts have no sender; attribute them to the chat itself
+        let sender = match message.from {
+            Some(user) => ZaloSender {
+                id: user.id.to_string(),
+                is_bot: user.is_bot,
+                display_name: Some(user.full_name()),
+            },
+            None => ZaloSender {
+                id: message.chat.id.to_string(),
+                is_bot: false,
+                display_name: None,
+            },
+        };
+        let chat_type = match message.chat.chat_type.as_str() {
+            "group" | "supergroup" => "GROUP",
+            _ => "PRIVATE",
+        };
+        ChatMessage {
+            sender,
+            chat: ZaloChat {
+                id: message.chat.id.to_string(),
+                chat_type: chat_type.to_string(),
+            },
+            text: message.text,
+            photo: None,
+            caption: message.caption,
+            message_id: message.message_id.to_string(),
+            date: message.date,
+            from_button: false,
+            kind: MessageKind::Text,
+        }
+    }
+}
+
+impl TelegramCallbackQuery {
+    /// The tap as a message from the user who tapped, in the chat holding the button
+    fn into_message(self) -> Option<ChatMessage> {
+        let mut message: ChatMessage = self.message?.into();
+        message.sender = ZaloSender {
+            id: self.from.id.to_string(),
+            is_bot: self.from.is_bot,
+            display_name: Some(self.from.full_name()),
+        };
+        message.text = self.data;
+        message.caption = None;
+        message.from_button = true;
+        Some(message)
+    }
+}
+
+impl TelegramUser {
+    fn full_name(&self) -> String {
+        match &self.last_name {
+            Some(last_name) => format!("{} {}", self.first_name, last_name),
+            None => self.first_name.clone(),
+        }
+    }
+}
+
+/// Telegram Bot API transport
+pub struct TelegramBot {
+    bot_token: String,
+    api_url: String,
+    client: reqwest::Client,
+    /// Applied to every request; `getUpdates` waits `poll_timeout` on top
+    timeout: Option<Duration>,
+    poll_timeout: u64,
+    retry_policy: RetryPolicy,
+    offset_store: Option<OffsetStore>,
+}
+
+impl TelegramBot {
+    pub fn new(bot_token: String) -> Self {
+        Self {
+            bot_token,
+            api_url: TELEGRAM_API_URL.to_string(),
+            client: default_client(),
+            timeout: None,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            offset_store: None,
+        }
+    }
+
+    /// Talks to another host, e.g. a local Bot API server or a mock server
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Gives up on a request after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Seconds `getUpdates` holds the connection open waiting for messages
+    pub fn with_poll_timeout(mut self, poll_timeout: u64) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sends requests through `client`, e.g. the chat route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Persists the `getUpdates` offset so messages aren't handled twice across restarts
+    pub fn with_offset_store(mut self, offset_store: OffsetStore) -> Self {
+        self.offset_store = Some(offset_store);
+        self
+    }
+
+    /// Calls a Bot API method and unwraps its `result`
+    async fn call<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        payload: &serde_json::Value,
+    ) -> Result<T> {
+        let url = format!("{}/bot{}/{}", self.api_url, self.bot_token, method);
+        let mut request = self.client.post(&url).json(payload);
+        // A long poll waits its `timeout` on top of the usual request time
+        if let Some(wait) = payload.get("timeout").and_then(|wait| wait.as_u64()) {
+            let limit = self.timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+            request = request.timeout(limit + Duration::from_secs(wait));
+        } else if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+
+        let parsed: TelegramResponse<T> = serde_json::from_str(&text).map_err(|e| {
+            BotError::telegram(
+                format!("Failed to parse {} response: {}", method, e),
+                Some(status),
+                text.clone(),
+            )
+        })?;
+        match parsed {
+            TelegramResponse {
+                ok: true,
+                result: Some(result),
+                ..
+            } => Ok(result),
+            TelegramResponse { description, .. } => Err(BotError::telegram(
+                format!("Telegram {} failed", method),
+                Some(status),
+                description.unwrap_or(text),
+            )),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ChatPlatform for TelegramBot {
+    async fn get_me(&self) -> Result<Option<BotIdentity>> {
+        let me: TelegramBotUser =
+            self.call("getMe", &serde_json::json!({}))
+                .await
+                .map_err(|e| match e {
+                    // Telegram answers 401 for a revoked token and 404 for a malformed one
+                    BotError::TelegramApi {
+                        context,
+                        status: Some(StatusCode::UNAUTHORIZED | StatusCode::NOT_FOUND),
+                        body,
+                    } => BotError::TelegramInvalidToken {
+                        context,
+                        description: body,
+                    },
+                    e => e,
+                })?;
+        let name = match me.username {
+            Some(username) => format!("{} @{}", me.first_name, username),
+            None => me.first_name,
+        };
+        Ok(Some(BotIdentity {
+            id: me.id.to_string(),
+            name: Some(name),
+        }))
+    }
+
+    async fn get_updates(&self) -> Result<Vec<ChatMessage>> {
+        let mut payload = serde_json::json!({
+            "timeout": self.poll_timeout,
+            "allowed_updates": ["message", "callback_query"],
+        });
+        if let Some(offset) = self.offset_store.as_ref().and_then(|s| s.current()) {
+            payload["offset"] = offset.into();
+        }
+        debug!(%payload, "Polling Telegram getUpdates");
+
+        let updates: Vec<TelegramUpdate> = self.call("getUpdates", &payload).await?;
+        let mut messages = Vec::new();
+        for update in updates {
+            if let Some(offset_store) = &self.offset_store {
+                if offset_store.is_handled(update.update_id) {
+                    continue;
+                }
+                if let Err(e) = offset_store.advance(update.update_id) {
+                    warn!("Failed to save update offset: {}", e);
+                }
+            }
+            if let Some(message) = update.message {
+                messages.push(message.into());
+            }
+            if let Some(query) = update.callback_query {
+                // Stops the button's loading spinner; the reply itself comes later
+                let payload = serde_json::json!({ "callback_query_id": query.id });
+                if let Err(e) = self
+                    .call::<serde_json::Value>("answerCallbackQuery", &payload)
+                    .await
+                {
+                    warn!("Failed to answer callback query: {}", e);
+                }
+                messages.extend(query.into_message());
+            }
+        }
+        Ok(messages)
+    }
+
+    async fn send_message(&self, chat_id: &str, text: &str) -> Result<String> {
+        let payload = serde_json::json!({ "chat_id": chat_id, "text": text });
+        let sent = retry(&self.retry_policy, "Telegram sendMessage", || {
+            self.call::<TelegramMessage>("sendMessage", &payload)
+        })
+        .await?;
+        info!("Message sent successfully to chat: {}", chat_id);
+        Ok(sent.message_id.to_string())
+    }
+
+    async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()> {
+        let message_id = parse_message_id(message_id)?;
+        let payload =
+            serde_json::json!({ "chat_id": chat_id, "message_id": message_id, "text": text });
+        retry(&self.retry_policy, "Telegram editMessageText", || async {
+            self.call::<serde_json::Value>("editMessageText", &payload)
+                .await
+                .map(|_| ())
+        })
+        .await
+    }
+
+    async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()> {
+        let payload =
+            serde_json::json!({ "chat_id": chat_id, "message_id": parse_message_id(message_id)? });
+        retry(&self.retry_policy, "Telegram deleteMessage", || async {
+            self.call::<serde_json::Value>("deleteMessage", &payload)
+                .await
+                .map(|_| ())
+        })
+        .await
+    }
+
+    async fn send_photo(&self, chat_id: &str, photo: &str, caption: &str) -> Result<()> {
+        let payload = serde_json::json!({ "chat_id": chat_id, "photo": photo, "caption": caption });
+        retry(&self.retry_policy, "Telegram sendPhoto", || async {
+            self.call::<serde_json::Value>("sendPhoto", &payload)
+                .await
+                .map(|_| ())
+        })
+        .await?;
+        info!("Photo sent successfully to chat: {}", chat_id);
+        Ok(())
+    }
+
+    async fn send_photo_file(&self, chat_id: &str, path: &Path, caption: &str) -> Result<()> {
+        let bytes = std::fs::read(path)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "question.png".to_string());
+        let url = format!("{}/bot{}/sendPhoto", self.api_url, self.bot_token);
+        retry(&self.retry_policy, "Telegram sendPhoto upload", || async {
+            // A multipart form can only be sent once, so each attempt builds its own
+            let form = reqwest::multipart::Form::new()
+                .text("chat_id", chat_id.to_string())
+                .text("caption", caption.to_string())
+                .part(
+                    "photo",
+                    reqwest::multipart::Part::bytes(bytes.clone())
+                        .file_name(file_name.clone())
+                        .mime_str(ImageFormat::mime_type_of(&bytes))?,
+                );
+            let mut request = self.client.post(&url).multipart(form);
+            if let Some(timeout) = self.timeout {
+                request = request.timeout(timeout);
+            }
+            let response = request.send().await?;
+            let status = response.status();
+            let text = response.text().await?;
+            let parsed: TelegramResponse<serde_json::Value> =
+                serde_json::from_str(&text).map_err(|e| {
+                    BotError::telegram(
+                        format!("Failed to parse sendPhoto response: {}", e),
+                        Some(status),
+                        text.clone(),
+                    )
+                })?;
+            if parsed.ok {
+                Ok(())
+            } else {
+                Err(BotError::telegram(
+                    "Telegram sendPhoto upload failed",
+                    Some(status),
+                    parsed.description.unwrap_or(text),
+                ))
+            }
+        })
+        .await?;
+        info!("Photo uploaded successfully to chat: {}", chat_id);
+        Ok(())
+    }
+
+    async fn send_choices(&self, chat_id: &str, text: &str, choices: &[&str]) -> Result<()> {
+        let buttons: Vec<_> = choices
+            .iter()
+            .map(|choice| serde_json::json!({ "text": choice, "callback_data": choice }))
+            .collect();
+        let payload = serde_json::json!({
+            "chat_id": chat_id,
+            "text": text,
+            "reply_markup": { "inline_keyboard": [buttons] },
+        });
+        retry(&self.retry_policy, "Telegram sendMessage", || async {
+            self.call::<serde_json::Value>("sendMessage", &payload)
+                .await
+                .map(|_| ())
+        })
+        .await?;
+        debug!("Answer buttons sent to chat: {}", chat_id);
+        Ok(())
+    }
+
+    async fn send_chat_action(&self, chat_id: &str, action: &str) -> Result<()> {
+        let payload = serde_json::json!({ "chat_id": chat_id, "action": action });
+        self.call::<serde_json::Value>("sendChatAction", &payload)
+            .await
+            .map(|_| ())
+    }
+
+    fn offset_store(&self) -> Option<&OffsetStore> {
+        self.offset_store.as_ref()
+    }
+}
+
+/// Telegram message IDs are integers; the bot passes them around as strings
+fn parse_message_id(message_id: &str) -> Result<i64> {
+    message_id
+        .parse()
+        .map_err(|_| BotError::telegram("Invalid message ID", None, message_id.to_string()))
+}
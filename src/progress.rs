@@ -0,0 +1,743 @@
+use crate::badge::{BADGES, Badge, level_for, next_level};
+use crate::mock::{MockSection, MockSession};
+use crate::storage::{PeriodStats, UserStats};
+use crate::weekly::WeeklyReport;
+use chrono::{Days, NaiveDate};
+
+/// Escapes text for safe inclusion in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats seconds as "45s" or "2m05s"
+fn format_seconds(secs: f64) -> String {
+    let secs = secs.round().max(0.0) as u64;
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Generates the HTML for a user's progress card
+pub fn generate_stats_card_html(name: Option<&str>, stats: &UserStats) -> String {
+    let accent_color = "#0068ff";
+    let title = match name {
+        Some(name) => format!("{}'s progress", escape_html(name)),
+        None => "Your progress".to_string(),
+    };
+
+    let (correct, graded) = stats
+        .by_type
+        .iter()
+        .fold((0, 0), |(c, g), (_, correct, graded)| {
+            (c + correct, g + graded)
+        });
+    let accuracy = match (correct * 100).checked_div(graded) {
+        Some(percent) => format!("{}%", percent),
+        None => "–".to_string(),
+    };
+    let pace = stats
+        .average_response_secs
+        .map(format_seconds)
+        .unwrap_or_else(|| "–".to_string());
+
+    let type_rows = stats
+        .by_type
+        .iter()
+        .map(|(question_type, correct, graded)| {
+            let percent = (correct * 100).checked_div(*graded).unwrap_or(0);
+            format!(
+                r#"<tr><td>{}</td><td>{}/{}</td><td><div class="bar"><div class="fill" style="width: {}%"></div></div></td><td>{}%</td></tr>"#,
+                question_type, correct, graded, percent, percent
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>GMAT Progress</title>
+    <script>
+        // No MathJax here, so the page is ready as soon as it loads
+        window.status = 'ready_to_print';
+    </script>
+    <style>
+        body {{
+            font-family: Georgia, 'Times New Roman', Times, serif;
+            max-width: 1000px;
+            margin: 0 auto;
+            padding: 30px;
+            background-color: #ffffff;
+            color: #333;
+        }}
+
+        .card-header {{
+            background: {};
+            color: white;
+            padding: 25px;
+            border-radius: 8px;
+            margin-bottom: 30px;
+        }}
+
+        .card-header h1 {{
+            font-size: 1.8em;
+            margin: 0;
+        }}
+
+        /* Table layout rather than flexbox, which wkhtmltoimage's WebKit lacks */
+        .totals {{
+            display: table;
+            width: 100%;
+            border-spacing: 20px 0;
+            margin: 0 -20px 30px;
+        }}
+
+        .total {{
+            display: table-cell;
+            width: 25%;
+            background: #f9f9f9;
+            padding: 20px;
+            text-align: center;
+        }}
+
+        .total .value {{
+            font-size: 2em;
+            font-weight: 700;
+            color: {};
+        }}
+
+        .total .label {{
+            font-size: 1em;
+            color: #7f8c8d;
+        }}
+
+        h3 {{
+            color: {};
+            font-size: 1.3em;
+        }}
+
+        table {{
+            border-collapse: collapse;
+            width: 100%;
+            font-size: 1.1em;
+        }}
+
+        td {{
+            padding: 12px;
+            border-bottom: 1px solid #eee;
+        }}
+
+        .bar {{
+            background: #eee;
+            height: 14px;
+            width: 400px;
+        }}
+
+        .fill {{
+            background: {};
+            height: 14px;
+        }}
+    </style>
+</head>
+<body>
+    <div class="card-header">
+        <h1>{}</h1>
+    </div>
+
+    <div class="totals">
+        <div class="total"><div class="value">{}</div><div class="label">Questions attempted</div></div>
+        <div class="total"><div class="value">{}</div><div class="label">Accuracy</div></div>
+        <div class="total"><div class="value">{}</div><div class="label">Current streak</div></div>
+        <div class="total"><div class="value">{}</div><div class="label">Average time</div></div>
+    </div>
+
+    <h3>Accuracy by type</h3>
+    <table>
+        {}
+    </table>
+</body>
+</html>
+    "#,
+        accent_color, // header background
+        accent_color, // total values
+        accent_color, // section title
+        accent_color, // accuracy bars
+        title,
+        stats.attempted,
+        accuracy,
+        stats.current_streak,
+        pace,
+        type_rows
+    )
+}
+
+/// Generates the HTML for a user's badge collection: their level, then every badge,
+/// with the ones not yet earned faded out
+pub fn generate_badges_card_html(
+    name: Option<&str>,
+    correct: u32,
+    earned: &[(&Badge, NaiveDate)],
+) -> String {
+    let accent_color = "#0068ff";
+    let title = match name {
+        Some(name) => format!("{}'s badges", escape_html(name)),
+        None => "Your badges".to_string(),
+    };
+
+    let level = level_for(correct);
+    let (next, percent) = match next_level(level) {
+        Some(next) => {
+            let span = next.min_correct - level.min_correct;
+            let percent = (correct - level.min_correct) * 100 / span;
+            let remaining = next.min_correct - correct;
+            let answers = if remaining == 1 { "answer" } else { "answers" };
+            (
+                format!(
+                    "{} more correct {} to level {}: {}",
+                    remaining, answers, next.number, next.name
+                ),
+                percent,
+            )
+        }
+        None => ("The highest level there is".to_string(), 100),
+    };
+
+    // Four to a row, as a table since wkhtmltoimage's WebKit lacks flexbox and grid
+    let cells = BADGES
+        .iter()
+        .map(|badge| {
+            let earned_on = earned
+                .iter()
+                .find(|(earned, _)| earned.id == badge.id)
+                .map(|(_, date)| *date);
+            let (class, status) = match earned_on {
+                Some(date) => ("badge", format!("Earned {}", date.format("%b %-d, %Y"))),
+                None => ("badge locked", "Not yet earned".to_string()),
+            };
+            format!(
+                r#"<td class="{}"><div class="icon">{}</div><div class="name">{}</div><div class="description">{}</div><div class="status">{}</div></td>"#,
+                class, badge.icon, badge.name, badge.description, status
+            )
+        })
+        .collect::<Vec<_>>();
+    let rows = cells
+        .chunks(4)
+        .map(|row| format!("<tr>{}</tr>", row.join("")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>GMAT Badges</title>
+    <script>
+        // No MathJax here, so the page is ready as soon as it loads
+        window.status = 'ready_to_print';
+    </script>
+    <style>
+        body {{
+            font-family: Georgia, 'Times New Roman', Times, serif;
+            max-width: 1000px;
+            margin: 0 auto;
+            padding: 30px;
+            background-color: #ffffff;
+            color: #333;
+        }}
+
+        .card-header {{
+            background: {};
+            color: white;
+            padding: 25px;
+            border-radius: 8px;
+            margin-bottom: 30px;
+        }}
+
+        .card-header h1 {{
+            font-size: 1.8em;
+            margin: 0;
+        }}
+
+        .level {{
+            background: #f9f9f9;
+            padding: 20px;
+            margin-bottom: 30px;
+        }}
+
+        .level .value {{
+            font-size: 1.6em;
+            font-weight: 700;
+            color: {};
+        }}
+
+        .level .next {{
+            color: #7f8c8d;
+            margin-top: 8px;
+        }}
+
+        .bar {{
+            background: #eee;
+            height: 14px;
+            margin-top: 12px;
+        }}
+
+        .fill {{
+            background: {};
+            height: 14px;
+        }}
+
+        table {{
+            border-collapse: separate;
+            border-spacing: 12px;
+            width: 100%;
+            margin: 0 -12px;
+        }}
+
+        .badge {{
+            width: 25%;
+            background: #f9f9f9;
+            padding: 16px;
+            text-align: center;
+            vertical-align: top;
+        }}
+
+        .badge.locked {{
+            opacity: 0.35;
+        }}
+
+        .icon {{
+            font-size: 2.4em;
+        }}
+
+        .name {{
+            font-weight: 700;
+            margin-top: 6px;
+        }}
+
+        .description, .status {{
+            font-size: 0.9em;
+            color: #7f8c8d;
+            margin-top: 4px;
+        }}
+    </style>
+</head>
+<body>
+    <div class="card-header">
+        <h1>{}</h1>
+    </div>
+
+    <div class="level">
+        <div class="value">Level {} · {}</div>
+        <div class="next">{} ({} correct so far)</div>
+        <div class="bar"><div class="fill" style="width: {}%"></div></div>
+    </div>
+
+    <table>
+        {}
+    </table>
+</body>
+</html>
+    "#,
+        accent_color, // header background
+        accent_color, // level
+        accent_color, // level bar
+        title,
+        level.number,
+        level.name,
+        next,
+        correct,
+        percent,
+        rows
+    )
+}
+
+/// Change from last week, e.g. "▲ 5 vs last week"; `unit` follows the number
+fn format_change(current: Option<u32>, previous: Option<u32>, unit: &str) -> String {
+    match (current, previous) {
+        (Some(current), Some(previous)) if current > previous => {
+            format!("▲ {}{} vs last week", current - previous, unit)
+        }
+        (Some(current), Some(previous)) if current < previous => {
+            format!("▼ {}{} vs last week", previous - current, unit)
+        }
+        (Some(_), Some(_)) => "Same as last week".to_string(),
+        _ => "Nothing to compare yet".to_string(),
+    }
+}
+
+/// Generates the HTML for the weekly progress report sent on Sundays
+pub fn generate_weekly_report_html(report: &WeeklyReport) -> String {
+    let accent_color = "#0068ff";
+    let week_end = report.week_start + Days::new(6);
+    let title = format!(
+        "Your week: {} – {}",
+        report.week_start.format("%b %-d"),
+        week_end.format("%b %-d")
+    );
+
+    let (this_week, last_week) = (&report.this_week, &report.last_week);
+    let percent = |accuracy: Option<u32>| {
+        accuracy
+            .map(|percent| format!("{}%", percent))
+            .unwrap_or_else(|| "–".to_string())
+    };
+    let attempted_change = format_change(
+        Some(this_week.attempted),
+        (last_week.attempted > 0).then_some(last_week.attempted),
+        "",
+    );
+    let accuracy_change = format_change(this_week.accuracy(), last_week.accuracy(), " pts");
+
+    // Every type answered in either week, this week's first
+    let mut types: Vec<_> = this_week.by_type.iter().map(|(t, _, _)| *t).collect();
+    for (question_type, _, _) in &last_week.by_type {
+        if !types.contains(question_type) {
+            types.push(*question_type);
+        }
+    }
+    let accuracy_of = |stats: &PeriodStats, question_type| {
+        stats
+            .by_type
+            .iter()
+            .find(|(t, _, _)| *t == question_type)
+            .map(|(_, correct, graded)| (*correct, *graded))
+    };
+    let type_rows = types
+        .iter()
+        .map(|question_type| {
+            let (correct, graded) = accuracy_of(this_week, *question_type).unwrap_or((0, 0));
+            let current = (correct * 100).checked_div(graded);
+            let previous = accuracy_of(last_week, *question_type)
+                .and_then(|(correct, graded)| (correct * 100).checked_div(graded));
+            format!(
+                r#"<tr><td>{}</td><td>{}/{}</td><td><div class="bar"><div class="fill" style="width: {}%"></div></div></td><td>{}</td><td class="change">{}</td></tr>"#,
+                question_type,
+                correct,
+                graded,
+                current.unwrap_or(0),
+                percent(current),
+                percent(previous)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>GMAT Weekly Report</title>
+    <script>
+        // No MathJax here, so the page is ready as soon as it loads
+        window.status = 'ready_to_print';
+    </script>
+    <style>
+        body {{
+            font-family: Georgia, 'Times New Roman', Times, serif;
+            max-width: 1000px;
+            margin: 0 auto;
+            padding: 30px;
+            background-color: #ffffff;
+            color: #333;
+        }}
+
+        .card-header {{
+            background: {};
+            color: white;
+            padding: 25px;
+            border-radius: 8px;
+            margin-bottom: 30px;
+        }}
+
+        .card-header h1 {{
+            font-size: 1.8em;
+            margin: 0;
+        }}
+
+        /* Table layout rather than flexbox, which wkhtmltoimage's WebKit lacks */
+        .totals {{
+            display: table;
+            width: 100%;
+            border-spacing: 20px 0;
+            margin: 0 -20px 30px;
+        }}
+
+        .total {{
+            display: table-cell;
+            width: 33%;
+            background: #f9f9f9;
+            padding: 20px;
+            text-align: center;
+        }}
+
+        .total .value {{
+            font-size: 2em;
+            font-weight: 700;
+            color: {};
+        }}
+
+        .total .label {{
+            font-size: 1em;
+            color: #7f8c8d;
+        }}
+
+        .change {{
+            font-size: 0.9em;
+            color: #7f8c8d;
+        }}
+
+        h3 {{
+            color: {};
+            font-size: 1.3em;
+        }}
+
+        table {{
+            border-collapse: collapse;
+            width: 100%;
+            font-size: 1.1em;
+        }}
+
+        th {{
+            text-align: left;
+            padding: 12px;
+            color: #7f8c8d;
+            font-weight: normal;
+        }}
+
+        td {{
+            padding: 12px;
+            border-bottom: 1px solid #eee;
+        }}
+
+        .bar {{
+            background: #eee;
+            height: 14px;
+            width: 300px;
+        }}
+
+        .fill {{
+            background: {};
+            height: 14px;
+        }}
+    </style>
+</head>
+<body>
+    <div class="card-header">
+        <h1>{}</h1>
+    </div>
+
+    <div class="totals">
+        <div class="total"><div class="value">{}</div><div class="label">Questions attempted</div><div class="change">{}</div></div>
+        <div class="total"><div class="value">{}</div><div class="label">Accuracy</div><div class="change">{}</div></div>
+        <div class="total"><div class="value">{}</div><div class="label">Day streak</div></div>
+    </div>
+
+    <h3>Accuracy by type</h3>
+    <table>
+        <tr><th>Type</th><th>Correct</th><th></th><th>This week</th><th>Last week</th></tr>
+        {}
+    </table>
+</body>
+</html>
+    "#,
+        accent_color, // header background
+        accent_color, // total values
+        accent_color, // section title
+        accent_color, // accuracy bars
+        title,
+        this_week.attempted,
+        attempted_change,
+        percent(this_week.accuracy()),
+        accuracy_change,
+        report.streak,
+        type_rows
+    )
+}
+
+/// Generates the HTML for the results of a finished mock test
+pub fn generate_mock_report_html(session: &MockSession) -> String {
+    let accent_color = "#0068ff";
+    let (correct, asked) = MockSection::ALL
+        .iter()
+        .map(|section| session.section_score(*section))
+        .fold((0, 0), |(c, a), (correct, asked)| (c + correct, a + asked));
+    let section_totals = MockSection::ALL
+        .iter()
+        .filter_map(|section| {
+            let (correct, asked) = session.section_score(*section);
+            (asked > 0).then(|| {
+                format!(
+                    r#"<div class="total"><div class="value">{}/{}</div><div class="label">{}</div></div>"#,
+                    correct, asked, section
+                )
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n        ");
+
+    let letter = |choice: Option<char>| choice.map_or("–".to_string(), String::from);
+    let mut question_rows = session
+        .answers
+        .iter()
+        .enumerate()
+        .map(|(i, answer)| {
+            let (mark, class) = match (answer.answer, answer.correct()) {
+                (None, _) => ("Timed out", "wrong"),
+                (_, Some(true)) => ("Correct", "right"),
+                (_, Some(false)) => ("Wrong", "wrong"),
+                (_, None) => ("Ungraded", ""),
+            };
+            format!(
+                r#"<tr><td>{}</td><td>#{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td><td class="{}">{}</td></tr>"#,
+                i + 1,
+                escape_html(&answer.content.id),
+                answer.question_type,
+                letter(answer.answer),
+                letter(answer.correct_answer),
+                format_seconds(answer.elapsed.as_secs_f64()),
+                class,
+                mark
+            )
+        })
+        .collect::<Vec<_>>();
+    for (i, question_type) in session.plan.iter().enumerate().skip(session.answers.len()) {
+        question_rows.push(format!(
+            r#"<tr><td>{}</td><td>–</td><td>{:?}</td><td>–</td><td>–</td><td>–</td><td class="wrong">Not reached</td></tr>"#,
+            i + 1,
+            question_type
+        ));
+    }
+
+    format!(
+        r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>GMAT Mock Test</title>
+    <script>
+        // No MathJax here, so the page is ready as soon as it loads
+        window.status = 'ready_to_print';
+    </script>
+    <style>
+        body {{
+            font-family: Georgia, 'Times New Roman', Times, serif;
+            max-width: 1000px;
+            margin: 0 auto;
+            padding: 30px;
+            background-color: #ffffff;
+            color: #333;
+        }}
+
+        .card-header {{
+            background: {};
+            color: white;
+            padding: 25px;
+            border-radius: 8px;
+            margin-bottom: 30px;
+        }}
+
+        .card-header h1 {{
+            font-size: 1.8em;
+            margin: 0;
+        }}
+
+        /* Table layout rather than flexbox, which wkhtmltoimage's WebKit lacks */
+        .totals {{
+            display: table;
+            width: 100%;
+            border-spacing: 20px 0;
+            margin: 0 -20px 30px;
+        }}
+
+        .total {{
+            display: table-cell;
+            background: #f9f9f9;
+            padding: 20px;
+            text-align: center;
+        }}
+
+        .total .value {{
+            font-size: 2em;
+            font-weight: 700;
+            color: {};
+        }}
+
+        .total .label {{
+            font-size: 1em;
+            color: #7f8c8d;
+        }}
+
+        h3 {{
+            color: {};
+            font-size: 1.3em;
+        }}
+
+        table {{
+            border-collapse: collapse;
+            width: 100%;
+            font-size: 1.1em;
+        }}
+
+        th {{
+            text-align: left;
+            padding: 10px;
+            color: #7f8c8d;
+            font-weight: normal;
+        }}
+
+        td {{
+            padding: 10px;
+            border-bottom: 1px solid #eee;
+        }}
+
+        .right {{
+            color: #27ae60;
+        }}
+
+        .wrong {{
+            color: #c0392b;
+        }}
+    </style>
+</head>
+<body>
+    <div class="card-header">
+        <h1>Mock test results</h1>
+    </div>
+
+    <div class="totals">
+        <div class="total"><div class="value">{}/{}</div><div class="label">Score</div></div>
+        {}
+        <div class="total"><div class="value">{}</div><div class="label">Time</div></div>
+    </div>
+
+    <h3>Questions</h3>
+    <table>
+        <tr><th></th><th>Question</th><th>Type</th><th>You</th><th>Answer</th><th>Time</th><th></th></tr>
+        {}
+    </table>
+</body>
+</html>
+    "#,
+        accent_color, // header background
+        accent_color, // total values
+        accent_color, // section title
+        correct,
+        asked,
+        section_totals,
+        format_seconds(session.total_time().as_secs_f64()),
+        question_rows.join("\n")
+    )
+}
@@ -0,0 +1,73 @@
+use crate::{BotError, Difficulty, QuestionType, Result};
+
+/// Band a user is served in a type before they've levelled up in it
+pub const STARTING_BAND: Difficulty = Difficulty::Easy;
+
+/// When a user moves up to harder questions of a type, for `serve --progression`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progression {
+    /// Percentage of the judged answers that must be right
+    pub accuracy: u32,
+    /// Graded answers in the current band the accuracy is judged on, newest first
+    pub answers: u32,
+}
+
+impl Default for Progression {
+    fn default() -> Self {
+        Self {
+            accuracy: 80,
+            answers: 10,
+        }
+    }
+}
+
+impl Progression {
+    /// The flags are range-checked by clap, but the config file isn't
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=100).contains(&self.accuracy) || self.answers == 0 {
+            return Err(BotError::Config(format!(
+                "Progression needs an accuracy between 1 and 100 and at least one answer, got {}% over {}",
+                self.accuracy, self.answers
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether answers in the current band, newest first, earn the next one: the last
+    /// `answers` of them must all be there and `accuracy`% right
+    pub fn levels_up(&self, results: &[bool]) -> bool {
+        let judged = self.answers as usize;
+        if results.len() < judged {
+            return false;
+        }
+        let correct = results[..judged].iter().filter(|correct| **correct).count() as u32;
+        correct * 100 >= self.accuracy * self.answers
+    }
+}
+
+/// The band after `band`, or `None` at the top
+pub fn next_band(band: Difficulty) -> Option<Difficulty> {
+    match band {
+        Difficulty::Easy => Some(Difficulty::Medium),
+        Difficulty::Medium => Some(Difficulty::Hard),
+        Difficulty::Hard => None,
+    }
+}
+
+/// Announces that the user moved up to `band` in `question_type`
+pub fn format_band_up(
+    name: Option<&str>,
+    question_type: QuestionType,
+    band: Difficulty,
+    progression: &Progression,
+) -> String {
+    let who = name.unwrap_or("You");
+    let next = match next_band(band) {
+        Some(_) => " Keep it up to unlock the next level.",
+        None => " That's the hardest level there is!",
+    };
+    format!(
+        "📈 Level up! {} got at least {}% of the last {} {} questions right, so {} ones come next.{}",
+        who, progression.accuracy, progression.answers, question_type, band, next
+    )
+}
@@ -0,0 +1,39 @@
+use crate::{DailyPost, MuteHours};
+use chrono::NaiveDate;
+
+/// Preference key a user's do-not-disturb window is stored under, e.g. "22:00-07:00"
+pub const QUIET_HOURS_PREFERENCE: &str = "quiet_hours";
+
+/// A "dnd" command: show the window, set it, or turn it off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DndCommand {
+    Show,
+    Set(MuteHours),
+    Off,
+}
+
+/// Parses "dnd", "dnd 22:00-07:00" and "dnd off"; a malformed window is an `Err` with the usage
+pub fn parse_dnd_command(text: &str) -> Option<Result<DndCommand, String>> {
+    let (command, value) = text
+        .trim()
+        .split_once(char::is_whitespace)
+        .map(|(c, v)| (c, v.trim()))
+        .unwrap_or((text.trim(), ""));
+    if !command.eq_ignore_ascii_case("dnd") {
+        return None;
+    }
+    Some(match value {
+        "" => Ok(DndCommand::Show),
+        off if off.eq_ignore_ascii_case("off") => Ok(DndCommand::Off),
+        window => window.parse().map(DndCommand::Set),
+    })
+}
+
+/// A proactive message held back by quiet hours, sent once they end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueuedPost {
+    /// One of the question-of-the-day posts for `date`
+    Daily(DailyPost, NaiveDate),
+    /// An admin broadcast: text, or a question ID
+    Broadcast(String),
+}
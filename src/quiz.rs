@@ -0,0 +1,165 @@
+use crate::{QuestionType, parse_question_type};
+use std::time::{Duration, Instant};
+
+/// Upper bound for "quiz N" so a typo can't start a 1000-question session
+pub const MAX_QUIZ_QUESTIONS: usize = 20;
+
+/// A graded question within a quiz
+#[derive(Debug, Clone)]
+pub struct QuizAnswer {
+    pub question_id: String,
+    pub question_type: QuestionType,
+    /// `None` when the official answer couldn't be determined
+    pub correct: Option<bool>,
+    pub elapsed: Duration,
+}
+
+/// An in-progress quiz for one chat
+#[derive(Debug)]
+pub struct QuizSession {
+    /// Restricts the quiz to one type; `None` mixes all supported types
+    pub question_type: Option<QuestionType>,
+    pub total: usize,
+    pub answers: Vec<QuizAnswer>,
+    /// When the current question was delivered
+    pub question_started: Instant,
+}
+
+impl QuizSession {
+    pub fn new(total: usize, question_type: Option<QuestionType>) -> Self {
+        Self {
+            question_type,
+            total,
+            answers: Vec::new(),
+            question_started: Instant::now(),
+        }
+    }
+
+    /// Records the answer to the current question, timed from its delivery
+    pub fn record(
+        &mut self,
+        question_id: &str,
+        question_type: QuestionType,
+        correct: Option<bool>,
+    ) {
+        self.answers.push(QuizAnswer {
+            question_id: question_id.to_string(),
+            question_type,
+            correct,
+            elapsed: self.question_started.elapsed(),
+        });
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.answers.len() >= self.total
+    }
+
+    /// 1-based number of the question about to be sent
+    pub fn next_number(&self) -> usize {
+        self.answers.len() + 1
+    }
+
+    /// Question type with the lowest accuracy, with its (correct, graded) counts
+    pub fn weakest_type(&self) -> Option<(QuestionType, usize, usize)> {
+        let mut by_type: Vec<(QuestionType, usize, usize)> = Vec::new();
+        for answer in &self.answers {
+            let Some(correct) = answer.correct else {
+                continue;
+            };
+            match by_type
+                .iter_mut()
+                .find(|(t, _, _)| *t == answer.question_type)
+            {
+                Some((_, right, graded)) => {
+                    *right += correct as usize;
+                    *graded += 1;
+                }
+                None => by_type.push((answer.question_type, correct as usize, 1)),
+            }
+        }
+        by_type
+            .into_iter()
+            .min_by(|(_, a_right, a_graded), (_, b_right, b_graded)| {
+                (*a_right as f64 / *a_graded as f64)
+                    .total_cmp(&(*b_right as f64 / *b_graded as f64))
+            })
+    }
+
+    /// End-of-quiz report: score, time per question and weakest type
+    pub fn summary(&self) -> String {
+        let correct = self
+            .answers
+            .iter()
+            .filter(|a| a.correct == Some(true))
+            .count();
+        let graded = self.answers.iter().filter(|a| a.correct.is_some()).count();
+        let mut summary = format!(
+            "🏁 Quiz finished! Score: {}/{}",
+            correct,
+            self.answers.len()
+        );
+        if let Some(percent) = (correct * 100).checked_div(graded) {
+            summary.push_str(&format!(" ({}%)", percent));
+        }
+
+        summary.push_str("\n\n⏱️ Time per question:");
+        for (i, answer) in self.answers.iter().enumerate() {
+            let mark = match answer.correct {
+                Some(true) => "✅",
+                Some(false) => "❌",
+                None => "❔",
+            };
+            summary.push_str(&format!(
+                "\n{}. #{} {} {}",
+                i + 1,
+                answer.question_id,
+                format_duration(answer.elapsed),
+                mark
+            ));
+        }
+
+        if !self.answers.is_empty() {
+            let total: Duration = self.answers.iter().map(|a| a.elapsed).sum();
+            summary.push_str(&format!(
+                "\nAverage: {}",
+                format_duration(total / self.answers.len() as u32)
+            ));
+        }
+
+        if let Some((question_type, right, graded)) = self.weakest_type() {
+            summary.push_str(&format!(
+                "\n\n📉 Weakest type: {} ({}/{})",
+                question_type, right, graded
+            ));
+        }
+        summary
+    }
+}
+
+/// Parses "quiz 5 ps", "quiz 5", "quiz ps" or "quiz" into (count, type)
+pub fn parse_quiz_command(text: &str) -> Option<(usize, Option<QuestionType>)> {
+    let mut words = text.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("quiz") {
+        return None;
+    }
+
+    let mut count = 5;
+    let mut question_type = None;
+    for word in words {
+        if let Ok(n) = word.parse::<usize>() {
+            count = n.clamp(1, MAX_QUIZ_QUESTIONS);
+        } else {
+            question_type = Some(parse_question_type(word)?);
+        }
+    }
+    Some((count, question_type))
+}
+
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full (idle) ones are pruned
+const MAX_IDLE_BUCKETS: usize = 1000;
+
+/// What to do with a message after checking the sender's bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allow,
+    /// Over the limit for the first time since the last allowed message; tell them once
+    Warn,
+    /// Still over the limit and already warned; ignore silently
+    Drop,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    warned: bool,
+}
+
+/// Token bucket per sender: `limit` messages per `interval`, refilled continuously
+pub struct RateLimiter {
+    limit: u32,
+    interval: Duration,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, interval: Duration) -> Self {
+        Self {
+            limit: limit.max(1),
+            interval,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token from `key`'s bucket if one is available
+    pub fn check(&self, key: &str) -> RateDecision {
+        let now = Instant::now();
+        let capacity = f64::from(self.limit);
+        let refill_per_sec = capacity / self.interval.as_secs_f64().max(f64::EPSILON);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + elapsed * refill_per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            warned: false,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.warned = false;
+            RateDecision::Allow
+        } else if bucket.warned {
+            RateDecision::Drop
+        } else {
+            bucket.warned = true;
+            RateDecision::Warn
+        }
+    }
+}
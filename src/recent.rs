@@ -0,0 +1,149 @@
+use crate::Result;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::warn;
+
+/// How long a question isn't sent to the same chat again unless configured otherwise
+pub const DEFAULT_RECENT_WINDOW: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// Most questions remembered per chat; the least recently sent are forgotten first
+pub const DEFAULT_RECENT_CAPACITY: usize = 1000;
+
+/// How often the polling service saves sends recorded since the last save
+pub const RECENT_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default location of persisted recent sends, `~/.gmat-zalo-bot/recent.json`
+pub fn default_recent_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".gmat-zalo-bot")
+        .join("recent.json")
+}
+
+/// Questions each chat was sent within a rolling window, so none repeats too soon even
+/// when no history is stored; kept in memory and, when given a path, saved by
+/// [`RecentSends::flush`] and when dropped
+pub struct RecentSends {
+    window: Duration,
+    capacity: usize,
+    path: Option<PathBuf>,
+    /// Per chat, (question ID, unix seconds sent) oldest first
+    chats: Mutex<HashMap<String, VecDeque<(String, i64)>>>,
+    /// Whether sends were recorded since the last save
+    dirty: AtomicBool,
+    /// Held while writing, so an older copy never lands after a newer one
+    saving: Mutex<()>,
+}
+
+impl Default for RecentSends {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_WINDOW, DEFAULT_RECENT_CAPACITY)
+    }
+}
+
+impl RecentSends {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity: capacity.max(1),
+            path: None,
+            chats: Mutex::new(HashMap::new()),
+            dirty: AtomicBool::new(false),
+            saving: Mutex::new(()),
+        }
+    }
+
+    /// Loads the sends saved at `path` and saves there from now on; a missing or
+    /// unreadable file starts fresh
+    pub fn load(path: impl AsRef<Path>, window: Duration, capacity: usize) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let chats = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable recent sends {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        let mut recent_sends = Self::new(window, capacity);
+        recent_sends.path = Some(path);
+        recent_sends.chats = Mutex::new(chats);
+        recent_sends
+    }
+
+    /// Remembers that `chat_id` was just sent `question_id`
+    pub fn record(&self, chat_id: &str, question_id: &str) {
+        let now = chrono::Utc::now().timestamp();
+        let mut chats = self.chats.lock().unwrap();
+        let sends = chats.entry(chat_id.to_string()).or_default();
+        sends.retain(|(id, _)| id != question_id);
+        sends.push_back((question_id.to_string(), now));
+        while sends.len() > self.capacity {
+            sends.pop_front();
+        }
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Forgets everything sent to `chat_id`, saving the file without it right away
+    pub fn forget(&self, chat_id: &str) {
+        if self.chats.lock().unwrap().remove(chat_id).is_some() {
+            self.dirty.store(true, Ordering::Release);
+            if let Err(e) = self.flush() {
+                warn!("Failed to save recent sends: {}", e);
+            }
+        }
+    }
+
+    /// Saves the sends recorded since the last save, if there is a path to save to.
+    /// The file is written outside the lock `record` takes, so sends don't wait on disk
+    pub fn flush(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let _saving = self.saving.lock().unwrap();
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let json = serde_json::to_vec(&*self.chats.lock().unwrap())?;
+        save(path, &json).inspect_err(|_| self.dirty.store(true, Ordering::Release))
+    }
+
+    /// IDs of the questions `chat_id` was sent within the window
+    pub fn recent_ids(&self, chat_id: &str) -> HashSet<String> {
+        let cutoff = chrono::Utc::now().timestamp() - self.window.as_secs() as i64;
+        self.chats
+            .lock()
+            .unwrap()
+            .get(chat_id)
+            .map(|sends| {
+                sends
+                    .iter()
+                    .filter(|(_, sent_at)| *sent_at > cutoff)
+                    .map(|(id, _)| id.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for RecentSends {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("Failed to save recent sends: {}", e);
+        }
+    }
+}
+
+fn save(path: &Path, json: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Write then rename so a crash mid-write keeps the previous copy
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, json)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use crate::{GmatDatabase, QuestionType};
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How often `serve` fetches the question index again unless configured otherwise
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The question index a running bot answers from, replaced whole when a refresh finds changes
+pub struct LiveDatabase {
+    current: RwLock<Arc<GmatDatabase>>,
+}
+
+impl LiveDatabase {
+    pub fn new(database: GmatDatabase) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(database)),
+        }
+    }
+
+    /// The index as it is now; a message being handled keeps its copy through a refresh
+    pub fn current(&self) -> Arc<GmatDatabase> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, database: GmatDatabase) {
+        *self.current.write().unwrap() = Arc::new(database);
+    }
+}
+
+/// Questions listed in one copy of the index but not the other
+#[derive(Debug, Default)]
+pub struct DatabaseDiff {
+    pub added: Vec<(QuestionType, String)>,
+    pub removed: Vec<(QuestionType, String)>,
+}
+
+impl DatabaseDiff {
+    pub fn between(old: &GmatDatabase, new: &GmatDatabase) -> Self {
+        let mut diff = DatabaseDiff::default();
+        for question_type in QuestionType::value_variants() {
+            let old_ids: HashSet<&String> =
+                old.get_questions_by_type(question_type).iter().collect();
+            let new_ids: HashSet<&String> =
+                new.get_questions_by_type(question_type).iter().collect();
+            diff.added.extend(
+                new.get_questions_by_type(question_type)
+                    .iter()
+                    .filter(|id| !old_ids.contains(id))
+                    .map(|id| (*question_type, id.clone())),
+            );
+            diff.removed.extend(
+                old.get_questions_by_type(question_type)
+                    .iter()
+                    .filter(|id| !new_ids.contains(id))
+                    .map(|id| (*question_type, id.clone())),
+            );
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
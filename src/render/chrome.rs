@@ -0,0 +1,226 @@
+use super::{ImageFormat, READY_STATUS, RenderOptions, Renderer, page_breaks, page_path};
+use crate::{BotError, Result};
+use headless_chrome::protocol::cdp::Page::{CaptureScreenshotFormatOption, Viewport};
+use headless_chrome::types::{Bounds, PrintToPdfOptions};
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Renders with a headless Chrome/Chromium instance
+///
+/// The browser binary is located automatically, or via the `CHROME` environment variable.
+pub struct ChromeRenderer {
+    pub options: RenderOptions,
+    /// How long to wait for MathJax to signal it is done before capturing anyway
+    pub ready_timeout: Duration,
+}
+
+impl ChromeRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for ChromeRenderer {
+    fn default() -> Self {
+        Self {
+            options: RenderOptions::default(),
+            ready_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Renderer for ChromeRenderer {
+    async fn check_available(&self) -> Result<()> {
+        headless_chrome::browser::default_executable()
+            .map(|_| ())
+            .map_err(|e| {
+                BotError::Render(format!(
+                    "Chrome/Chromium was not found: {}. Install it or set the CHROME environment variable to its path.",
+                    e
+                ))
+            })
+    }
+
+    fn options(&self) -> RenderOptions {
+        self.options
+    }
+
+    async fn render(&self, html_path: &Path, output_path: &Path) -> Result<()> {
+        let html_path = html_path.canonicalize()?;
+        let output_path = output_path.to_path_buf();
+        let (options, ready_timeout) = (self.options, self.ready_timeout);
+
+        // headless_chrome is synchronous, keep it off the async worker threads
+        tokio::task::spawn_blocking(move || {
+            capture(&html_path, &output_path, options, ready_timeout, false)
+        })
+        .await
+        .map_err(|e| BotError::Render(format!("Chrome render task failed: {}", e)))?
+        .map(|_| ())
+        .map_err(|e| BotError::Render(format!("Chrome render failed: {}", e)))
+    }
+
+    async fn render_pages(&self, html_path: &Path, output_path: &Path) -> Result<Vec<PathBuf>> {
+        let html_path = html_path.canonicalize()?;
+        let output_path = output_path.to_path_buf();
+        let (options, ready_timeout) = (self.options, self.ready_timeout);
+
+        tokio::task::spawn_blocking(move || {
+            capture(&html_path, &output_path, options, ready_timeout, true)
+        })
+        .await
+        .map_err(|e| BotError::Render(format!("Chrome render task failed: {}", e)))?
+        .map_err(|e| BotError::Render(format!("Chrome render failed: {}", e)))
+    }
+
+    async fn render_pdf(&self, html_path: &Path, output_path: &Path) -> Result<()> {
+        let html_path = html_path.canonicalize()?;
+        let output_path = output_path.to_path_buf();
+        let (options, ready_timeout) = (self.options, self.ready_timeout);
+
+        tokio::task::spawn_blocking(move || print(&html_path, &output_path, options, ready_timeout))
+            .await
+            .map_err(|e| BotError::Render(format!("Chrome PDF task failed: {}", e)))?
+            .map_err(|e| BotError::Render(format!("Chrome PDF failed: {}", e)))
+    }
+}
+
+/// Opens the page in a new tab and waits for it to signal ready
+fn open_ready_tab(
+    browser: &Browser,
+    html_path: &Path,
+    ready_timeout: Duration,
+) -> anyhow::Result<Arc<Tab>> {
+    let tab = browser.new_tab()?;
+    tab.navigate_to(&format!("file://{}", html_path.display()))?
+        .wait_until_navigated()?;
+
+    // Equivalent of wkhtmltoimage's --window-status: wait until MathJax marks the page ready
+    let deadline = Instant::now() + ready_timeout;
+    loop {
+        let status = tab.evaluate("window.status", false)?.value;
+        if status.as_ref().and_then(|v| v.as_str()) == Some(READY_STATUS) {
+            break;
+        }
+        if Instant::now() >= deadline {
+            warn!("Page did not signal ready in time, capturing anyway");
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(tab)
+}
+
+fn launch(options: RenderOptions) -> anyhow::Result<Browser> {
+    // The scale factor makes screenshots sharper without changing the page's layout width
+    let scale_factor = OsString::from(format!("--force-device-scale-factor={}", options.scale));
+    Browser::new(
+        LaunchOptions::default_builder()
+            .window_size(Some((options.width, 800)))
+            .args(vec![scale_factor.as_os_str()])
+            .build()?,
+    )
+}
+
+fn print(
+    html_path: &Path,
+    output_path: &Path,
+    options: RenderOptions,
+    ready_timeout: Duration,
+) -> anyhow::Result<()> {
+    let browser = launch(options)?;
+    let tab = open_ready_tab(&browser, html_path, ready_timeout)?;
+    let pdf = tab.print_to_pdf(Some(PrintToPdfOptions {
+        print_background: Some(true),
+        ..Default::default()
+    }))?;
+    std::fs::write(output_path, pdf)?;
+    Ok(())
+}
+
+/// Offsets where a line of text or a picture ends, leaving out any that would cut through
+/// typeset math or an image
+const LINE_ENDS_SCRIPT: &str = "(() => {
+    const y = window.scrollY;
+    const solid = Array.from(document.body.querySelectorAll('mjx-container, img, svg'),
+        e => e.getBoundingClientRect());
+    const ends = solid.map(r => r.bottom + y);
+    const walker = document.createTreeWalker(document.body, NodeFilter.SHOW_TEXT);
+    const range = document.createRange();
+    while (walker.nextNode()) {
+        range.selectNodeContents(walker.currentNode);
+        for (const rect of range.getClientRects()) ends.push(rect.bottom + y);
+    }
+    return JSON.stringify(ends.filter(end =>
+        !solid.some(r => r.top + y < end && end < r.bottom + y)));
+})()";
+
+/// Screenshots the page, cut into pages when `split` is set and it is taller than the maximum
+fn capture(
+    html_path: &Path,
+    output_path: &Path,
+    options: RenderOptions,
+    ready_timeout: Duration,
+    split: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let browser = launch(options)?;
+    let tab = open_ready_tab(&browser, html_path, ready_timeout)?;
+
+    // Grow the window to the full page height so the screenshot isn't cut off
+    let height = tab
+        .evaluate("document.documentElement.scrollHeight", false)?
+        .value
+        .and_then(|v| v.as_f64())
+        .unwrap_or(800.0);
+    tab.set_bounds(Bounds::Normal {
+        left: Some(0),
+        top: Some(0),
+        width: Some(options.width as f64),
+        height: Some(height),
+    })?;
+
+    let format = match options.format {
+        ImageFormat::Png => CaptureScreenshotFormatOption::Png,
+        ImageFormat::Jpg => CaptureScreenshotFormatOption::Jpeg,
+        ImageFormat::Webp => CaptureScreenshotFormatOption::Webp,
+    };
+    let max_height = options.max_height.filter(|_| split).map(f64::from);
+    let Some(max_height) = max_height.filter(|&max_height| height > max_height) else {
+        let image = tab.capture_screenshot(format, Some(options.quality), None, true)?;
+        std::fs::write(output_path, image)?;
+        return Ok(vec![output_path.to_path_buf()]);
+    };
+
+    let line_ends: Vec<f64> = tab
+        .evaluate(LINE_ENDS_SCRIPT, false)?
+        .value
+        .and_then(|v| v.as_str().and_then(|json| serde_json::from_str(json).ok()))
+        .unwrap_or_default();
+    let mut pages = Vec::new();
+    for (i, (top, bottom)) in page_breaks(height, max_height, &line_ends)
+        .into_iter()
+        .enumerate()
+    {
+        let clip = Viewport {
+            x: 0.0,
+            y: top,
+            width: options.width as f64,
+            height: bottom - top,
+            scale: 1.0,
+        };
+        let image =
+            tab.capture_screenshot(format.clone(), Some(options.quality), Some(clip), true)?;
+        let page = page_path(output_path, i);
+        std::fs::write(&page, image)?;
+        pages.push(page);
+    }
+    Ok(pages)
+}
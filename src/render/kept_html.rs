@@ -0,0 +1,91 @@
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// File in the output directory listing the pages kept with `--keep-html`, one JSON record per line
+pub const HTML_INDEX_FILE: &str = "html_index.jsonl";
+
+/// A generated question page and the images rendered from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeptHtml {
+    pub question_id: String,
+    pub show_explanations: bool,
+    pub theme: String,
+    pub html_path: PathBuf,
+    pub image_paths: Vec<PathBuf>,
+    pub rendered_at: DateTime<Utc>,
+}
+
+/// Saves `html` in `dir` and appends which images came from it to [`HTML_INDEX_FILE`] there
+///
+/// The file is named after the image at `output_path`, the theme, whether explanations
+/// were shown and the time, e.g. `question_42_dark_explained_20261016T093000123.html`,
+/// so no render replaces another's page.
+pub fn keep_html(
+    html: &str,
+    dir: &Path,
+    output_path: &Path,
+    question_id: &str,
+    show_explanations: bool,
+    theme: &str,
+    image_paths: &[PathBuf],
+) -> Result<KeptHtml> {
+    fs::create_dir_all(dir)?;
+    let rendered_at = Utc::now();
+    let stem = format!(
+        "{}_{}_{}_{}",
+        output_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy(),
+        theme,
+        if show_explanations {
+            "explained"
+        } else {
+            "plain"
+        },
+        rendered_at.format("%Y%m%dT%H%M%S%3f")
+    );
+    let html_path = create_unique(dir, &stem, html)?;
+    let record = KeptHtml {
+        question_id: question_id.to_string(),
+        show_explanations,
+        theme: theme.to_string(),
+        html_path,
+        image_paths: image_paths.to_vec(),
+        rendered_at,
+    };
+
+    // One short append per record, so concurrent renders don't interleave lines
+    let mut line = serde_json::to_string(&record)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(HTML_INDEX_FILE))?
+        .write_all(line.as_bytes())?;
+    Ok(record)
+}
+
+/// Writes `contents` to `{stem}.html` in `dir`, or `{stem}_2.html` and so on when a render
+/// in the same millisecond got there first
+fn create_unique(dir: &Path, stem: &str, contents: &str) -> Result<PathBuf> {
+    for sequence in 1.. {
+        let path = match sequence {
+            1 => dir.join(format!("{}.html", stem)),
+            _ => dir.join(format!("{}_{}.html", stem, sequence)),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!("ran out of sequence numbers")
+}
@@ -0,0 +1,209 @@
+use crate::{BotError, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub mod chrome;
+pub mod kept_html;
+pub mod svg;
+pub mod text;
+pub mod wkhtmltoimage;
+
+pub use chrome::ChromeRenderer;
+pub use kept_html::{HTML_INDEX_FILE, KeptHtml, keep_html};
+pub use svg::SvgRenderer;
+pub use text::question_text;
+pub use wkhtmltoimage::{WkhtmltoimageRenderer, check_wkhtmltoimage};
+
+/// Value the generated page assigns to `window.status` once MathJax has finished typesetting
+pub const READY_STATUS: &str = "ready_to_print";
+
+/// How long an external renderer may run on one page unless configured otherwise
+pub const DEFAULT_RENDER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// File format of rendered images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    #[value(alias = "jpeg")]
+    #[serde(alias = "jpeg")]
+    Jpg,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+
+    /// Format of an encoded image, read from its leading bytes
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG") {
+            Some(ImageFormat::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpg)
+        } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+            Some(ImageFormat::Webp)
+        } else {
+            None
+        }
+    }
+
+    /// MIME type of an encoded image, assuming PNG when the bytes aren't recognised
+    pub fn mime_type_of(bytes: &[u8]) -> &'static str {
+        Self::detect(bytes).unwrap_or(ImageFormat::Png).mime_type()
+    }
+}
+
+/// Size and encoding of rendered images
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    /// Page width the question is laid out at, in CSS pixels
+    pub width: u32,
+    /// JPEG/WebP quality from 1 to 100; PNG ignores it
+    pub quality: u32,
+    pub format: ImageFormat,
+    /// Device pixels per CSS pixel, so a 1.5 scale turns an 800px page into a 1200px image
+    pub scale: f64,
+    /// Tallest an image may be, in CSS pixels, before the page is split into several
+    pub max_height: Option<u32>,
+}
+
+impl RenderOptions {
+    /// Images sent to chats: laid out narrow enough to read on a phone, and small to download
+    pub const CHAT: Self = Self {
+        width: 800,
+        quality: 70,
+        format: ImageFormat::Jpg,
+        scale: 1.5,
+        max_height: Some(2000),
+    };
+
+    /// Images kept on disk by `render`, `render-all` and `export`: wider, sharper and lossless
+    pub const EXPORT: Self = Self {
+        width: 1200,
+        quality: 90,
+        format: ImageFormat::Png,
+        scale: 2.0,
+        max_height: None,
+    };
+
+    /// Width of the finished image in device pixels
+    pub fn pixel_width(&self) -> u32 {
+        (self.width as f64 * self.scale).round() as u32
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self::CHAT
+    }
+}
+
+/// Turns a generated question page into an image
+#[async_trait::async_trait]
+pub trait Renderer: Send + Sync {
+    /// Fails early with an installation hint when the backend isn't available
+    async fn check_available(&self) -> Result<()>;
+
+    /// Size and format of the images this renderer writes
+    fn options(&self) -> RenderOptions;
+
+    /// Renders the HTML page at `html_path` into an image at `output_path`
+    async fn render(&self, html_path: &Path, output_path: &Path) -> Result<()>;
+
+    /// Like [`Renderer::render`], but a page taller than [`RenderOptions::max_height`]
+    /// is cut into several images named after `output_path` (see [`page_path`]).
+    /// Returns the images top to bottom.
+    async fn render_pages(&self, html_path: &Path, output_path: &Path) -> Result<Vec<PathBuf>> {
+        self.render(html_path, output_path).await?;
+        Ok(vec![output_path.to_path_buf()])
+    }
+
+    /// Prints the HTML page at `html_path` into a paginated PDF at `output_path`
+    async fn render_pdf(&self, _html_path: &Path, _output_path: &Path) -> Result<()> {
+        Err(BotError::Render(
+            "This renderer can't produce PDFs. Use --renderer wkhtmltoimage (needs wkhtmltopdf) or chrome.".to_string(),
+        ))
+    }
+}
+
+/// Where page `index` (0-based) of a split image goes, e.g. `question_12-p2.jpg` for the second
+pub fn page_path(output_path: &Path, index: usize) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match output_path.extension() {
+        Some(extension) => format!("{}-p{}.{}", stem, index + 1, extension.to_string_lossy()),
+        None => format!("{}-p{}", stem, index + 1),
+    };
+    output_path.with_file_name(name)
+}
+
+/// Splits a page `height` tall into (top, bottom) slices no taller than `max_height`.
+///
+/// Each cut is made at the lowest of `candidates` (offsets where a line of text
+/// ends) in the lower half of the slice, so text isn't sliced through; without
+/// one the slice is cut at its full height.
+pub(crate) fn page_breaks(height: f64, max_height: f64, candidates: &[f64]) -> Vec<(f64, f64)> {
+    let mut pages = Vec::new();
+    let mut top = 0.0;
+    while height - top > max_height {
+        let limit = top + max_height;
+        let bottom = candidates
+            .iter()
+            .copied()
+            .filter(|&c| c > top + max_height / 2.0 && c <= limit)
+            .max_by(f64::total_cmp)
+            .unwrap_or(limit);
+        pages.push((top, bottom));
+        top = bottom;
+    }
+    pages.push((top, height));
+    pages
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RendererKind {
+    /// wkhtmltoimage command line tool
+    Wkhtmltoimage,
+    /// Headless Chrome/Chromium via the DevTools protocol
+    Chrome,
+    /// Built-in pure-Rust text renderer; no external binary, but no LaTeX
+    Svg,
+}
+
+impl RendererKind {
+    /// The renderer, with `timeout` bounding each run of an external process
+    pub fn build(self, options: RenderOptions, timeout: Duration) -> Box<dyn Renderer> {
+        match self {
+            RendererKind::Wkhtmltoimage => {
+                Box::new(WkhtmltoimageRenderer::new(options).with_timeout(timeout))
+            }
+            RendererKind::Chrome => Box::new(ChromeRenderer::new(options)),
+            RendererKind::Svg => Box::new(SvgRenderer::new(options)),
+        }
+    }
+
+    /// Whether the backend can write images in `format`; the built-in renderer
+    /// always writes PNG and is accepted for any
+    pub fn supports(self, format: ImageFormat) -> bool {
+        !(self == RendererKind::Wkhtmltoimage && format == ImageFormat::Webp)
+    }
+}
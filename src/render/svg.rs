@@ -0,0 +1,270 @@
+use super::{ImageFormat, RenderOptions, Renderer, page_path};
+use crate::{BotError, Result};
+use resvg::{tiny_skia, usvg};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// TeX commands whose presence means the plain-text fallback would be unreadable
+const LATEX_MARKERS: &[&str] = &["\\frac", "\\sqrt", "\\(", "\\[", "$$", "^{", "_{"];
+
+/// How many TeX markers a question may contain before the fallback refuses it
+const MAX_LATEX_MARKERS: usize = 3;
+
+/// Pure-Rust fallback that lays the page's text out as SVG and rasterizes it with resvg
+///
+/// Needs no external binary, only a system font. Formatting is reduced to paragraphs and
+/// MathJax is not run, so LaTeX-heavy questions are rejected. Images are always PNG.
+pub struct SvgRenderer {
+    pub options: RenderOptions,
+    pub font_size: u32,
+    pub margin: u32,
+}
+
+impl SvgRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        Self {
+            options: RenderOptions {
+                format: ImageFormat::Png,
+                ..options
+            },
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self {
+            options: RenderOptions {
+                format: ImageFormat::Png,
+                ..RenderOptions::default()
+            },
+            font_size: 22,
+            margin: 40,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Renderer for SvgRenderer {
+    async fn check_available(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn options(&self) -> RenderOptions {
+        self.options
+    }
+
+    async fn render(&self, html_path: &Path, output_path: &Path) -> Result<()> {
+        let lines = self.lines(&fs::read_to_string(html_path)?)?;
+        self.rasterize_page(&lines, output_path).await
+    }
+
+    async fn render_pages(&self, html_path: &Path, output_path: &Path) -> Result<Vec<PathBuf>> {
+        let lines = self.lines(&fs::read_to_string(html_path)?)?;
+        let line_height = self.line_height();
+        let lines_per_page = match self.options.max_height {
+            Some(max_height) => {
+                (max_height.saturating_sub(2 * self.margin) / line_height).max(1) as usize
+            }
+            None => lines.len().max(1),
+        };
+        if lines.len() <= lines_per_page {
+            self.rasterize_page(&lines, output_path).await?;
+            return Ok(vec![output_path.to_path_buf()]);
+        }
+
+        let mut pages = Vec::new();
+        for (i, page_lines) in lines.chunks(lines_per_page).enumerate() {
+            let page = page_path(output_path, i);
+            self.rasterize_page(page_lines, &page).await?;
+            pages.push(page);
+        }
+        Ok(pages)
+    }
+}
+
+impl SvgRenderer {
+    fn line_height(&self) -> u32 {
+        self.font_size * 3 / 2
+    }
+
+    /// The page's text wrapped to the page width, with a blank line after each paragraph
+    fn lines(&self, html: &str) -> Result<Vec<String>> {
+        // Only the body counts; the MathJax config in the head names the delimiters too
+        let body = html.find("<body").map_or(html, |start| &html[start..]);
+        let latex_markers: usize = LATEX_MARKERS.iter().map(|m| body.matches(m).count()).sum();
+        if latex_markers > MAX_LATEX_MARKERS {
+            return Err(BotError::Render(
+                "This question relies on LaTeX, which the built-in renderer can't typeset. Install wkhtmltoimage or Chrome.".to_string(),
+            ));
+        }
+
+        // Serif glyphs average about half an em wide
+        let chars_per_line =
+            ((self.options.width - 2 * self.margin) / (self.font_size / 2)) as usize;
+        let mut lines = Vec::new();
+        for paragraph in extract_paragraphs(body) {
+            lines.extend(wrap(&strip_emoji(&paragraph), chars_per_line));
+            lines.push(String::new());
+        }
+        Ok(lines)
+    }
+
+    async fn rasterize_page(&self, lines: &[String], output_path: &Path) -> Result<()> {
+        let svg = self.layout(lines);
+        let output_path = output_path.to_path_buf();
+        let scale = self.options.scale as f32;
+        tokio::task::spawn_blocking(move || rasterize(&svg, scale, &output_path))
+            .await
+            .map_err(|e| BotError::Render(format!("SVG render task failed: {}", e)))?
+    }
+
+    /// Emits one `<text>` per line
+    fn layout(&self, lines: &[String]) -> String {
+        let line_height = self.line_height();
+        let height = 2 * self.margin + lines.len() as u32 * line_height;
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}"><rect width="100%" height="100%" fill="white"/><g font-family="DejaVu Serif, Liberation Serif, Times New Roman, serif" font-size="{fs}" fill="black">"#,
+            w = self.options.width,
+            h = height,
+            fs = self.font_size
+        );
+        for (i, line) in lines.iter().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            svg.push_str(&format!(
+                r#"<text x="{}" y="{}">{}</text>"#,
+                self.margin,
+                self.margin + (i as u32 + 1) * line_height,
+                escape_xml(line)
+            ));
+        }
+        svg.push_str("</g></svg>");
+        svg
+    }
+}
+
+fn rasterize(svg: &str, scale: f32, output_path: &Path) -> Result<()> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    if options.fontdb.is_empty() {
+        return Err(BotError::Render(
+            "No system fonts found for the built-in renderer. Install a font package such as fonts-dejavu.".to_string(),
+        ));
+    }
+
+    let tree = usvg::Tree::from_str(svg, &options)
+        .map_err(|e| BotError::Render(format!("Failed to parse generated SVG: {}", e)))?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or_else(|| BotError::Render("Rendered page has zero size".to_string()))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| BotError::Render("Rendered page has zero size".to_string()))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap
+        .save_png(output_path)
+        .map_err(|e| BotError::Render(format!("Failed to write PNG: {}", e)))
+}
+
+/// Pulls the visible text out of the page body, one entry per block element
+pub(crate) fn extract_paragraphs(html: &str) -> Vec<String> {
+    let body = html.find("<body").map_or(html, |start| &html[start..]);
+
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        current.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 1..start + end].to_lowercase();
+        rest = &rest[start + end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        match name.as_str() {
+            // Skip non-visible content entirely
+            "script" | "style" if !tag.starts_with('/') => {
+                if let Some(close) = rest.to_lowercase().find(&format!("</{}", name)) {
+                    rest = &rest[close..];
+                }
+            }
+            "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "hr" => {
+                push_paragraph(&mut paragraphs, &mut current);
+            }
+            _ => {}
+        }
+    }
+    current.push_str(rest);
+    push_paragraph(&mut paragraphs, &mut current);
+    paragraphs
+}
+
+fn push_paragraph(paragraphs: &mut Vec<String>, current: &mut String) {
+    let text = decode_entities(&current.split_whitespace().collect::<Vec<_>>().join(" "));
+    if !text.is_empty() {
+        paragraphs.push(text);
+    }
+    current.clear();
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&rsquo;", "’")
+        .replace("&ldquo;", "“")
+        .replace("&rdquo;", "”")
+        .replace("&mdash;", "—")
+        .replace("&amp;", "&")
+}
+
+/// System serif fonts have no emoji, which would otherwise show as empty boxes
+fn strip_emoji(text: &str) -> String {
+    text.chars()
+        .filter(|c| !matches!(c, '\u{1F000}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' | '\u{FE0F}'))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Greedy word wrap by character count
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
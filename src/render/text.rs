@@ -0,0 +1,37 @@
+use super::svg::extract_paragraphs;
+use crate::{ANSWER_CHOICES, QuestionContent, latex_to_unicode};
+
+/// A question as plain text: its text, the labelled answer choices, then the
+/// explanations if asked for. Sent instead of the image when rendering or
+/// uploading fails, and written by `render --format text`
+pub fn question_text(content: &QuestionContent, show_explanations: bool) -> String {
+    let mut text = paragraphs_to_text(&content.question);
+    for (i, answer) in content.answers.iter().enumerate() {
+        let label = ANSWER_CHOICES
+            .get(i)
+            .map_or_else(|| (i + 1).to_string(), |label| label.to_string());
+        let answer = extract_paragraphs(answer).join(" ");
+        text.push_str(&format!(
+            "{}{}) {}",
+            if i == 0 { "\n\n" } else { "\n" },
+            label,
+            latex_to_unicode(&answer)
+        ));
+    }
+    if show_explanations {
+        for explanation in &content.explanations {
+            text.push_str("\n\nExplanation:\n");
+            text.push_str(&paragraphs_to_text(explanation));
+        }
+    }
+    text
+}
+
+/// Visible text of an HTML fragment, one line per paragraph, with TeX made readable
+fn paragraphs_to_text(html: &str) -> String {
+    extract_paragraphs(html)
+        .iter()
+        .map(|paragraph| latex_to_unicode(paragraph))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,181 @@
+use super::{
+    DEFAULT_RENDER_TIMEOUT, READY_STATUS, RenderOptions, Renderer, page_breaks, page_path,
+};
+use crate::{BotError, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::Duration;
+
+/// Fails early with an installation hint when the `wkhtmltoimage` binary is missing
+pub async fn check_wkhtmltoimage() -> Result<()> {
+    let mut command = tokio::process::Command::new("wkhtmltoimage");
+    command.arg("--version");
+    match run_with_timeout(command, "wkhtmltoimage", Duration::from_secs(10)).await? {
+        Ok(_) => Ok(()),
+        Err(_) => Err(BotError::Render("wkhtmltoimage is not installed or not in PATH. Please install it first. Visit: https://wkhtmltopdf.org/downloads.html".to_string())),
+    }
+}
+
+/// Renders with the `wkhtmltoimage` binary, which writes PNG and JPEG but not WebP
+pub struct WkhtmltoimageRenderer {
+    pub options: RenderOptions,
+    /// How long one run may take before the process is killed, e.g. when MathJax never
+    /// signals ready and wkhtmltoimage waits forever
+    pub timeout: Duration,
+}
+
+impl WkhtmltoimageRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Renders the page, or with `crop` only the (top, height) rows of it, in image pixels
+    async fn run(
+        &self,
+        html_path: &Path,
+        output_path: &Path,
+        crop: Option<(u32, u32)>,
+    ) -> Result<()> {
+        let options = self.options;
+        // Run wkhtmltoimage command with window status for better page load detection.
+        // Zooming scales the page up to the wider image, so the layout stays at `width`
+        let mut command = tokio::process::Command::new("wkhtmltoimage");
+        command
+            .arg("--format")
+            .arg(options.format.extension())
+            .arg("--width")
+            .arg(options.pixel_width().to_string())
+            .arg("--zoom")
+            .arg(options.scale.to_string())
+            .arg("--disable-smart-width")
+            .arg("--quality")
+            .arg(options.quality.to_string())
+            .arg("--enable-javascript")
+            .arg("--enable-local-file-access")
+            .arg("--window-status")
+            .arg(READY_STATUS);
+        if let Some((top, height)) = crop {
+            command
+                .arg("--crop-x")
+                .arg("0")
+                .arg("--crop-y")
+                .arg(top.to_string())
+                .arg("--crop-w")
+                .arg(options.pixel_width().to_string())
+                .arg("--crop-h")
+                .arg(height.to_string());
+        }
+        command.arg(html_path).arg(output_path);
+        let output = run_with_timeout(command, "wkhtmltoimage", self.timeout).await??;
+
+        if !output.status.success() {
+            return Err(BotError::Render(format!(
+                "wkhtmltoimage failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for WkhtmltoimageRenderer {
+    fn default() -> Self {
+        Self {
+            options: RenderOptions::default(),
+            timeout: DEFAULT_RENDER_TIMEOUT,
+        }
+    }
+}
+
+/// Runs `command` to completion, killing it once `timeout` passes; the outer error is
+/// the timeout, the inner one a process that couldn't be started
+async fn run_with_timeout(
+    mut command: tokio::process::Command,
+    program: &'static str,
+    timeout: Duration,
+) -> Result<std::io::Result<Output>> {
+    // Dropping the unfinished child when the timeout fires kills it
+    command.kill_on_drop(true);
+    tokio::time::timeout(timeout, command.output())
+        .await
+        .map_err(|_| BotError::RenderTimeout { program, timeout })
+}
+
+#[async_trait::async_trait]
+impl Renderer for WkhtmltoimageRenderer {
+    async fn check_available(&self) -> Result<()> {
+        check_wkhtmltoimage().await
+    }
+
+    fn options(&self) -> RenderOptions {
+        self.options
+    }
+
+    async fn render(&self, html_path: &Path, output_path: &Path) -> Result<()> {
+        self.run(html_path, output_path, None).await
+    }
+
+    async fn render_pages(&self, html_path: &Path, output_path: &Path) -> Result<Vec<PathBuf>> {
+        self.run(html_path, output_path, None).await?;
+        let Some(max_height) = self.options.max_height else {
+            return Ok(vec![output_path.to_path_buf()]);
+        };
+        let height = imagesize::size(output_path)
+            .map_err(|e| BotError::Render(format!("Failed to read rendered image size: {}", e)))?
+            .height as f64;
+        let max_height = max_height as f64 * self.options.scale;
+        if height <= max_height {
+            return Ok(vec![output_path.to_path_buf()]);
+        }
+
+        // The page's layout isn't known here, so each slice is rendered again cropped to its rows
+        let mut pages = Vec::new();
+        for (i, (top, bottom)) in page_breaks(height, max_height, &[]).into_iter().enumerate() {
+            let page = page_path(output_path, i);
+            self.run(html_path, &page, Some((top as u32, (bottom - top) as u32)))
+                .await?;
+            pages.push(page);
+        }
+        fs::remove_file(output_path)?;
+        Ok(pages)
+    }
+
+    async fn render_pdf(&self, html_path: &Path, output_path: &Path) -> Result<()> {
+        // wkhtmltopdf ships in the same package as wkhtmltoimage
+        let mut command = tokio::process::Command::new("wkhtmltopdf");
+        command
+            .arg("--page-size")
+            .arg("A4")
+            .arg("--enable-javascript")
+            .arg("--enable-local-file-access")
+            .arg("--window-status")
+            .arg(READY_STATUS)
+            .arg(html_path)
+            .arg(output_path);
+        let output = run_with_timeout(command, "wkhtmltopdf", self.timeout)
+            .await?
+            .map_err(|e| {
+                BotError::Render(format!(
+                    "wkhtmltopdf could not be started: {}. Install it from https://wkhtmltopdf.org/downloads.html",
+                    e
+                ))
+            })?;
+
+        if !output.status.success() {
+            return Err(BotError::Render(format!(
+                "wkhtmltopdf failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+}
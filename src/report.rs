@@ -0,0 +1,135 @@
+use crate::QuestionType;
+use chrono::DateTime;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// A question users flagged as broken, with every report about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlaggedQuestion {
+    pub question_id: String,
+    pub question_type: Option<QuestionType>,
+    /// Unix seconds of the first and latest report
+    pub first_reported_at: i64,
+    pub last_reported_at: i64,
+    /// What each reporter wrote after the command, oldest first; empty notes are left out
+    pub notes: Vec<String>,
+    pub reports: u32,
+}
+
+/// How `reports` prints flagged questions and `verify` its findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// A readable list, most reported first
+    Text,
+    /// One row per question, for spreadsheets
+    Csv,
+    /// A JSON array, for scripts
+    Json,
+    /// Question IDs only, one per line
+    Ids,
+}
+
+/// Parses "flag" and "report broken", each optionally followed by a note such as
+/// "flag wrong answer key"; returns the note
+pub fn parse_flag_command(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (command, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let note = if command.eq_ignore_ascii_case("flag") {
+        rest
+    } else if command.eq_ignore_ascii_case("report") {
+        let rest = rest.trim_start();
+        let (word, note) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if !word.eq_ignore_ascii_case("broken") {
+            return None;
+        }
+        note
+    } else {
+        return None;
+    };
+    Some(note.trim().to_string())
+}
+
+fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+fn type_code(question_type: Option<QuestionType>) -> String {
+    question_type
+        .map(|t| format!("{:?}", t))
+        .unwrap_or_default()
+}
+
+/// Quotes a CSV field when it needs it
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Renders flagged questions for the `reports` command
+pub fn format_reports(flagged: &[FlaggedQuestion], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Text => {
+            if flagged.is_empty() {
+                return "No questions have been flagged.\n".to_string();
+            }
+            let mut text = String::new();
+            for question in flagged {
+                text.push_str(&format!(
+                    "#{} {} - {} report(s), last {}\n",
+                    question.question_id,
+                    type_code(question.question_type),
+                    question.reports,
+                    format_time(question.last_reported_at)
+                ));
+                for note in &question.notes {
+                    text.push_str(&format!("    \"{}\"\n", note));
+                }
+            }
+            text
+        }
+        ReportFormat::Csv => {
+            let mut csv = "question_id,question_type,reports,first_reported,last_reported,notes\n"
+                .to_string();
+            for question in flagged {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    csv_field(&question.question_id),
+                    type_code(question.question_type),
+                    question.reports,
+                    format_time(question.first_reported_at),
+                    format_time(question.last_reported_at),
+                    csv_field(&question.notes.join(" | "))
+                ));
+            }
+            csv
+        }
+        ReportFormat::Json => {
+            let rows: Vec<_> = flagged
+                .iter()
+                .map(|question| {
+                    serde_json::json!({
+                        "question_id": question.question_id,
+                        "question_type": question.question_type.map(|t| format!("{:?}", t)),
+                        "reports": question.reports,
+                        "first_reported_at": question.first_reported_at,
+                        "last_reported_at": question.last_reported_at,
+                        "notes": question.notes,
+                    })
+                })
+                .collect();
+            let mut json = serde_json::to_string_pretty(&rows).unwrap_or_default();
+            json.push('\n');
+            json
+        }
+        ReportFormat::Ids => flagged
+            .iter()
+            .map(|question| format!("{}\n", question.question_id))
+            .collect(),
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{BotError, Result};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// How often and how patiently a network call is retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total tries, including the first one
+    pub max_attempts: u32,
+    /// Delay cap for the first retry; doubles on each further retry
+    pub base_delay: Duration,
+    /// Upper bound for any single delay
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with full jitter for the given retry (1-based)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        cap.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// Runs `operation` until it succeeds, fails with a non-retryable error or runs out of attempts
+///
+/// Errors after at least one retry are wrapped in [`BotError::Retry`] so callers can see how
+/// many attempts were made.
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, name: &str, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if !e.is_retryable() || attempt >= policy.max_attempts => {
+                return Err(if attempt > 1 {
+                    BotError::Retry {
+                        operation: name.to_string(),
+                        attempts: attempt,
+                        source: Box::new(e),
+                    }
+                } else {
+                    e
+                });
+            }
+            Err(e) => {
+                let delay = policy.delay(attempt);
+                warn!(
+                    "{} failed (attempt {}/{}), retrying in {:?}: {}",
+                    name, attempt, policy.max_attempts, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
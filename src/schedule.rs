@@ -0,0 +1,192 @@
+use crate::{
+    BotError, GmatDatabase, ImageHost, PipelineLimits, QuestionType, Result, ZaloBot,
+    pick_random_questions_excluding, send_questions_to_users, shutdown_signal,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use std::collections::HashSet;
+use std::str::FromStr;
+use tracing::{error, info, warn};
+
+/// Questions tried per scheduled run before the users still waiting are left for the next one
+const MAX_SCHEDULED_ATTEMPTS: usize = 3;
+
+/// A user who receives scheduled questions, with the timezone the schedule is evaluated in
+#[derive(Debug, Clone)]
+pub struct ScheduledRecipient {
+    pub user_id: String,
+    pub timezone: Tz,
+}
+
+impl ScheduledRecipient {
+    /// Next time this recipient is due after `after`
+    fn next_run(&self, schedule: &Schedule, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        schedule
+            .after(&after.with_timezone(&self.timezone))
+            .next()
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+/// Parses a cron expression, accepting both the classic 5-field form ("0 8 * * *")
+/// and the 6/7-field form with seconds
+pub fn parse_schedule(expression: &str) -> Result<Schedule> {
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    Schedule::from_str(&expression)
+        .map_err(|e| BotError::Config(format!("Invalid schedule '{}': {}", expression, e)))
+}
+
+/// Parses `--user-ids` entries of the form `user_id` or `user_id@Area/City`
+pub fn parse_recipients(
+    entries: &[String],
+    default_timezone: Tz,
+) -> Result<Vec<ScheduledRecipient>> {
+    entries
+        .iter()
+        .map(|entry| match entry.split_once('@') {
+            Some((user_id, timezone)) => Ok(ScheduledRecipient {
+                user_id: user_id.to_string(),
+                timezone: parse_timezone(timezone)?,
+            }),
+            None => Ok(ScheduledRecipient {
+                user_id: entry.clone(),
+                timezone: default_timezone,
+            }),
+        })
+        .collect()
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.parse()
+        .map_err(|_| BotError::Config(format!("Unknown timezone '{}'", name)))
+}
+
+/// Stays running and sends a random question to each recipient whenever the
+/// schedule fires in their timezone, until Ctrl+C or SIGTERM
+#[allow(clippy::too_many_arguments)]
+pub async fn run_schedule(
+    zalo_bot: &ZaloBot,
+    database: &GmatDatabase,
+    schedule: &Schedule,
+    recipients: &[ScheduledRecipient],
+    question_type: &Option<QuestionType>,
+    output_dir: &str,
+    image_host: &dyn ImageHost,
+    show_explanations: bool,
+    limits: PipelineLimits,
+) -> Result<()> {
+    info!(
+        "Scheduled delivery started for {} user(s)",
+        recipients.len()
+    );
+    info!("Press Ctrl+C to stop the bot");
+
+    let mut last_run = Utc::now();
+    loop {
+        let Some(next_run) = recipients
+            .iter()
+            .filter_map(|recipient| recipient.next_run(schedule, last_run))
+            .min()
+        else {
+            info!("Schedule has no upcoming runs, stopping");
+            return Ok(());
+        };
+
+        info!("Next delivery at {}", next_run);
+        let wait = (next_run - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = shutdown_signal() => {
+                info!("Received shutdown signal. Stopping scheduler...");
+                return Ok(());
+            }
+            _ = tokio::time::sleep(wait) => {}
+        }
+
+        // Everyone whose slot falls on this run gets the same question, unless it
+        // lands in their quiet hours; they get the next one instead
+        let due: Vec<String> = recipients
+            .iter()
+            .filter(|recipient| recipient.next_run(schedule, last_run) == Some(next_run))
+            .filter(|recipient| {
+                let quiet = zalo_bot
+                    .quiet_hours(&recipient.user_id)
+                    .is_some_and(|quiet_hours| quiet_hours.is_active(recipient.timezone));
+                if quiet {
+                    info!(user_id = %recipient.user_id, "Skipping delivery during quiet hours");
+                }
+                !quiet
+            })
+            .map(|recipient| recipient.user_id.clone())
+            .collect();
+        last_run = next_run;
+
+        // A failed delivery picks a new question for the users who didn't get it
+        let mut due = due;
+        let mut failed = HashSet::new();
+        for attempt in 1..=MAX_SCHEDULED_ATTEMPTS {
+            if due.is_empty() {
+                break;
+            }
+            // Prefer a question none of them got lately, repeating one only when none is left
+            let recent: HashSet<String> = due
+                .iter()
+                .flat_map(|user_id| zalo_bot.recent_sends.recent_ids(user_id))
+                .chain(failed.iter().cloned())
+                .collect();
+            let exclusions = &zalo_bot.exclusions;
+            let Some((selected_type, question_id)) =
+                pick_random_questions_excluding(database, exclusions, question_type, 1, &recent)
+                    .pop()
+                    .or_else(|| {
+                        pick_random_questions_excluding(
+                            database,
+                            exclusions,
+                            question_type,
+                            1,
+                            &failed,
+                        )
+                        .pop()
+                    })
+            else {
+                warn!("No questions available for the scheduled delivery");
+                break;
+            };
+            info!(
+                "Sending scheduled {} question {} to {} user(s)",
+                selected_type,
+                question_id,
+                due.len()
+            );
+            let results = send_questions_to_users(
+                zalo_bot,
+                &due,
+                &[(selected_type, question_id.clone())],
+                output_dir,
+                image_host,
+                show_explanations,
+                limits,
+                None,
+            )
+            .await;
+            let Some(e) = results.into_iter().find_map(|(_, result)| result.err()) else {
+                break;
+            };
+            error!(
+                "Scheduled delivery of question {} failed (attempt {}/{}): {}",
+                question_id, attempt, MAX_SCHEDULED_ATTEMPTS, e
+            );
+            due.retain(|user_id| {
+                !zalo_bot
+                    .recent_sends
+                    .recent_ids(user_id)
+                    .contains(&question_id)
+            });
+            failed.insert(question_id);
+        }
+    }
+}
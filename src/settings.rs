@@ -0,0 +1,99 @@
+use crate::{Difficulty, QuestionType, parse_difficulty, parse_question_type};
+
+/// Per-user defaults, changed with "settings ..." commands
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserSettings {
+    /// Types "next" picks from; `None` picks from every type the chat allows
+    pub default_types: Option<Vec<QuestionType>>,
+    /// Send explanations with every question instead of only on request
+    pub always_explain: bool,
+    /// Difficulty used when a request doesn't name one; `None` is any difficulty
+    pub difficulty: Option<Difficulty>,
+    /// Question of the day in the user's private chat; kept as a subscription, not with the rest
+    pub daily: bool,
+    /// Show answer choices in a random order, so their letters can't be remembered
+    pub shuffle_answers: bool,
+    /// Language code picking caption templates, e.g. "vi"; `None` uses the default ones
+    pub language: Option<String>,
+}
+
+impl UserSettings {
+    /// Applies a "settings ..." command, returning the reply for the user
+    pub fn apply_command(&mut self, args: &str) -> Result<String, String> {
+        let (setting, value) = args
+            .trim()
+            .split_once(char::is_whitespace)
+            .map(|(s, v)| (s, v.trim()))
+            .unwrap_or((args.trim(), ""));
+
+        match setting.to_lowercase().as_str() {
+            "" => {}
+            "types" if value.eq_ignore_ascii_case("all") => self.default_types = None,
+            "types" => {
+                let types = value
+                    .split(',')
+                    .map(|t| {
+                        parse_question_type(t)
+                            .ok_or_else(|| format!("Unknown question type '{}'", t.trim()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.default_types = Some(types);
+            }
+            "explanations" => self.always_explain = parse_switch(value)?,
+            "difficulty" if value.eq_ignore_ascii_case("any") => self.difficulty = None,
+            "difficulty" => {
+                self.difficulty = Some(
+                    parse_difficulty(value)
+                        .ok_or_else(|| "Difficulty is easy, medium, hard or any".to_string())?,
+                )
+            }
+            "daily" => self.daily = parse_switch(value)?,
+            "shuffle" => self.shuffle_answers = parse_switch(value)?,
+            "language" if value.eq_ignore_ascii_case("default") => self.language = None,
+            "language" => {
+                if value.is_empty()
+                    || value.len() > 10
+                    || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                {
+                    return Err("Language is a code like 'vi' or 'en', or 'default'".to_string());
+                }
+                self.language = Some(value.to_lowercase());
+            }
+            _ => return Err(SETTINGS_USAGE.to_string()),
+        }
+        Ok(self.describe())
+    }
+
+    /// Human-readable summary of the settings
+    pub fn describe(&self) -> String {
+        let types = match &self.default_types {
+            Some(types) => types
+                .iter()
+                .map(|t| format!("{:?}", t))
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => "all".to_string(),
+        };
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        format!(
+            "👤 Your settings\nQuestion types for 'next': {}\nAlways show explanations: {}\nDifficulty: {}\nQuestion of the day: {}\nShuffle answer choices: {}\nCaption language: {}",
+            types,
+            on_off(self.always_explain),
+            self.difficulty.map_or("any".to_string(), |d| d.to_string()),
+            on_off(self.daily),
+            on_off(self.shuffle_answers),
+            self.language.as_deref().unwrap_or("default")
+        )
+    }
+}
+
+const SETTINGS_USAGE: &str = "Usage: settings types ps,ds|all | settings explanations on|off | settings difficulty easy|medium|hard|any | settings daily on|off | settings shuffle on|off | settings language vi|default";
+
+/// Parses "on"/"off"
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(SETTINGS_USAGE.to_string()),
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{ANSWER_CHOICES, QuestionContent, QuestionType};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+/// Random order to show a question's choices in, where `order[i]` is the
+/// original index of the choice shown in position `i`. `None` when the choices
+/// stay put: DS choices are the same five statements in a fixed order, and
+/// multi-part questions go out as they are.
+pub fn shuffle_order<R: Rng + ?Sized>(
+    content: &QuestionContent,
+    question_type: QuestionType,
+    rng: &mut R,
+) -> Option<Vec<usize>> {
+    let choices = content.answers.len();
+    if question_type == QuestionType::DS
+        || !content.sub_questions.is_empty()
+        || !(2..=ANSWER_CHOICES.len()).contains(&choices)
+    {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..choices).collect();
+    order.shuffle(rng);
+    Some(order)
+}
+
+/// The question with its choices shown in `order`
+pub fn reorder_choices(content: &QuestionContent, order: &[usize]) -> QuestionContent {
+    let mut reordered = content.clone();
+    reordered.answers = order
+        .iter()
+        .filter_map(|&i| content.answers.get(i).cloned())
+        .collect();
+    reordered
+}
+
+/// Original letter of the choice shown as `shown`
+pub fn original_choice(order: &[usize], shown: char) -> Option<char> {
+    let position = choice_index(shown)?;
+    order.get(position).map(|&i| choice_letter(i))
+}
+
+/// Letter the choice originally labelled `original` is shown as
+pub fn shown_choice(order: &[usize], original: char) -> Option<char> {
+    let index = choice_index(original)?;
+    order.iter().position(|&i| i == index).map(choice_letter)
+}
+
+/// The original letters in the order shown, like "CAEBD"
+pub fn order_code(order: &[usize]) -> String {
+    order.iter().map(|&i| choice_letter(i)).collect()
+}
+
+fn choice_index(letter: char) -> Option<usize> {
+    ANSWER_CHOICES
+        .iter()
+        .position(|choice| choice.starts_with(letter.to_ascii_uppercase()))
+}
+
+fn choice_letter(index: usize) -> char {
+    char::from(b'A' + index as u8)
+}
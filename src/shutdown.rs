@@ -0,0 +1,98 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::warn;
+
+/// How long shutdown waits for in-flight messages unless configured otherwise
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Coordinates a graceful stop: the first Ctrl+C or SIGTERM (or `trigger`) stops new
+/// work, then in-flight work gets until the drain timeout, or a second signal, to finish
+pub struct ShutdownController {
+    drain_timeout: Duration,
+    requested: watch::Sender<bool>,
+}
+
+impl ShutdownController {
+    pub fn new(drain_timeout: Duration) -> Self {
+        Self {
+            drain_timeout,
+            requested: watch::Sender::new(false),
+        }
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
+    /// Requests a shutdown without a signal, e.g. from an embedding program
+    pub fn trigger(&self) {
+        self.requested.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Resolves once a shutdown signal arrives or `trigger` is called
+    pub async fn requested(&self) {
+        let mut requested = self.requested.subscribe();
+        tokio::select! {
+            _ = shutdown_signal() => self.trigger(),
+            _ = requested.wait_for(|requested| *requested) => {}
+        }
+    }
+
+    /// Runs `work` until it finishes, the drain timeout passes or a second shutdown
+    /// signal arrives; returns whether it finished
+    pub async fn drain<F: Future<Output = ()>>(&self, work: F) -> bool {
+        tokio::select! {
+            result = tokio::time::timeout(self.drain_timeout, work) => {
+                if result.is_err() {
+                    warn!("In-flight work did not finish within {:?}", self.drain_timeout);
+                }
+                result.is_ok()
+            }
+            _ = shutdown_signal() => {
+                warn!("Received a second shutdown signal, abandoning in-flight work");
+                false
+            }
+        }
+    }
+}
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM, which is how systemd, Docker and Kubernetes
+/// stop a process; a signal that can't be listened for is only logged
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+impl Default for ShutdownController {
+    fn default() -> Self {
+        Self::new(DEFAULT_DRAIN_TIMEOUT)
+    }
+}
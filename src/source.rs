@@ -0,0 +1,115 @@
+use crate::http::default_client;
+use crate::retry::{RetryPolicy, retry};
+use crate::{BotError, GmatDatabase, QuestionContent, Result};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
+/// The hosted question bank
+pub const DEFAULT_DATABASE_URL: &str = "https://mister-teddy.github.io/gmat-database";
+
+/// Where the question index (`index.json`) and per-question JSON (`<id>.json`) come from
+#[derive(Debug, Clone)]
+pub enum DatabaseSource {
+    /// A web server or mirror, by base URL, and the client fetching from it
+    Url {
+        base: String,
+        client: reqwest::Client,
+    },
+    /// A local directory with the same layout, for air-gapped or self-hosted banks
+    Path(PathBuf),
+}
+
+impl Default for DatabaseSource {
+    fn default() -> Self {
+        DatabaseSource::url(DEFAULT_DATABASE_URL)
+    }
+}
+
+impl std::fmt::Display for DatabaseSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseSource::Url { base, .. } => write!(f, "{}", base),
+            DatabaseSource::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl DatabaseSource {
+    /// A web server or mirror at `base`, fetched with the default HTTP settings
+    pub fn url(base: impl Into<String>) -> Self {
+        DatabaseSource::Url {
+            base: base.into(),
+            client: default_client(),
+        }
+    }
+
+    /// Fetches through `client`, e.g. the database route's from an [`crate::HttpContext`];
+    /// a local directory has no use for one
+    pub fn with_http_client(self, client: reqwest::Client) -> Self {
+        match self {
+            DatabaseSource::Url { base, .. } => DatabaseSource::Url { base, client },
+            DatabaseSource::Path(dir) => DatabaseSource::Path(dir),
+        }
+    }
+
+    /// Loads the index of question IDs by type
+    pub async fn fetch_index(&self) -> Result<GmatDatabase> {
+        match self {
+            DatabaseSource::Url { base, client } => {
+                let url = format!("{}/index.json", base.trim_end_matches('/'));
+                retry(&RetryPolicy::default(), "Fetch GMAT database", || async {
+                    let response = client.get(&url).send().await?.error_for_status()?;
+                    let database: GmatDatabase = response.json().await?;
+                    Ok(database)
+                })
+                .await
+            }
+            DatabaseSource::Path(dir) => read_json(&dir.join("index.json")),
+        }
+    }
+
+    /// Loads one question's content
+    pub async fn fetch_question(&self, question_id: &str) -> Result<QuestionContent> {
+        match self {
+            DatabaseSource::Url { base, client } => {
+                let url = format!("{}/{}.json", base.trim_end_matches('/'), question_id);
+                retry(&RetryPolicy::default(), "Fetch question", || async {
+                    let response = client.get(&url).send().await?;
+                    if !response.status().is_success() {
+                        return Err(BotError::QuestionFetch {
+                            question_id: question_id.to_string(),
+                            status: response.status(),
+                        });
+                    }
+                    let content: QuestionContent = response.json().await?;
+                    Ok(content)
+                })
+                .await
+            }
+            DatabaseSource::Path(dir) => {
+                // IDs may come from chat messages, so keep them inside the directory
+                if !question_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    return Err(BotError::Config(format!(
+                        "Invalid question ID: {}",
+                        question_id
+                    )));
+                }
+                read_json(&dir.join(format!("{}.json", question_id)))
+            }
+        }
+    }
+}
+
+/// Reads a JSON file, naming the file in any error
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("Failed to read {}: {}", path.display(), e),
+        )
+    })?;
+    Ok(serde_json::from_str(&text)?)
+}
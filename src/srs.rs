@@ -0,0 +1,63 @@
+/// Ease factor new review items start with
+pub const INITIAL_EASE: f64 = 2.5;
+
+/// Lowest ease factor SM-2 allows
+const MIN_EASE: f64 = 1.3;
+
+/// Recall quality recorded for a correct answer (0–5 scale)
+const QUALITY_CORRECT: u8 = 4;
+
+/// Recall quality recorded for a wrong answer
+const QUALITY_WRONG: u8 = 1;
+
+/// SM-2 scheduling state of one question for one user
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReviewState {
+    pub ease: f64,
+    pub interval_days: u32,
+    pub repetitions: u32,
+}
+
+impl Default for ReviewState {
+    fn default() -> Self {
+        Self {
+            ease: INITIAL_EASE,
+            interval_days: 0,
+            repetitions: 0,
+        }
+    }
+}
+
+impl ReviewState {
+    /// Applies one answer and returns the updated state; `interval_days` is the wait until the next review
+    pub fn answered(self, correct: bool) -> Self {
+        let quality = if correct {
+            QUALITY_CORRECT
+        } else {
+            QUALITY_WRONG
+        };
+        let q = f64::from(5 - quality);
+        let ease = (self.ease + 0.1 - q * (0.08 + q * 0.02)).max(MIN_EASE);
+
+        if quality < 3 {
+            // Forgotten: start the ladder again tomorrow
+            return Self {
+                ease,
+                interval_days: 1,
+                repetitions: 0,
+            };
+        }
+
+        let repetitions = self.repetitions + 1;
+        let interval_days = match repetitions {
+            1 => 1,
+            2 => 6,
+            _ => (f64::from(self.interval_days) * self.ease).round() as u32,
+        };
+        Self {
+            ease,
+            interval_days,
+            repetitions,
+        }
+    }
+}
@@ -0,0 +1,1738 @@
+use crate::assignment::{Assignment, AssignmentStatus, StudentProgress};
+use crate::badge::BadgeProgress;
+use crate::chat_config::ChatConfig;
+use crate::image_manifest::HostedImage;
+use crate::report::FlaggedQuestion;
+use crate::settings::UserSettings;
+use crate::srs::ReviewState;
+use crate::user_data::UserData;
+use crate::{Difficulty, PendingQuestion, QuestionContent, QuestionType, Result, parse_difficulty};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use rusqlite::types::{Type, ValueRef};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SCHEMA: &str = "
+    -- user_id is the chat a question went to: the user in a private chat, or a group
+    CREATE TABLE IF NOT EXISTS sent_questions (
+        user_id TEXT NOT NULL,
+        question_id TEXT NOT NULL,
+        question_type TEXT NOT NULL,
+        sent_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_sent_questions_user ON sent_questions (user_id);
+
+    CREATE TABLE IF NOT EXISTS answers (
+        user_id TEXT NOT NULL,
+        question_id TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        correct INTEGER,
+        answered_at INTEGER NOT NULL,
+        chat_id TEXT,
+        display_name TEXT,
+        question_type TEXT,
+        response_secs INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS idx_answers_user ON answers (user_id);
+
+    CREATE TABLE IF NOT EXISTS preferences (
+        user_id TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (user_id, key)
+    );
+
+    CREATE TABLE IF NOT EXISTS pending_questions (
+        chat_id TEXT PRIMARY KEY,
+        question_type TEXT NOT NULL,
+        content TEXT NOT NULL,
+        correct_answer TEXT,
+        sent_at INTEGER NOT NULL,
+        choice_order TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS reviews (
+        user_id TEXT NOT NULL,
+        question_id TEXT NOT NULL,
+        question_type TEXT NOT NULL,
+        ease REAL NOT NULL,
+        interval_days INTEGER NOT NULL,
+        repetitions INTEGER NOT NULL,
+        due_at INTEGER NOT NULL,
+        PRIMARY KEY (user_id, question_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_reviews_due ON reviews (user_id, due_at);
+
+    CREATE TABLE IF NOT EXISTS chat_configs (
+        chat_id TEXT PRIMARY KEY,
+        command_prefix TEXT NOT NULL,
+        allowed_types TEXT,
+        mute_start INTEGER,
+        mute_end INTEGER,
+        timezone TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS users (
+        user_id TEXT PRIMARY KEY,
+        display_name TEXT,
+        private_chat_id TEXT,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        banned INTEGER NOT NULL DEFAULT 0,
+        inactive INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE IF NOT EXISTS hosted_images (
+        question_id TEXT NOT NULL,
+        show_explanations INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        uploaded_at INTEGER NOT NULL,
+        PRIMARY KEY (question_id, show_explanations)
+    );
+
+    CREATE TABLE IF NOT EXISTS subscriptions (
+        chat_id TEXT PRIMARY KEY,
+        subscribed_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS question_reports (
+        question_id TEXT NOT NULL,
+        question_type TEXT,
+        chat_id TEXT NOT NULL,
+        user_id TEXT NOT NULL,
+        note TEXT NOT NULL,
+        reported_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS user_settings (
+        user_id TEXT PRIMARY KEY,
+        default_types TEXT,
+        always_explain INTEGER NOT NULL,
+        difficulty TEXT,
+        shuffle_answers INTEGER NOT NULL DEFAULT 0,
+        language TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_posts (
+        date TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        question_id TEXT NOT NULL,
+        posted_at INTEGER NOT NULL,
+        PRIMARY KEY (date, kind)
+    );
+
+    CREATE TABLE IF NOT EXISTS badges (
+        user_id TEXT NOT NULL,
+        badge TEXT NOT NULL,
+        awarded_at INTEGER NOT NULL,
+        PRIMARY KEY (user_id, badge)
+    );
+
+    CREATE TABLE IF NOT EXISTS assignments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT,
+        created_by TEXT,
+        created_at INTEGER NOT NULL,
+        question_ids TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS assignment_students (
+        assignment_id INTEGER NOT NULL,
+        user_id TEXT NOT NULL,
+        delivered_at INTEGER,
+        PRIMARY KEY (assignment_id, user_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS progression (
+        user_id TEXT NOT NULL,
+        question_type TEXT NOT NULL,
+        band TEXT NOT NULL,
+        after_answer INTEGER NOT NULL,
+        PRIMARY KEY (user_id, question_type)
+    );
+";
+
+/// Columns added after the first release, applied to existing databases on open
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("answers", "chat_id", "TEXT"),
+    ("answers", "display_name", "TEXT"),
+    ("answers", "question_type", "TEXT"),
+    ("answers", "response_secs", "INTEGER"),
+    ("users", "inactive", "INTEGER NOT NULL DEFAULT 0"),
+    ("pending_questions", "choice_order", "TEXT"),
+    (
+        "user_settings",
+        "shuffle_answers",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("user_settings", "language", "TEXT"),
+    ("chat_configs", "mute_hours", "TEXT"),
+];
+
+/// Tables holding rows about a user, with the column naming them
+const USER_TABLES: &[(&str, &str)] = &[
+    ("users", "user_id"),
+    ("answers", "user_id"),
+    ("preferences", "user_id"),
+    ("user_settings", "user_id"),
+    ("reviews", "user_id"),
+    ("badges", "user_id"),
+    ("question_reports", "user_id"),
+    ("assignment_students", "user_id"),
+    ("progression", "user_id"),
+];
+
+/// Tables keyed by chat, whose rows for the user's private chat are theirs too
+const PRIVATE_CHAT_TABLES: &[(&str, &str)] = &[
+    ("sent_questions", "user_id"),
+    ("pending_questions", "chat_id"),
+    ("subscriptions", "chat_id"),
+    ("chat_configs", "chat_id"),
+];
+
+/// One row of a chat's leaderboard
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub user_id: String,
+    pub display_name: Option<String>,
+    pub correct: u32,
+    pub incorrect: u32,
+}
+
+/// A user's answer history, shown on their progress card
+#[derive(Debug, Clone, Default)]
+pub struct UserStats {
+    pub attempted: u32,
+    /// (type, correct, graded) for each type the user has answered
+    pub by_type: Vec<(QuestionType, u32, u32)>,
+    /// Correct answers in a row, counting back from the latest
+    pub current_streak: u32,
+    pub average_response_secs: Option<f64>,
+}
+
+/// A user's answers over a stretch of time, compared week to week in the weekly report
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeriodStats {
+    pub attempted: u32,
+    /// (type, correct, graded) for each type the user answered in the period
+    pub by_type: Vec<(QuestionType, u32, u32)>,
+}
+
+impl PeriodStats {
+    /// Percentage of graded answers that were right, if any were graded
+    pub fn accuracy(&self) -> Option<u32> {
+        let (correct, graded) = self
+            .by_type
+            .iter()
+            .fold((0, 0), |(c, g), (_, correct, graded)| {
+                (c + correct, g + graded)
+            });
+        (correct * 100).checked_div(graded)
+    }
+}
+
+/// A question sent to a chat and how it was answered
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub question_id: String,
+    pub question_type: QuestionType,
+    pub sent_at: i64,
+    pub answered: bool,
+    /// Whether the first answer was right; `None` when unanswered or ungraded
+    pub correct: Option<bool>,
+}
+
+/// Bot-wide activity totals for the admin `usage` command
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageStats {
+    pub users: u32,
+    pub active_users: u32,
+    pub banned_users: u32,
+    pub questions_sent: u32,
+    pub recent_questions_sent: u32,
+    pub answers: u32,
+    pub recent_answers: u32,
+    pub correct_answers: u32,
+    pub graded_answers: u32,
+}
+
+/// Bot-wide activity over a stretch of time, posted in the daily webhook summary
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivityStats {
+    /// Users who answered at least once
+    pub active_users: u32,
+    pub questions_sent: u32,
+    pub answers: u32,
+    pub correct_answers: u32,
+    pub graded_answers: u32,
+}
+
+/// SQLite-backed store for per-user bot state
+pub struct Storage {
+    conn: Mutex<Connection>,
+}
+
+impl Storage {
+    /// Opens (or creates) the database file at `path` and applies the schema
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Opens a throwaway in-memory database
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        for (table, column, column_type) in ADDED_COLUMNS {
+            let exists = conn
+                .prepare(&format!(
+                    "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+                    table
+                ))?
+                .exists(params![column])?;
+            if !exists {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, column_type
+                ))?;
+            }
+        }
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_answers_chat ON answers (chat_id, answered_at)",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Writes cached pages back to the database file
+    pub fn flush(&self) -> Result<()> {
+        self.conn.lock().unwrap().cache_flush()?;
+        Ok(())
+    }
+
+    /// Records a question delivered to `chat_id`. History is kept per chat: a private
+    /// chat is one user's, while a group shares one history so no member sees a repeat
+    pub fn record_question_sent(
+        &self,
+        chat_id: &str,
+        question_id: &str,
+        question_type: &QuestionType,
+    ) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO sent_questions (user_id, question_id, question_type, sent_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![chat_id, question_id, type_code(question_type), now()],
+        )?;
+        Ok(())
+    }
+
+    /// IDs of every question `chat_id` has already received
+    pub fn sent_question_ids(&self, chat_id: &str) -> Result<HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT question_id FROM sent_questions WHERE user_id = ?1")?;
+        let ids = stmt
+            .query_map(params![chat_id], |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?;
+        Ok(ids)
+    }
+
+    /// The `limit` questions sent most often across all chats, most sent first
+    pub fn most_sent_questions(&self, limit: usize) -> Result<Vec<(QuestionType, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT question_type, question_id FROM sent_questions
+             GROUP BY question_id
+             ORDER BY COUNT(*) DESC, MAX(sent_at) DESC
+             LIMIT ?1",
+        )?;
+        let questions = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|(code, question_id)| Ok((parse_type_code(&code)?, question_id)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(questions)
+    }
+
+    /// Questions sent to a chat, newest first, with the outcome of the first
+    /// answer given in that chat after each was sent
+    pub fn question_history(
+        &self,
+        chat_id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT s.question_id, s.question_type, s.sent_at,
+                    EXISTS (SELECT 1 FROM answers a
+                            WHERE a.chat_id = s.user_id AND a.question_id = s.question_id
+                              AND a.answered_at >= s.sent_at),
+                    (SELECT a.correct FROM answers a
+                     WHERE a.chat_id = s.user_id AND a.question_id = s.question_id
+                       AND a.answered_at >= s.sent_at
+                     ORDER BY a.answered_at, a.rowid LIMIT 1)
+             FROM sent_questions s
+             WHERE s.user_id = ?1
+             ORDER BY s.sent_at DESC, s.rowid DESC
+             LIMIT ?2 OFFSET ?3",
+        )?;
+        let rows = stmt
+            .query_map(params![chat_id, limit as i64, offset as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, Option<bool>>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(question_id, code, sent_at, answered, correct)| {
+                Ok(HistoryEntry {
+                    question_id,
+                    question_type: parse_type_code(&code)?,
+                    sent_at,
+                    answered,
+                    correct,
+                })
+            })
+            .collect()
+    }
+
+    pub fn record_answer(
+        &self,
+        chat_id: &str,
+        user_id: &str,
+        display_name: Option<&str>,
+        pending: &PendingQuestion,
+        answer: char,
+        correct: Option<bool>,
+    ) -> Result<()> {
+        let answered_at = now();
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO answers
+             (user_id, question_id, answer, correct, answered_at, chat_id, display_name,
+              question_type, response_secs)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                user_id,
+                pending.content.id,
+                answer.to_string(),
+                correct,
+                answered_at,
+                chat_id,
+                display_name,
+                type_code(&pending.question_type),
+                answered_at - pending.sent_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Totals, per-type accuracy, streak and pace across all of a user's answers
+    pub fn user_stats(&self, user_id: &str) -> Result<UserStats> {
+        let conn = self.conn.lock().unwrap();
+        let (attempted, average_response_secs) = conn.query_row(
+            "SELECT COUNT(*), AVG(response_secs) FROM answers WHERE user_id = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT question_type, SUM(correct = 1), COUNT(*)
+             FROM answers
+             WHERE user_id = ?1 AND question_type IS NOT NULL AND correct IS NOT NULL
+             GROUP BY question_type
+             ORDER BY question_type",
+        )?;
+        let by_type = stmt
+            .query_map(params![user_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, u32>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|(code, correct, graded)| Ok((parse_type_code(&code)?, correct, graded)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT correct FROM answers
+             WHERE user_id = ?1 AND correct IS NOT NULL
+             ORDER BY answered_at DESC, rowid DESC",
+        )?;
+        let mut streak = 0;
+        let mut rows = stmt.query(params![user_id])?;
+        while let Some(row) = rows.next()? {
+            if !row.get::<_, bool>(0)? {
+                break;
+            }
+            streak += 1;
+        }
+
+        Ok(UserStats {
+            attempted,
+            by_type,
+            current_streak: streak,
+            average_response_secs,
+        })
+    }
+
+    /// Answers given from `since` up to, but not including, `until` (unix seconds)
+    pub fn period_stats(&self, user_id: &str, since: i64, until: i64) -> Result<PeriodStats> {
+        let conn = self.conn.lock().unwrap();
+        let attempted = conn.query_row(
+            "SELECT COUNT(*) FROM answers
+             WHERE user_id = ?1 AND answered_at >= ?2 AND answered_at < ?3",
+            params![user_id, since, until],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT question_type, SUM(correct = 1), COUNT(*)
+             FROM answers
+             WHERE user_id = ?1 AND answered_at >= ?2 AND answered_at < ?3
+               AND question_type IS NOT NULL AND correct IS NOT NULL
+             GROUP BY question_type
+             ORDER BY question_type",
+        )?;
+        let by_type = stmt
+            .query_map(params![user_id, since, until], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, u32>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|(code, correct, graded)| Ok((parse_type_code(&code)?, correct, graded)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(PeriodStats { attempted, by_type })
+    }
+
+    /// The question the user answered most recently, with its type when it was recorded
+    pub fn last_answered_question(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<(String, Option<QuestionType>)>> {
+        let row = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT question_id, question_type FROM answers
+                 WHERE user_id = ?1
+                 ORDER BY answered_at DESC, rowid DESC LIMIT 1",
+                params![user_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?;
+        let Some((question_id, question_type)) = row else {
+            return Ok(None);
+        };
+        let question_type = question_type.as_deref().map(parse_type_code).transpose()?;
+        Ok(Some((question_id, question_type)))
+    }
+
+    /// Top answerers in a chat since `since` (unix seconds), ranked by correct answers
+    pub fn leaderboard(
+        &self,
+        chat_id: &str,
+        since: i64,
+        limit: usize,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id,
+                    MAX(display_name),
+                    SUM(correct = 1),
+                    SUM(correct = 0)
+             FROM answers
+             WHERE chat_id = ?1 AND answered_at >= ?2 AND correct IS NOT NULL
+             GROUP BY user_id
+             ORDER BY SUM(correct = 1) DESC, SUM(correct = 0) ASC
+             LIMIT ?3",
+        )?;
+        let entries = stmt
+            .query_map(params![chat_id, since, limit as i64], |row| {
+                Ok(LeaderboardEntry {
+                    user_id: row.get(0)?,
+                    display_name: row.get(1)?,
+                    correct: row.get(2)?,
+                    incorrect: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Registers a sender, remembering their private chat so broadcasts can reach
+    /// them; a user marked inactive counts as active again
+    pub fn touch_user(
+        &self,
+        user_id: &str,
+        display_name: Option<&str>,
+        private_chat_id: Option<&str>,
+    ) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO users (user_id, display_name, private_chat_id, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT (user_id) DO UPDATE SET
+                display_name = COALESCE(excluded.display_name, display_name),
+                private_chat_id = COALESCE(excluded.private_chat_id, private_chat_id),
+                last_seen = excluded.last_seen,
+                inactive = 0",
+            params![user_id, display_name, private_chat_id, now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn is_banned(&self, user_id: &str) -> Result<bool> {
+        let banned = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT banned FROM users WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(banned.unwrap_or(false))
+    }
+
+    /// Bans or unbans a user, registering them first if they were never seen
+    pub fn set_banned(&self, user_id: &str, banned: bool) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO users (user_id, first_seen, last_seen, banned) VALUES (?1, ?2, ?2, ?3)
+             ON CONFLICT (user_id) DO UPDATE SET banned = excluded.banned",
+            params![user_id, now(), banned],
+        )?;
+        Ok(())
+    }
+
+    /// Stops broadcasts and reports going to a private chat whose user blocked the
+    /// bot, until they message it again
+    pub fn mark_inactive(&self, private_chat_id: &str) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE users SET inactive = 1 WHERE private_chat_id = ?1",
+            params![private_chat_id],
+        )?;
+        Ok(())
+    }
+
+    /// The user whose private chat this is, if the bot has seen them there
+    pub fn private_chat_user(&self, chat_id: &str) -> Result<Option<String>> {
+        let user_id = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT user_id FROM users WHERE private_chat_id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(user_id)
+    }
+
+    /// The private chat a user last messaged the bot from
+    pub fn private_chat_id(&self, user_id: &str) -> Result<Option<String>> {
+        let chat_id = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT private_chat_id FROM users WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        Ok(chat_id.flatten())
+    }
+
+    /// `(user_id, private_chat_id)` of users who aren't banned and whose display name
+    /// (ignoring case) or user ID is `name`, most recently seen first
+    pub fn find_users(&self, name: &str) -> Result<Vec<(String, Option<String>)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id, display_name, private_chat_id FROM users
+             WHERE banned = 0 ORDER BY last_seen DESC",
+        )?;
+        let lowercase = name.to_lowercase();
+        let users = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|(user_id, display_name, _)| {
+                *user_id == name
+                    || display_name
+                        .as_deref()
+                        .is_some_and(|display_name| display_name.to_lowercase() == lowercase)
+            })
+            .map(|(user_id, _, private_chat_id)| (user_id, private_chat_id))
+            .collect();
+        Ok(users)
+    }
+
+    /// Every row about the user and their private chat, for "mydata" and `user-data export`
+    pub fn export_user_data(&self, user_id: &str) -> Result<UserData> {
+        let private_chat_id = self.private_chat_id(user_id)?;
+        let correct_streak = self.user_stats(user_id)?.current_streak;
+        let practice_days = self.practice_days(user_id, Tz::UTC)?;
+
+        let conn = self.conn.lock().unwrap();
+        let mut tables = BTreeMap::new();
+        let private_tables = private_chat_id.iter().flat_map(|chat_id| {
+            PRIVATE_CHAT_TABLES
+                .iter()
+                .map(move |table| (table, chat_id.as_str()))
+        });
+        for ((table, column), key) in USER_TABLES
+            .iter()
+            .map(|table| (table, user_id))
+            .chain(private_tables)
+        {
+            let mut stmt =
+                conn.prepare(&format!("SELECT * FROM {} WHERE {} = ?1", table, column))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let rows = stmt
+                .query_map(params![key], |row| {
+                    let mut values = serde_json::Map::new();
+                    for (i, column) in columns.iter().enumerate() {
+                        let value = match row.get_ref(i)? {
+                            ValueRef::Null => serde_json::Value::Null,
+                            ValueRef::Integer(n) => n.into(),
+                            ValueRef::Real(x) => x.into(),
+                            ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+                            ValueRef::Blob(bytes) => bytes
+                                .iter()
+                                .map(|b| format!("{:02x}", b))
+                                .collect::<String>()
+                                .into(),
+                        };
+                        values.insert(column.clone(), value);
+                    }
+                    Ok(values)
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            if !rows.is_empty() {
+                tables.insert(table.to_string(), rows);
+            }
+        }
+        Ok(UserData {
+            user_id: user_id.to_string(),
+            exported_at: now(),
+            correct_streak,
+            practice_days,
+            tables,
+        })
+    }
+
+    /// Deletes every row about the user and their private chat; returns how many went
+    pub fn forget_user(&self, user_id: &str) -> Result<usize> {
+        let private_chat_id = self.private_chat_id(user_id)?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut deleted = 0;
+        if let Some(chat_id) = &private_chat_id {
+            for (table, column) in PRIVATE_CHAT_TABLES {
+                deleted += tx.execute(
+                    &format!("DELETE FROM {} WHERE {} = ?1", table, column),
+                    params![chat_id],
+                )?;
+            }
+        }
+        for (table, column) in USER_TABLES {
+            deleted += tx.execute(
+                &format!("DELETE FROM {} WHERE {} = ?1", table, column),
+                params![user_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Saves a tutor's assignment of `question_ids` for `user_ids`; returns its ID
+    pub fn create_assignment(
+        &self,
+        name: Option<&str>,
+        created_by: Option<&str>,
+        question_ids: &[String],
+        user_ids: &[String],
+    ) -> Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO assignments (name, created_by, created_at, question_ids)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![name, created_by, now(), question_ids.join(",")],
+        )?;
+        let id = tx.last_insert_rowid();
+        for user_id in user_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO assignment_students (assignment_id, user_id) VALUES (?1, ?2)",
+                params![id, user_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Every assignment, newest first
+    pub fn assignments(&self) -> Result<Vec<Assignment>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, created_by, created_at, question_ids FROM assignments ORDER BY id DESC",
+        )?;
+        let assignments = stmt
+            .query_map([], assignment_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(assignments)
+    }
+
+    pub fn assignment(&self, id: i64) -> Result<Option<Assignment>> {
+        let assignment = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT id, name, created_by, created_at, question_ids FROM assignments WHERE id = ?1",
+                params![id],
+                assignment_from_row,
+            )
+            .optional()?;
+        Ok(assignment)
+    }
+
+    /// `(assignment_id, user_id, private_chat_id)` of students still waiting for an
+    /// assignment who can now be messaged privately, oldest assignment first
+    pub fn undelivered_assignments(&self) -> Result<Vec<(i64, String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT s.assignment_id, s.user_id, u.private_chat_id
+             FROM assignment_students s JOIN users u ON u.user_id = s.user_id
+             WHERE s.delivered_at IS NULL AND u.private_chat_id IS NOT NULL
+               AND u.banned = 0 AND u.inactive = 0
+             ORDER BY s.assignment_id, s.user_id",
+        )?;
+        let students = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(students)
+    }
+
+    pub fn mark_assignment_delivered(&self, assignment_id: i64, user_id: &str) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "UPDATE assignment_students SET delivered_at = ?3
+             WHERE assignment_id = ?1 AND user_id = ?2",
+            params![assignment_id, user_id, now()],
+        )?;
+        Ok(())
+    }
+
+    /// An assignment with each student's progress, for `assignments status`
+    pub fn assignment_status(&self, id: i64) -> Result<Option<AssignmentStatus>> {
+        let Some(assignment) = self.assignment(id)? else {
+            return Ok(None);
+        };
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT s.user_id, u.display_name, s.delivered_at
+             FROM assignment_students s LEFT JOIN users u ON u.user_id = s.user_id
+             WHERE s.assignment_id = ?1 ORDER BY s.user_id",
+        )?;
+        let students = stmt
+            .query_map(params![id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|(user_id, display_name, delivered_at)| {
+                student_progress(&conn, &assignment, user_id, display_name, delivered_at)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(AssignmentStatus {
+            assignment,
+            students,
+        }))
+    }
+
+    /// Assignments delivered to a student, oldest first, with how far they got
+    pub fn student_assignments(&self, user_id: &str) -> Result<Vec<(Assignment, StudentProgress)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT a.id, a.name, a.created_by, a.created_at, a.question_ids, s.delivered_at
+             FROM assignments a JOIN assignment_students s ON s.assignment_id = a.id
+             WHERE s.user_id = ?1 AND s.delivered_at IS NOT NULL ORDER BY a.id",
+        )?;
+        let assignments = stmt
+            .query_map(params![user_id], |row| {
+                Ok((assignment_from_row(row)?, row.get::<_, i64>(5)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        assignments
+            .into_iter()
+            .map(|(assignment, delivered_at)| {
+                let progress = student_progress(
+                    &conn,
+                    &assignment,
+                    user_id.to_string(),
+                    None,
+                    Some(delivered_at),
+                )?;
+                Ok((assignment, progress))
+            })
+            .collect()
+    }
+
+    /// Local dates (in `timezone`) on which the user answered at least one question
+    pub fn practice_days(&self, user_id: &str, timezone: Tz) -> Result<Vec<NaiveDate>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT answered_at FROM answers WHERE user_id = ?1 ORDER BY answered_at DESC",
+        )?;
+        let mut days = stmt
+            .query_map(params![user_id], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter_map(|answered_at| DateTime::from_timestamp(answered_at, 0))
+            .map(|answered_at| answered_at.with_timezone(&timezone).date_naive())
+            .collect::<Vec<_>>();
+        days.dedup();
+        Ok(days)
+    }
+
+    /// `(user_id, private_chat_id)` of users who answered since `since` (unix
+    /// seconds), aren't banned or inactive and can be messaged privately
+    pub fn recently_practiced_users(&self, since: i64) -> Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT user_id, private_chat_id FROM users
+             WHERE private_chat_id IS NOT NULL AND banned = 0 AND inactive = 0
+               AND EXISTS (SELECT 1 FROM answers
+                           WHERE answers.user_id = users.user_id AND answered_at >= ?1)
+             ORDER BY first_seen",
+        )?;
+        let users = stmt
+            .query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(users)
+    }
+
+    /// Private chats of every known user who isn't banned or inactive
+    pub fn broadcast_chat_ids(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT private_chat_id FROM users
+             WHERE private_chat_id IS NOT NULL AND banned = 0 AND inactive = 0
+             ORDER BY first_seen",
+        )?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Subscribes a chat to the question of the day; returns false if it already was
+    pub fn subscribe(&self, chat_id: &str) -> Result<bool> {
+        let inserted = self.conn.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO subscriptions (chat_id, subscribed_at) VALUES (?1, ?2)",
+            params![chat_id, now()],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Unsubscribes a chat; returns false if it wasn't subscribed
+    pub fn unsubscribe(&self, chat_id: &str) -> Result<bool> {
+        let deleted = self.conn.lock().unwrap().execute(
+            "DELETE FROM subscriptions WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Whether a chat gets the question of the day
+    pub fn is_subscribed(&self, chat_id: &str) -> Result<bool> {
+        let subscribed = self
+            .conn
+            .lock()
+            .unwrap()
+            .prepare("SELECT 1 FROM subscriptions WHERE chat_id = ?1")?
+            .exists(params![chat_id])?;
+        Ok(subscribed)
+    }
+
+    /// Chats subscribed to the question of the day, oldest first
+    pub fn subscribed_chat_ids(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT chat_id FROM subscriptions ORDER BY subscribed_at, chat_id")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Whether the `kind` post ("question" or "answer") for `date` has gone out
+    pub fn daily_posted(&self, date: NaiveDate, kind: &str) -> Result<bool> {
+        let posted = self
+            .conn
+            .lock()
+            .unwrap()
+            .prepare("SELECT 1 FROM daily_posts WHERE date = ?1 AND kind = ?2")?
+            .exists(params![date.to_string(), kind])?;
+        Ok(posted)
+    }
+
+    pub fn record_daily_post(&self, date: NaiveDate, kind: &str, question_id: &str) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO daily_posts (date, kind, question_id, posted_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![date.to_string(), kind, question_id, now()],
+        )?;
+        Ok(())
+    }
+
+    /// Answer totals and current runs of correct answers; the practice streak is left at 0,
+    /// since it depends on the chat's timezone
+    pub fn badge_progress(&self, user_id: &str) -> Result<BadgeProgress> {
+        let conn = self.conn.lock().unwrap();
+        let (answered, correct) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(correct = 1), 0) FROM answers WHERE user_id = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT question_type, correct FROM answers
+             WHERE user_id = ?1 AND correct IS NOT NULL
+             ORDER BY answered_at DESC, rowid DESC",
+        )?;
+        let mut progress = BadgeProgress {
+            answered,
+            correct,
+            ..BadgeProgress::default()
+        };
+        // Newest first: a run ends at the first wrong answer, overall and per type
+        let mut run_over = false;
+        let mut types_over = Vec::new();
+        let mut rows = stmt.query(params![user_id])?;
+        while let Some(row) = rows.next()? {
+            let code: Option<String> = row.get(0)?;
+            let is_correct: bool = row.get(1)?;
+            if !run_over {
+                if is_correct {
+                    progress.correct_run += 1;
+                } else {
+                    run_over = true;
+                }
+            }
+            let Some(question_type) = code.as_deref().map(parse_type_code).transpose()? else {
+                continue;
+            };
+            if types_over.contains(&question_type) {
+                continue;
+            }
+            if !is_correct {
+                types_over.push(question_type);
+                continue;
+            }
+            match progress
+                .type_runs
+                .iter_mut()
+                .find(|(t, _)| *t == question_type)
+            {
+                Some((_, run)) => *run += 1,
+                None => progress.type_runs.push((question_type, 1)),
+            }
+        }
+        Ok(progress)
+    }
+
+    /// Records that the user earned `badge`; false when they already had it
+    pub fn award_badge(&self, user_id: &str, badge: &str) -> Result<bool> {
+        let inserted = self.conn.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO badges (user_id, badge, awarded_at) VALUES (?1, ?2, ?3)",
+            params![user_id, badge, now()],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// `(badge, awarded_at)` of every badge the user has earned, oldest first
+    pub fn user_badges(&self, user_id: &str) -> Result<Vec<(String, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT badge, awarded_at FROM badges WHERE user_id = ?1
+             ORDER BY awarded_at, rowid",
+        )?;
+        let badges = stmt
+            .query_map(params![user_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(badges)
+    }
+
+    /// Totals across all users, with "recent" counting from `since` (unix seconds)
+    pub fn usage_stats(&self, since: i64) -> Result<UsageStats> {
+        let conn = self.conn.lock().unwrap();
+        let (users, active_users, banned_users) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(last_seen >= ?1), 0), COALESCE(SUM(banned), 0)
+             FROM users",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let (questions_sent, recent_questions_sent) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(sent_at >= ?1), 0) FROM sent_questions",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let (answers, recent_answers, correct_answers, graded_answers) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(answered_at >= ?1), 0),
+                    COALESCE(SUM(correct = 1), 0), COUNT(correct)
+             FROM answers",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        Ok(UsageStats {
+            users,
+            active_users,
+            banned_users,
+            questions_sent,
+            recent_questions_sent,
+            answers,
+            recent_answers,
+            correct_answers,
+            graded_answers,
+        })
+    }
+
+    /// Activity from `since` up to, but not including, `until` (unix seconds)
+    pub fn activity_stats(&self, since: i64, until: i64) -> Result<ActivityStats> {
+        let conn = self.conn.lock().unwrap();
+        let questions_sent = conn.query_row(
+            "SELECT COUNT(*) FROM sent_questions WHERE sent_at >= ?1 AND sent_at < ?2",
+            params![since, until],
+            |row| row.get(0),
+        )?;
+        let (active_users, answers, correct_answers, graded_answers) = conn.query_row(
+            "SELECT COUNT(DISTINCT user_id), COUNT(*), COALESCE(SUM(correct = 1), 0),
+                    COUNT(correct)
+             FROM answers WHERE answered_at >= ?1 AND answered_at < ?2",
+            params![since, until],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        Ok(ActivityStats {
+            active_users,
+            questions_sent,
+            answers,
+            correct_answers,
+            graded_answers,
+        })
+    }
+
+    /// The difficulty band the user has reached in a type, if they've levelled up in it
+    pub fn progression_band(
+        &self,
+        user_id: &str,
+        question_type: &QuestionType,
+    ) -> Result<Option<Difficulty>> {
+        let band: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT band FROM progression WHERE user_id = ?1 AND question_type = ?2",
+                params![user_id, type_code(question_type)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(band.as_deref().and_then(parse_difficulty))
+    }
+
+    /// Moves the user to `band` in a type; only answers after this count towards the next one
+    pub fn set_progression_band(
+        &self,
+        user_id: &str,
+        question_type: &QuestionType,
+        band: Difficulty,
+    ) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO progression (user_id, question_type, band, after_answer)
+             VALUES (?1, ?2, ?3, (SELECT COALESCE(MAX(rowid), 0) FROM answers))
+             ON CONFLICT (user_id, question_type)
+             DO UPDATE SET band = excluded.band, after_answer = excluded.after_answer",
+            params![user_id, type_code(question_type), band.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Whether each of the user's latest graded answers of a type since their last level
+    /// up was right, newest first, at most `limit` of them
+    pub fn band_results(
+        &self,
+        user_id: &str,
+        question_type: &QuestionType,
+        limit: usize,
+    ) -> Result<Vec<bool>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT correct FROM answers
+             WHERE user_id = ?1 AND question_type = ?2 AND correct IS NOT NULL
+               AND rowid > COALESCE(
+                   (SELECT after_answer FROM progression
+                    WHERE user_id = ?1 AND question_type = ?2), 0)
+             ORDER BY rowid DESC
+             LIMIT ?3",
+        )?;
+        let results = stmt
+            .query_map(
+                params![user_id, type_code(question_type), limit as i64],
+                |row| row.get(0),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(results)
+    }
+
+    pub fn get_preference(&self, user_id: &str, key: &str) -> Result<Option<String>> {
+        let value = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT value FROM preferences WHERE user_id = ?1 AND key = ?2",
+                params![user_id, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn set_preference(&self, user_id: &str, key: &str, value: &str) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO preferences (user_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id, key) DO UPDATE SET value = excluded.value",
+            params![user_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Persists the question a chat is expected to answer, replacing any previous one
+    pub fn save_pending_question(&self, chat_id: &str, pending: &PendingQuestion) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO pending_questions
+             (chat_id, question_type, content, correct_answer, sent_at, choice_order)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                chat_id,
+                type_code(&pending.question_type),
+                serde_json::to_string(&pending.content)?,
+                pending.correct_answer.map(|c| c.to_string()),
+                pending.sent_at,
+                pending
+                    .choice_order
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Removes and returns the pending question for a chat
+    pub fn take_pending_question(&self, chat_id: &str) -> Result<Option<PendingQuestion>> {
+        let conn = self.conn.lock().unwrap();
+        let row = conn
+            .query_row(
+                "SELECT question_type, content, correct_answer, sent_at, choice_order
+                 FROM pending_questions WHERE chat_id = ?1",
+                params![chat_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+        conn.execute(
+            "DELETE FROM pending_questions WHERE chat_id = ?1",
+            params![chat_id],
+        )?;
+
+        let Some((question_type, content, correct_answer, sent_at, choice_order)) = row else {
+            return Ok(None);
+        };
+        let content: QuestionContent = serde_json::from_str(&content)?;
+        Ok(Some(PendingQuestion {
+            content,
+            question_type: parse_type_code(&question_type)?,
+            correct_answer: correct_answer.and_then(|c| c.chars().next()),
+            sent_at,
+            choice_order: choice_order
+                .map(|order| serde_json::from_str(&order))
+                .transpose()?,
+        }))
+    }
+
+    /// Updates the spaced-repetition schedule after an answer
+    ///
+    /// Only missed questions enter the schedule; once in, every answer moves the due date.
+    pub fn record_review(
+        &self,
+        user_id: &str,
+        question_id: &str,
+        question_type: &QuestionType,
+        correct: bool,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let state = conn
+            .query_row(
+                "SELECT ease, interval_days, repetitions FROM reviews
+                 WHERE user_id = ?1 AND question_id = ?2",
+                params![user_id, question_id],
+                |row| {
+                    Ok(ReviewState {
+                        ease: row.get(0)?,
+                        interval_days: row.get(1)?,
+                        repetitions: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        let state = match state {
+            Some(state) => state,
+            None if correct => return Ok(()),
+            None => ReviewState::default(),
+        }
+        .answered(correct);
+
+        let due_at = now() + i64::from(state.interval_days) * 24 * 60 * 60;
+        conn.execute(
+            "INSERT OR REPLACE INTO reviews
+             (user_id, question_id, question_type, ease, interval_days, repetitions, due_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                user_id,
+                question_id,
+                type_code(question_type),
+                state.ease,
+                state.interval_days,
+                state.repetitions,
+                due_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most overdue review item for a user, if any is due now
+    pub fn next_due_review(&self, user_id: &str) -> Result<Option<(QuestionType, String)>> {
+        let row = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT question_type, question_id FROM reviews
+                 WHERE user_id = ?1 AND due_at <= ?2
+                 ORDER BY due_at LIMIT 1",
+                params![user_id, now()],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?;
+        let Some((question_type, question_id)) = row else {
+            return Ok(None);
+        };
+        Ok(Some((parse_type_code(&question_type)?, question_id)))
+    }
+
+    /// When the user's next review item becomes due (unix seconds)
+    pub fn next_review_time(&self, user_id: &str) -> Result<Option<i64>> {
+        let due_at = self.conn.lock().unwrap().query_row(
+            "SELECT MIN(due_at) FROM reviews WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get(0),
+        )?;
+        Ok(due_at)
+    }
+
+    pub fn get_chat_config(&self, chat_id: &str) -> Result<Option<ChatConfig>> {
+        let row = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT command_prefix, allowed_types, mute_start, mute_end, timezone, mute_hours
+                 FROM chat_configs WHERE chat_id = ?1",
+                params![chat_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<u32>>(2)?,
+                        row.get::<_, Option<u32>>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((command_prefix, allowed_types, mute_start, mute_end, timezone, mute_hours)) = row
+        else {
+            return Ok(None);
+        };
+        // Stores written before minute precision only have whole hours
+        let mute_hours = match mute_hours {
+            Some(mute_hours) => Some(mute_hours.parse().map_err(crate::BotError::Config)?),
+            None => mute_start
+                .zip(mute_end)
+                .map(|(start, end)| format!("{}-{}", start, end).parse())
+                .transpose()
+                .map_err(crate::BotError::Config)?,
+        };
+
+        Ok(Some(ChatConfig {
+            command_prefix,
+            allowed_types: allowed_types
+                .map(|types| types.split(',').map(parse_type_code).collect())
+                .transpose()?,
+            mute_hours,
+            timezone: crate::parse_timezone(&timezone)?,
+        }))
+    }
+
+    pub fn save_chat_config(&self, chat_id: &str, config: &ChatConfig) -> Result<()> {
+        let allowed_types = config
+            .allowed_types
+            .as_ref()
+            .map(|types| types.iter().map(type_code).collect::<Vec<_>>().join(","));
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO chat_configs
+             (chat_id, command_prefix, allowed_types, mute_hours, timezone)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                chat_id,
+                config.command_prefix,
+                allowed_types,
+                config.mute_hours.map(|mute_hours| mute_hours.to_string()),
+                config.timezone.name()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records a user's report that a question is broken
+    pub fn record_question_report(
+        &self,
+        question_id: &str,
+        question_type: QuestionType,
+        chat_id: &str,
+        user_id: &str,
+        note: &str,
+    ) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO question_reports
+             (question_id, question_type, chat_id, user_id, note, reported_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                question_id,
+                type_code(&question_type),
+                chat_id,
+                user_id,
+                note,
+                now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every flagged question, most reported first
+    pub fn flagged_questions(&self) -> Result<Vec<FlaggedQuestion>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT question_id, question_type, note, reported_at
+             FROM question_reports
+             ORDER BY reported_at, rowid",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut flagged: Vec<FlaggedQuestion> = Vec::new();
+        for (question_id, question_type, note, reported_at) in rows {
+            let question_type = question_type.as_deref().map(parse_type_code).transpose()?;
+            let question = match flagged.iter_mut().find(|q| q.question_id == question_id) {
+                Some(question) => question,
+                None => {
+                    flagged.push(FlaggedQuestion {
+                        question_id,
+                        question_type: None,
+                        first_reported_at: reported_at,
+                        last_reported_at: reported_at,
+                        notes: Vec::new(),
+                        reports: 0,
+                    });
+                    flagged.last_mut().unwrap()
+                }
+            };
+            question.reports += 1;
+            question.last_reported_at = reported_at;
+            question.question_type = question.question_type.or(question_type);
+            if !note.is_empty() {
+                question.notes.push(note);
+            }
+        }
+        flagged.sort_by(|a, b| {
+            b.reports
+                .cmp(&a.reports)
+                .then(b.last_reported_at.cmp(&a.last_reported_at))
+        });
+        Ok(flagged)
+    }
+
+    /// A user's saved defaults; `daily` is left off, it lives in the subscriptions
+    pub fn get_user_settings(&self, user_id: &str) -> Result<Option<UserSettings>> {
+        let row = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT default_types, always_explain, difficulty, shuffle_answers, language
+                 FROM user_settings WHERE user_id = ?1",
+                params![user_id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, bool>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, bool>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((default_types, always_explain, difficulty, shuffle_answers, language)) = row
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(UserSettings {
+            default_types: default_types
+                .map(|types| types.split(',').map(parse_type_code).collect())
+                .transpose()?,
+            always_explain,
+            difficulty: difficulty.as_deref().and_then(parse_difficulty),
+            daily: false,
+            shuffle_answers,
+            language,
+        }))
+    }
+
+    /// Saves a user's defaults, replacing any earlier ones
+    pub fn save_user_settings(&self, user_id: &str, settings: &UserSettings) -> Result<()> {
+        let default_types = settings
+            .default_types
+            .as_ref()
+            .map(|types| types.iter().map(type_code).collect::<Vec<_>>().join(","));
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO user_settings
+             (user_id, default_types, always_explain, difficulty, shuffle_answers, language)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                user_id,
+                default_types,
+                settings.always_explain,
+                settings.difficulty.map(|d| d.to_string()),
+                settings.shuffle_answers,
+                settings.language
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// URLs of a previously rendered and hosted image for this question variant,
+    /// one per page when it was split; ones uploaded longer than `max_age` ago are left out
+    pub fn hosted_image_urls(
+        &self,
+        question_id: &str,
+        show_explanations: bool,
+        max_age: Option<Duration>,
+    ) -> Result<Option<Vec<String>>> {
+        let uploaded_after = max_age.map_or(i64::MIN, |age| now() - age.as_secs() as i64);
+        let urls: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT url FROM hosted_images
+                 WHERE question_id = ?1 AND show_explanations = ?2 AND uploaded_at > ?3",
+                params![question_id, show_explanations, uploaded_after],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(urls.map(|urls| urls.lines().map(str::to_string).collect()))
+    }
+
+    /// Remembers the hosted pages of an image, stored one URL per line
+    pub fn save_hosted_image_urls(
+        &self,
+        question_id: &str,
+        show_explanations: bool,
+        urls: &[String],
+    ) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO hosted_images
+             (question_id, show_explanations, url, uploaded_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![question_id, show_explanations, urls.join("\n"), now()],
+        )?;
+        Ok(())
+    }
+
+    /// Every hosted image, by key and then without explanations first
+    pub fn hosted_images(&self) -> Result<Vec<HostedImage>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT question_id, show_explanations, url, uploaded_at FROM hosted_images
+             ORDER BY question_id, show_explanations",
+        )?;
+        let images = stmt
+            .query_map([], |row| {
+                Ok(HostedImage {
+                    key: row.get(0)?,
+                    show_explanations: row.get(1)?,
+                    urls: row
+                        .get::<_, String>(2)?
+                        .lines()
+                        .map(str::to_string)
+                        .collect(),
+                    uploaded_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(images)
+    }
+
+    /// Saves a hosted image from elsewhere unless a later upload of it is already
+    /// stored; false when it was kept out
+    pub fn import_hosted_image(&self, image: &HostedImage) -> Result<bool> {
+        let changed = self.conn.lock().unwrap().execute(
+            "INSERT INTO hosted_images (question_id, show_explanations, url, uploaded_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (question_id, show_explanations)
+             DO UPDATE SET url = excluded.url, uploaded_at = excluded.uploaded_at
+             WHERE excluded.uploaded_at > hosted_images.uploaded_at",
+            params![
+                image.key,
+                image.show_explanations,
+                image.urls.join("\n"),
+                image.uploaded_at
+            ],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn forget_hosted_image(&self, question_id: &str, show_explanations: bool) -> Result<()> {
+        self.conn.lock().unwrap().execute(
+            "DELETE FROM hosted_images WHERE question_id = ?1 AND show_explanations = ?2",
+            params![question_id, show_explanations],
+        )?;
+        Ok(())
+    }
+}
+
+fn assignment_from_row(row: &rusqlite::Row) -> rusqlite::Result<Assignment> {
+    let question_ids: String = row.get(4)?;
+    Ok(Assignment {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_by: row.get(2)?,
+        created_at: row.get(3)?,
+        question_ids: question_ids.split(',').map(str::to_string).collect(),
+    })
+}
+
+/// The student's first answer to each of the assignment's questions since it was created
+fn student_progress(
+    conn: &Connection,
+    assignment: &Assignment,
+    user_id: String,
+    display_name: Option<String>,
+    delivered_at: Option<i64>,
+) -> Result<StudentProgress> {
+    let mut stmt = conn.prepare(
+        "SELECT question_id, correct FROM answers
+         WHERE user_id = ?1 AND answered_at >= ?2 ORDER BY answered_at, rowid",
+    )?;
+    let mut answers = BTreeMap::new();
+    let mut latest_first_answer = None;
+    for answer in stmt.query_map(params![user_id, assignment.created_at], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<bool>>(1)?))
+    })? {
+        let (question_id, correct) = answer?;
+        if !assignment.question_ids.contains(&question_id) {
+            continue;
+        }
+        latest_first_answer = if answers.contains_key(&question_id) {
+            None
+        } else {
+            answers.insert(question_id.clone(), correct);
+            Some(question_id)
+        };
+    }
+    Ok(StudentProgress {
+        user_id,
+        display_name,
+        delivered_at,
+        answers,
+        latest_first_answer,
+    })
+}
+
+/// Short code used to store a question type, e.g. "ps"
+fn type_code(question_type: &QuestionType) -> String {
+    question_type
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_type_code(code: &str) -> Result<QuestionType> {
+    QuestionType::from_str(code, true)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, e.into()).into())
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
@@ -0,0 +1,36 @@
+use chrono::{Days, NaiveDate};
+
+/// Preference key holding the last date a user was reminded about their streak
+pub const STREAK_REMINDED_PREFERENCE: &str = "streak_reminded";
+
+/// Consecutive practice days ending today, or yesterday when today has no
+/// practice yet; `days` may be in any order and contain duplicates
+pub fn practice_streak(days: &[NaiveDate], today: NaiveDate) -> u32 {
+    let yesterday = today - Days::new(1);
+    let mut day = if days.contains(&today) {
+        today
+    } else if days.contains(&yesterday) {
+        yesterday
+    } else {
+        return 0;
+    };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day = day - Days::new(1);
+    }
+    streak
+}
+
+/// Caption line for a streak, e.g. "🔥 Day 7 streak!"; a single day isn't a streak yet
+pub fn format_streak(streak: u32) -> Option<String> {
+    (streak >= 2).then(|| format!("🔥 Day {} streak!", streak))
+}
+
+/// Nudge sent to a user whose streak will end unless they practice today
+pub fn format_streak_reminder(streak: u32) -> String {
+    format!(
+        "🔥 You're on a {}-day streak! Answer a question today to keep it going. Send 'ps', 'ds', 'cr' or 'sc' to get one.",
+        streak
+    )
+}
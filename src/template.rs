@@ -0,0 +1,161 @@
+use crate::{
+    ANSWER_CHOICES, BotError, MATHJAX_CDN_URL, QuestionContent, QuestionStats, QuestionType,
+    Result, Theme, type_style,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tera::{Context, Tera};
+
+/// Name of the question page template, both bundled and in a `--template-dir`
+pub const QUESTION_TEMPLATE: &str = "question.html";
+
+const BUNDLED_QUESTION_TEMPLATE: &str = include_str!("../templates/question.html");
+
+/// Question fields exposed to templates as `question`
+#[derive(Serialize)]
+struct QuestionContext<'a> {
+    id: &'a str,
+    src: &'a str,
+    /// Full type name, e.g. "Problem Solving"
+    type_name: String,
+    question: &'a str,
+    answers: Vec<AnswerContext<'a>>,
+    explanations: &'a [String],
+    difficulty: Option<&'a str>,
+    topics: &'a [String],
+    /// Source-page stats, when the question was enriched
+    stats: Option<&'a QuestionStats>,
+}
+
+#[derive(Serialize)]
+struct AnswerContext<'a> {
+    label: String,
+    text: &'a str,
+}
+
+/// Tera templates that turn question content into the HTML page to render
+pub struct QuestionTemplates {
+    tera: Tera,
+    /// `src` of the MathJax script, exposed to templates as `mathjax_url`
+    mathjax_url: String,
+    /// Directory to save each generated page in, see [`crate::keep_html`]
+    kept_html_dir: Option<PathBuf>,
+}
+
+impl QuestionTemplates {
+    /// The default templates compiled into the binary
+    pub fn bundled() -> Self {
+        let mut tera = Tera::default();
+        tera.add_raw_template(QUESTION_TEMPLATE, BUNDLED_QUESTION_TEMPLATE)
+            .expect("bundled question template is valid");
+        Self {
+            tera,
+            mathjax_url: MATHJAX_CDN_URL.to_string(),
+            kept_html_dir: None,
+        }
+    }
+
+    /// Loads MathJax from `mathjax_url` instead of the CDN, see `mathjax_script_url`
+    pub fn with_mathjax_url(mut self, mathjax_url: String) -> Self {
+        self.mathjax_url = mathjax_url;
+        self
+    }
+
+    /// Keeps every page rendered to an image in `dir`, for reproducing rendering issues
+    pub fn with_kept_html(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.kept_html_dir = Some(dir.into());
+        self
+    }
+
+    pub fn kept_html_dir(&self) -> Option<&Path> {
+        self.kept_html_dir.as_deref()
+    }
+
+    /// Loads `question.html` from `dir`, keeping the bundled template when it isn't there
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(BotError::Config(format!(
+                "Template directory {} does not exist",
+                dir.display()
+            )));
+        }
+        let mut templates = Self::bundled();
+        let path = dir.join(QUESTION_TEMPLATE);
+        if path.exists() {
+            templates
+                .tera
+                .add_template_file(&path, Some(QUESTION_TEMPLATE))
+                .map_err(|e| template_error(&format!("Invalid template {}", path.display()), e))?;
+        }
+        Ok(templates)
+    }
+
+    /// Renders the question page; `show_explanations`, the theme's `palette` and the
+    /// question type's `style` are in the context too
+    pub fn render_question(
+        &self,
+        content: &QuestionContent,
+        question_type: &QuestionType,
+        show_explanations: bool,
+        theme: Theme,
+    ) -> Result<String> {
+        let answers = content
+            .answers
+            .iter()
+            .enumerate()
+            .map(|(i, answer)| AnswerContext {
+                label: ANSWER_CHOICES
+                    .get(i)
+                    .map_or_else(|| (i + 1).to_string(), |label| label.to_string()),
+                text: answer,
+            })
+            .collect();
+        let question = QuestionContext {
+            id: &content.id,
+            src: &content.src,
+            type_name: question_type.to_string(),
+            question: &content.question,
+            answers,
+            explanations: &content.explanations,
+            difficulty: content.difficulty.as_deref(),
+            topics: &content.topics,
+            stats: content.stats.as_ref(),
+        };
+
+        let mut context = Context::new();
+        context.insert("question", &question);
+        context.insert("show_explanations", &show_explanations);
+        context.insert("theme", &theme.to_string());
+        context.insert("palette", &theme.palette());
+        context.insert("style", &type_style(*question_type, theme));
+        context.insert("mathjax_url", &self.mathjax_url);
+        self.tera
+            .render(QUESTION_TEMPLATE, &context)
+            .map_err(|e| template_error("Failed to render question template", e))
+    }
+}
+
+impl Default for QuestionTemplates {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+/// The bundled templates, built once
+pub(crate) fn bundled_templates() -> &'static QuestionTemplates {
+    static TEMPLATES: OnceLock<QuestionTemplates> = OnceLock::new();
+    TEMPLATES.get_or_init(QuestionTemplates::bundled)
+}
+
+/// Tera keeps the useful part (line, missing variable) in the error's sources
+fn template_error(context: &str, error: tera::Error) -> BotError {
+    let mut message = format!("{}: {}", context, error);
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    BotError::Render(message)
+}
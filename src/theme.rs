@@ -0,0 +1,161 @@
+use crate::QuestionType;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Preference key a user's chosen theme is stored under
+pub const THEME_PREFERENCE: &str = "theme";
+
+/// Colour scheme for rendered question images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+    /// Black background with white text and yellow accents
+    HighContrast,
+    /// Black on white with no tinted panels, for printing
+    Print,
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default();
+        write!(f, "{}", name)
+    }
+}
+
+/// CSS colours a theme fills into the question template
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Palette {
+    pub background: &'static str,
+    pub text: &'static str,
+    /// Header background, section titles and links
+    pub accent: &'static str,
+    /// Text on the accent-coloured header
+    pub header_text: &'static str,
+    /// Background of the answer, explanation and source boxes
+    pub panel: &'static str,
+    /// Question text and bold text
+    pub strong: &'static str,
+    pub muted: &'static str,
+    pub border: &'static str,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Light => Palette {
+                background: "#ffffff",
+                text: "#333",
+                accent: "#0068ff",
+                header_text: "white",
+                panel: "#f9f9f9",
+                strong: "#2c3e50",
+                muted: "#7f8c8d",
+                border: "#eee",
+            },
+            Theme::Dark => Palette {
+                background: "#1e1e1e",
+                text: "#e0e0e0",
+                accent: "#4d9bff",
+                header_text: "white",
+                panel: "#2a2a2a",
+                strong: "#ffffff",
+                muted: "#a0a0a0",
+                border: "#3a3a3a",
+            },
+            Theme::HighContrast => Palette {
+                background: "#000000",
+                text: "#ffffff",
+                accent: "#ffff00",
+                header_text: "#000000",
+                panel: "#000000",
+                strong: "#ffffff",
+                muted: "#ffffff",
+                border: "#ffffff",
+            },
+            Theme::Print => Palette {
+                background: "#ffffff",
+                text: "#000000",
+                accent: "#000000",
+                header_text: "#ffffff",
+                panel: "#ffffff",
+                strong: "#000000",
+                muted: "#444444",
+                border: "#999999",
+            },
+        }
+    }
+}
+
+/// How a question type sets itself apart on the rendered page
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TypeStyle {
+    /// Header background and section titles; replaces the palette's accent
+    pub accent: &'static str,
+    pub icon: &'static str,
+    /// One line under the type name saying what the question asks for
+    pub subtitle: &'static str,
+    /// Instructions shown above the answer choices, for types with fixed ones
+    pub legend: Option<&'static str>,
+}
+
+/// The style for `question_type` in `theme`. High-contrast and print keep their
+/// own accent, since a per-type colour would undo what they are for
+pub fn type_style(question_type: QuestionType, theme: Theme) -> TypeStyle {
+    let (light, dark, icon, subtitle, legend) = match question_type {
+        QuestionType::RC => (
+            "#00796b",
+            "#4db6ac",
+            "📖",
+            "Read the passage, then answer the question",
+            None,
+        ),
+        QuestionType::SC => (
+            "#7b1fa2",
+            "#ba68c8",
+            "✏️",
+            "Choose the best version of the underlined part",
+            None,
+        ),
+        QuestionType::CR => ("#e65100", "#ffb74d", "🧠", "Evaluate the argument", None),
+        QuestionType::PS => (
+            "#0068ff",
+            "#4d9bff",
+            "🔢",
+            "Solve the problem and pick the answer",
+            None,
+        ),
+        QuestionType::DS => (
+            "#2e7d32",
+            "#81c784",
+            "📊",
+            "Decide whether the data given are sufficient",
+            Some(
+                "Statements (1) and (2): decide whether the data in each statement are \
+                 sufficient to answer the question, using the facts in the question and \
+                 everyday knowledge.",
+            ),
+        ),
+    };
+    let accent = match theme {
+        Theme::Light => light,
+        Theme::Dark => dark,
+        Theme::HighContrast | Theme::Print => theme.palette().accent,
+    };
+    TypeStyle {
+        accent,
+        icon,
+        subtitle,
+        legend,
+    }
+}
+
+/// Parses the argument of a "theme dark" command
+pub fn parse_theme(text: &str) -> Option<Theme> {
+    Theme::from_str(text.trim(), true).ok()
+}
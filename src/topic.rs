@@ -0,0 +1,162 @@
+use crate::{
+    BotError, DatabaseSource, ExclusionList, GmatDatabase, QuestionCache, QuestionContent,
+    QuestionEnricher, QuestionType, Result, fetch_question_content,
+    pick_random_questions_excluding,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+/// How many untagged random candidates are fetched while looking for a topic
+const MAX_TOPIC_PROBES: usize = 30;
+
+/// Whether two topic names are the same, ignoring case, spaces and dashes, so
+/// "bold-face" matches "Boldface"
+pub fn same_topic(a: &str, b: &str) -> bool {
+    let key = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    key(a) == key(b)
+}
+
+/// Whether a question is tagged with `topic`, in its own topics or its source thread's tags
+pub fn has_topic(content: &QuestionContent, topic: &str) -> bool {
+    content
+        .topics
+        .iter()
+        .chain(content.stats.iter().flat_map(|stats| &stats.tags))
+        .any(|tag| same_topic(tag, topic))
+}
+
+/// Topic tags for questions the question bank doesn't tag, from a JSON file mapping
+/// question IDs to topics: `{"12345": ["geometry", "triangles"]}`
+#[derive(Debug, Clone, Default)]
+pub struct TopicTags {
+    by_question: HashMap<String, Vec<String>>,
+}
+
+impl TopicTags {
+    pub fn new(by_question: HashMap<String, Vec<String>>) -> Self {
+        Self { by_question }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|e| {
+            BotError::Config(format!(
+                "Failed to read topic tags {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let by_question = serde_json::from_str(&json).map_err(|e| {
+            BotError::Config(format!("Invalid topic tags {}: {}", path.display(), e))
+        })?;
+        Ok(Self::new(by_question))
+    }
+
+    pub fn topics_of(&self, question_id: &str) -> &[String] {
+        self.by_question
+            .get(question_id)
+            .map_or(&[], |topics| topics.as_slice())
+    }
+
+    pub fn is_tagged(&self, question_id: &str, topic: &str) -> bool {
+        self.topics_of(question_id)
+            .iter()
+            .any(|tag| same_topic(tag, topic))
+    }
+
+    /// Every topic in the file, sorted, each spelled as it first appears
+    pub fn all_topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = Vec::new();
+        for tag in self.by_question.values().flatten() {
+            if !topics.iter().any(|topic| same_topic(topic, tag)) {
+                topics.push(tag.clone());
+            }
+        }
+        topics.sort_by_key(|topic| topic.to_lowercase());
+        topics
+    }
+
+    /// Adds the file's topics for `content` to those it already has
+    pub fn apply(&self, content: &mut QuestionContent) {
+        for tag in self.topics_of(&content.id) {
+            if !content.topics.iter().any(|topic| same_topic(topic, tag)) {
+                content.topics.push(tag.clone());
+            }
+        }
+    }
+}
+
+/// Where a question's topics are looked up: the tag file, then its content, then its
+/// source thread when an enricher is set
+#[derive(Clone, Copy)]
+pub struct TopicLookup<'a> {
+    pub source: &'a DatabaseSource,
+    pub cache: Option<&'a QuestionCache>,
+    pub enricher: Option<&'a QuestionEnricher>,
+    pub tags: Option<&'a TopicTags>,
+}
+
+/// Picks questions about `topic`: ones the tag file lists first, then random others,
+/// fetched to read their own tags, until there are enough or the probes run out
+pub async fn pick_random_questions_by_topic(
+    database: &GmatDatabase,
+    exclusions: &ExclusionList,
+    question_type: &Option<QuestionType>,
+    topic: &str,
+    count: usize,
+    exclude: &HashSet<String>,
+    lookup: TopicLookup<'_>,
+) -> Vec<(QuestionType, QuestionContent)> {
+    let is_tagged = |id: &str| lookup.tags.is_some_and(|tags| tags.is_tagged(id, topic));
+    let mut candidates =
+        pick_random_questions_excluding(database, exclusions, question_type, usize::MAX, exclude);
+    // Stable, so both groups stay in random order
+    candidates.sort_by_key(|(_, id)| !is_tagged(id));
+
+    let mut results = Vec::new();
+    let mut probes = 0;
+    for (qtype, question_id) in candidates {
+        if results.len() >= count {
+            break;
+        }
+        if !is_tagged(&question_id) {
+            if probes >= MAX_TOPIC_PROBES {
+                break;
+            }
+            probes += 1;
+        }
+        match fetch_question_content(lookup.source, &question_id, lookup.cache).await {
+            Ok(mut content) => {
+                if let Some(tags) = lookup.tags {
+                    tags.apply(&mut content);
+                }
+                if !has_topic(&content, topic)
+                    && let Some(enricher) = lookup.enricher
+                {
+                    enricher.enrich(&mut content).await;
+                }
+                if has_topic(&content, topic) {
+                    results.push((qtype, content));
+                }
+            }
+            Err(e) => warn!("Skipping question {}: {}", question_id, e),
+        }
+    }
+    results
+}
+
+/// Parses "topic geometry" into the topic; "topic" alone gives an empty one
+pub fn parse_topic_command(text: &str) -> Option<String> {
+    let text = text.trim();
+    let (command, topic) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    command
+        .eq_ignore_ascii_case("topic")
+        .then(|| topic.trim().to_string())
+}
@@ -0,0 +1,135 @@
+use crate::report::csv_field;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Longest chat message an export is split into
+pub const MAX_EXPORT_MESSAGE_CHARS: usize = 3000;
+
+/// How "mydata" and `user-data export` write a user's data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserDataFormat {
+    /// Everything in one JSON document
+    #[default]
+    Json,
+    /// One `table,row,column,value` line per stored value, for spreadsheets
+    Csv,
+}
+
+/// Everything the bot stores about one user
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserData {
+    pub user_id: String,
+    /// Unix seconds
+    pub exported_at: i64,
+    /// Correct answers in a row, counting back from the latest
+    pub correct_streak: u32,
+    /// UTC dates with at least one answer, latest first
+    pub practice_days: Vec<NaiveDate>,
+    /// Stored rows about the user or their private chat, by table
+    pub tables: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+impl UserData {
+    /// Rows stored across all tables
+    pub fn records(&self) -> usize {
+        self.tables.values().map(Vec::len).sum()
+    }
+}
+
+/// Parses "mydata", "mydata json" or "mydata csv"
+pub fn parse_mydata_command(text: &str) -> Option<UserDataFormat> {
+    let mut words = text.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("mydata") {
+        return None;
+    }
+    match words.next() {
+        None => Some(UserDataFormat::Json),
+        Some(format) => UserDataFormat::from_str(format, true).ok(),
+    }
+}
+
+pub fn format_user_data(data: &UserData, format: UserDataFormat) -> String {
+    match format {
+        UserDataFormat::Json => {
+            let mut json = serde_json::to_string_pretty(data).unwrap_or_default();
+            json.push('\n');
+            json
+        }
+        UserDataFormat::Csv => {
+            let mut csv = "table,row,column,value\n".to_string();
+            let summary = [
+                ("user_id", data.user_id.clone()),
+                ("exported_at", data.exported_at.to_string()),
+                ("correct_streak", data.correct_streak.to_string()),
+                (
+                    "practice_days",
+                    data.practice_days
+                        .iter()
+                        .map(|day| day.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+            ];
+            for (column, value) in summary {
+                csv.push_str(&format!("summary,1,{},{}\n", column, csv_field(&value)));
+            }
+            for (table, rows) in &data.tables {
+                for (i, row) in rows.iter().enumerate() {
+                    for (column, value) in row {
+                        let value = match value {
+                            Value::Null => String::new(),
+                            Value::String(text) => text.clone(),
+                            other => other.to_string(),
+                        };
+                        csv.push_str(&format!(
+                            "{},{},{},{}\n",
+                            table,
+                            i + 1,
+                            column,
+                            csv_field(&value)
+                        ));
+                    }
+                }
+            }
+            csv
+        }
+    }
+}
+
+/// Splits `text` into chat messages of at most `max_chars` characters, between
+/// lines where it can
+pub fn split_into_messages(text: &str, max_chars: usize) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        let mut line = line.to_string();
+        // A line too long for one message is cut wherever the limit falls
+        while line.chars().count() > max_chars {
+            let cut = line
+                .char_indices()
+                .nth(max_chars)
+                .map_or(line.len(), |(i, _)| i);
+            let rest = line.split_off(cut);
+            if !current.is_empty() {
+                messages.push(std::mem::take(&mut current));
+            }
+            messages.push(line);
+            line = rest;
+        }
+        if !current.is_empty() && current.chars().count() + 1 + line.chars().count() > max_chars {
+            messages.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
@@ -0,0 +1,216 @@
+use crate::report::csv_field;
+use crate::{
+    BotError, DatabaseSource, GmatDatabase, QuestionContent, QuestionType, ReportFormat, Result,
+};
+use clap::ValueEnum;
+use futures_util::{StreamExt, stream};
+use reqwest::StatusCode;
+use std::fmt;
+use tracing::info;
+
+/// What is wrong with a question listed in the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyIssue {
+    /// Its JSON doesn't exist
+    Missing,
+    /// Its JSON doesn't parse as a question
+    Invalid(String),
+    /// It parses but has no answer choices to pick from
+    EmptyAnswers,
+    /// It couldn't be fetched, e.g. the server kept failing, so it may well be fine
+    Unreachable(String),
+}
+
+impl VerifyIssue {
+    /// Whether the question itself is bad, rather than just couldn't be checked
+    pub fn is_broken(&self) -> bool {
+        !matches!(self, VerifyIssue::Unreachable(_))
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            VerifyIssue::Missing => "missing",
+            VerifyIssue::Invalid(_) => "invalid",
+            VerifyIssue::EmptyAnswers => "empty-answers",
+            VerifyIssue::Unreachable(_) => "unreachable",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            VerifyIssue::Invalid(detail) | VerifyIssue::Unreachable(detail) => detail,
+            VerifyIssue::Missing | VerifyIssue::EmptyAnswers => "",
+        }
+    }
+}
+
+impl fmt::Display for VerifyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyIssue::Missing => write!(f, "missing"),
+            VerifyIssue::Invalid(detail) => write!(f, "invalid: {}", detail),
+            VerifyIssue::EmptyAnswers => write!(f, "no answer choices"),
+            VerifyIssue::Unreachable(detail) => write!(f, "couldn't be fetched: {}", detail),
+        }
+    }
+}
+
+/// A question that failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyProblem {
+    pub question_id: String,
+    pub question_type: QuestionType,
+    pub issue: VerifyIssue,
+}
+
+/// The outcome of checking every question in the index
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Questions checked, good or bad
+    pub checked: usize,
+    /// Problems in index order
+    pub problems: Vec<VerifyProblem>,
+}
+
+impl VerifyReport {
+    /// IDs of the questions that are bad, leaving out ones that merely couldn't be fetched
+    pub fn broken_ids(&self) -> Vec<&str> {
+        self.problems
+            .iter()
+            .filter(|problem| problem.issue.is_broken())
+            .map(|problem| problem.question_id.as_str())
+            .collect()
+    }
+}
+
+/// Fetches every question in the index, or only those of `question_type`, straight from
+/// `source` with `concurrency` requests in flight, and reports the ones that are missing,
+/// don't parse or have no answer choices
+pub async fn verify_database(
+    source: &DatabaseSource,
+    database: &GmatDatabase,
+    question_type: Option<QuestionType>,
+    concurrency: usize,
+) -> VerifyReport {
+    let questions: Vec<(QuestionType, &String)> = QuestionType::value_variants()
+        .iter()
+        .filter(|t| question_type.is_none_or(|wanted| wanted == **t))
+        .flat_map(|t| {
+            database
+                .get_questions_by_type(t)
+                .iter()
+                .map(move |id| (*t, id))
+        })
+        .collect();
+    info!("Verifying {} question(s) from {}", questions.len(), source);
+
+    let mut outcomes: Vec<(usize, Option<VerifyProblem>)> =
+        stream::iter(questions.iter().enumerate())
+            .map(|(index, (question_type, question_id))| async move {
+                let issue = classify(source.fetch_question(question_id).await);
+                let problem = issue.map(|issue| VerifyProblem {
+                    question_id: question_id.to_string(),
+                    question_type: *question_type,
+                    issue,
+                });
+                (index, problem)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+    outcomes.sort_by_key(|(index, _)| *index);
+
+    VerifyReport {
+        checked: questions.len(),
+        problems: outcomes
+            .into_iter()
+            .filter_map(|(_, problem)| problem)
+            .collect(),
+    }
+}
+
+/// What's wrong with a fetched question, if anything
+fn classify(fetched: Result<QuestionContent>) -> Option<VerifyIssue> {
+    let content = match fetched {
+        Ok(content) => content,
+        Err(e) => {
+            return Some(match e.root() {
+                BotError::QuestionFetch { status, .. } if *status == StatusCode::NOT_FOUND => {
+                    VerifyIssue::Missing
+                }
+                BotError::Io(io) if io.kind() == std::io::ErrorKind::NotFound => {
+                    VerifyIssue::Missing
+                }
+                BotError::Json(json) => VerifyIssue::Invalid(json.to_string()),
+                BotError::Http(http) if http.is_decode() => VerifyIssue::Invalid(http.to_string()),
+                // An ID the local source refuses to read, like one with a slash in it
+                BotError::Config(message) => VerifyIssue::Invalid(message.clone()),
+                _ => VerifyIssue::Unreachable(e.to_string()),
+            });
+        }
+    };
+    let has_answers = !content.answers.is_empty()
+        || content
+            .sub_questions
+            .iter()
+            .any(|sub| !sub.answers.is_empty());
+    (!has_answers).then_some(VerifyIssue::EmptyAnswers)
+}
+
+/// Renders a verification report for the `verify` command; the `ids` format lists only
+/// broken questions, ready to exclude from selection
+pub fn format_verify_report(report: &VerifyReport, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Text => {
+            let broken = report.broken_ids().len();
+            let mut text = format!(
+                "Checked {} question(s): {} broken, {} couldn't be fetched\n",
+                report.checked,
+                broken,
+                report.problems.len() - broken
+            );
+            for problem in &report.problems {
+                text.push_str(&format!(
+                    "#{} {:?} - {}\n",
+                    problem.question_id, problem.question_type, problem.issue
+                ));
+            }
+            text
+        }
+        ReportFormat::Csv => {
+            let mut csv = "question_id,question_type,issue,detail\n".to_string();
+            for problem in &report.problems {
+                csv.push_str(&format!(
+                    "{},{:?},{},{}\n",
+                    csv_field(&problem.question_id),
+                    problem.question_type,
+                    problem.issue.code(),
+                    csv_field(problem.issue.detail())
+                ));
+            }
+            csv
+        }
+        ReportFormat::Json => {
+            let rows: Vec<_> = report
+                .problems
+                .iter()
+                .map(|problem| {
+                    serde_json::json!({
+                        "question_id": problem.question_id,
+                        "question_type": format!("{:?}", problem.question_type),
+                        "issue": problem.issue.code(),
+                        "detail": problem.issue.detail(),
+                    })
+                })
+                .collect();
+            let mut json = serde_json::to_string_pretty(&rows).unwrap_or_default();
+            json.push('\n');
+            json
+        }
+        ReportFormat::Ids => report
+            .broken_ids()
+            .iter()
+            .map(|id| format!("{}\n", id))
+            .collect(),
+    }
+}
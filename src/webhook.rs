@@ -0,0 +1,313 @@
+use crate::http::default_client;
+use crate::{BotError, QuestionType, Result};
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use tracing::{debug, warn};
+
+/// Header carrying `sha256=<hex HMAC of the body>` for webhooks with a secret
+pub const SIGNATURE_HEADER: &str = "X-Gmat-Signature";
+
+/// Names of the events webhooks can subscribe to
+pub const WEBHOOK_EVENTS: [&str; 4] = [
+    "question_sent",
+    "answer_recorded",
+    "render_failed",
+    "daily_summary",
+];
+
+/// Something that happened in the bot, posted to webhooks as a JSON object with its
+/// name under `event`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    QuestionSent {
+        chat_id: String,
+        question_id: String,
+        question_type: QuestionType,
+    },
+    AnswerRecorded {
+        chat_id: String,
+        user_id: String,
+        question_id: String,
+        question_type: QuestionType,
+        /// The choice under its original letter
+        answer: char,
+        /// `None` when the answer key is unknown
+        correct: Option<bool>,
+        /// Seconds since the question was sent
+        response_secs: i64,
+    },
+    /// The question went out as text instead
+    RenderFailed {
+        /// `None` when rendered once for every recipient of a `send`
+        chat_id: Option<String>,
+        question_id: String,
+        error: String,
+    },
+    /// The bot's activity over one UTC day, posted after it ends
+    DailySummary {
+        date: NaiveDate,
+        active_users: u32,
+        questions_sent: u32,
+        answers: u32,
+        correct_answers: u32,
+        graded_answers: u32,
+    },
+}
+
+impl WebhookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::QuestionSent { .. } => "question_sent",
+            WebhookEvent::AnswerRecorded { .. } => "answer_recorded",
+            WebhookEvent::RenderFailed { .. } => "render_failed",
+            WebhookEvent::DailySummary { .. } => "daily_summary",
+        }
+    }
+
+    /// One line for chat apps such as Slack and Discord
+    pub fn summary(&self) -> String {
+        match self {
+            WebhookEvent::QuestionSent {
+                chat_id,
+                question_id,
+                question_type,
+            } => format!(
+                "📨 Question #{} ({}) sent to chat {}",
+                question_id, question_type, chat_id
+            ),
+            WebhookEvent::AnswerRecorded {
+                user_id,
+                question_id,
+                answer,
+                correct,
+                response_secs,
+                ..
+            } => {
+                let verdict = match correct {
+                    Some(true) => "correct",
+                    Some(false) => "wrong",
+                    None => "ungraded",
+                };
+                format!(
+                    "✍️ User {} answered ({}) to question #{} in {}s: {}",
+                    user_id, answer, question_id, response_secs, verdict
+                )
+            }
+            WebhookEvent::RenderFailed {
+                chat_id,
+                question_id,
+                error,
+            } => match chat_id {
+                Some(chat_id) => format!(
+                    "⚠️ Rendering question #{} for chat {} failed: {}",
+                    question_id, chat_id, error
+                ),
+                None => format!("⚠️ Rendering question #{} failed: {}", question_id, error),
+            },
+            WebhookEvent::DailySummary {
+                date,
+                active_users,
+                questions_sent,
+                answers,
+                correct_answers,
+                graded_answers,
+            } => {
+                let accuracy = (correct_answers * 100)
+                    .checked_div(*graded_answers)
+                    .map(|percent| format!(", {}% correct", percent))
+                    .unwrap_or_default();
+                format!(
+                    "📈 {}: {} active user(s), {} question(s) sent, {} answer(s){}",
+                    date, active_users, questions_sent, answers, accuracy
+                )
+            }
+        }
+    }
+}
+
+/// How a webhook's request body is shaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// The event's fields plus `timestamp`, for n8n, Zapier or your own endpoint
+    #[default]
+    Json,
+    /// `{"text": ...}` for a Slack incoming webhook
+    Slack,
+    /// `{"content": ...}` for a Discord webhook
+    Discord,
+}
+
+/// One `[[webhooks]]` entry of the config file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Events posted to this URL; every event when omitted
+    #[serde(default)]
+    pub events: Option<Vec<String>>,
+    /// Signs each body with HMAC-SHA256 in the [`SIGNATURE_HEADER`] header
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+impl WebhookConfig {
+    /// A webhook getting every event as plain JSON, as `--webhook-url` adds
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            format: WebhookFormat::Json,
+            events: None,
+            secret: None,
+        }
+    }
+
+    pub fn wants(&self, event: &WebhookEvent) -> bool {
+        self.events
+            .as_ref()
+            .is_none_or(|events| events.iter().any(|name| name == event.name()))
+    }
+}
+
+/// The body posted to a webhook of `format` for `event`
+pub fn webhook_payload(event: &WebhookEvent, format: WebhookFormat, timestamp: &str) -> Value {
+    match format {
+        WebhookFormat::Json => {
+            let mut payload = serde_json::to_value(event).unwrap_or_default();
+            if let Value::Object(fields) = &mut payload {
+                fields.insert("timestamp".to_string(), json!(timestamp));
+            }
+            payload
+        }
+        WebhookFormat::Slack => json!({ "text": event.summary() }),
+        WebhookFormat::Discord => json!({ "content": event.summary() }),
+    }
+}
+
+/// The URLs bot events are posted to
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks {
+    targets: Vec<WebhookConfig>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    /// Checks every URL and event name, so a typo fails at startup
+    pub fn new(targets: Vec<WebhookConfig>) -> Result<Self> {
+        for target in &targets {
+            reqwest::Url::parse(&target.url).map_err(|e| {
+                BotError::Config(format!("Invalid webhook URL {}: {}", target.url, e))
+            })?;
+            for name in target.events.iter().flatten() {
+                if !WEBHOOK_EVENTS.contains(&name.as_str()) {
+                    return Err(BotError::Config(format!(
+                        "Unknown webhook event '{}', expected one of {}",
+                        name,
+                        WEBHOOK_EVENTS.join(", ")
+                    )));
+                }
+            }
+        }
+        Ok(Self {
+            targets,
+            client: default_client(),
+        })
+    }
+
+    /// Posts through `client`, e.g. the webhook route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Posts the event to every webhook that wants it in the background, so a slow
+    /// endpoint never holds up a chat
+    pub fn emit(&self, event: WebhookEvent) {
+        if !self.targets.iter().any(|target| target.wants(&event)) {
+            return;
+        }
+        let webhooks = self.clone();
+        tokio::spawn(async move {
+            webhooks.deliver(&event).await;
+        });
+    }
+
+    /// Posts the event to every webhook that wants it; returns how many accepted it.
+    /// Failures are only logged: events are for monitoring, not worth a retry queue
+    pub async fn deliver(&self, event: &WebhookEvent) -> usize {
+        let mut delivered = 0;
+        for target in self.targets.iter().filter(|target| target.wants(event)) {
+            match self.post(target, event).await {
+                Ok(()) => {
+                    debug!(event = event.name(), endpoint = %endpoint(&target.url), "Webhook delivered");
+                    delivered += 1;
+                }
+                Err(e) => warn!(
+                    "Failed to post {} event to {}: {}",
+                    event.name(),
+                    endpoint(&target.url),
+                    e
+                ),
+            }
+        }
+        delivered
+    }
+
+    /// Posts the event to one webhook. Errors never carry the URL, whose path may
+    /// hold the secret
+    pub async fn post(&self, target: &WebhookConfig, event: &WebhookEvent) -> Result<()> {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let body = serde_json::to_vec(&webhook_payload(event, target.format, &timestamp))?;
+        let mut request = self
+            .client
+            .post(&target.url)
+            .header("Content-Type", "application/json");
+        if let Some(secret) = &target.secret {
+            request = request.header(SIGNATURE_HEADER, webhook_signature(secret, &body));
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(BotError::Webhook {
+                url: endpoint(&target.url),
+                status,
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The scheme and host of a webhook URL; chat apps put the secret in the path
+fn endpoint(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => format!(
+            "{}://{}",
+            parsed.scheme(),
+            parsed.host_str().unwrap_or_default()
+        ),
+        Err(_) => "webhook".to_string(),
+    }
+}
+
+/// `sha256=<hex>` HMAC of a webhook body, for receivers to check it came from the bot
+pub fn webhook_signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
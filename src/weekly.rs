@@ -0,0 +1,43 @@
+use crate::storage::PeriodStats;
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeZone, Timelike, Weekday};
+use chrono_tz::Tz;
+
+/// Preference key holding the Monday of the last week a user got a report for
+pub const WEEKLY_REPORTED_PREFERENCE: &str = "weekly_reported";
+
+/// A user's week next to the one before it, for the Sunday report
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyReport {
+    /// Monday the reported week started on, in the user's timezone
+    pub week_start: NaiveDate,
+    pub this_week: PeriodStats,
+    pub last_week: PeriodStats,
+    /// Consecutive practice days, as in the question caption
+    pub streak: u32,
+}
+
+impl WeeklyReport {
+    /// Whether the user answered anything in either week; quiet users get no report
+    pub fn is_active(&self) -> bool {
+        self.this_week.attempted > 0 || self.last_week.attempted > 0
+    }
+}
+
+/// Monday of the week whose report is due at `now`: on Sundays from `hour` onwards
+pub fn report_week(now: DateTime<Tz>, hour: u32) -> Option<NaiveDate> {
+    if now.weekday() != Weekday::Sun || now.hour() < hour {
+        return None;
+    }
+    now.date_naive().checked_sub_days(Days::new(6))
+}
+
+/// Unix-second bounds of the week starting on `monday`, in `timezone`
+pub fn week_bounds(monday: NaiveDate, timezone: Tz) -> (i64, i64) {
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|midnight| timezone.from_local_datetime(&midnight).earliest())
+            .map(|midnight| midnight.timestamp())
+            .unwrap_or_default()
+    };
+    (midnight(monday), midnight(monday + Days::new(7)))
+}
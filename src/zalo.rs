@@ -0,0 +1,564 @@
+use crate::http::{DEFAULT_REQUEST_TIMEOUT, default_client};
+use crate::retry::{RetryPolicy, retry};
+use crate::{BotError, ImageFormat, Result};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, info, trace};
+
+/// Zalo Bot API host
+pub const DEFAULT_BASE_URL: &str = "https://bot-api.zapps.me";
+
+/// Seconds `getUpdates` waits for a message before returning nothing
+pub const DEFAULT_POLL_TIMEOUT: u64 = 30;
+
+/// `error_code` for a token the Bot API doesn't accept
+const ERROR_UNAUTHORIZED: i64 = 401;
+
+/// `error_code` for a chat the bot may not message, usually because the user blocked it
+const ERROR_FORBIDDEN: i64 = 403;
+
+/// `error_code` for an unknown chat, or an unknown token on methods that take no chat
+const ERROR_NOT_FOUND: i64 = 404;
+
+/// Methods that don't name a chat, so "not found" can only be about the token
+const TOKEN_ONLY_METHODS: &[&str] = &["getMe", "getUpdates"];
+
+/// Envelope every Bot API method responds with
+#[derive(Debug, Deserialize)]
+pub struct ApiResponse<T> {
+    pub ok: bool,
+    pub result: Option<T>,
+    pub description: Option<String>,
+    pub error_code: Option<i64>,
+}
+
+/// The bot's own account, from `getMe`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BotInfo {
+    pub id: String,
+    #[serde(default)]
+    pub account_name: Option<String>,
+    #[serde(default)]
+    pub account_type: Option<String>,
+    #[serde(default)]
+    pub can_join_groups: Option<bool>,
+}
+
+/// A chat's details, from `getChat`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatInfo {
+    pub id: String,
+    #[serde(default)]
+    pub chat_type: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZaloMessage {
+    #[serde(rename = "from")]
+    pub sender: ZaloSender,
+    pub chat: ZaloChat,
+    pub text: Option<String>,
+    pub photo: Option<String>,
+    pub caption: Option<String>,
+    pub message_id: String,
+    pub date: u64,
+    /// The text is a tapped reply button, or otherwise addresses the bot outright
+    /// like a Discord mention
+    #[serde(skip)]
+    pub from_button: bool,
+    /// What kind of event brought the message, from the update's `event_name`
+    #[serde(skip)]
+    pub kind: MessageKind,
+}
+
+/// The event behind a [`ZaloMessage`]; only text messages are read as commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageKind {
+    #[default]
+    Text,
+    Photo,
+    Sticker,
+    /// A message the sender changed after sending it
+    Edited,
+    /// The sender just joined the group
+    MemberJoined,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZaloSender {
+    pub id: String,
+    pub is_bot: bool,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZaloChat {
+    pub id: String,
+    pub chat_type: String,
+}
+
+impl ZaloChat {
+    pub fn is_group(&self) -> bool {
+        self.chat_type.eq_ignore_ascii_case("GROUP")
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZaloUpdate {
+    /// Sequence number used to acknowledge updates via the `offset` parameter
+    #[serde(default)]
+    pub update_id: Option<u64>,
+    pub message: Option<ZaloMessage>,
+    pub event_name: String,
+}
+
+/// `event_name` of a text message
+pub const EVENT_TEXT: &str = "message.text.received";
+/// `event_name` of a photo, sent with an optional caption
+pub const EVENT_IMAGE: &str = "message.image.received";
+/// `event_name` of a sticker
+pub const EVENT_STICKER: &str = "message.sticker.received";
+/// `event_name` of a text message the sender edited
+pub const EVENT_EDITED: &str = "message.text.edited";
+/// `event_name` of a user joining a group the bot is in
+pub const EVENT_MEMBER_JOINED: &str = "group.member.joined";
+
+/// A [`ZaloUpdate`] sorted by its `event_name`
+#[derive(Debug)]
+pub enum ZaloEvent {
+    Text(ZaloMessage),
+    Photo(ZaloMessage),
+    Sticker(ZaloMessage),
+    Edited(ZaloMessage),
+    MemberJoined(ZaloMessage),
+    /// An event the bot has no use for, or one without a message
+    Unsupported {
+        event_name: String,
+    },
+}
+
+impl ZaloUpdate {
+    /// Sorts the update by event. Unknown events that still carry text are read as
+    /// text messages, as every update was before events were told apart
+    pub fn into_event(self) -> ZaloEvent {
+        let Some(message) = self.message else {
+            return ZaloEvent::Unsupported {
+                event_name: self.event_name,
+            };
+        };
+        match self.event_name.as_str() {
+            EVENT_TEXT => ZaloEvent::Text(message),
+            EVENT_IMAGE => ZaloEvent::Photo(message),
+            EVENT_STICKER => ZaloEvent::Sticker(message),
+            EVENT_EDITED => ZaloEvent::Edited(message),
+            EVENT_MEMBER_JOINED => ZaloEvent::MemberJoined(message),
+            _ if message.text.is_some() => ZaloEvent::Text(message),
+            _ => ZaloEvent::Unsupported {
+                event_name: self.event_name,
+            },
+        }
+    }
+}
+
+impl ZaloEvent {
+    /// The event's message tagged with its [`MessageKind`], for the platform-neutral handler
+    pub fn into_message(self) -> Option<ZaloMessage> {
+        let (mut message, kind) = match self {
+            ZaloEvent::Text(message) => (message, MessageKind::Text),
+            ZaloEvent::Photo(message) => (message, MessageKind::Photo),
+            ZaloEvent::Sticker(message) => (message, MessageKind::Sticker),
+            ZaloEvent::Edited(message) => (message, MessageKind::Edited),
+            ZaloEvent::MemberJoined(message) => (message, MessageKind::MemberJoined),
+            ZaloEvent::Unsupported { .. } => return None,
+        };
+        message.kind = kind;
+        Some(message)
+    }
+}
+
+/// `getUpdates` returns a single update, a list, or an empty object when nothing arrived
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ZaloUpdatesResult {
+    Single(Box<ZaloUpdate>),
+    Multiple(Vec<ZaloUpdate>),
+    Empty(serde_json::Value),
+}
+
+impl ZaloUpdatesResult {
+    pub fn into_updates(self) -> Vec<ZaloUpdate> {
+        match self {
+            ZaloUpdatesResult::Single(update) => vec![*update],
+            ZaloUpdatesResult::Multiple(updates) => updates,
+            ZaloUpdatesResult::Empty(value) => {
+                debug!(?value, "Received empty/unknown getUpdates result");
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// A delivered message, from `sendMessage` and `sendPhoto`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ZaloSendResult {
+    pub message_id: String,
+    pub date: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetUpdatesRequest {
+    pub timeout: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendMessageRequest<'a> {
+    pub chat_id: &'a str,
+    pub text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EditMessageRequest<'a> {
+    pub chat_id: &'a str,
+    pub message_id: &'a str,
+    pub text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteMessageRequest<'a> {
+    pub chat_id: &'a str,
+    pub message_id: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendPhotoRequest<'a> {
+    pub chat_id: &'a str,
+    /// Public URL of the image
+    pub photo: &'a str,
+    pub caption: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendChatActionRequest<'a> {
+    pub chat_id: &'a str,
+    /// e.g. "typing" or "upload_photo"
+    pub action: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetChatRequest<'a> {
+    pub chat_id: &'a str,
+}
+
+/// Typed client for the Zalo Bot API
+#[derive(Debug, Clone)]
+pub struct Client {
+    token: String,
+    base_url: String,
+    /// Applied to every request except `getUpdates`, which waits `poll_timeout` on top
+    timeout: Option<Duration>,
+    poll_timeout: u64,
+    retry_policy: RetryPolicy,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(token: String) -> Self {
+        Self {
+            token,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            timeout: None,
+            poll_timeout: DEFAULT_POLL_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+            http: default_client(),
+        }
+    }
+
+    /// Talks to another host, e.g. a proxy or a mock server
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Gives up on a request after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Seconds `getUpdates` holds the connection open waiting for messages
+    pub fn with_poll_timeout(mut self, poll_timeout: u64) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    /// Backoff for the send methods; `getUpdates` is never retried here
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sends requests through `client`, e.g. the chat route's from an [`crate::HttpContext`]
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.http = client;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!("{}/bot{}/{}", self.base_url, self.token, method)
+    }
+
+    /// Calls a Bot API method with a JSON payload and unwraps its `result`
+    async fn call<P: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        method: &str,
+        payload: &P,
+        timeout: Option<Duration>,
+    ) -> Result<T> {
+        let mut request = self.http.post(self.method_url(method)).json(payload);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
+        parse_response(method, response).await
+    }
+
+    pub async fn get_me(&self) -> Result<BotInfo> {
+        self.call("getMe", &serde_json::json!({}), self.timeout)
+            .await
+    }
+
+    /// Checks the token with `getMe`, failing with [`BotError::ZaloInvalidToken`]
+    /// if the API rejects it
+    pub async fn validate_token(&self) -> Result<BotInfo> {
+        let me = self.get_me().await?;
+        info!(
+            "Connected to Zalo as {}",
+            me.account_name.as_deref().unwrap_or(&me.id)
+        );
+        Ok(me)
+    }
+
+    /// Long-polls for updates after `offset`
+    pub async fn get_updates(&self, offset: Option<u64>) -> Result<Vec<ZaloUpdate>> {
+        let request = GetUpdatesRequest {
+            timeout: self.poll_timeout,
+            offset,
+        };
+        // The URL embeds the bot token, so only the payload is logged
+        debug!(?request, "Polling getUpdates");
+        // Held open for the poll on top of the usual request time, which the shared
+        // client would otherwise cut short
+        let timeout = Some(
+            self.timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+                + Duration::from_secs(self.poll_timeout),
+        );
+        let result: ZaloUpdatesResult = self.call("getUpdates", &request, timeout).await?;
+        Ok(result.into_updates())
+    }
+
+    pub async fn send_message(&self, chat_id: &str, text: &str) -> Result<ZaloSendResult> {
+        let request = SendMessageRequest { chat_id, text };
+        let sent = retry(&self.retry_policy, "sendMessage", || {
+            self.call("sendMessage", &request, self.timeout)
+        })
+        .await?;
+        info!("Message sent successfully to chat: {}", chat_id);
+        Ok(sent)
+    }
+
+    /// Replaces the text of a message the bot sent earlier
+    pub async fn edit_message(&self, chat_id: &str, message_id: &str, text: &str) -> Result<()> {
+        let request = EditMessageRequest {
+            chat_id,
+            message_id,
+            text,
+        };
+        retry(&self.retry_policy, "editMessageText", || {
+            self.call::<_, serde_json::Value>("editMessageText", &request, self.timeout)
+        })
+        .await?;
+        debug!("Message {} edited in chat: {}", message_id, chat_id);
+        Ok(())
+    }
+
+    /// Removes a message the bot sent earlier
+    pub async fn delete_message(&self, chat_id: &str, message_id: &str) -> Result<()> {
+        let request = DeleteMessageRequest {
+            chat_id,
+            message_id,
+        };
+        retry(&self.retry_policy, "deleteMessage", || {
+            self.call::<_, serde_json::Value>("deleteMessage", &request, self.timeout)
+        })
+        .await?;
+        debug!("Message {} deleted in chat: {}", message_id, chat_id);
+        Ok(())
+    }
+
+    /// Sends an image by public URL
+    pub async fn send_photo(
+        &self,
+        chat_id: &str,
+        photo: &str,
+        caption: &str,
+    ) -> Result<ZaloSendResult> {
+        let request = SendPhotoRequest {
+            chat_id,
+            photo,
+            caption,
+        };
+        let sent = retry(&self.retry_policy, "sendPhoto", || {
+            self.call("sendPhoto", &request, self.timeout)
+        })
+        .await?;
+        info!("Photo sent successfully to chat: {}", chat_id);
+        Ok(sent)
+    }
+
+    /// Sends a local image as a multipart file upload
+    pub async fn send_photo_file(
+        &self,
+        chat_id: &str,
+        path: &Path,
+        caption: &str,
+    ) -> Result<ZaloSendResult> {
+        let bytes = std::fs::read(path)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "question.png".to_string());
+
+        let sent = retry(&self.retry_policy, "sendPhoto upload", || async {
+            // A multipart form can only be sent once, so each attempt builds its own
+            let form = reqwest::multipart::Form::new()
+                .text("chat_id", chat_id.to_string())
+                .text("caption", caption.to_string())
+                .part(
+                    "photo",
+                    reqwest::multipart::Part::bytes(bytes.clone())
+                        .file_name(file_name.clone())
+                        .mime_str(ImageFormat::mime_type_of(&bytes))?,
+                );
+            let mut request = self.http.post(self.method_url("sendPhoto")).multipart(form);
+            if let Some(timeout) = self.timeout {
+                request = request.timeout(timeout);
+            }
+            parse_response("sendPhoto", request.send().await?).await
+        })
+        .await?;
+        info!("Photo uploaded successfully to chat: {}", chat_id);
+        Ok(sent)
+    }
+
+    /// Shows a chat action such as the typing indicator until the next message arrives
+    pub async fn send_chat_action(&self, chat_id: &str, action: &str) -> Result<()> {
+        let request = SendChatActionRequest { chat_id, action };
+        self.call::<_, serde_json::Value>("sendChatAction", &request, self.timeout)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_chat(&self, chat_id: &str) -> Result<ChatInfo> {
+        self.call("getChat", &GetChatRequest { chat_id }, self.timeout)
+            .await
+    }
+}
+
+/// Maps a Bot API response to its `result`, or to an error carrying the API's
+/// description when there is one
+async fn parse_response<T: DeserializeOwned>(
+    method: &str,
+    response: reqwest::Response,
+) -> Result<T> {
+    let status = response.status();
+    let text = response.text().await?;
+    // Message text is user content, so the raw body is only visible at trace level
+    trace!(body = %text, "{} response body", method);
+
+    let parsed: ApiResponse<T> = match serde_json::from_str(&text) {
+        Ok(parsed) => parsed,
+        Err(_) if !status.is_success() => {
+            return Err(api_error(method, status, None, None, text));
+        }
+        Err(e) => {
+            return Err(BotError::zalo(
+                format!("Failed to parse {} response: {}", method, e),
+                Some(status),
+                text,
+            ));
+        }
+    };
+    match parsed {
+        ApiResponse {
+            ok: true,
+            result: Some(result),
+            ..
+        } if status.is_success() => Ok(result),
+        ApiResponse {
+            ok: true,
+            result: None,
+            ..
+        } if status.is_success() => Err(BotError::zalo(
+            format!("Zalo {} returned no result", method),
+            Some(status),
+            text,
+        )),
+        ApiResponse {
+            description,
+            error_code,
+            ..
+        } => Err(api_error(method, status, error_code, description, text)),
+    }
+}
+
+/// A typed error for the failures the bot handles differently (a bad token, an
+/// unknown chat, a user who blocked the bot), or a [`BotError::ZaloApi`] otherwise
+fn api_error(
+    method: &str,
+    status: StatusCode,
+    error_code: Option<i64>,
+    description: Option<String>,
+    body: String,
+) -> BotError {
+    let context = format!("Zalo {} failed", method);
+    let code = error_code.unwrap_or(i64::from(status.as_u16()));
+    let mentions = |phrase: &str| {
+        description
+            .as_deref()
+            .is_some_and(|description| description.to_lowercase().contains(phrase))
+    };
+    let invalid_token = code == ERROR_UNAUTHORIZED
+        || (code == ERROR_NOT_FOUND && TOKEN_ONLY_METHODS.contains(&method));
+    let blocked = code == ERROR_FORBIDDEN || mentions("blocked");
+    let chat_not_found = code == ERROR_NOT_FOUND || mentions("chat not found");
+    let description = description.unwrap_or(body);
+    if invalid_token {
+        BotError::ZaloInvalidToken {
+            context,
+            description,
+        }
+    } else if blocked {
+        BotError::ZaloUserBlocked {
+            context,
+            description,
+        }
+    } else if chat_not_found {
+        BotError::ZaloChatNotFound {
+            context,
+            description,
+        }
+    } else {
+        BotError::zalo(context, Some(status), description)
+    }
+}
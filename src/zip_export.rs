@@ -0,0 +1,70 @@
+use crate::{BotError, Difficulty, QuestionContent, QuestionType, Result};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Name of the file in the zip listing every question and its images
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// A rendered question packed by `export zip`
+#[derive(Debug, Clone)]
+pub struct ZipQuestion {
+    pub content: QuestionContent,
+    pub question_type: QuestionType,
+    /// The question's images, in order
+    pub images: Vec<PathBuf>,
+    /// Its explanations images, when they were rendered
+    pub explanation_images: Vec<PathBuf>,
+}
+
+/// Writes the questions' images into a zip under `questions/` and `explanations/`,
+/// with a `manifest.json` of their IDs and metadata
+pub fn write_question_zip(questions: &[ZipQuestion], output_path: &Path) -> Result<()> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut zip = ZipWriter::new(File::create(output_path)?);
+    let options = SimpleFileOptions::default();
+    let mut add_images = |dir: &str, images: &[PathBuf]| -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for image in images {
+            let file_name = image.file_name().ok_or_else(|| {
+                BotError::Config(format!("Image path has no file name: {}", image.display()))
+            })?;
+            let name = format!("{}/{}", dir, file_name.to_string_lossy());
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(&fs::read(image)?)?;
+            names.push(name);
+        }
+        Ok(names)
+    };
+
+    let mut entries = Vec::new();
+    for question in questions {
+        let content = &question.content;
+        entries.push(json!({
+            "id": content.id,
+            "type": question.question_type,
+            "type_name": question.question_type.to_string(),
+            "difficulty": Difficulty::of(content).map(|d| d.to_string()),
+            "level": content.difficulty,
+            "answer": content.correct_answer().map(String::from),
+            "topics": content.topics,
+            "source": content.src,
+            "images": add_images("questions", &question.images)?,
+            "explanation_images": add_images("explanations", &question.explanation_images)?,
+        }));
+    }
+    let manifest = json!({
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "count": entries.len(),
+        "questions": entries,
+    });
+    zip.start_file(MANIFEST_NAME, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Pointing a built bot at another Bot API host, with its own long-poll wait
+
+use gmat_zalo_bot::{ApiConfig, Platform, ZaloBot};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn the_builder_talks_to_the_configured_host() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/bottest-token/getUpdates"))
+        .and(body_partial_json(json!({"timeout": 2})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "ok": true,
+            "result": []
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let service = ZaloBot::builder("test-token")
+        .with_api(ApiConfig {
+            base_url: Some(server.uri()),
+            poll_timeout: 2,
+            request_timeout: Some(Duration::from_secs(5)),
+        })
+        .build()
+        .unwrap();
+
+    assert!(service.bot.get_updates().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn telegram_polls_the_configured_host_for_its_wait() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/bottest-token/getUpdates"))
+        .and(body_partial_json(json!({"timeout": 7})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "ok": true,
+            "result": []
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let service = ZaloBot::builder("test-token")
+        .with_platform(Platform::Telegram)
+        .with_api(ApiConfig {
+            base_url: Some(server.uri()),
+            poll_timeout: 7,
+            ..ApiConfig::default()
+        })
+        .build()
+        .unwrap();
+
+    assert!(service.bot.get_updates().await.unwrap().is_empty());
+}
@@ -0,0 +1,124 @@
+//! Tutor assignments: the admin commands, delivery and each student's progress
+
+mod common;
+
+use common::answer;
+use gmat_zalo_bot::{
+    AdminCommand, QuestionType, Storage, format_assignment_status, parse_admin_command,
+    parse_assign_command,
+};
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn the_commands_name_questions_and_students() {
+    assert_eq!(
+        parse_assign_command("101, 102 103 TO user-1,user-2"),
+        Some((ids(&["101", "102", "103"]), ids(&["user-1", "user-2"])))
+    );
+    assert_eq!(parse_assign_command("101 102"), None);
+    assert_eq!(parse_assign_command("101 to "), None);
+
+    assert_eq!(
+        parse_admin_command("assign 1 to u"),
+        Some(AdminCommand::Assign("1 to u"))
+    );
+    assert_eq!(
+        parse_admin_command("assignments status #3"),
+        Some(AdminCommand::Assignments(Some(3)))
+    );
+    assert_eq!(
+        parse_admin_command("assignments status"),
+        Some(AdminCommand::Assignments(None))
+    );
+    assert_eq!(parse_admin_command("assignments status x"), None);
+}
+
+#[test]
+fn assignments_wait_for_a_private_chat() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .touch_user("user-1", Some("Lan"), Some("chat-1"))
+        .unwrap();
+    let id = storage
+        .create_assignment(
+            Some("Week 1"),
+            Some("tutor"),
+            &ids(&["1", "2"]),
+            &ids(&["user-1", "user-2"]),
+        )
+        .unwrap();
+
+    assert_eq!(
+        storage.undelivered_assignments().unwrap(),
+        [(id, "user-1".to_string(), "chat-1".to_string())]
+    );
+    storage.mark_assignment_delivered(id, "user-1").unwrap();
+    assert!(storage.undelivered_assignments().unwrap().is_empty());
+
+    storage.touch_user("user-2", None, Some("chat-2")).unwrap();
+    assert_eq!(storage.undelivered_assignments().unwrap()[0].1, "user-2");
+}
+
+#[test]
+fn progress_counts_first_answers_in_order() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .touch_user("user-1", Some("Lan"), Some("chat-1"))
+        .unwrap();
+    let id = storage
+        .create_assignment(None, None, &ids(&["1", "2", "3"]), &ids(&["user-1"]))
+        .unwrap();
+    storage.mark_assignment_delivered(id, "user-1").unwrap();
+
+    answer(&storage, "user-1", "2", QuestionType::PS, true);
+    let (assignment, progress) = storage.student_assignments("user-1").unwrap().remove(0);
+    assert_eq!(assignment.next_question(&progress), Some((1, "1")));
+    assert!(progress.just_answered("2"));
+
+    answer(&storage, "user-1", "1", QuestionType::PS, false);
+    answer(&storage, "user-1", "2", QuestionType::PS, false);
+    answer(&storage, "user-1", "99", QuestionType::PS, true);
+    let (assignment, progress) = storage.student_assignments("user-1").unwrap().remove(0);
+    assert_eq!(progress.answered(), 2);
+    assert_eq!(progress.correct(), 1);
+    assert!(!progress.just_answered("2"));
+    assert_eq!(assignment.next_question(&progress), Some((3, "3")));
+
+    answer(&storage, "user-1", "3", QuestionType::PS, true);
+    let (assignment, progress) = storage.student_assignments("user-1").unwrap().remove(0);
+    assert!(assignment.is_complete(&progress));
+    assert_eq!(progress.accuracy(), Some(66));
+}
+
+#[test]
+fn the_status_report_shows_every_student() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .touch_user("user-1", Some("Lan"), Some("chat-1"))
+        .unwrap();
+    let id = storage
+        .create_assignment(
+            Some("Week 1"),
+            None,
+            &ids(&["1", "2"]),
+            &ids(&["user-1", "user-2"]),
+        )
+        .unwrap();
+    storage.mark_assignment_delivered(id, "user-1").unwrap();
+    answer(&storage, "user-1", "1", QuestionType::PS, true);
+    answer(&storage, "user-1", "2", QuestionType::PS, true);
+
+    let report = format_assignment_status(&storage.assignment_status(id).unwrap().unwrap());
+
+    assert!(report.contains("Assignment #1 Week 1: 2 question(s), 2 student(s)"));
+    assert!(report.contains("👤 Lan (user-1): 2/2 done ✅, 100% correct"));
+    assert!(report.contains("👤 user-2: not delivered yet"));
+    assert!(storage.assignment_status(id + 1).unwrap().is_none());
+
+    storage.forget_user("user-1").unwrap();
+    let status = storage.assignment_status(id).unwrap().unwrap();
+    assert_eq!(status.students.len(), 1);
+}
@@ -0,0 +1,68 @@
+//! Badges and levels worked out from answers recorded in storage
+
+mod common;
+
+use chrono::NaiveDate;
+use common::answer;
+use gmat_zalo_bot::{
+    BADGES, QuestionType, Storage, earned_badges, find_badge, format_badge_award,
+    generate_badges_card_html, level_for, next_level,
+};
+
+#[test]
+fn runs_of_correct_answers_earn_badges_once() {
+    let storage = Storage::open_in_memory().unwrap();
+    answer(&storage, "user-1", "0", QuestionType::CR, false);
+    for i in 1..=10 {
+        answer(&storage, "user-1", &i.to_string(), QuestionType::CR, true);
+        // Another type in between doesn't break the CR run, only the overall one
+        if i == 5 {
+            answer(&storage, "user-1", "ps", QuestionType::PS, false);
+        }
+    }
+
+    let progress = storage.badge_progress("user-1").unwrap();
+    assert_eq!(progress.answered, 12);
+    assert_eq!(progress.correct, 10);
+    assert_eq!(progress.correct_run, 5);
+    assert!(progress.type_runs.contains(&(QuestionType::CR, 10)));
+    assert!(
+        !progress
+            .type_runs
+            .iter()
+            .any(|(t, _)| *t == QuestionType::PS)
+    );
+
+    let earned: Vec<_> = earned_badges(&progress).iter().map(|b| b.id).collect();
+    assert_eq!(earned, ["answered_1", "answered_10", "run_5", "cr_run_10"]);
+
+    assert!(storage.award_badge("user-1", "cr_run_10").unwrap());
+    assert!(!storage.award_badge("user-1", "cr_run_10").unwrap());
+    let awarded = storage.user_badges("user-1").unwrap();
+    assert_eq!(awarded.len(), 1);
+    assert_eq!(awarded[0].0, "cr_run_10");
+}
+
+#[test]
+fn levels_follow_correct_answers() {
+    assert_eq!(level_for(0).number, 1);
+    assert_eq!(level_for(9).number, 1);
+    assert_eq!(level_for(10).name, "Apprentice");
+    assert_eq!(next_level(level_for(10)).unwrap().min_correct, 50);
+    assert_eq!(next_level(level_for(5000)), None);
+}
+
+#[test]
+fn the_card_shows_every_badge_and_the_level() {
+    let badge = find_badge("streak_7").unwrap();
+    let earned = [(badge, NaiveDate::from_ymd_opt(2024, 6, 9).unwrap())];
+
+    let html = generate_badges_card_html(Some("Lan <3"), 12, &earned);
+
+    assert!(html.contains("Lan &lt;3's badges"));
+    assert!(html.contains("Level 2 · Apprentice"));
+    assert!(html.contains("38 more correct answers to level 3: Scholar"));
+    assert!(html.contains("Earned Jun 9, 2024"));
+    assert_eq!(html.matches("badge locked").count(), BADGES.len() - 1);
+    assert!(format_badge_award(Some("Lan"), badge).contains("for Lan: 🗓️ Week Warrior"));
+}
@@ -0,0 +1,99 @@
+//! Caption templates: placeholders, per-language variants and the `[captions]` table
+
+mod common;
+
+use common::question;
+use gmat_zalo_bot::{
+    CaptionContext, CaptionTemplates, CaptionValues, Config, QuestionType, Storage, UserSettings,
+    fill_caption,
+};
+
+#[test]
+fn placeholders_are_filled_and_blank_lines_dropped() {
+    let values = CaptionValues::new(&question("42"), QuestionType::PS).with_position(3, 10);
+
+    assert_eq!(
+        fill_caption("#{id} ({index}/{total})\n{streak}\nGo!", &values),
+        "#42 (3/10)\nGo!"
+    );
+
+    let values = values.with_streak(Some("🔥 Day 7 streak!".to_string()));
+    assert_eq!(
+        fill_caption("{id}\n{streak}", &values),
+        "42\n🔥 Day 7 streak!"
+    );
+}
+
+#[test]
+fn templates_fall_back_from_language_to_default_to_builtin() {
+    let captions = CaptionTemplates::default()
+        .with_template(CaptionContext::Question, "Question {id}")
+        .with_template(CaptionContext::Quiz, "Quiz {index}/{total}")
+        .with_language_template("vi", CaptionContext::Question, "Câu {id}");
+
+    assert_eq!(
+        captions.template(CaptionContext::Quiz, Some("VI")),
+        "Câu {id}"
+    );
+    assert_eq!(
+        captions.template(CaptionContext::Quiz, Some("en")),
+        "Quiz {index}/{total}"
+    );
+    assert_eq!(
+        captions.template(CaptionContext::Daily, None),
+        "Question {id}"
+    );
+    assert_eq!(
+        captions.template(CaptionContext::Explanations, Some("vi")),
+        "Explanations 📖"
+    );
+    assert_eq!(captions.languages().collect::<Vec<_>>(), ["vi"]);
+}
+
+#[test]
+fn the_config_table_holds_contexts_and_languages() {
+    let config: Config = toml::from_str(
+        r#"
+        [captions]
+        quiz = "Quiz {index}/{total}"
+
+        [captions.vi]
+        question = "Cố lên! 💪"
+        "#,
+    )
+    .unwrap();
+    let captions = config.captions.unwrap();
+
+    assert_eq!(
+        captions,
+        CaptionTemplates::default()
+            .with_template(CaptionContext::Quiz, "Quiz {index}/{total}")
+            .with_language_template("vi", CaptionContext::Question, "Cố lên! 💪")
+    );
+
+    let error = toml::from_str::<Config>("[captions]\nquizz = \"x\"\n").unwrap_err();
+    assert!(error.to_string().contains("unknown caption 'quizz'"));
+}
+
+#[test]
+fn users_pick_a_caption_language() {
+    let mut settings = UserSettings::default();
+
+    assert!(settings.apply_command("language VI").is_ok());
+    assert_eq!(settings.language.as_deref(), Some("vi"));
+    assert!(settings.apply_command("language not a code").is_err());
+
+    let storage = Storage::open_in_memory().unwrap();
+    storage.save_user_settings("user-1", &settings).unwrap();
+    assert_eq!(
+        storage
+            .get_user_settings("user-1")
+            .unwrap()
+            .unwrap()
+            .language,
+        settings.language
+    );
+
+    settings.apply_command("language default").unwrap();
+    assert_eq!(settings.language, None);
+}
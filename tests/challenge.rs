@@ -0,0 +1,143 @@
+//! Head-to-head challenges: the command, finding the friend and comparing both players
+
+mod common;
+
+use gmat_zalo_bot::{
+    ChallengePlayer, ChallengeRequest, ChallengeSession, MAX_CHALLENGE_QUESTIONS, QuestionContent,
+    QuestionType, QuizSession, Storage, ZaloBot, parse_challenge_command,
+};
+use std::sync::{Arc, Barrier};
+
+fn question(id: &str) -> (QuestionType, QuestionContent) {
+    (QuestionType::PS, common::question(id))
+}
+
+fn session() -> ChallengeSession {
+    ChallengeSession::new(
+        ChallengePlayer::new("user-1", "Lan", "chat-1"),
+        ChallengePlayer::new("user-2", "Minh Anh", "chat-2"),
+        vec![question("1"), question("2")],
+        "group-1",
+    )
+}
+
+#[test]
+fn the_command_takes_a_name_then_count_and_type() {
+    assert_eq!(
+        parse_challenge_command("challenge @Minh Anh 5 ps"),
+        Some(ChallengeRequest {
+            opponent: "Minh Anh".to_string(),
+            count: 5,
+            question_type: Some(QuestionType::PS),
+        })
+    );
+    let request = parse_challenge_command("Challenge @Lan 99").unwrap();
+    assert_eq!(request.count, MAX_CHALLENGE_QUESTIONS);
+    assert_eq!(request.question_type, None);
+    assert_eq!(parse_challenge_command("challenge Lan"), None);
+    assert_eq!(parse_challenge_command("challenge @ 5"), None);
+    assert_eq!(parse_challenge_command("quiz 5"), None);
+}
+
+#[test]
+fn friends_are_found_by_name_or_id() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .touch_user("user-2", Some("Minh Anh"), Some("chat-2"))
+        .unwrap();
+    storage.touch_user("user-3", Some("Lan"), None).unwrap();
+
+    assert_eq!(
+        storage.find_users("minh anh").unwrap(),
+        [("user-2".to_string(), Some("chat-2".to_string()))]
+    );
+    assert_eq!(
+        storage.find_users("user-3").unwrap(),
+        [("user-3".to_string(), None)]
+    );
+
+    storage.set_banned("user-2", true).unwrap();
+    assert!(storage.find_users("Minh Anh").unwrap().is_empty());
+}
+
+#[test]
+fn each_player_answers_at_their_own_pace() {
+    let mut session = session();
+    assert_eq!(session.next_question("chat-1").unwrap().0, 1);
+
+    session.question_sent("chat-1", "1");
+    assert!(session.is_current("chat-1", "1"));
+    assert!(!session.is_current("chat-2", "1"));
+    session.record("chat-1", Some(true));
+    session.question_sent("chat-1", "2");
+    session.record("chat-1", Some(false));
+
+    assert!(session.player_finished("chat-1"));
+    assert!(!session.is_finished());
+    assert_eq!(session.next_question("chat-2").unwrap().1.1.id, "1");
+
+    session.question_sent("chat-2", "1");
+    session.record("chat-2", Some(true));
+    session.skip("chat-2");
+    assert!(session.is_finished());
+}
+
+#[test]
+fn more_correct_answers_win_and_missed_questions_show() {
+    let mut session = session();
+    for id in ["1", "2"] {
+        session.question_sent("chat-2", id);
+        session.record("chat-2", Some(true));
+    }
+    session.question_sent("chat-1", "1");
+    session.record("chat-1", Some(true));
+
+    assert_eq!(session.winner().unwrap().user_id, "user-2");
+    let summary = session.summary();
+    assert!(summary.contains("Lan vs Minh Anh"));
+    assert!(summary.contains("🏆 Minh Anh wins!"));
+    assert!(summary.contains("Lan: 1/2"));
+    assert!(summary.contains("2. #2 Lan: — · Minh Anh: ✅"));
+}
+
+#[test]
+fn an_unplayed_challenge_is_a_draw() {
+    assert!(session().winner().is_none());
+    assert!(session().summary().contains("🤝 It's a draw!"));
+}
+
+#[test]
+fn a_player_can_only_join_one_of_two_challenges_started_at_once() {
+    let bot = Arc::new(ZaloBot::new("test-token".to_string()));
+    // Lan challenges Minh Anh and Hoa at the same moment
+    let barrier = Arc::new(Barrier::new(2));
+    let starts: Vec<_> = [("user-2", "chat-2"), ("user-3", "chat-3")]
+        .into_iter()
+        .map(|(opponent, opponent_chat)| {
+            let (bot, barrier) = (bot.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                let session = ChallengeSession::new(
+                    ChallengePlayer::new("user-1", "Lan", "chat-1"),
+                    ChallengePlayer::new(opponent, opponent, opponent_chat),
+                    vec![question("1")],
+                    "group-1",
+                );
+                barrier.wait();
+                bot.begin_challenge(session)
+            })
+        })
+        .collect();
+    let started: Vec<bool> = starts.into_iter().map(|s| s.join().unwrap()).collect();
+
+    assert_eq!(started.iter().filter(|started| **started).count(), 1);
+    assert_eq!(bot.challenges.lock().unwrap().len(), 1);
+
+    // Someone who has started a quiz in the meantime can't be claimed either
+    bot.challenges.lock().unwrap().clear();
+    bot.quiz_sessions
+        .lock()
+        .unwrap()
+        .insert("chat-2".to_string(), QuizSession::new(5, None));
+    assert!(!bot.begin_challenge(session()));
+    assert!(bot.challenges.lock().unwrap().is_empty());
+}
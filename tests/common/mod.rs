@@ -0,0 +1,50 @@
+//! Questions and answers shared by the integration tests
+
+// Each test crate uses only some of these
+#![allow(dead_code)]
+
+use gmat_zalo_bot::{PendingQuestion, QuestionContent, QuestionType, Storage};
+
+/// A short Problem Solving question whose answer is B
+pub fn question(id: &str) -> QuestionContent {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "src": "https://gmatclub.com/forum/topic.html",
+        "explanations": [],
+        "type": "ps",
+        "question": "What is 2 + 2?",
+        "answers": ["3", "4", "5"],
+    }))
+    .unwrap()
+}
+
+/// [`question`] as sent to a chat and awaiting an answer, filed under `question_type`
+pub fn pending(id: &str, question_type: QuestionType) -> PendingQuestion {
+    PendingQuestion {
+        content: question(id),
+        question_type,
+        correct_answer: Some('B'),
+        sent_at: 0,
+        choice_order: None,
+    }
+}
+
+/// Records `user_id` answering question `id` in chat-1, right or wrong
+pub fn answer(
+    storage: &Storage,
+    user_id: &str,
+    id: &str,
+    question_type: QuestionType,
+    correct: bool,
+) {
+    storage
+        .record_answer(
+            "chat-1",
+            user_id,
+            None,
+            &pending(id, question_type),
+            if correct { 'B' } else { 'A' },
+            Some(correct),
+        )
+        .unwrap();
+}
@@ -0,0 +1,237 @@
+//! The control API: request parsing, bearer tokens and the bot's replies
+
+use gmat_zalo_bot::{
+    ControlApi, ControlRequest, ControlResponse, DirectUpload, GmatDatabase, QuestionType, Storage,
+    ZaloBot, serve_control,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+fn database() -> GmatDatabase {
+    GmatDatabase {
+        reading_comprehension: Vec::new(),
+        sentence_correction: Vec::new(),
+        critical_reasoning: Vec::new(),
+        problem_solving: vec!["42".to_string()],
+        data_sufficiency: Vec::new(),
+    }
+}
+
+/// Serves the control API on a free local port, answering each request with its
+/// debug form so tests can see what the server parsed
+async fn serve(token: Option<&str>) -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    // Borrow a free port from the OS
+    let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let api = ControlApi {
+        addr,
+        token: token.map(String::from),
+    };
+    let server = tokio::spawn(async move {
+        serve_control(&api, async |request| {
+            ControlResponse::ok(json!({ "request": format!("{:?}", request) }))
+        })
+        .await
+        .unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    (addr, server)
+}
+
+#[tokio::test]
+async fn requests_are_read_from_method_path_and_body() {
+    let (addr, server) = serve(None).await;
+    let client = reqwest::Client::new();
+    let url = |path: &str| format!("http://{}{}", addr, path);
+    let parsed = |response: reqwest::Response| async move {
+        assert_eq!(response.status(), StatusCode::OK);
+        response.json::<serde_json::Value>().await.unwrap()["request"].clone()
+    };
+
+    let send = client
+        .post(url("/send"))
+        .json(&json!({ "user_id": " u1 ", "question_id": "42" }))
+        .send()
+        .await
+        .unwrap();
+    let expected = ControlRequest::Send {
+        user_id: "u1".to_string(),
+        question_id: "42".to_string(),
+    };
+    assert_eq!(parsed(send).await, format!("{:?}", expected));
+    let stats = client.get(url("/stats?days=30")).send().await.unwrap();
+    assert_eq!(
+        parsed(stats).await,
+        format!("{:?}", ControlRequest::Stats { days: 30 })
+    );
+    let stats = client.get(url("/stats")).send().await.unwrap();
+    assert_eq!(
+        parsed(stats).await,
+        format!("{:?}", ControlRequest::Stats { days: 7 })
+    );
+    let broadcast = client
+        .post(url("/broadcast"))
+        .json(&json!({ "text": "Mock test tonight!" }))
+        .send()
+        .await
+        .unwrap();
+    let expected = ControlRequest::Broadcast {
+        text: "Mock test tonight!".to_string(),
+    };
+    assert_eq!(parsed(broadcast).await, format!("{:?}", expected));
+    let sessions = client.get(url("/sessions")).send().await.unwrap();
+    assert_eq!(
+        parsed(sessions).await,
+        format!("{:?}", ControlRequest::Sessions)
+    );
+
+    let errors = [
+        (
+            client.post(url("/send")).json(&json!({})),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            client.post(url("/send")).body("{"),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+        (
+            client
+                .post(url("/send"))
+                .json(&json!({ "user_id": "", "question_id": "42" })),
+            StatusCode::BAD_REQUEST,
+        ),
+        (client.get(url("/stats?days=0")), StatusCode::BAD_REQUEST),
+        (client.get(url("/stats?days=soon")), StatusCode::BAD_REQUEST),
+        (
+            client.get(url("/broadcast")),
+            StatusCode::METHOD_NOT_ALLOWED,
+        ),
+        (client.get(url("/users")), StatusCode::NOT_FOUND),
+        (
+            client
+                .post(url("/broadcast"))
+                .json(&json!({ "text": "x".repeat(100_000) })),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        ),
+    ];
+    for (request, status) in errors {
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), status);
+        let body = response.json::<serde_json::Value>().await.unwrap();
+        assert!(body["error"].is_string(), "{}", body);
+    }
+    server.abort();
+}
+
+#[test]
+fn only_a_localhost_api_may_go_without_a_token() {
+    let api = |addr: &str, token: Option<&str>| ControlApi {
+        addr: addr.parse().unwrap(),
+        token: token.map(String::from),
+    };
+
+    assert!(api("127.0.0.1:8081", None).validate().is_ok());
+    assert!(api("0.0.0.0:8081", None).validate().is_err());
+    assert!(api("0.0.0.0:8081", Some("s3cret")).validate().is_ok());
+
+    let secured = api("0.0.0.0:8081", Some("s3cret"));
+    assert!(secured.authorizes(Some("Bearer s3cret")));
+    assert!(!secured.authorizes(Some("Bearer wrong")));
+    assert!(!secured.authorizes(None));
+    assert!(api("127.0.0.1:8081", None).authorizes(None));
+}
+
+#[tokio::test]
+async fn the_server_checks_the_token_before_handling_requests() {
+    let (addr, server) = serve(Some("s3cret")).await;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/sessions", addr);
+    let unauthorized = client.get(&url).send().await.unwrap();
+    assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+    let unknown = client
+        .get(format!("http://{}/users", addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client.get(&url).bearer_auth("s3cret").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        json!({ "request": "Sessions" })
+    );
+    server.abort();
+}
+
+#[tokio::test]
+async fn a_slow_broadcast_does_not_hold_up_other_calls() {
+    let addr: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let api = ControlApi { addr, token: None };
+    let server = tokio::spawn(async move {
+        serve_control(&api, async |request| {
+            if let ControlRequest::Broadcast { .. } = request {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+            ControlResponse::ok(json!({}))
+        })
+        .await
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = reqwest::Client::new();
+    let broadcast = client
+        .post(format!("http://{}/broadcast", addr))
+        .json(&json!({ "text": "Mock test tonight!" }))
+        .send();
+    let broadcast = tokio::spawn(broadcast);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let sessions = client.get(format!("http://{}/sessions", addr)).send();
+    let response = tokio::time::timeout(Duration::from_secs(5), sessions)
+        .await
+        .expect("sessions waited for the broadcast")
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    broadcast.abort();
+    server.abort();
+}
+
+#[tokio::test]
+async fn the_bot_reports_stats_sessions_and_unknown_questions() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .record_question_sent("chat-1", "42", &QuestionType::PS)
+        .unwrap();
+    let bot = ZaloBot::new("test-token".to_string()).with_storage(storage);
+    let database = database();
+    let handle = async |request| {
+        bot.handle_control_request(request, &database, "output", &DirectUpload)
+            .await
+    };
+
+    let stats = handle(ControlRequest::Stats { days: 7 }).await;
+    assert_eq!(stats.status, StatusCode::OK);
+    assert_eq!(stats.body["questions_sent"], 1);
+    assert_eq!(stats.body["recent_days"], 7);
+
+    let sessions = handle(ControlRequest::Sessions).await;
+    assert_eq!(sessions.body["pending_questions"], json!([]));
+    assert_eq!(sessions.body["challenges"], json!([]));
+
+    let unknown = handle(ControlRequest::Send {
+        user_id: "u1".to_string(),
+        question_id: "999".to_string(),
+    })
+    .await;
+    assert_eq!(unknown.status, StatusCode::NOT_FOUND);
+    assert_eq!(unknown.body["error"], "Unknown question ID 999");
+}
@@ -0,0 +1,275 @@
+//! Discord transport: the gateway, catching up after a restart, mentions, direct
+//! messages and token checks
+
+use futures_util::{SinkExt, StreamExt};
+use gmat_zalo_bot::{ChatPlatform, DiscordBot, MessageKind, OffsetStore, RetryPolicy};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio_websockets::{Message, ServerBuilder};
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A snowflake made well after the bot started, so its messages count as new
+const NEW_MESSAGE_ID: &str = "9223372036854775000";
+/// Nothing listens here, so the gateway never connects
+const NO_GATEWAY: &str = "ws://127.0.0.1:9";
+
+fn discord(server: &MockServer) -> DiscordBot {
+    DiscordBot::new("test-token".to_string())
+        .with_api_url(server.uri())
+        .with_gateway_url(NO_GATEWAY)
+        .with_poll_timeout(0)
+        .with_retry_policy(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        })
+}
+
+async fn mount_bot_user(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/users/@me"))
+        .and(header("Authorization", "Bot test-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "100",
+            "username": "gmat_bot",
+            "global_name": "GMAT Daily",
+            "bot": true
+        })))
+        .mount(server)
+        .await;
+}
+
+/// A gateway that says hello, waits for the bot to identify, then sends `events`
+/// as dispatches and keeps the connection open
+async fn fake_gateway(events: Vec<(&'static str, Value)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let resume_url = url.clone();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (_request, mut socket) = ServerBuilder::new().accept(stream).await.unwrap();
+        let hello = json!({ "op": 10, "d": { "heartbeat_interval": 45000 } });
+        socket.send(Message::text(hello.to_string())).await.unwrap();
+        let identify = socket.next().await.unwrap().unwrap();
+        let identify: Value = serde_json::from_str(identify.as_text().unwrap()).unwrap();
+        assert_eq!(identify["op"], 2);
+        let ready = json!({ "resume_gateway_url": resume_url, "session_id": "session-1" });
+        let dispatches = std::iter::once(("READY", ready)).chain(events);
+        for (sequence, (name, data)) in dispatches.enumerate() {
+            let dispatch = json!({ "op": 0, "t": name, "s": sequence + 1, "d": data });
+            socket
+                .send(Message::text(dispatch.to_string()))
+                .await
+                .unwrap();
+        }
+        while socket.next().await.is_some() {}
+    });
+    url
+}
+
+#[tokio::test]
+async fn users_can_start_in_their_direct_messages() {
+    let server = MockServer::start().await;
+    mount_bot_user(&server).await;
+    Mock::given(method("POST"))
+        .and(path("/users/@me/channels"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/channels/55/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "900" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/channels/10/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "901" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let gateway = fake_gateway(vec![
+        (
+            "GUILD_CREATE",
+            json!({ "id": "1", "channels": [{ "id": "10", "type": 0 }, { "id": "11", "type": 2 }] }),
+        ),
+        (
+            "MESSAGE_CREATE",
+            json!({
+                "id": NEW_MESSAGE_ID,
+                "channel_id": "55",
+                "type": 0,
+                "author": { "id": "7", "username": "lan", "global_name": "Lan" },
+                "content": "PS"
+            }),
+        ),
+    ])
+    .await;
+    let discord = discord(&server)
+        .with_gateway_url(gateway)
+        .with_poll_timeout(10);
+
+    let messages = discord.get_updates().await.unwrap();
+
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    assert_eq!(message.text.as_deref(), Some("PS"));
+    assert!(!message.chat.is_group());
+    assert_eq!(message.chat.id, "7");
+    assert_eq!(message.sender.display_name.as_deref(), Some("Lan"));
+    // Replies go to the channel the user wrote in, and server channels are known
+    // from the gateway
+    discord.send_message("7", "Hello").await.unwrap();
+    discord.send_message("10", "Daily question").await.unwrap();
+}
+
+#[tokio::test]
+async fn chats_are_caught_up_on_from_their_saved_cursors() {
+    let dir = tempfile::tempdir().unwrap();
+    let offset_path = dir.path().join("discord-offset");
+    OffsetStore::load(&offset_path)
+        .advance_cursor("10", 9223372036854773000)
+        .unwrap();
+    let server = MockServer::start().await;
+    mount_bot_user(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/channels/10/messages"))
+        .and(query_param("after", "9223372036854773000"))
+        .and(query_param("limit", "100"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": NEW_MESSAGE_ID,
+                "channel_id": "10",
+                "type": 0,
+                "author": { "id": "100", "username": "gmat_bot", "bot": true },
+                "content": "✅ Correct!"
+            },
+            {
+                "id": "9223372036854774000",
+                "channel_id": "10",
+                "type": 0,
+                "author": { "id": "7", "username": "lan", "global_name": "Lan" },
+                "content": "<@100> PS",
+                "mentions": [{ "id": "100", "username": "gmat_bot", "bot": true }]
+            }
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let discord = discord(&server).with_offset_store(OffsetStore::load(&offset_path));
+    let messages = discord.get_updates().await.unwrap();
+
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    assert_eq!(message.text.as_deref(), Some("PS"));
+    assert!(message.from_button);
+    assert!(message.chat.is_group());
+    assert_eq!(message.chat.id, "10");
+    assert_eq!(message.sender.id, "7");
+    assert_eq!(message.kind, MessageKind::Text);
+    // The cursor moves past the bot's own reply too
+    assert_eq!(
+        OffsetStore::load(&offset_path).cursor("10"),
+        Some(NEW_MESSAGE_ID.parse().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn a_failing_chat_does_not_hold_back_the_others() {
+    let dir = tempfile::tempdir().unwrap();
+    let offset_store = OffsetStore::load(dir.path().join("discord-offset"));
+    offset_store.advance_cursor("10", 1).unwrap();
+    offset_store.advance_cursor("20", 1).unwrap();
+    let server = MockServer::start().await;
+    mount_bot_user(&server).await;
+    let message = |channel_id: &str, id: &str| {
+        json!({
+            "id": id,
+            "channel_id": channel_id,
+            "author": { "id": "7", "username": "lan" },
+            "content": "PS"
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/channels/10/messages"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/channels/10/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([message("10", "5")])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/channels/20/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([message("20", "6")])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let discord = discord(&server).with_offset_store(offset_store);
+    let first = discord.get_updates().await.unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].chat.id, "20");
+    assert_eq!(discord.offset_store().unwrap().cursor("10"), Some(1));
+
+    // The failed chat is tried again, and its message isn't lost
+    let second = discord.get_updates().await.unwrap();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].chat.id, "10");
+    assert_eq!(discord.offset_store().unwrap().cursor("10"), Some(5));
+}
+
+#[tokio::test]
+async fn users_are_messaged_in_their_direct_messages() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/@me/channels"))
+        .and(body_json(json!({ "recipient_id": "7" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "55", "type": 1 })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/channels/55/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "900" })))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let discord = discord(&server);
+    assert_eq!(discord.send_message("7", "Hello").await.unwrap(), "900");
+    // The direct message channel is opened once and reused
+    discord
+        .send_photo("7", "https://example.com/q.png", "Question #1")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn a_rejected_token_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/@me"))
+        .respond_with(
+            ResponseTemplate::new(401).set_body_json(json!({ "message": "401: Unauthorized" })),
+        )
+        .mount(&server)
+        .await;
+
+    let error = discord(&server).get_me().await.unwrap_err();
+    assert!(error.is_invalid_token());
+    assert!(error.to_string().contains("401: Unauthorized"));
+}
+
+#[tokio::test]
+async fn the_bot_signs_in_under_its_display_name() {
+    let server = MockServer::start().await;
+    mount_bot_user(&server).await;
+
+    let me = discord(&server).get_me().await.unwrap().unwrap();
+    assert_eq!(me.to_string(), "GMAT Daily (ID 100)");
+}
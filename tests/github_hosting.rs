@@ -0,0 +1,416 @@
+//! GitHub release hosting against a mock server: uploads, reusing existing assets, error
+//! statuses, retries, rotation and GitHub App installation tokens
+
+use gmat_zalo_bot::hosting::{content_file_name, count_release_assets, rotation_tag};
+use gmat_zalo_bot::{
+    BotError, GitHubApp, GitHubAuth, GitHubConfig, GitHubReleaseHost, ImageHost, RetryPolicy,
+    upload_to_github_release,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, header, header_regex, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const REPO: &str = "owner/images";
+const TOKEN: &str = "gh-token";
+/// Throwaway key generated for these tests only
+const APP_KEY: &str = include_str!("fixtures/github_app_key.pem");
+
+fn client() -> reqwest::Client {
+    reqwest::Client::new()
+}
+
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+    }
+}
+
+fn config(server: &MockServer, release_id: u64) -> GitHubConfig {
+    GitHubConfig {
+        api_url: server.uri(),
+        repo: REPO.to_string(),
+        release_id,
+        auth: TOKEN.into(),
+    }
+}
+
+/// A rendered image on disk, removed with the returned directory
+fn image() -> (tempfile::TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let image = dir.path().join("question_42.png");
+    std::fs::write(&image, b"fake png bytes").unwrap();
+    let image = image.to_string_lossy().to_string();
+    (dir, image)
+}
+
+/// Serves the release lookup, pointing uploads back at the mock server
+async fn mount_release(server: &MockServer, release_id: u64) {
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/{}", REPO, release_id)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": release_id,
+            "upload_url": format!("{}/uploads/{}/assets{{?name,label}}", server.uri(), release_id)
+        })))
+        .mount(server)
+        .await;
+}
+
+fn asset(name: &str) -> ResponseTemplate {
+    ResponseTemplate::new(201).set_body_json(json!({
+        "browser_download_url": format!("https://github.com/{}/releases/download/{}", REPO, name)
+    }))
+}
+
+#[tokio::test]
+async fn upload_posts_the_image_to_the_release() {
+    let server = MockServer::start().await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .and(header("Authorization", format!("token {}", TOKEN).as_str()))
+        .and(header("Content-Type", "image/png"))
+        .respond_with(asset("question_42.png"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let (_dir, image) = image();
+    let url = upload_to_github_release(&client(), &server.uri(), REPO, 7, &TOKEN.into(), &image)
+        .await
+        .unwrap();
+
+    assert!(url.ends_with("/releases/download/question_42.png"));
+    let requests = server.received_requests().await.unwrap();
+    let upload = requests
+        .iter()
+        .find(|r| r.method.as_str() == "POST")
+        .unwrap();
+    let name = upload
+        .url
+        .query_pairs()
+        .find(|(k, _)| k == "name")
+        .unwrap()
+        .1;
+    assert!(name.starts_with("question_42__") && name.ends_with(".png"));
+    assert_eq!(upload.body, b"fake png bytes");
+}
+
+#[tokio::test]
+async fn assets_already_in_the_release_are_reused() {
+    let server = MockServer::start().await;
+    let (_dir, image) = image();
+    let name = content_file_name(&image, b"fake png bytes");
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/7", REPO)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": 7,
+            "upload_url": format!("{}/uploads/7/assets{{?name,label}}", server.uri()),
+            "assets": [{
+                "name": name,
+                "browser_download_url": format!("https://github.com/{}/releases/download/{}", REPO, name)
+            }]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(asset("unexpected.png"))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let url = upload_to_github_release(&client(), &server.uri(), REPO, 7, &TOKEN.into(), &image)
+        .await
+        .unwrap();
+
+    assert!(url.ends_with(&format!("/releases/download/{}", name)));
+}
+
+#[tokio::test]
+async fn duplicate_asset_found_after_a_422_is_reused() {
+    let server = MockServer::start().await;
+    mount_release(&server, 7).await;
+    let (_dir, image) = image();
+    let name = content_file_name(&image, b"fake png bytes");
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .respond_with(ResponseTemplate::new(422).set_body_string("already_exists"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/7/assets", REPO)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "name": name,
+            "browser_download_url": "https://github.com/owner/images/releases/download/existing.png"
+        }])))
+        .mount(&server)
+        .await;
+
+    let url = upload_to_github_release(&client(), &server.uri(), REPO, 7, &TOKEN.into(), &image)
+        .await
+        .unwrap();
+
+    assert!(url.ends_with("/existing.png"));
+}
+
+#[tokio::test]
+async fn missing_release_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/99", REPO)))
+        .respond_with(ResponseTemplate::new(404).set_body_string("Not Found"))
+        .mount(&server)
+        .await;
+
+    let (_dir, image) = image();
+    let error = upload_to_github_release(&client(), &server.uri(), REPO, 99, &TOKEN.into(), &image)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, BotError::GitHubUpload { .. }));
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(404));
+    assert!(error.to_string().contains("Release not found"));
+}
+
+#[tokio::test]
+async fn duplicate_asset_is_reported() {
+    let server = MockServer::start().await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .respond_with(ResponseTemplate::new(422).set_body_string("already_exists"))
+        .mount(&server)
+        .await;
+
+    let (_dir, image) = image();
+    let error = upload_to_github_release(&client(), &server.uri(), REPO, 7, &TOKEN.into(), &image)
+        .await
+        .unwrap_err();
+
+    assert!(!error.is_retryable());
+    assert!(error.to_string().contains("duplicate filename"));
+}
+
+#[tokio::test]
+async fn host_retries_server_errors() {
+    let server = MockServer::start().await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .respond_with(asset("question_42.png"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(config(&server, 7))
+        .with_retry_policy(fast_retries())
+        .with_max_assets(None);
+    let (_dir, image) = image();
+
+    assert!(host.upload(&image).await.is_ok());
+}
+
+#[tokio::test]
+async fn host_requests_carry_the_shared_user_agent() {
+    let server = MockServer::start().await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .and(header_regex("user-agent", r"^gmat-zalo-bot/\d+\.\d+\.\d+$"))
+        .respond_with(asset("question_42.png"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(config(&server, 7)).with_max_assets(None);
+    let (_dir, image) = image();
+
+    assert!(host.upload(&image).await.is_ok());
+}
+
+#[tokio::test]
+async fn host_does_not_retry_client_errors() {
+    let server = MockServer::start().await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("Bad credentials"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(config(&server, 7))
+        .with_retry_policy(fast_retries())
+        .with_max_assets(None);
+    let (_dir, image) = image();
+    let error = host.upload(&image).await.unwrap_err();
+
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(401));
+}
+
+#[tokio::test]
+async fn asset_count_follows_pagination() {
+    let server = MockServer::start().await;
+    let assets = |n: usize| ResponseTemplate::new(200).set_body_json(vec![json!({}); n]);
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/7/assets", REPO)))
+        .and(query_param("page", "1"))
+        .respond_with(assets(100))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/7/assets", REPO)))
+        .and(query_param("page", "2"))
+        .respond_with(assets(30))
+        .mount(&server)
+        .await;
+
+    let count = count_release_assets(&client(), &server.uri(), REPO, 7, &TOKEN.into())
+        .await
+        .unwrap();
+
+    assert_eq!(count, 130);
+}
+
+#[tokio::test]
+async fn full_release_rotates_to_a_new_one() {
+    let server = MockServer::start().await;
+    let tag = rotation_tag(chrono::Utc::now().date_naive(), 1);
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/7/assets", REPO)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![json!({}); 2]))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{}/releases/tags/{}", REPO, tag)))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(format!("/repos/{}/releases", REPO)))
+        .and(body_partial_json(json!({"tag_name": tag})))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({"id": 8})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_release(&server, 8).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/8/assets"))
+        .respond_with(asset("question_42.png"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(config(&server, 7))
+        .with_retry_policy(fast_retries())
+        .with_max_assets(Some(2));
+    let (_dir, image) = image();
+    host.upload(&image).await.unwrap();
+
+    assert_eq!(host.config().await.release_id, 8);
+}
+
+/// Answers the installation token exchange with `token`, valid for `valid_for`
+async fn mount_installation_token(server: &MockServer, token: &str, valid_for: chrono::Duration) {
+    Mock::given(method("POST"))
+        .and(path("/app/installations/5/access_tokens"))
+        .and(header_regex(
+            "Authorization",
+            r"^Bearer [\w-]+\.[\w-]+\.[\w-]+$",
+        ))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "token": token,
+            "expires_at": chrono::Utc::now() + valid_for
+        })))
+        .mount(server)
+        .await;
+}
+
+fn app_config(server: &MockServer) -> GitHubConfig {
+    let app = GitHubApp::new(server.uri(), "12345", 5, APP_KEY).unwrap();
+    GitHubConfig {
+        auth: GitHubAuth::App(Arc::new(app)),
+        ..config(server, 7)
+    }
+}
+
+#[tokio::test]
+async fn app_installation_token_is_reused_until_it_nears_expiry() {
+    let server = MockServer::start().await;
+    mount_installation_token(&server, "ghs_installation", chrono::Duration::hours(1)).await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .and(header("Authorization", "token ghs_installation"))
+        .respond_with(asset("question_42.png"))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(app_config(&server)).with_max_assets(None);
+    let (_dir, image) = image();
+    host.upload(&image).await.unwrap();
+    host.upload(&image).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let exchanges = requests
+        .iter()
+        .filter(|r| r.url.path() == "/app/installations/5/access_tokens")
+        .count();
+    assert_eq!(exchanges, 1);
+}
+
+#[tokio::test]
+async fn app_installation_token_is_refreshed_before_it_expires() {
+    let server = MockServer::start().await;
+    mount_installation_token(&server, "ghs_short", chrono::Duration::minutes(2)).await;
+    mount_release(&server, 7).await;
+    Mock::given(method("POST"))
+        .and(path("/uploads/7/assets"))
+        .respond_with(asset("question_42.png"))
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(app_config(&server)).with_max_assets(None);
+    let (_dir, image) = image();
+    host.upload(&image).await.unwrap();
+    host.upload(&image).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let exchanges = requests
+        .iter()
+        .filter(|r| r.url.path() == "/app/installations/5/access_tokens")
+        .count();
+    assert_eq!(exchanges, 2);
+}
+
+#[tokio::test]
+async fn rejected_app_credentials_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/app/installations/5/access_tokens"))
+        .respond_with(
+            ResponseTemplate::new(401).set_body_string("A JSON web token could not be decoded"),
+        )
+        .mount(&server)
+        .await;
+
+    let host = GitHubReleaseHost::new(app_config(&server)).with_max_assets(None);
+    let (_dir, image) = image();
+    let error = host.upload(&image).await.unwrap_err();
+
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(401));
+    assert!(error.to_string().contains("installation token"));
+}
@@ -0,0 +1,37 @@
+//! `/healthz`: the status code follows the bot's health and other paths get 404
+
+use gmat_zalo_bot::{HealthCheck, HealthMonitor, serve_health};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn healthz_reports_whether_the_question_index_is_loaded() {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let monitor = Arc::new(HealthMonitor::default());
+    let check = HealthCheck {
+        addr,
+        unhealthy_after: Duration::from_secs(300),
+    };
+    tokio::spawn(serve_health(check, monitor.clone()));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://{}/healthz", addr);
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["database_loaded"], false);
+
+    monitor.database_loaded(12);
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["database_questions"], 12);
+
+    let response = reqwest::get(format!("http://{}/metrics", addr))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
@@ -0,0 +1,51 @@
+//! The shared HTTP clients against a mock server: their user agent and request timeout
+
+use gmat_zalo_bot::{DatabaseSource, HttpContext, HttpSettings, ProxyRoute};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{header_regex, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn question_bank_requests_identify_the_bot() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/index.json"))
+        .and(header_regex("user-agent", r"^gmat-zalo-bot/\d+\.\d+\.\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "RC": [], "SC": [], "CR": ["7"], "PS": [], "DS": []
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let database = DatabaseSource::url(server.uri())
+        .fetch_index()
+        .await
+        .unwrap();
+
+    assert_eq!(database.critical_reasoning, ["7"]);
+}
+
+#[tokio::test]
+async fn slow_responses_time_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let context = HttpContext::new(&HttpSettings {
+        timeout: Duration::from_millis(200),
+        ..HttpSettings::default()
+    })
+    .unwrap();
+    let error = context
+        .client(ProxyRoute::Database)
+        .get(server.uri())
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(error.is_timeout(), "{:?}", error);
+}
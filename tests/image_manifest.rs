@@ -0,0 +1,110 @@
+//! The URL manifest `preupload` writes and `serve --image-manifest` loads
+
+use gmat_zalo_bot::{HostedImage, ImageManifest, Storage};
+use std::time::Duration;
+
+#[test]
+fn the_manifest_lists_every_hosted_image() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .save_hosted_image_urls("42", true, &["https://img/42-e.png".to_string()])
+        .unwrap();
+    storage
+        .save_hosted_image_urls(
+            "42",
+            false,
+            &[
+                "https://img/42-1.png".to_string(),
+                "https://img/42-2.png".to_string(),
+            ],
+        )
+        .unwrap();
+    storage
+        .save_hosted_image_urls("7@dark", false, &["https://img/7-dark.png".to_string()])
+        .unwrap();
+
+    let manifest = ImageManifest::from_storage(&storage).unwrap();
+    let listed: Vec<(&str, bool, usize)> = manifest
+        .images
+        .iter()
+        .map(|image| {
+            (
+                image.key.as_str(),
+                image.show_explanations,
+                image.urls.len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        listed,
+        vec![("42", false, 2), ("42", true, 1), ("7@dark", false, 1)]
+    );
+}
+
+#[test]
+fn a_saved_manifest_fills_a_fresh_store() {
+    let source = Storage::open_in_memory().unwrap();
+    source
+        .save_hosted_image_urls("42", false, &["https://img/42.png".to_string()])
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("manifests").join("image-manifest.json");
+    ImageManifest::from_storage(&source)
+        .unwrap()
+        .save(&path)
+        .unwrap();
+
+    let manifest = ImageManifest::load(&path).unwrap();
+    let storage = Storage::open_in_memory().unwrap();
+    assert_eq!(manifest.import_into(&storage).unwrap(), 1);
+    assert_eq!(
+        storage.hosted_image_urls("42", false, None).unwrap(),
+        Some(vec!["https://img/42.png".to_string()])
+    );
+    // Importing again changes nothing
+    assert_eq!(manifest.import_into(&storage).unwrap(), 0);
+}
+
+#[test]
+fn a_later_upload_in_the_store_is_kept() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .save_hosted_image_urls("42", false, &["https://img/new.png".to_string()])
+        .unwrap();
+    let stale = HostedImage {
+        key: "42".to_string(),
+        show_explanations: false,
+        urls: vec!["https://img/old.png".to_string()],
+        uploaded_at: 0,
+    };
+
+    assert!(!storage.import_hosted_image(&stale).unwrap());
+    assert_eq!(
+        storage.hosted_image_urls("42", false, None).unwrap(),
+        Some(vec!["https://img/new.png".to_string()])
+    );
+    // Old uploads still count towards the age limit of expiring URLs
+    let fresh_only = Some(Duration::from_secs(3600));
+    assert!(
+        storage
+            .import_hosted_image(&HostedImage {
+                key: "7".to_string(),
+                ..stale
+            })
+            .unwrap()
+    );
+    assert_eq!(
+        storage.hosted_image_urls("7", false, fresh_only).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn a_missing_manifest_is_reported() {
+    let error = ImageManifest::load("/nonexistent/image-manifest.json").unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Failed to read image manifest /nonexistent/image-manifest.json")
+    );
+}
@@ -0,0 +1,174 @@
+//! `--keep-html`: the page behind each rendered image is saved next to it and listed in the index
+
+mod common;
+
+use gmat_zalo_bot::{
+    HTML_INDEX_FILE, KeptHtml, QuestionContent, QuestionTemplates, QuestionType, RenderOptions,
+    SvgRenderer, Theme, render_question_to_image, render_scratch_dir,
+};
+use std::path::{Path, PathBuf};
+
+fn question() -> QuestionContent {
+    QuestionContent {
+        explanations: vec!["Add them up".to_string()],
+        ..common::question("42")
+    }
+}
+
+fn index(dir: &Path) -> Vec<KeptHtml> {
+    std::fs::read_to_string(dir.join(HTML_INDEX_FILE))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn rendered_pages_are_kept_and_indexed() {
+    let output = tempfile::tempdir().unwrap();
+    let output_dir = output.path().to_string_lossy().to_string();
+    let templates = QuestionTemplates::bundled().with_kept_html(output.path());
+    let renderer = SvgRenderer::new(RenderOptions::CHAT);
+
+    let images = render_question_to_image(
+        &question(),
+        &QuestionType::PS,
+        true,
+        Theme::default(),
+        &templates,
+        &output_dir,
+        &renderer,
+    )
+    .await
+    .unwrap();
+
+    let records = index(output.path());
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].question_id, "42");
+    assert!(records[0].show_explanations);
+    let html_path = &records[0].html_path;
+    assert_eq!(html_path.parent(), Some(output.path()));
+    let file_name = html_path.file_name().unwrap().to_string_lossy();
+    assert!(
+        file_name.starts_with("question_42_light_explained_") && file_name.ends_with(".html"),
+        "{}",
+        file_name
+    );
+    let html = std::fs::read_to_string(html_path).unwrap();
+    assert!(html.contains("What is 2 + 2?"));
+    assert!(html.contains("Add them up"));
+    assert_eq!(
+        records[0].image_paths,
+        images.iter().map(PathBuf::from).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn pages_are_not_kept_by_default() {
+    let output = tempfile::tempdir().unwrap();
+    let output_dir = output.path().to_string_lossy().to_string();
+
+    render_question_to_image(
+        &question(),
+        &QuestionType::PS,
+        false,
+        Theme::default(),
+        &QuestionTemplates::bundled(),
+        &output_dir,
+        &SvgRenderer::new(RenderOptions::CHAT),
+    )
+    .await
+    .unwrap();
+
+    let html_files = std::fs::read_dir(output.path())
+        .unwrap()
+        .filter(|entry| {
+            entry.as_ref().unwrap().path().extension() == Some(std::ffi::OsStr::new("html"))
+        })
+        .count();
+    assert_eq!(html_files, 0);
+    assert!(!output.path().join(HTML_INDEX_FILE).exists());
+}
+
+#[tokio::test]
+async fn pages_outlive_the_scratch_directories_they_were_rendered_in() {
+    let output = tempfile::tempdir().unwrap();
+    let output_dir = output.path().to_string_lossy().to_string();
+    let templates = QuestionTemplates::bundled().with_kept_html(output.path());
+    let renderer = SvgRenderer::new(RenderOptions::CHAT);
+    let render = async |show_explanations| {
+        let scratch = render_scratch_dir(&output_dir).unwrap();
+        let images = render_question_to_image(
+            &question(),
+            &QuestionType::PS,
+            show_explanations,
+            Theme::default(),
+            &templates,
+            &scratch.path().to_string_lossy(),
+            &renderer,
+        )
+        .await
+        .unwrap();
+        (scratch, images)
+    };
+
+    // Renders of the same question at once don't share a file
+    let (first, question_images) = render(false).await;
+    let (second, explained_images) = render(true).await;
+    assert_ne!(first.path(), second.path());
+    assert_ne!(question_images, explained_images);
+
+    drop((first, second));
+    assert!(!Path::new(&question_images[0]).exists());
+    let records = index(output.path());
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|record| record.html_path.exists()));
+}
+
+#[tokio::test]
+async fn rendering_a_question_again_keeps_both_pages() {
+    let output = tempfile::tempdir().unwrap();
+    let output_dir = output.path().to_string_lossy().to_string();
+    let templates = QuestionTemplates::bundled().with_kept_html(output.path());
+    let renderer = SvgRenderer::new(RenderOptions::CHAT);
+
+    for theme in [Theme::Dark, Theme::Dark, Theme::Light] {
+        render_question_to_image(
+            &question(),
+            &QuestionType::PS,
+            false,
+            theme,
+            &templates,
+            &output_dir,
+            &renderer,
+        )
+        .await
+        .unwrap();
+    }
+
+    let records = index(output.path());
+    assert_eq!(records.len(), 3);
+    assert_ne!(records[0].html_path, records[1].html_path);
+    let names: Vec<String> = records
+        .iter()
+        .map(|record| {
+            record
+                .html_path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    assert!(
+        names[0].starts_with("question_42_dark_plain_"),
+        "{:?}",
+        names
+    );
+    assert!(
+        names[2].starts_with("question_42_light_plain_"),
+        "{:?}",
+        names
+    );
+    assert!(records.iter().all(|record| record.html_path.exists()));
+}
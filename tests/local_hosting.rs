@@ -0,0 +1,52 @@
+//! Hosting images on the bot itself: uploads land in the served directory and are
+//! fetched back over HTTP from the public URL
+
+use gmat_zalo_bot::{ImageHost, LocalHost};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really an image";
+
+#[tokio::test]
+async fn uploaded_images_are_served_back() {
+    let renders = tempfile::tempdir().unwrap();
+    let image_path = renders.path().join("question_42.png");
+    std::fs::write(&image_path, PNG).unwrap();
+    let served = tempfile::tempdir().unwrap();
+    std::fs::write(served.path().join("..secret"), "private").unwrap();
+    // Half-written uploads and hidden staging files stay private too
+    std::fs::write(served.path().join("question_7__abc.tmp"), PNG).unwrap();
+    std::fs::write(served.path().join(".question_7.png"), PNG).unwrap();
+
+    let host = LocalHost::new(served.path(), "http://placeholder");
+    let addr = host.serve("127.0.0.1:0".parse().unwrap()).await.unwrap();
+    let host = LocalHost::new(served.path(), format!("http://{}/", addr));
+
+    let url = host.upload(&image_path.to_string_lossy()).await.unwrap();
+    assert!(
+        url.starts_with(&format!("http://{}/images/question_42__", addr)),
+        "{}",
+        url
+    );
+    // The same render gets the same name, so it is stored once
+    let again = host.upload(&image_path.to_string_lossy()).await.unwrap();
+    assert_eq!(again, url);
+    // The copy outlives the rendered file, which is deleted once sent
+    std::fs::remove_file(&image_path).unwrap();
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), PNG);
+
+    for path in [
+        "/images/..secret",
+        "/images/%2E%2E%2Fsecret",
+        "/images/question_7__abc.tmp",
+        "/images/.question_7.png",
+        "/question_42.png",
+    ] {
+        let response = reqwest::get(format!("http://{}{}", addr, path))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404, "{}", path);
+    }
+}
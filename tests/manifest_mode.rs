@@ -0,0 +1,107 @@
+//! `serve --manifest`: sending pre-uploaded images without rendering anything
+
+mod common;
+
+use common::question;
+use gmat_zalo_bot::zalo::Client;
+use gmat_zalo_bot::{
+    GmatDatabase, HostedImage, ImageManifest, ManifestImages, NoImageHost, QuestionType, Theme,
+    ZaloBot,
+};
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn image(key: &str, urls: &[&str]) -> HostedImage {
+    HostedImage {
+        key: key.to_string(),
+        show_explanations: false,
+        urls: urls.iter().map(|url| url.to_string()).collect(),
+        uploaded_at: 0,
+    }
+}
+
+fn manifest() -> ManifestImages {
+    ManifestImages::from(ImageManifest {
+        generated_at: "2026-10-16T00:00:00+00:00".to_string(),
+        images: vec![
+            image("42", &["https://img/42.png"]),
+            image("7-passage", &["https://img/7-passage.png"]),
+            image("9@dark", &["https://img/9-dark.png"]),
+        ],
+    })
+}
+
+fn ok(result: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({"ok": true, "result": result}))
+}
+
+#[test]
+fn questions_are_looked_up_by_theme_and_passage() {
+    let manifest = manifest();
+    assert_eq!(manifest.len(), 3);
+    assert_eq!(
+        manifest.urls("42", false),
+        Some(&["https://img/42.png".to_string()][..])
+    );
+    assert_eq!(manifest.urls("42", true), None);
+
+    assert!(manifest.has_question("42", Theme::Light));
+    // Multi-part questions start with their passage
+    assert!(manifest.has_question("7", Theme::Light));
+    assert!(!manifest.has_question("9", Theme::Light));
+    assert!(manifest.has_question("9", Theme::Dark));
+}
+
+#[test]
+fn the_database_keeps_only_questions_in_the_manifest() {
+    let mut database = GmatDatabase {
+        reading_comprehension: vec!["7".to_string(), "8".to_string()],
+        sentence_correction: Vec::new(),
+        critical_reasoning: Vec::new(),
+        problem_solving: vec!["42".to_string(), "43".to_string()],
+        data_sufficiency: vec!["9".to_string()],
+    };
+    let manifest = manifest();
+
+    database.retain(|id| manifest.has_question(id, Theme::Light));
+
+    assert_eq!(database.reading_comprehension, vec!["7".to_string()]);
+    assert_eq!(database.problem_solving, vec!["42".to_string()]);
+    assert!(database.data_sufficiency.is_empty());
+}
+
+#[tokio::test]
+async fn listed_questions_are_sent_as_photos_and_the_rest_as_text() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/bottest-token/sendPhoto"))
+        .and(body_string_contains("https://img/42.png"))
+        .respond_with(ok(json!({"message_id": "msg-1", "date": 1718000000})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/bottest-token/sendMessage"))
+        .and(body_string_contains("What is 2 + 2?"))
+        .respond_with(ok(json!({"message_id": "msg-2", "date": 1718000000})))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let bot = ZaloBot::new("test-token".to_string())
+        .with_api_client(Client::new("test-token".to_string()).with_base_url(server.uri()))
+        .with_manifest(manifest());
+
+    for id in ["42", "43"] {
+        bot.send_question(
+            "chat-1",
+            &question(id),
+            Some(&QuestionType::PS),
+            "output",
+            &NoImageHost,
+            false,
+        )
+        .await
+        .unwrap();
+    }
+}
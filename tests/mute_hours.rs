@@ -0,0 +1,60 @@
+//! Mute hours of chats and quiet hours of users, which share one window type
+
+use chrono::NaiveTime;
+use gmat_zalo_bot::{ChatConfig, MuteHours, QUIET_HOURS_PREFERENCE, Storage, ZaloBot};
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn windows_are_parsed_to_the_minute_and_may_wrap_midnight() {
+    let overnight: MuteHours = "22:30-07:15".parse().unwrap();
+    assert!(overnight.contains(time(23, 0)));
+    assert!(overnight.contains(time(7, 0)));
+    assert!(!overnight.contains(time(7, 15)));
+    assert!(!overnight.contains(time(22, 29)));
+    assert_eq!(overnight.to_string(), "22:30-07:15");
+
+    // Whole hours may be written short
+    let lunch: MuteHours = "12-13".parse().unwrap();
+    assert_eq!(lunch.to_string(), "12:00-13:00");
+
+    assert!("22:00-22:00".parse::<MuteHours>().is_err());
+    assert!("late".parse::<MuteHours>().is_err());
+}
+
+#[test]
+fn chat_mute_hours_are_stored_with_minutes() {
+    let storage = Storage::open_in_memory().unwrap();
+    let mut config = ChatConfig::default();
+    let reply = config.apply_command("mute 22:30-06:45").unwrap();
+    assert!(reply.contains("Mute hours: 22:30-06:45"));
+
+    storage.save_chat_config("chat-1", &config).unwrap();
+    let saved = storage.get_chat_config("chat-1").unwrap().unwrap();
+    assert_eq!(saved.mute_hours, Some("22:30-06:45".parse().unwrap()));
+}
+
+#[test]
+fn users_without_their_own_quiet_hours_get_the_default() {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .set_preference("early-bird", QUIET_HOURS_PREFERENCE, "20:00-05:00")
+        .unwrap();
+    // "dnd off" turns the default off too
+    storage
+        .set_preference("night-owl", QUIET_HOURS_PREFERENCE, "")
+        .unwrap();
+    let default: MuteHours = "22:00-07:00".parse().unwrap();
+    let bot = ZaloBot::new("test-token".to_string())
+        .with_storage(storage)
+        .with_default_quiet_hours(default);
+
+    assert_eq!(bot.quiet_hours("someone"), Some(default));
+    assert_eq!(
+        bot.quiet_hours("early-bird"),
+        Some("20:00-05:00".parse().unwrap())
+    );
+    assert_eq!(bot.quiet_hours("night-owl"), None);
+}
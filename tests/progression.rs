@@ -0,0 +1,126 @@
+//! Difficulty progression: when users level up and how their bands are stored
+
+mod common;
+
+use common::answer;
+use gmat_zalo_bot::{
+    Config, Difficulty, Progression, QuestionType, Storage, format_band_up, next_band,
+};
+
+#[test]
+fn users_level_up_after_sustained_accuracy() {
+    let progression = Progression {
+        accuracy: 80,
+        answers: 5,
+    };
+
+    // Too few answers to judge, however good
+    assert!(!progression.levels_up(&[true; 4]));
+    assert!(progression.levels_up(&[true, true, false, true, true]));
+    assert!(!progression.levels_up(&[true, false, false, true, true]));
+    // Only the latest answers count
+    assert!(progression.levels_up(&[true, true, true, true, true, false, false]));
+
+    assert_eq!(next_band(Difficulty::Easy), Some(Difficulty::Medium));
+    assert_eq!(next_band(Difficulty::Hard), None);
+
+    assert!(Progression::default().validate().is_ok());
+    assert!(
+        Progression {
+            accuracy: 120,
+            answers: 10
+        }
+        .validate()
+        .is_err()
+    );
+}
+
+#[test]
+fn bands_are_kept_per_type_and_start_afresh() {
+    let storage = Storage::open_in_memory().unwrap();
+    assert_eq!(
+        storage
+            .progression_band("user-1", &QuestionType::PS)
+            .unwrap(),
+        None
+    );
+
+    answer(&storage, "user-1", "42", QuestionType::PS, true);
+    answer(&storage, "user-1", "42", QuestionType::CR, false);
+    answer(&storage, "user-1", "42", QuestionType::PS, false);
+    assert_eq!(
+        storage
+            .band_results("user-1", &QuestionType::PS, 10)
+            .unwrap(),
+        vec![false, true]
+    );
+
+    storage
+        .set_progression_band("user-1", &QuestionType::PS, Difficulty::Medium)
+        .unwrap();
+    assert_eq!(
+        storage
+            .progression_band("user-1", &QuestionType::PS)
+            .unwrap(),
+        Some(Difficulty::Medium)
+    );
+    assert_eq!(
+        storage
+            .progression_band("user-1", &QuestionType::CR)
+            .unwrap(),
+        None
+    );
+    // Answers from before the level up don't count towards the next one
+    assert!(
+        storage
+            .band_results("user-1", &QuestionType::PS, 10)
+            .unwrap()
+            .is_empty()
+    );
+    answer(&storage, "user-1", "42", QuestionType::PS, true);
+    assert_eq!(
+        storage
+            .band_results("user-1", &QuestionType::PS, 10)
+            .unwrap(),
+        vec![true]
+    );
+
+    storage.forget_user("user-1").unwrap();
+    assert_eq!(
+        storage
+            .progression_band("user-1", &QuestionType::PS)
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn the_level_up_message_names_the_type_and_band() {
+    let message = format_band_up(
+        Some("Lan"),
+        QuestionType::PS,
+        Difficulty::Medium,
+        &Progression::default(),
+    );
+    assert_eq!(
+        message,
+        "📈 Level up! Lan got at least 80% of the last 10 Problem Solving questions right, so medium ones come next. Keep it up to unlock the next level."
+    );
+}
+
+#[test]
+fn the_config_sets_progression() {
+    let config: Config = toml::from_str(
+        r#"
+        [progression]
+        enabled = true
+        accuracy = 70
+        answers = 8
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.progression.enabled, Some(true));
+    assert_eq!(config.progression.accuracy, Some(70));
+    assert_eq!(config.progression.answers, Some(8));
+}
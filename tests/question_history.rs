@@ -0,0 +1,25 @@
+//! Which questions count as already sent: history is kept per chat
+
+use gmat_zalo_bot::{QuestionType, Storage, ZaloBot};
+
+#[test]
+fn private_chats_and_groups_keep_their_own_history() {
+    let storage = Storage::open_in_memory().unwrap();
+    // Lan's private chat, then a group she's in
+    storage
+        .record_question_sent("chat-1", "1", &QuestionType::PS)
+        .unwrap();
+    storage
+        .record_question_sent("group-1", "2", &QuestionType::CR)
+        .unwrap();
+    let bot = ZaloBot::new("test-token".to_string()).with_storage(storage);
+    bot.recent_sends.record("group-1", "3");
+
+    assert_eq!(bot.seen_question_ids("chat-1"), ["1".to_string()].into());
+    // Every member of the group is kept from repeats, whoever asked
+    assert_eq!(
+        bot.seen_question_ids("group-1"),
+        ["2".to_string(), "3".to_string()].into()
+    );
+    assert!(bot.seen_question_ids("chat-2").is_empty());
+}
@@ -0,0 +1,160 @@
+//! Question stats from GMAT Club thread pages served by a mock server: parsing, caching,
+//! the rendered footer and picking questions by topic
+
+use gmat_zalo_bot::{
+    DatabaseSource, ExclusionList, GmatDatabase, QuestionContent, QuestionEnricher, QuestionType,
+    Theme, TopicLookup, TopicTags, generate_html_content, pick_random_questions_by_topic,
+};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const THREAD: &str = r#"<html><body>
+<div class="difficulty">Difficulty: <b>55%</b> (hard)</div>
+<div class="statistics">Question Stats: <b>65%</b> <span>(01:42)</span> correct
+<b>35%</b> <span>(02:03)</span> wrong <span>based on <b>1,234</b> sessions</span></div>
+<div class="tags">
+  <a href="https://gmatclub.com/forum/search.php?view=search_tags&amp;tag_id=187">655-705 Level</a>
+  <a href="https://gmatclub.com/forum/search.php?view=search_tags&amp;tag_id=50">Geometry</a>
+</div>
+</body></html>"#;
+
+fn question(src: String) -> QuestionContent {
+    serde_json::from_value(json!({
+        "id": "q-42",
+        "src": src,
+        "explanations": [],
+        "type": "ps",
+        "question": "What is the area of the triangle?",
+        "answers": ["1", "2", "3", "4", "5"],
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn stats_are_parsed_and_cached() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/forum/topic-42.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(THREAD))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let cache_dir = tempfile::tempdir().unwrap();
+    let enricher = QuestionEnricher::new(cache_dir.path(), Duration::from_secs(3600));
+    let content = question(format!("{}/forum/topic-42.html", server.uri()));
+
+    let enriched = enricher.enriched(&content).await;
+    let stats = enriched.stats.as_ref().unwrap();
+    assert_eq!(stats.percent_correct, Some(65));
+    assert_eq!(stats.difficulty_percent, Some(55));
+    assert_eq!(stats.sessions, Some(1234));
+    assert_eq!(stats.tags, ["655-705 Level", "Geometry"]);
+    assert_eq!(enriched.difficulty.as_deref(), Some("655-705 Level"));
+
+    // Served from the cache; the mock allows a single request
+    assert_eq!(enricher.stats(&content).await.as_ref(), Some(stats));
+
+    let html = generate_html_content(&enriched, &QuestionType::PS, Theme::Light).unwrap();
+    assert!(html.contains("65% answered correctly"));
+}
+
+#[tokio::test]
+async fn unreachable_pages_leave_questions_unchanged() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let cache_dir = tempfile::tempdir().unwrap();
+    let enricher = QuestionEnricher::new(cache_dir.path(), Duration::from_secs(3600));
+    let content = question(format!("{}/forum/topic-42.html", server.uri()));
+
+    // Failures aren't cached, so the page is tried again next time
+    for _ in 0..2 {
+        let enriched = enricher.enriched(&content).await;
+        assert!(enriched.stats.is_none());
+        assert!(enriched.difficulty.is_none());
+    }
+
+    let html = generate_html_content(&content, &QuestionType::PS, Theme::Light).unwrap();
+    assert!(!html.contains("answered correctly"));
+}
+
+#[tokio::test]
+async fn topics_come_from_the_tag_file_the_database_and_threads() {
+    let server = MockServer::start().await;
+    let questions = [
+        ("1", "/forum/topic-1.html", json!([])),
+        ("2", "/forum/topic-2.html", json!([])),
+        ("3", "/forum/topic-3.html", json!(["Algebra"])),
+    ];
+    for (id, thread, topics) in questions {
+        let mut content = json!({
+            "id": id,
+            "src": format!("{}{}", server.uri(), thread),
+            "explanations": [],
+            "type": "ps",
+            "question": "Question",
+            "answers": ["1", "2", "3", "4", "5"],
+        });
+        content["topics"] = topics;
+        Mock::given(method("GET"))
+            .and(path(format!("/bank/{}.json", id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(content))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/forum/topic-2.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(THREAD))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+        .mount(&server)
+        .await;
+
+    let database = GmatDatabase {
+        reading_comprehension: Vec::new(),
+        sentence_correction: Vec::new(),
+        critical_reasoning: Vec::new(),
+        problem_solving: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+        data_sufficiency: Vec::new(),
+    };
+    let source = DatabaseSource::url(format!("{}/bank", server.uri()));
+    let cache_dir = tempfile::tempdir().unwrap();
+    let enricher = QuestionEnricher::new(cache_dir.path(), Duration::from_secs(3600));
+    let tags = TopicTags::new(HashMap::from([(
+        "1".to_string(),
+        vec!["geometry".to_string()],
+    )]));
+    let lookup = TopicLookup {
+        source: &source,
+        cache: None,
+        enricher: Some(&enricher),
+        tags: Some(&tags),
+    };
+    for (topic, expected) in [
+        ("Geometry", vec!["1", "2"]),
+        ("algebra", vec!["3"]),
+        ("bold-face", vec![]),
+    ] {
+        let picked = pick_random_questions_by_topic(
+            &database,
+            &ExclusionList::default(),
+            &None,
+            topic,
+            5,
+            &HashSet::new(),
+            lookup,
+        )
+        .await;
+        let mut ids: Vec<String> = picked.into_iter().map(|(_, content)| content.id).collect();
+        ids.sort();
+        assert_eq!(ids, expected, "questions on {}", topic);
+    }
+}
@@ -0,0 +1,37 @@
+//! Recent sends saved to a file: off the send path, on flush and when dropped
+
+use gmat_zalo_bot::{DEFAULT_RECENT_CAPACITY, DEFAULT_RECENT_WINDOW, RecentSends};
+use std::path::Path;
+
+fn load(path: &Path) -> RecentSends {
+    RecentSends::load(path, DEFAULT_RECENT_WINDOW, DEFAULT_RECENT_CAPACITY)
+}
+
+#[test]
+fn sends_are_saved_on_flush_rather_than_on_each_send() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recent.json");
+    let recent_sends = load(&path);
+
+    recent_sends.record("chat-1", "42");
+    assert!(!path.exists());
+
+    recent_sends.flush().unwrap();
+    assert!(load(&path).recent_ids("chat-1").contains("42"));
+    // Nothing new to save leaves the file alone
+    std::fs::remove_file(&path).unwrap();
+    recent_sends.flush().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn unsaved_sends_are_saved_when_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("recent.json");
+    let recent_sends = load(&path);
+    recent_sends.record("chat-1", "42");
+
+    drop(recent_sends);
+
+    assert!(load(&path).recent_ids("chat-1").contains("42"));
+}
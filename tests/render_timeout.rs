@@ -0,0 +1,47 @@
+//! A wkhtmltoimage that never finishes, faked with a script on PATH: the render is
+//! stopped at its timeout with an error the caller can retry on
+
+use gmat_zalo_bot::{BotError, RenderOptions, Renderer, WkhtmltoimageRenderer};
+use std::os::unix::fs::PermissionsExt;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn a_hung_renderer_is_killed_at_its_timeout() {
+    let bin = tempfile::tempdir().unwrap();
+    let fake = bin.path().join("wkhtmltoimage");
+    std::fs::write(&fake, "#!/bin/sh\nsleep 30\n").unwrap();
+    std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    // The only test in this binary, so nothing else reads PATH meanwhile
+    unsafe { std::env::set_var("PATH", path) };
+
+    let pages = tempfile::tempdir().unwrap();
+    let html_path = pages.path().join("question.html");
+    std::fs::write(&html_path, "<html></html>").unwrap();
+    let renderer =
+        WkhtmltoimageRenderer::new(RenderOptions::CHAT).with_timeout(Duration::from_millis(300));
+
+    let started = Instant::now();
+    let error = renderer
+        .render(&html_path, &pages.path().join("question.jpg"))
+        .await
+        .unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(
+        matches!(
+            error,
+            BotError::RenderTimeout {
+                program: "wkhtmltoimage",
+                ..
+            }
+        ),
+        "{:?}",
+        error
+    );
+    assert!(error.is_retryable());
+}
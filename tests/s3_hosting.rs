@@ -0,0 +1,186 @@
+//! S3-compatible hosting against a mock server: signed uploads under a prefix, public and
+//! presigned URLs, reusing objects already uploaded, and how long hosted URLs are reused
+
+use gmat_zalo_bot::hosting::content_file_name;
+use gmat_zalo_bot::{BotError, ImageHost, RetryPolicy, S3Config, S3Host, Storage};
+use std::time::Duration;
+use wiremock::matchers::{header, header_regex, method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really an image";
+
+fn config(endpoint: String) -> S3Config {
+    S3Config {
+        endpoint,
+        bucket: "images".to_string(),
+        region: "us-east-1".to_string(),
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "secret".to_string(),
+        session_token: None,
+        prefix: "/gmat/questions/".to_string(),
+        public_url: None,
+        presign_ttl: None,
+    }
+}
+
+fn image() -> (tempfile::TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let image_path = dir.path().join("question_42.png");
+    std::fs::write(&image_path, PNG).unwrap();
+    let image_path = image_path.to_string_lossy().to_string();
+    (dir, image_path)
+}
+
+#[tokio::test]
+async fn uploads_are_signed_and_land_under_the_prefix() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path_regex(r"^/images/gmat/questions/question_42__[0-9a-f]{16}\.png$"))
+        .and(header_regex(
+            "authorization",
+            r"^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/\d{8}/us-east-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token, Signature=[0-9a-f]{64}$",
+        ))
+        .and(header("x-amz-security-token", "session"))
+        .and(header("content-type", "image/png"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let (_dir, image_path) = image();
+
+    let mut s3_config = config(server.uri());
+    s3_config.session_token = Some("session".to_string());
+    let host = S3Host::new(s3_config.clone());
+    let url = host.upload(&image_path).await.unwrap();
+    let bucket_url = format!("{}/images/gmat/questions/question_42__", server.uri());
+    assert!(url.starts_with(&bucket_url), "{}", url);
+    assert_eq!(host.url_lifetime(), None);
+
+    s3_config.public_url = Some("https://cdn.example.com/".to_string());
+    let url = S3Host::new(s3_config).upload(&image_path).await.unwrap();
+    assert!(
+        url.starts_with("https://cdn.example.com/gmat/questions/question_42__"),
+        "{}",
+        url
+    );
+}
+
+#[tokio::test]
+async fn presigned_urls_expire_and_fetch_without_credentials() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex(
+            r"^/images/gmat/questions/question_42__[0-9a-f]{16}\.png$",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(PNG))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let (_dir, image_path) = image();
+
+    let mut s3_config = config(server.uri());
+    s3_config.public_url = Some("https://cdn.example.com".to_string());
+    s3_config.presign_ttl = Some(Duration::from_secs(3600));
+    let host = S3Host::new(s3_config);
+    assert_eq!(host.url_lifetime(), Some(Duration::from_secs(3600)));
+
+    let url = host.upload(&image_path).await.unwrap();
+    assert!(url.starts_with(&server.uri()), "{}", url);
+    for param in [
+        "X-Amz-Algorithm=AWS4-HMAC-SHA256",
+        "X-Amz-Credential=AKIDEXAMPLE%2F",
+        "X-Amz-Expires=3600",
+        "X-Amz-SignedHeaders=host",
+        "X-Amz-Signature=",
+    ] {
+        assert!(url.contains(param), "{} lacks {}", url, param);
+    }
+    let fetched = reqwest::get(&url).await.unwrap();
+    assert!(fetched.status().is_success());
+
+    // Signature Version 4 caps presigned URLs at a week
+    let err = host
+        .presigned_url("question.png", Duration::from_secs(8 * 24 * 3600))
+        .unwrap_err();
+    assert!(matches!(err, BotError::Config(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn objects_already_in_the_bucket_are_not_uploaded_again() {
+    let server = MockServer::start().await;
+    let (_dir, image_path) = image();
+    let name = content_file_name(&image_path, PNG);
+    Mock::given(method("HEAD"))
+        .and(path(format!("/images/gmat/questions/{}", name)))
+        .and(header_regex("authorization", r"^AWS4-HMAC-SHA256 "))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let url = S3Host::new(config(server.uri()))
+        .upload(&image_path)
+        .await
+        .unwrap();
+    assert_eq!(
+        url,
+        format!("{}/images/gmat/questions/{}", server.uri(), name)
+    );
+}
+
+#[tokio::test]
+async fn rejected_uploads_report_the_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(403).set_body_string("<Error>AccessDenied</Error>"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let (_dir, image_path) = image();
+
+    let host = S3Host::new(config(server.uri())).with_retry_policy(RetryPolicy {
+        max_attempts: 3,
+        ..RetryPolicy::default()
+    });
+    match host.upload(&image_path).await {
+        Err(BotError::S3Upload { status, body }) => {
+            assert_eq!(status, 403);
+            assert!(body.contains("AccessDenied"));
+        }
+        other => panic!("expected an S3 upload error, got {:?}", other),
+    }
+}
+
+#[test]
+fn hosted_urls_past_their_reuse_age_are_ignored() {
+    let storage = Storage::open_in_memory().unwrap();
+    let urls = vec!["https://example.com/q.png".to_string()];
+    storage.save_hosted_image_urls("q-1", false, &urls).unwrap();
+
+    assert_eq!(
+        storage.hosted_image_urls("q-1", false, None).unwrap(),
+        Some(urls.clone())
+    );
+    assert_eq!(
+        storage
+            .hosted_image_urls("q-1", false, Some(Duration::from_secs(3600)))
+            .unwrap(),
+        Some(urls)
+    );
+    assert_eq!(
+        storage
+            .hosted_image_urls("q-1", false, Some(Duration::ZERO))
+            .unwrap(),
+        None
+    );
+}
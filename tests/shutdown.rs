@@ -0,0 +1,37 @@
+//! Graceful shutdown on the signals service managers send
+
+use gmat_zalo_bot::ShutdownController;
+use std::time::Duration;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn sigterm_requests_a_shutdown() {
+    let shutdown = ShutdownController::default();
+    let requested = shutdown.requested();
+    tokio::pin!(requested);
+    // Start listening before the signal is sent, or it would end the test process
+    tokio::select! {
+        _ = &mut requested => panic!("shutdown requested before any signal"),
+        _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+    }
+
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_secs(5), requested)
+        .await
+        .expect("SIGTERM should request a shutdown");
+    assert!(shutdown.is_requested());
+}
+
+#[tokio::test]
+async fn trigger_requests_a_shutdown_without_a_signal() {
+    let shutdown = ShutdownController::default();
+    shutdown.trigger();
+    tokio::time::timeout(Duration::from_secs(5), shutdown.requested())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,81 @@
+//! The `getMe` check run before polling, against mock Zalo and Telegram servers: the
+//! account is looked up, a rejected token stops the bot and other failures don't
+
+use gmat_zalo_bot::zalo::Client;
+use gmat_zalo_bot::{BotError, BotIdentity, TelegramBot, ZaloBot};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_get_me(server: &MockServer, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path("/bottest-token/getMe"))
+        .respond_with(response)
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn the_zalo_account_is_looked_up() {
+    let server = MockServer::start().await;
+    mock_get_me(
+        &server,
+        ResponseTemplate::new(200).set_body_json(json!({
+            "ok": true,
+            "result": {"id": "bot-1", "account_name": "GMAT Daily"}
+        })),
+    )
+    .await;
+    let bot = ZaloBot::new("test-token".to_string())
+        .with_api_client(Client::new("test-token".to_string()).with_base_url(server.uri()));
+
+    let identity = bot.check_identity().await.unwrap().unwrap();
+
+    assert_eq!(
+        identity,
+        BotIdentity {
+            id: "bot-1".to_string(),
+            name: Some("GMAT Daily".to_string()),
+        }
+    );
+    assert_eq!(identity.to_string(), "GMAT Daily (ID bot-1)");
+}
+
+#[tokio::test]
+async fn a_rejected_telegram_token_stops_the_bot() {
+    let server = MockServer::start().await;
+    mock_get_me(
+        &server,
+        ResponseTemplate::new(401).set_body_json(json!({
+            "ok": false,
+            "error_code": 401,
+            "description": "Unauthorized"
+        })),
+    )
+    .await;
+    let bot = ZaloBot::new("test-token".to_string()).with_platform(Box::new(
+        TelegramBot::new("test-token".to_string()).with_api_url(server.uri()),
+    ));
+
+    let error = bot.check_identity().await.unwrap_err();
+
+    assert!(
+        matches!(error, BotError::TelegramInvalidToken { .. }),
+        "{:?}",
+        error
+    );
+    assert!(error.is_invalid_token());
+    assert!(error.to_string().contains("invalid bot token"));
+}
+
+#[tokio::test]
+async fn other_failures_only_warn() {
+    let server = MockServer::start().await;
+    mock_get_me(&server, ResponseTemplate::new(502)).await;
+    let bot = ZaloBot::new("test-token".to_string()).with_platform(Box::new(
+        TelegramBot::new("test-token".to_string()).with_api_url(server.uri()),
+    ));
+
+    assert_eq!(bot.check_identity().await.unwrap(), None);
+}
@@ -0,0 +1,182 @@
+//! "mydata" and "forgetme": exporting and deleting everything stored about a user
+
+mod common;
+
+use common::{pending, question};
+use gmat_zalo_bot::{
+    ChallengePlayer, ChallengeSession, DEFAULT_RECENT_CAPACITY, DEFAULT_RECENT_WINDOW, MockSession,
+    QuestionType, QueuedPost, QuizSession, RecentSends, Storage, UserData, UserDataFormat, ZaloBot,
+    format_user_data, parse_mydata_command, split_into_messages,
+};
+
+/// Lan answers privately and in a group; Minh only in the group
+fn storage() -> Storage {
+    let storage = Storage::open_in_memory().unwrap();
+    storage
+        .touch_user("user-1", Some("Lan"), Some("chat-1"))
+        .unwrap();
+    storage.touch_user("user-2", Some("Minh"), None).unwrap();
+    storage
+        .record_question_sent("chat-1", "1", &QuestionType::CR)
+        .unwrap();
+    storage
+        .record_answer(
+            "chat-1",
+            "user-1",
+            Some("Lan"),
+            &pending("1", QuestionType::CR),
+            'A',
+            Some(true),
+        )
+        .unwrap();
+    storage
+        .record_answer(
+            "group-1",
+            "user-1",
+            Some("Lan"),
+            &pending("2", QuestionType::CR),
+            'B',
+            Some(false),
+        )
+        .unwrap();
+    storage
+        .record_answer(
+            "group-1",
+            "user-2",
+            Some("Minh"),
+            &pending("2", QuestionType::CR),
+            'A',
+            Some(true),
+        )
+        .unwrap();
+    storage.set_preference("user-1", "theme", "dark").unwrap();
+    storage.subscribe("chat-1").unwrap();
+    storage
+}
+
+#[test]
+fn the_export_holds_the_users_rows_only() {
+    let data = storage().export_user_data("user-1").unwrap();
+
+    assert_eq!(data.user_id, "user-1");
+    assert_eq!(data.tables["answers"].len(), 2);
+    assert!(
+        data.tables["answers"]
+            .iter()
+            .all(|row| row["user_id"] == "user-1")
+    );
+    assert_eq!(data.tables["preferences"][0]["value"], "dark");
+    assert_eq!(data.tables["sent_questions"].len(), 1);
+    assert_eq!(data.tables["subscriptions"].len(), 1);
+    assert_eq!(data.tables["users"][0]["display_name"], "Lan");
+    assert_eq!(data.correct_streak, 0);
+    assert_eq!(data.practice_days.len(), 1);
+    assert_eq!(data.records(), 6);
+
+    let csv = format_user_data(&data, UserDataFormat::Csv);
+    assert!(csv.starts_with("table,row,column,value\n"));
+    assert!(csv.contains("preferences,1,value,dark\n"));
+    let json = format_user_data(&data, UserDataFormat::Json);
+    assert_eq!(serde_json::from_str::<UserData>(&json).unwrap(), data);
+}
+
+#[test]
+fn forgetting_a_user_leaves_others_alone() {
+    let storage = storage();
+
+    assert_eq!(storage.forget_user("user-1").unwrap(), 6);
+
+    let data = storage.export_user_data("user-1").unwrap();
+    assert_eq!(data.records(), 0);
+    assert!(!storage.is_subscribed("chat-1").unwrap());
+    assert_eq!(storage.export_user_data("user-2").unwrap().records(), 2);
+    assert_eq!(storage.forget_user("user-1").unwrap(), 0);
+}
+
+#[test]
+fn forgetting_a_user_drops_what_the_bot_holds_for_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let recent_path = dir.path().join("recent.json");
+    let recent_sends =
+        RecentSends::load(&recent_path, DEFAULT_RECENT_WINDOW, DEFAULT_RECENT_CAPACITY);
+    recent_sends.record("chat-1", "1");
+    recent_sends.record("chat-2", "1");
+    let bot = ZaloBot::new("test-token".to_string())
+        .with_storage(storage())
+        .with_recent_sends(recent_sends);
+    for chat_id in ["chat-1", "chat-2"] {
+        bot.pending_questions
+            .lock()
+            .unwrap()
+            .insert(chat_id.to_string(), pending("3", QuestionType::PS));
+        bot.quiz_sessions
+            .lock()
+            .unwrap()
+            .insert(chat_id.to_string(), QuizSession::new(5, None));
+        bot.quiet_queue.lock().unwrap().insert(
+            chat_id.to_string(),
+            vec![QueuedPost::Broadcast("Hi".to_string())],
+        );
+    }
+    let mock = MockSession::new("user-1", |_| true, &mut rand::thread_rng()).unwrap();
+    bot.mock_sessions
+        .lock()
+        .unwrap()
+        .insert("group-1".to_string(), mock);
+    let challenge = |challenger: &str, opponent: &str| {
+        ChallengeSession::new(
+            ChallengePlayer::new(challenger, challenger, challenger),
+            ChallengePlayer::new(opponent, opponent, opponent),
+            vec![(QuestionType::PS, question("3"))],
+            "group-1",
+        )
+    };
+    bot.challenges
+        .lock()
+        .unwrap()
+        .extend([challenge("user-2", "user-1"), challenge("user-2", "user-3")]);
+
+    assert_eq!(bot.forget_user("user-1").unwrap(), 6);
+
+    for (chat_id, kept) in [("chat-1", false), ("chat-2", true)] {
+        assert_eq!(
+            bot.pending_questions.lock().unwrap().contains_key(chat_id),
+            kept
+        );
+        assert_eq!(
+            bot.quiz_sessions.lock().unwrap().contains_key(chat_id),
+            kept
+        );
+        assert_eq!(bot.quiet_queue.lock().unwrap().contains_key(chat_id), kept);
+        assert_eq!(bot.recent_sends.recent_ids(chat_id).is_empty(), !kept);
+    }
+    assert!(bot.mock_sessions.lock().unwrap().is_empty());
+    let challenges = bot.challenges.lock().unwrap();
+    assert_eq!(challenges.len(), 1);
+    assert_eq!(challenges[0].players[1].user_id, "user-3");
+    // The saved sends no longer mention the forgotten chat
+    let saved = RecentSends::load(&recent_path, DEFAULT_RECENT_WINDOW, DEFAULT_RECENT_CAPACITY);
+    assert!(saved.recent_ids("chat-1").is_empty());
+    assert!(!saved.recent_ids("chat-2").is_empty());
+}
+
+#[test]
+fn the_command_picks_a_format() {
+    assert_eq!(parse_mydata_command("mydata"), Some(UserDataFormat::Json));
+    assert_eq!(
+        parse_mydata_command("MyData CSV"),
+        Some(UserDataFormat::Csv)
+    );
+    assert_eq!(parse_mydata_command("mydata xml"), None);
+    assert_eq!(parse_mydata_command("data"), None);
+}
+
+#[test]
+fn long_exports_are_split_between_lines() {
+    let text = "aaaa\nbbbb\ncccc\ndddddddddddd";
+
+    let messages = split_into_messages(text, 10);
+
+    assert_eq!(messages, ["aaaa\nbbbb", "cccc", "dddddddddd", "dd"]);
+    assert_eq!(split_into_messages("", 10), Vec::<String>::new());
+}
@@ -0,0 +1,117 @@
+//! Verifying a question bank served by a mock server: missing, invalid and empty-answer
+//! questions, and keeping broken ones out of random picks
+
+use gmat_zalo_bot::{
+    AdminCommand, DatabaseSource, ExclusionCommand, ExclusionList, GmatDatabase, ReportFormat,
+    VerifyIssue, daily_question, format_verify_report, parse_admin_command, pick_random_questions,
+    run_exclusion_command, verify_database,
+};
+use serde_json::json;
+use std::sync::Arc;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn question(id: &str, answers: &[&str]) -> serde_json::Value {
+    json!({
+        "id": id,
+        "src": "https://gmatclub.com/forum/topic.html",
+        "explanations": [],
+        "type": "ps",
+        "question": "What is x?",
+        "answers": answers,
+    })
+}
+
+#[tokio::test]
+async fn broken_questions_are_reported_in_index_order() {
+    let server = MockServer::start().await;
+    let responses = [
+        (
+            "ok",
+            ResponseTemplate::new(200).set_body_json(question("ok", &["1", "2"])),
+        ),
+        ("gone", ResponseTemplate::new(404)),
+        (
+            "garbled",
+            ResponseTemplate::new(200).set_body_string("{\"id\": "),
+        ),
+        (
+            "blank",
+            ResponseTemplate::new(200).set_body_json(question("blank", &[])),
+        ),
+    ];
+    for (id, response) in responses {
+        Mock::given(method("GET"))
+            .and(path(format!("/bank/{}.json", id)))
+            .respond_with(response)
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let database = GmatDatabase {
+        reading_comprehension: Vec::new(),
+        sentence_correction: Vec::new(),
+        critical_reasoning: vec!["blank".to_string()],
+        problem_solving: vec!["ok".to_string(), "gone".to_string(), "garbled".to_string()],
+        data_sufficiency: Vec::new(),
+    };
+    let source = DatabaseSource::url(format!("{}/bank", server.uri()));
+    let report = verify_database(&source, &database, None, 4).await;
+
+    assert_eq!(report.checked, 4);
+    let found: Vec<(&str, &VerifyIssue)> = report
+        .problems
+        .iter()
+        .map(|problem| (problem.question_id.as_str(), &problem.issue))
+        .collect();
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[0], ("blank", &VerifyIssue::EmptyAnswers));
+    assert_eq!(found[1], ("gone", &VerifyIssue::Missing));
+    assert_eq!(found[2].0, "garbled");
+    assert!(matches!(found[2].1, VerifyIssue::Invalid(_)));
+
+    assert_eq!(
+        format_verify_report(&report, ReportFormat::Ids),
+        "blank\ngone\ngarbled\n"
+    );
+    let text = format_verify_report(&report, ReportFormat::Text);
+    assert!(text.starts_with("Checked 4 question(s): 3 broken"));
+}
+
+#[test]
+fn excluded_questions_are_never_picked_and_the_list_persists() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("excluded.txt");
+    let exclusions = Arc::new(ExclusionList::load(&path).unwrap());
+    let database = GmatDatabase {
+        reading_comprehension: Vec::new(),
+        sentence_correction: Vec::new(),
+        critical_reasoning: Vec::new(),
+        problem_solving: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+        data_sufficiency: Vec::new(),
+    };
+
+    let Some(AdminCommand::Exclude(command)) = parse_admin_command("exclude add #1, 2") else {
+        panic!("not an exclude command");
+    };
+    assert_eq!(command, ExclusionCommand::Add("#1, 2"));
+    let reply = run_exclusion_command(&exclusions, command);
+    assert!(reply.contains("#1, #2 won't be picked"), "{}", reply);
+
+    // The list changed through the command is the one question selection checks
+    for _ in 0..10 {
+        let picked = pick_random_questions(&database, &exclusions, &None, 3);
+        assert_eq!(picked.len(), 1);
+        assert_eq!(picked[0].1, "3");
+    }
+    let today = chrono::Utc::now().date_naive();
+    assert_eq!(
+        daily_question(&database, &exclusions, today).unwrap().1,
+        "3"
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n2\n");
+
+    assert_eq!(exclusions.remove(["2".to_string()]).unwrap(), ["2"]);
+    assert_eq!(ExclusionList::load(&path).unwrap().ids(), ["1"]);
+}
@@ -0,0 +1,168 @@
+//! Outbound webhooks: payload shapes, event filters, signatures and delivery
+
+use gmat_zalo_bot::{
+    Config, QuestionType, SIGNATURE_HEADER, Storage, WebhookConfig, WebhookEvent, WebhookFormat,
+    Webhooks, webhook_payload, webhook_signature,
+};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn answer_recorded() -> WebhookEvent {
+    WebhookEvent::AnswerRecorded {
+        chat_id: "chat-1".to_string(),
+        user_id: "user-1".to_string(),
+        question_id: "42".to_string(),
+        question_type: QuestionType::PS,
+        answer: 'B',
+        correct: Some(true),
+        response_secs: 95,
+    }
+}
+
+#[test]
+fn payloads_match_the_format() {
+    let event = answer_recorded();
+
+    assert_eq!(
+        webhook_payload(&event, WebhookFormat::Json, "2026-10-16T08:00:00+00:00"),
+        json!({
+            "event": "answer_recorded",
+            "chat_id": "chat-1",
+            "user_id": "user-1",
+            "question_id": "42",
+            "question_type": "ps",
+            "answer": "B",
+            "correct": true,
+            "response_secs": 95,
+            "timestamp": "2026-10-16T08:00:00+00:00",
+        })
+    );
+    assert_eq!(
+        webhook_payload(&event, WebhookFormat::Slack, ""),
+        json!({"text": "✍️ User user-1 answered (B) to question #42 in 95s: correct"})
+    );
+
+    let summary = WebhookEvent::DailySummary {
+        date: chrono::NaiveDate::from_ymd_opt(2026, 10, 15).unwrap(),
+        active_users: 3,
+        questions_sent: 12,
+        answers: 9,
+        correct_answers: 6,
+        graded_answers: 8,
+    };
+    assert_eq!(
+        webhook_payload(&summary, WebhookFormat::Discord, ""),
+        json!({"content": "📈 2026-10-15: 3 active user(s), 12 question(s) sent, 9 answer(s), 75% correct"})
+    );
+}
+
+#[test]
+fn webhooks_filter_events_and_reject_typos() {
+    let mut config = WebhookConfig::new("https://example.com/hook");
+    assert!(config.wants(&answer_recorded()));
+
+    config.events = Some(vec!["render_failed".to_string()]);
+    assert!(!config.wants(&answer_recorded()));
+    assert!(Webhooks::new(vec![config.clone()]).is_ok());
+
+    config.events = Some(vec!["answer_recoded".to_string()]);
+    let error = Webhooks::new(vec![config]).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Unknown webhook event 'answer_recoded'")
+    );
+
+    assert!(Webhooks::new(vec![WebhookConfig::new("not a url")]).is_err());
+}
+
+#[test]
+fn the_config_lists_webhooks() {
+    let config: Config = toml::from_str(
+        r#"
+        [[webhooks]]
+        url = "https://hooks.slack.com/services/T0/B0/x"
+        format = "slack"
+        events = ["render_failed", "daily_summary"]
+
+        [[webhooks]]
+        url = "https://n8n.example.com/webhook/gmat"
+        secret = "s3cret"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.webhooks.len(), 2);
+    assert_eq!(config.webhooks[0].format, WebhookFormat::Slack);
+    assert_eq!(config.webhooks[1].format, WebhookFormat::Json);
+    assert_eq!(config.webhooks[1].secret.as_deref(), Some("s3cret"));
+}
+
+#[tokio::test]
+async fn events_are_posted_with_a_signature() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_partial_json(
+            json!({"event": "answer_recorded", "question_id": "42"}),
+        ))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/broken"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut signed = WebhookConfig::new(format!("{}/hook", server.uri()));
+    signed.secret = Some("s3cret".to_string());
+    let webhooks = Webhooks::new(vec![
+        signed,
+        WebhookConfig::new(format!("{}/broken", server.uri())),
+    ])
+    .unwrap();
+
+    assert_eq!(webhooks.deliver(&answer_recorded()).await, 1);
+
+    let request = &server.received_requests().await.unwrap()[0];
+    assert_eq!(
+        request.headers.get(SIGNATURE_HEADER).unwrap(),
+        webhook_signature("s3cret", &request.body).as_str()
+    );
+}
+
+#[tokio::test]
+async fn a_failed_connection_keeps_the_url_secret_out_of_the_error() {
+    // Nothing listens on the discard port
+    let target = WebhookConfig::new("http://127.0.0.1:9/hooks/T000/s3cret-path");
+    let webhooks = Webhooks::new(vec![target.clone()]).unwrap();
+
+    let error = webhooks
+        .post(&target, &answer_recorded())
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(!error.contains("s3cret-path"), "{}", error);
+}
+
+#[test]
+fn activity_is_counted_for_one_day() {
+    let storage = Storage::open_in_memory().unwrap();
+    let now = chrono::Utc::now().timestamp();
+    storage
+        .record_question_sent("chat-1", "42", &QuestionType::PS)
+        .unwrap();
+
+    let stats = storage.activity_stats(now - 60, now + 60).unwrap();
+    assert_eq!(stats.questions_sent, 1);
+    assert_eq!(stats.answers, 0);
+    assert_eq!(
+        storage.activity_stats(now + 60, now + 120).unwrap(),
+        Default::default()
+    );
+}
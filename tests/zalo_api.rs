@@ -0,0 +1,353 @@
+//! Zalo Bot API client against a mock server: update parsing, error statuses and retries
+
+use gmat_zalo_bot::zalo::Client;
+use gmat_zalo_bot::{BotError, MessageKind, RetryPolicy};
+use serde_json::json;
+use std::time::Duration;
+use wiremock::matchers::{body_json, body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const TOKEN: &str = "test-token";
+
+/// Retries without waiting, so the suite stays fast
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+    }
+}
+
+fn client(server: &MockServer) -> Client {
+    Client::new(TOKEN.to_string())
+        .with_base_url(server.uri())
+        .with_retry_policy(fast_retries())
+}
+
+fn api_path(method: &str) -> String {
+    format!("/bot{}/{}", TOKEN, method)
+}
+
+fn update(update_id: u64, text: &str) -> serde_json::Value {
+    json!({
+        "update_id": update_id,
+        "event_name": "message.text.received",
+        "message": {
+            "from": {"id": "user-1", "is_bot": false, "display_name": "Lan"},
+            "chat": {"id": "chat-1", "chat_type": "PRIVATE"},
+            "text": text,
+            "message_id": format!("msg-{}", update_id),
+            "date": 1718000000
+        }
+    })
+}
+
+fn ok(result: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({"ok": true, "result": result}))
+}
+
+#[tokio::test]
+async fn get_updates_accepts_a_single_update() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("getUpdates")))
+        .respond_with(ok(update(5, "PS")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let updates = client(&server).get_updates(None).await.unwrap();
+
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0].update_id, Some(5));
+    let message = updates[0].message.as_ref().unwrap();
+    assert_eq!(message.text.as_deref(), Some("PS"));
+    assert_eq!(message.chat.id, "chat-1");
+    assert!(!message.chat.is_group());
+}
+
+#[tokio::test]
+async fn get_updates_accepts_a_list_and_sends_the_offset() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("getUpdates")))
+        .and(body_json(json!({"timeout": 30, "offset": 7})))
+        .respond_with(ok(json!([update(7, "PS"), update(8, "DS")])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let updates = client(&server).get_updates(Some(7)).await.unwrap();
+
+    let ids: Vec<_> = updates.iter().map(|u| u.update_id).collect();
+    assert_eq!(ids, vec![Some(7), Some(8)]);
+}
+
+#[tokio::test]
+async fn get_updates_tells_events_apart() {
+    let server = MockServer::start().await;
+    let mut photo = update(2, "");
+    photo["event_name"] = json!("message.image.received");
+    photo["message"]["text"] = json!(null);
+    let mut joined = update(3, "");
+    joined["event_name"] = json!("group.member.joined");
+    joined["message"]["text"] = json!(null);
+    let mut unsupported = update(4, "");
+    unsupported["event_name"] = json!("message.unsupported.received");
+    unsupported["message"]["text"] = json!(null);
+    Mock::given(method("POST"))
+        .and(path(api_path("getUpdates")))
+        .respond_with(ok(json!([update(1, "PS"), photo, joined, unsupported])))
+        .mount(&server)
+        .await;
+
+    let updates = client(&server).get_updates(None).await.unwrap();
+
+    let kinds: Vec<_> = updates
+        .into_iter()
+        .map(|update| update.into_event().into_message().map(|m| m.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(MessageKind::Text),
+            Some(MessageKind::Photo),
+            Some(MessageKind::MemberJoined),
+            None
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_updates_treats_an_empty_object_as_no_updates() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("getUpdates")))
+        .respond_with(ok(json!({})))
+        .mount(&server)
+        .await;
+
+    let updates = client(&server).get_updates(None).await.unwrap();
+
+    assert!(updates.is_empty());
+}
+
+#[tokio::test]
+async fn get_updates_is_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("getUpdates")))
+        .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let error = client(&server).get_updates(None).await.unwrap_err();
+
+    assert!(matches!(error, BotError::ZaloApi { .. }));
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(502));
+    assert!(error.to_string().contains("Bad Gateway"));
+}
+
+#[tokio::test]
+async fn api_errors_carry_the_description() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendMessage")))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "ok": false,
+            "error_code": 400,
+            "description": "chat not found"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let error = client(&server)
+        .send_message("missing-chat", "hello")
+        .await
+        .unwrap_err();
+
+    // Client errors won't succeed on a retry
+    assert!(matches!(error, BotError::ZaloChatNotFound { .. }));
+    assert!(!error.is_retryable());
+    assert!(error.to_string().contains("chat not found"));
+}
+
+#[tokio::test]
+async fn a_rejected_token_is_reported_as_invalid() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("getMe")))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "ok": false,
+            "error_code": 401,
+            "description": "Unauthorized"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let error = client(&server).validate_token().await.unwrap_err();
+
+    assert!(matches!(error, BotError::ZaloInvalidToken { .. }));
+}
+
+#[tokio::test]
+async fn a_user_who_blocked_the_bot_is_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendMessage")))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "ok": false,
+            "error_code": 403,
+            "description": "Forbidden: bot was blocked by the user"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let error = client(&server)
+        .send_message("chat-1", "hello")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error.root(), BotError::ZaloUserBlocked { .. }));
+}
+
+#[tokio::test]
+async fn ok_false_with_a_success_status_is_an_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendMessage")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"ok": false, "description": "bot was blocked"})),
+        )
+        .mount(&server)
+        .await;
+
+    let error = client(&server)
+        .send_message("chat-1", "hello")
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("bot was blocked"));
+}
+
+#[tokio::test]
+async fn malformed_responses_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("getMe")))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>not json</html>"))
+        .mount(&server)
+        .await;
+
+    let error = client(&server).get_me().await.unwrap_err();
+
+    assert!(error.to_string().contains("Failed to parse getMe response"));
+}
+
+#[tokio::test]
+async fn send_photo_posts_the_url_and_caption() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendPhoto")))
+        .and(body_json(json!({
+            "chat_id": "chat-1",
+            "photo": "https://example.com/question_1.png",
+            "caption": "Question #1"
+        })))
+        .respond_with(ok(json!({"message_id": "msg-9", "date": 1718000000})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sent = client(&server)
+        .send_photo(
+            "chat-1",
+            "https://example.com/question_1.png",
+            "Question #1",
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(sent.message_id, "msg-9");
+}
+
+#[tokio::test]
+async fn send_photo_retries_server_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendPhoto")))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendPhoto")))
+        .respond_with(ok(json!({"message_id": "msg-10", "date": 1718000000})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sent = client(&server)
+        .send_photo("chat-1", "https://example.com/question_1.png", "")
+        .await
+        .unwrap();
+
+    assert_eq!(sent.message_id, "msg-10");
+}
+
+#[tokio::test]
+async fn send_photo_gives_up_after_the_last_attempt() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendPhoto")))
+        .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+            "ok": false,
+            "description": "Too many requests"
+        })))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let error = client(&server)
+        .send_photo("chat-1", "https://example.com/question_1.png", "")
+        .await
+        .unwrap_err();
+
+    match &error {
+        BotError::Retry { attempts, .. } => assert_eq!(*attempts, 3),
+        other => panic!("expected a retry error, got {:?}", other),
+    }
+    assert_eq!(error.status().map(|s| s.as_u16()), Some(429));
+}
+
+#[tokio::test]
+async fn send_photo_file_uploads_a_multipart_form() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(api_path("sendPhoto")))
+        .and(body_string_contains(
+            "name=\"photo\"; filename=\"question_1.png\"",
+        ))
+        .and(body_string_contains("Question #1"))
+        .respond_with(ok(json!({"message_id": "msg-11", "date": 1718000000})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let image = dir.path().join("question_1.png");
+    std::fs::write(&image, b"fake png bytes").unwrap();
+
+    let sent = client(&server)
+        .send_photo_file("chat-1", &image, "Question #1")
+        .await
+        .unwrap();
+
+    assert_eq!(sent.message_id, "msg-11");
+}
@@ -0,0 +1,89 @@
+//! `export zip`: question and explanations images packed with a manifest
+
+mod common;
+
+use gmat_zalo_bot::{
+    MANIFEST_NAME, QuestionContent, QuestionType, ZipQuestion, write_question_zip,
+};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+fn image(dir: &Path, name: &str) -> PathBuf {
+    fs::create_dir_all(dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, name).unwrap();
+    path
+}
+
+fn question(id: &str, dir: &Path, explanations: bool) -> ZipQuestion {
+    ZipQuestion {
+        content: QuestionContent {
+            explanations: vec!["OA: B".to_string()],
+            difficulty: Some("655-705 Level".to_string()),
+            topics: vec!["arithmetic".to_string()],
+            ..common::question(id)
+        },
+        question_type: QuestionType::PS,
+        images: vec![image(
+            &dir.join("questions"),
+            &format!("question_{}.png", id),
+        )],
+        explanation_images: if explanations {
+            vec![image(
+                &dir.join("explanations"),
+                &format!("question_{}.png", id),
+            )]
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+fn read(archive: &mut zip::ZipArchive<File>, name: &str) -> String {
+    let mut text = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    text
+}
+
+#[test]
+fn images_and_manifest_are_packed() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("out/questions.zip");
+    let questions = [
+        question("1", dir.path(), true),
+        question("2", dir.path(), false),
+    ];
+
+    write_question_zip(&questions, &output).unwrap();
+
+    let mut archive = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+    assert_eq!(archive.len(), 4);
+    assert_eq!(
+        read(&mut archive, "explanations/question_1.png"),
+        "question_1.png"
+    );
+    let manifest: serde_json::Value =
+        serde_json::from_str(&read(&mut archive, MANIFEST_NAME)).unwrap();
+    assert_eq!(manifest["count"], 2);
+    let first = &manifest["questions"][0];
+    assert_eq!(first["id"], "1");
+    assert_eq!(first["type"], "ps");
+    assert_eq!(first["type_name"], "Problem Solving");
+    assert_eq!(first["answer"], "B");
+    assert_eq!(first["level"], "655-705 Level");
+    assert_eq!(first["topics"][0], "arithmetic");
+    assert_eq!(first["images"][0], "questions/question_1.png");
+    assert_eq!(
+        first["explanation_images"][0],
+        "explanations/question_1.png"
+    );
+    assert_eq!(
+        manifest["questions"][1]["explanation_images"],
+        serde_json::json!([])
+    );
+}